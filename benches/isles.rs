@@ -0,0 +1,125 @@
+//! Throughput benchmark of connecting to distant isles on a metropolitan-scale grid
+//!
+//! `cargo bench --bench isles` - batches of independent shortest path queries, then PWRP solves where most of the allocated streets are one-way dead ends,
+//! sequentially and in batches. Counts heap allocations with a wrapping global allocator, as the PWRP benchmark does.
+
+use std::{alloc::{GlobalAlloc, Layout, System}, sync::atomic::{AtomicUsize, Ordering}, time::Instant};
+use wfbfa_solver_cli::graph::{Edge, Graph, HashSet, Scratch, heuristics};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.alloc(layout)
+	}
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout)
+	}
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.realloc(ptr, layout, new_size)
+	}
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// One-way street between 2 nodes
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+struct Street(u32, u32);
+
+impl Edge<u32> for Street {
+	fn p1(&self) -> u32 {
+		self.0
+	}
+	fn p2(&self) -> u32 {
+		self.1
+	}
+	fn directed(&self) -> bool {
+		true
+	}
+}
+
+/// Two-way `n`×`n` grid, with a one-way dead end (2 nodes past the grid, one after the other) off every `every`th intersection both ways
+///
+/// Returns: the grid, and the last street of every dead end
+fn city(n: u32, every: u32) -> (Graph<u32, (), Street>, Vec<Street>) {
+	let mut g: Graph<_, _, _> = Default::default();
+	for i in 0..n*n {
+		g.add_node(i, ());
+	}
+	let mut dead_ends = Vec::new();
+	for y in 0..n {
+		for x in 0..n {
+			let u = y*n + x;
+			for v in [(x+1 < n).then(|| u+1), (y+1 < n).then(|| u+n)].iter().flatten() {
+				g.add_edge(Street(u, *v));
+				g.add_edge(Street(*v, u));
+			}
+			if x % every == every / 2 && y % every == every / 2 {
+				let (a, b) = (n*n + 2*dead_ends.len() as u32, n*n + 2*dead_ends.len() as u32 + 1);
+				g.add_node(a, ());
+				g.add_node(b, ());
+				g.add_edge(Street(u, a));
+				g.add_edge(Street(a, b));
+				dead_ends.push(Street(a, b));
+			}
+		}
+	}
+	(g, dead_ends)
+}
+
+/// Runs `f`, printing its allocations, time and throughput (of `count` things)
+fn measure<R>(name: &str, count: usize, f: impl FnOnce() -> R) -> R {
+	let (a, t) = (ALLOCATIONS.load(Ordering::Relaxed), Instant::now());
+	let r = f();
+	let elapsed = t.elapsed().as_secs_f64();
+	println!("{:<36} {:>10} allocations {:>10.1}ms {:>12.0}/s", name, ALLOCATIONS.load(Ordering::Relaxed) - a, elapsed * 1e3, count as f64 / elapsed);
+	r
+}
+
+/// Solves PWRP from the first intersection
+///
+/// Returns: the length of the path, or how many allocated streets can't be reached
+fn solve<'a>(g: &'a Graph<u32, (), Street>, alloc: &HashSet<&'a Street>, buffers: &mut heuristics::Buffers<'a, u32, Street, i32>) -> Result<usize, usize> {
+	heuristics::solve_pwrp_buffered::<_, _, _, _, _, _, true>(g, 0, alloc.clone(), None, |_| Some(1i32), heuristics::Limits::none(), |_| {}, buffers).map(|p| p.len()).map_err(|e| match e {
+		heuristics::PwrpError::Unreachable(es) | heuristics::PwrpError::Interrupted(_, es) => es.len(),
+	})
+}
+
+fn main() {
+	let n = 120;
+	let threads = std::thread::available_parallelism().map_or(1, |t| t.get());
+	let (g, dead_ends) = city(n, 6);
+	println!("grid of {} streets, {} dead ends, {} threads", g.edges().count(), dead_ends.len(), threads);
+
+	// independent queries between pseudo-random intersections
+	let mut seed = 0x2545f491u64;
+	let mut random = || {
+		seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+		((seed >> 33) % (n*n) as u64) as u32
+	};
+	let queries: Vec<_> = (0..500).map(|_| (random(), random())).collect();
+	let weight = |_: &Street| Some(1i32);
+	let found = measure("pathfind, fresh buffers", queries.len(), || queries.iter().filter(|(u, v)| g.pathfind::<_, _, true>(*u, *v, weight).is_some()).count());
+	let mut results = Vec::new();
+	let mut scratches: Vec<Scratch<_, _, i32>> = vec![Default::default()];
+	g.pathfind_batch_into::<_, _, true>(&queries, &weight, &mut scratches, &mut results);
+	measure("pathfind batch, 1 thread", queries.len(), || g.pathfind_batch_into::<_, _, true>(&queries, &weight, &mut scratches, &mut results));
+	assert_eq!(results.iter().filter(|(f, _)| *f).count(), found);
+	let mut scratches: Vec<Scratch<_, _, i32>> = (0..threads).map(|_| Default::default()).collect();
+	g.pathfind_batch_into::<_, _, true>(&queries, &weight, &mut scratches, &mut results);
+	measure(&format!("pathfind batch, {} threads", threads), queries.len(), || g.pathfind_batch_into::<_, _, true>(&queries, &weight, &mut scratches, &mut results));
+	assert_eq!(results.iter().filter(|(f, _)| *f).count(), found);
+
+	// a few streets to clear far from the start, and the ends of dead ends there's no way back from
+	let far: Vec<_> = g.edges().filter(|e| e.0.max(e.1) < n*n && e.0 / n > n - 4).take(20).copied().collect();
+	let alloc: HashSet<&Street> = far.iter().chain(&dead_ends).map(|e| g.get_edge(e).unwrap()).collect();
+	let sequential = measure("solve_pwrp among dead ends", dead_ends.len(), || solve(&g, &alloc, &mut Default::default()));
+	assert_eq!(sequential, Err(dead_ends.len()));
+	assert_eq!(measure("solve_pwrp among dead ends, batch of 1", dead_ends.len(), || solve(&g, &alloc, &mut heuristics::Buffers::batched(1))), sequential);
+	assert_eq!(measure(&format!("solve_pwrp among dead ends, batch of {}", threads), dead_ends.len(), || solve(&g, &alloc, &mut heuristics::Buffers::batched(threads))), sequential);
+}
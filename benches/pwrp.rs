@@ -0,0 +1,88 @@
+//! Allocation & time benchmark of PWRP on a large grid
+//!
+//! `cargo bench --bench pwrp` - counts heap allocations with a wrapping global allocator, so no heaptrack needed.
+
+use std::{alloc::{GlobalAlloc, Layout, System}, sync::atomic::{AtomicUsize, Ordering}, time::Instant};
+use wfbfa_solver_cli::graph::{Edge, Graph, HashSet, heuristics};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+		System.alloc(layout)
+	}
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout)
+	}
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		BYTES.fetch_add(new_size, Ordering::Relaxed);
+		System.realloc(ptr, layout, new_size)
+	}
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Street between 2 grid intersections, with a discriminator for the 2 directions
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+struct Street(u32, u32);
+
+impl Edge<u32> for Street {
+	fn p1(&self) -> u32 {
+		self.0
+	}
+	fn p2(&self) -> u32 {
+		self.1
+	}
+	fn directed(&self) -> bool {
+		true
+	}
+}
+
+/// Two-way `n`×`n` grid
+fn grid(n: u32) -> Graph<u32, (), Street> {
+	let mut g: Graph<_, _, _> = Default::default();
+	for i in 0..n*n {
+		g.add_node(i, ());
+	}
+	for y in 0..n {
+		for x in 0..n {
+			let u = y*n + x;
+			for v in [(x+1 < n).then(|| u+1), (y+1 < n).then(|| u+n)].iter().flatten() {
+				g.add_edge(Street(u, *v));
+				g.add_edge(Street(*v, u));
+			}
+		}
+	}
+	g
+}
+
+fn measure<R>(name: &str, f: impl FnOnce() -> R) -> R {
+	let (a, b, t) = (ALLOCATIONS.load(Ordering::Relaxed), BYTES.load(Ordering::Relaxed), Instant::now());
+	let r = f();
+	println!("{:<32} {:>10} allocations {:>14} bytes {:>10.1}ms", name, ALLOCATIONS.load(Ordering::Relaxed) - a, BYTES.load(Ordering::Relaxed) - b, t.elapsed().as_secs_f64() * 1e3);
+	r
+}
+
+fn main() {
+	let g = grid(40);
+	let alloc: HashSet<&Street> = g.edges().filter(|e| (e.0 + e.1) % 3 != 0).collect();
+	println!("grid of {} streets, {} allocated", g.edges().count(), alloc.len());
+	let path = measure("solve_pwrp closed", || heuristics::solve_pwrp::<_, _, _, _, _, true>(&g, 0, alloc.clone(), None, |_| Some(1i32)).unwrap());
+	assert!(path.len() >= alloc.len());
+	measure("solve_pwrp open", || heuristics::solve_pwrp::<_, _, _, _, _, true>(&g, 0, alloc.clone(), Some(&std::iter::once(40*40 - 1).collect()), |_| Some(1i32)).unwrap());
+	measure("10 solves, fresh buffers", || for _ in 0..10 {
+		heuristics::solve_pwrp_bounded::<_, _, _, _, _, _, true>(&g, 0, alloc.clone(), None, |_| Some(1i32), heuristics::Limits::none(), |_| {}).unwrap();
+	});
+	let mut buffers = heuristics::Buffers::default();
+	measure("10 solves, reused buffers", || for _ in 0..10 {
+		let path = heuristics::solve_pwrp_buffered::<_, _, _, _, _, _, true>(&g, 0, alloc.clone(), None, |_| Some(1i32), heuristics::Limits::none(), |_| {}, &mut buffers).unwrap();
+		buffers.recycle(path);
+	});
+}
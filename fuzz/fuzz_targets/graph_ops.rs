@@ -0,0 +1,64 @@
+//! Graph mutation sequences, checking the graph stays consistent
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use std::collections::HashSet;
+use wfbfa_solver_cli::graph::{Edge, Graph};
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+struct Mixed(u8, u8, bool);
+
+impl Edge<u8> for Mixed {
+	fn p1(&self) -> u8 {
+		self.0
+	}
+	fn p2(&self) -> u8 {
+		self.1
+	}
+	fn directed(&self) -> bool {
+		self.2
+	}
+}
+
+fuzz_target!(|data: &[u8]| {
+	let mut g: Graph<u8, (), Mixed> = Default::default();
+	for op in data.chunks_exact(3) {
+		let (u, v, d) = (op[1] % 16, op[2] % 16, op[0] & 0x10 != 0);
+		match op[0] % 8 {
+			0 => {
+				g.add_node(u, ());
+			},
+			1 | 2 => {
+				g.add_edge(Mixed(u, v, d));
+			},
+			3 => {
+				g.remove_edge(&Mixed(u, v, d));
+			},
+			4 => {
+				g.disable_node(u);
+				g.disable_edge(Mixed(v, u, d));
+			},
+			5 => {
+				g.enable_node(u);
+				g.enable_edge(&Mixed(v, u, d));
+			},
+			6 => {
+				let edges = g.edge_count();
+				let sccs = g.strongly_connected_components::<true, true>();
+				g.patch_sccs::<_, true>(&sccs, |Mixed(u, v, _)| Mixed(u, v, false));
+				assert!(g.edge_count() <= edges);
+			},
+			_ => {
+				let _ = g.pathfind::<_, _, true>(u, v, |_| Some(1i64));
+			},
+		}
+		let nodes: HashSet<u8> = g.nodes().map(|(n, _)| n).collect();
+		let edges: Vec<&Mixed> = g.edges().collect();
+		assert_eq!(g.edge_count(), edges.len());
+		for e in &edges {
+			assert!(nodes.contains(&e.p1()) && nodes.contains(&e.p2()));
+			assert!(g.get_edges(e.p1()).any(|f| f == *e) && g.get_edges(e.p2()).any(|f| f == *e));
+		}
+		let sccs = g.strongly_connected_components::<true, true>();
+		assert_eq!(sccs.iter().map(|c| c.len()).sum::<usize>(), nodes.len());
+	}
+});
@@ -0,0 +1,14 @@
+//! Road graph loading: any JSON either fails to deserialize/check, or makes a solver graph
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use wfbfa_solver_cli::*;
+
+fuzz_target!(|data: &[u8]| {
+	if let Ok(roads) = serde_json::from_slice::<data::RoadGraph>(data) {
+		roads.parallel_indices();
+		let checked = roads.check();
+		assert_eq!(plow::road::dump(roads.clone()).is_ok(), checked.is_ok());
+		assert_eq!(plow::sidewalk::dump(roads.clone()).is_ok(), checked.is_ok());
+		assert_eq!(plow::fly::dump(roads).is_ok(), checked.is_ok());
+	}
+});
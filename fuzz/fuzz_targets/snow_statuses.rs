@@ -0,0 +1,22 @@
+//! Snow status loading, against a small fixed road graph
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use wfbfa_solver_cli::*;
+
+const ROADS: &str = r#"{
+	"nodes": [{"id": "a", "coordinates": [0, 0]}, {"id": "b", "coordinates": [0.01, 0]}, {"id": "c", "coordinates": [0.01, 0.01]}],
+	"roads": [
+		{"p1": "a", "p2": "b", "directed": false, "distance": 1000},
+		{"p1": "b", "p2": "c", "directed": true, "distance": 1000},
+		{"p1": "c", "p2": "a", "discriminator": "b", "directed": false, "distance": 1500}
+	]
+}"#;
+
+fuzz_target!(|data: &[u8]| {
+	if let Ok(snow) = serde_json::from_slice::<data::SnowStatuses>(data) {
+		let roads: data::RoadGraph = serde_json::from_str(ROADS).unwrap();
+		let interpolated = roads.interpolate_snow(&snow, 2);
+		assert!(interpolated.len() >= snow.len());
+		gj::snows_to_geofeatures(&roads, snow);
+	}
+});
@@ -0,0 +1,127 @@
+//! Calibration of the planning assumptions against past GPS traces
+//!
+//! Traces are map-matched (as in [`eval`]) into segment traversals, timed from the last fix at the node left to the first fix at the node reached.
+//! Traversals of clear segments tell how fast vehicles drive, the first traversal of a snowy segment - clearing it - how much slower clearing is.
+
+use crate::*;
+use data::*;
+use meta::{Slowdown, Speeds};
+use view::{SegmentKey, segment_key};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use serde::*;
+
+/// Vehicle type of vehicles without one
+pub const DEFAULT_TYPE: &str = "default";
+
+/// Calibrated meta parameters of a vehicle type - a fragment of [`meta::Parameters`]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Calibration {
+	pub speeds: Speeds,
+	pub slowdown: Slowdown,
+}
+
+/// Travelled distance and time
+#[derive(Clone, Copy, Default)]
+struct Sum {
+	distance: f64,
+	time: f64,
+}
+
+impl Sum {
+	fn add(&mut self, distance: f64, time: f64) {
+		self.distance += distance;
+		self.time += time;
+	}
+	fn speed(&self) -> N64 {
+		n64(self.distance / self.time)
+	}
+}
+
+/// Estimates driving speeds by street class, and the clearing slowdown by snow depth, of every vehicle type
+///
+/// Arguments:
+/// - `snow`: snow statuses before the traces
+/// - `types`: vehicle type of every vehicle, [`DEFAULT_TYPE`] by default
+/// - `bucket`: width of the snow depth intervals the slowdown is estimated on
+/// - `metric`: to snap GPS fixes to the nearest node by
+///
+/// Types without any clear traversal can't be calibrated, and are left out. Without any clearing one, the slowdown is `1`.
+pub fn calibrate(roads: &RoadGraph, snow: &SnowStatuses, traces: &GpsTraces, types: &IndexMap<VehicleId, String>, bucket: f64, metric: Metric) -> IndexMap<String, Calibration> {
+	let segments = eval::segments_by_nodes(roads);
+	let mut depths: HashMap<SegmentKey, N64> = HashMap::new();
+	for s in snow.iter().filter(|s| s.depth > 0.0) {
+		let d = depths.entry(segment_key(&s.p1, &s.p2, &s.discriminator)).or_insert(s.depth);
+		*d = (*d).max(s.depth);
+	}
+	let mut traversals: Vec<(&str, &RoadSegment, f64, f64)> = traces.0.iter().flat_map(|(id, trace)| {
+		let t = types.get(id).map_or(DEFAULT_TYPE, String::as_str);
+		eval::map_match(roads, &segments, trace, metric).into_iter().map(move |(e, t0, t1)| (t, e, t0, t1))
+	}).filter(|(_, _, t0, t1)| t1 > t0).collect();
+	traversals.sort_by(|a, b| a.2.total_cmp(&b.2));
+	// by type: driving by class, and clearing by depth bucket
+	let mut driving: IndexMap<&str, HashMap<Option<&StreetClass>, Sum>> = IndexMap::new();
+	let mut clearing: IndexMap<&str, Vec<(&RoadSegment, N64, f64)>> = IndexMap::new();
+	let mut cleared = HashSet::new();
+	for (t, e, t0, t1) in traversals {
+		let k = segment_key(&e.p1, &e.p2, &e.discriminator);
+		match depths.get(&k) {
+			Some(depth) if cleared.insert(k) => clearing.entry(t).or_default().push((e, *depth, t1 - t0)),
+			_ => driving.entry(t).or_default().entry(e.class.as_ref()).or_default().add(e.distance.raw(), t1 - t0),
+		}
+	}
+	driving.into_iter().map(|(t, classes)| {
+		let mut all = Sum::default();
+		for s in classes.values() {
+			all.add(s.distance, s.time);
+		}
+		let speeds = Speeds {
+			default: all.speed(),
+			classes: classes.iter().filter_map(|(c, s)| c.map(|c| (c.clone(), s.speed()))).sorted_by(|a, b| a.0.cmp(&b.0)).collect(),
+		};
+		let mut buckets: BTreeMap<i64, (f64, usize, Sum)> = BTreeMap::new();
+		for (e, depth, time) in clearing.get(t).into_iter().flatten() {
+			let (depths, n, s) = buckets.entry((depth.raw() / bucket).floor() as i64).or_default();
+			*depths += depth.raw();
+			*n += 1;
+			s.add(e.distance.raw() / speeds.of(e.class.as_ref()).raw(), *time);
+		}
+		let slowdown = if buckets.is_empty() {
+			Slowdown::Constant(n64(1.0))
+		} else {
+			Slowdown::Curve {
+				depth: buckets.into_values().map(|(depths, n, s)| (n64(depths / n as f64), n64(s.time / s.distance))).collect(),
+				temperature: vec![],
+			}
+		};
+		(t.to_string(), Calibration { speeds, slowdown })
+	}).collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_calibrate(){
+		let node = |id: &'static str, x: f64| Node { id: id.into(), coordinates: (x, 0.0), elevation: None };
+		let road = |p1: &'static str, p2: &'static str, class: Option<&'static str>| RoadSegment { class: class.map(Into::into), ..RoadSegment::new(p1, p2, 100.0) };
+		let roads = RoadGraph {
+			roads: vec![road("a", "b", Some("primary")), road("b", "c", None), road("c", "d", None)],
+			nodes: RoadGraphNodes { nodes: vec![node("a", 0.0), node("b", 1.0), node("c", 2.0), node("d", 3.0)] },
+		};
+		let snow = vec![SnowStatusElement { p1: "c".into(), p2: "b".into(), discriminator: None, parallel_index: None, depth: n64(10.0), variance: None, estimated: false }];
+		let fix = |x: f64, time: f64| GpsFix { coordinates: (x, 0.1), time };
+		// a-b at 20, b-c clearing at 5, back c-b (cleared by then) and c-d at 10
+		let traces = vec![("t".to_string(), vec![fix(0.0, 0.0), fix(1.0, 5.0), fix(2.0, 25.0), fix(1.0, 35.0), fix(2.0, 45.0), fix(3.0, 55.0)])].into_iter().collect();
+		let calibrations = calibrate(&roads, &snow, &traces, &IndexMap::new(), 5.0, Metric::default());
+		let c = &calibrations[DEFAULT_TYPE];
+		assert_eq!(c.speeds.classes["primary"], n64(20.0));
+		assert_eq!(c.speeds.of(None), n64(400.0 / 35.0));
+		assert!((c.slowdown.factor(n64(10.0), None) - 20.0 / (100.0 * 35.0 / 400.0)).abs() < 1e-9);
+		let types = vec![("t".to_string(), "truck".to_string())].into_iter().collect();
+		assert_eq!(calibrate(&roads, &vec![], &traces, &types, 5.0, Metric::default())["truck"].slowdown, Slowdown::Constant(n64(1.0)));
+	}
+}
@@ -0,0 +1,86 @@
+//! Config loading errors
+//!
+//! serde says what's wrong with a file, but only the line and column of where - not much to go on in a road graph of thousands of segments.
+//! Loaders go through here to report the file, the JSON pointer of the offending value and what was expected instead, the same way for every subcommand.
+
+use serde::{Serialize, de::DeserializeOwned};
+
+/// Why a config file didn't load
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct ConfigError {
+	pub file: String,
+	/// JSON pointer of the offending value, `""` for the whole document
+	pub pointer: String,
+	/// What the value should have been, when serde tells
+	pub expected: Option<String>,
+	pub message: String,
+}
+
+impl std::fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} at {}: {}", self.file, if self.pointer.is_empty() { "/" } else { &self.pointer }, self.message)
+	}
+}
+
+impl std::error::Error for ConfigError {}
+
+impl ConfigError {
+	/// An error with the whole document
+	pub fn document(file: &str, message: impl ToString) -> Self {
+		Self { file: file.to_string(), pointer: String::new(), expected: None, message: message.to_string() }
+	}
+}
+
+/// Deserializes a JSON document
+///
+/// serde only gets to flattened fields (the road graph's nodes, say) through a buffer, so their errors point at the object.
+pub fn from_str<T: DeserializeOwned>(file: &str, text: &str) -> Result<T, ConfigError> {
+	serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(text)).map_err(|e| error(file, e))
+}
+
+/// Deserializes an already parsed document (YAML, say) - its pointers are the same as JSON's
+pub fn from_value<T: DeserializeOwned>(file: &str, value: &serde_json::Value) -> Result<T, ConfigError> {
+	serde_path_to_error::deserialize(value).map_err(|e| error(file, e))
+}
+
+fn error(file: &str, e: serde_path_to_error::Error<serde_json::Error>) -> ConfigError {
+	use serde_path_to_error::Segment;
+	let pointer = e.path().iter().filter_map(|s| match s {
+		Segment::Seq { index } => Some(index.to_string()),
+		Segment::Map { key } => Some(key.replace('~', "~0").replace('/', "~1")),
+		Segment::Enum { .. } | Segment::Unknown => None,
+	}).map(|s| format!("/{}", s)).collect();
+	let message = e.into_inner().to_string();
+	// the location is the pointer's job
+	let message = message.rsplit_once(" at line ").map_or(message.as_str(), |(m, _)| m).to_string();
+	ConfigError {
+		file: file.to_string(),
+		pointer,
+		expected: message.split_once(", expected ").map(|(_, x)| x.to_string()),
+		message,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::data::*;
+
+	#[test]
+	fn test_pointer(){
+		let error = |text: &str| from_str::<RoadGraph>("roads.json", text).unwrap_err();
+		let e = error(r#"{"nodes": [{"id": "a", "coordinates": [0, 0]}, {"id": "b", "coordinates": [1, 0]}],
+			"roads": [{"p1": "a", "p2": "b", "directed": false, "distance": 5}, {"p1": "b", "p2": "a", "directed": false, "distance": "far"}]}"#);
+		assert_eq!((e.pointer.as_str(), e.expected.as_deref()), ("/roads/1/distance", Some("f64")));
+		assert_eq!(e.to_string(), r#"roads.json at /roads/1/distance: invalid type: string "far", expected f64"#);
+		let e = from_str::<Vec<Node>>("nodes.json", r#"[{"id": "a", "coordinates": [0, 0]}, {"id": "a/b~", "coordinates": [1, "x"]}]"#).unwrap_err();
+		assert_eq!(e.pointer, "/1/coordinates/1");
+		let e = from_str::<std::collections::HashMap<String, Node>>("nodes.json", r#"{"a/b~": {"id": "a"}}"#).unwrap_err();
+		assert_eq!((e.pointer.as_str(), e.expected), ("/a~1b~0", None));
+		let e = from_value::<crate::meta::Annealing>("meta.yaml", &serde_json::json!({"main_iterations": -1})).unwrap_err();
+		assert_eq!(e.pointer, "/main_iterations");
+		// no text to rescan - the pointer comes from where deserializing failed
+		let e = from_value::<Vec<Node>>("nodes.yaml", &serde_json::json!([{"id": "a", "coordinates": [0, 0]}, {"id": "b", "coords": [1, "x"]}])).unwrap_err();
+		assert_eq!(e.pointer, "/1/coords/1");
+	}
+}
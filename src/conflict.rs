@@ -0,0 +1,158 @@
+//! Crossing vehicle conflicts
+//!
+//! Two plows on the same intersection or segment at about the same time are an accident waiting to happen.
+//! Conflicts are detected on route timelines, and can be mitigated by delaying the starts of some vehicles.
+
+use crate::*;
+use data::*;
+use timeline::Timeline;
+
+use std::collections::HashMap;
+use indexmap::IndexMap;
+use serde::*;
+
+/// Where vehicles meet
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Place {
+	#[serde(rename="node")]
+	Node(NodeId),
+	/// an (undirected) road segment
+	#[serde(rename="segment")]
+	Segment(NodeId, NodeId, Option<NodeId>),
+}
+
+/// Two vehicles occupying the same place within the time window
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Conflict {
+	pub place: Place,
+	pub a: VehicleId,
+	/// When `a` occupies the place
+	pub a_time: (f64, f64),
+	pub b: VehicleId,
+	/// When `b` occupies the place
+	pub b_time: (f64, f64),
+	/// Wall-clock `a_time`, if the timeline is aligned to a [`Clock`](timeline::Clock)
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub a_at: Option<(String, String)>,
+	/// Wall-clock `b_time`, likewise
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub b_at: Option<(String, String)>,
+}
+
+/// Conflict detection (and mitigation) report
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Report {
+	/// Start delay of every vehicle
+	pub offsets: IndexMap<VehicleId, f64>,
+	/// Conflicts remaining, with the offsets applied
+	pub conflicts: Vec<Conflict>,
+}
+
+/// A place occupied, with the start & end times
+type Visit = (Place, f64, f64);
+type Occupancy = Vec<(VehicleId, Vec<Visit>)>;
+
+/// Places occupied by each vehicle and when - nodes from arrival till departure (breaks included), segments while traversed
+fn occupancy(plan: &Paths, tl: &Timeline) -> Occupancy {
+	plan.0.iter().filter_map(|(id, path)| tl.vehicles.get(id).map(|v| (id, path, &v.legs))).map(|(id, path, legs)| {
+		let mut occ = Vec::new();
+		for (i, s) in path.iter().enumerate() {
+			let arrival = if i == 0 { legs.first().map_or(0.0, |l| l.0) } else { legs[i-1].1 };
+			let departure = legs.get(i).map_or(arrival, |l| l.0);
+			occ.push((Place::Node(s.node.clone()), arrival, departure));
+			if let (Some(v), Some((start, end))) = (path.get(i+1), legs.get(i)) {
+				let (p1, p2) = if s.node <= v.node { (&s.node, &v.node) } else { (&v.node, &s.node) };
+				occ.push((Place::Segment(p1.clone(), p2.clone(), v.discriminator.clone()), *start, *end));
+			}
+		}
+		(id.clone(), occ)
+	}).collect()
+}
+
+fn detect_in(occ: &[(VehicleId, Vec<Visit>)], offsets: &[f64], window: f64, only: Option<usize>) -> Vec<Conflict> {
+	let mut at: HashMap<&Place, Vec<(usize, f64, f64)>> = HashMap::new();
+	for (i, (_, places)) in occ.iter().enumerate() {
+		for (p, s, e) in places {
+			at.entry(p).or_default().push((i, s + offsets[i], e + offsets[i]));
+		}
+	}
+	let mut conflicts = Vec::new();
+	for (p, visits) in at {
+		for (x, (i, s1, e1)) in visits.iter().enumerate() {
+			for (j, s2, e2) in &visits[x+1..] {
+				if i != j && only.is_none_or(|k| k == *i || k == *j) && *s1 <= e2 + window && *s2 <= e1 + window {
+					conflicts.push(Conflict { place: p.clone(), a: occ[*i].0.clone(), a_time: (*s1, *e1), b: occ[*j].0.clone(), b_time: (*s2, *e2), a_at: None, b_at: None });
+				}
+			}
+		}
+	}
+	conflicts.sort_by(|c1, c2| c1.a_time.0.total_cmp(&c2.a_time.0).then(c1.b_time.0.total_cmp(&c2.b_time.0)).then(c1.a_time.1.total_cmp(&c2.a_time.1)));
+	conflicts
+}
+
+/// Finds all pairs of vehicles occupying the same node or segment within `window` of each other
+pub fn detect(plan: &Paths, tl: &Timeline, window: f64) -> Vec<Conflict> {
+	let occ = occupancy(plan, tl);
+	stamp(detect_in(&occ, &vec![0.0; occ.len()], window, None), tl)
+}
+
+fn stamp(mut conflicts: Vec<Conflict>, tl: &Timeline) -> Vec<Conflict> {
+	for c in &mut conflicts {
+		c.a_at = tl.at(c.a_time);
+		c.b_at = tl.at(c.b_time);
+	}
+	conflicts
+}
+
+/// Delays vehicle starts to avoid conflicts
+///
+/// Vehicles are scheduled in order, each with the smallest delay (in `step`s, up to `max_offset`) that has the fewest conflicts with the ones scheduled before it.
+pub fn deconflict(plan: &Paths, tl: &Timeline, window: f64, step: f64, max_offset: f64) -> Report {
+	let occ = occupancy(plan, tl);
+	let mut offsets = vec![0.0; occ.len()];
+	let steps = if step > 0.0 { (max_offset / step).floor() as usize } else { 0 };
+	for i in 0..occ.len() {
+		let scheduled = &occ[..=i];
+		let mut best = (usize::MAX, 0.0);
+		for k in 0..=steps {
+			offsets[i] = k as f64 * step;
+			let n = detect_in(scheduled, &offsets[..=i], window, Some(i)).len();
+			if n < best.0 {
+				best = (n, offsets[i]);
+			}
+			if n == 0 {
+				break;
+			}
+		}
+		offsets[i] = best.1;
+	}
+	Report {
+		conflicts: stamp(detect_in(&occ, &offsets, window, None), tl),
+		offsets: occ.into_iter().map(|(id, _)| id).zip(offsets).collect(),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use timeline::*;
+
+	#[test]
+	fn test_conflicts(){
+		let road = |p1: &'static str, p2: &'static str| RoadSegment::new(p1, p2, 10.0);
+		let roads = RoadGraph {
+			roads: vec![road("a", "b"), road("b", "c"), road("d", "b")],
+			nodes: RoadGraphNodes { nodes: vec![] },
+		};
+		let seg = |n: &'static str| PathSegment { node: n.into(), discriminator: None, reversal: false, leg: None };
+		let plan = vec![("t".to_string(), vec![seg("a"), seg("b"), seg("c")]), ("u".to_string(), vec![seg("d"), seg("b"), seg("c")])].into_iter().collect();
+		let tl = timeline::timeline(&roads, &plan, Some(&vec![]), Pace { speed: 1.0, slowdown: 1.0, bucket: 10.0 }, &Breaks::default());
+		let conflicts = detect(&plan, &tl, 0.0);
+		assert_eq!(conflicts.iter().map(|c| &c.place).collect::<Vec<_>>(), vec![&Place::Node("b".into()), &Place::Segment("b".into(), "c".into(), None), &Place::Node("c".into())]);
+		assert_eq!(detect(&plan, &tl, 5.0).len(), 3);
+		let report = deconflict(&plan, &tl, 5.0, 10.0, 60.0);
+		assert_eq!(report.offsets.values().cloned().collect::<Vec<_>>(), vec![0.0, 20.0]);
+		assert!(report.conflicts.is_empty());
+		assert_eq!(deconflict(&plan, &tl, 5.0, 10.0, 10.0).conflicts.len(), 1);
+	}
+}
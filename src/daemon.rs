@@ -0,0 +1,127 @@
+//! Scheduled, snow-triggered planning
+//!
+//! Operations wants plans ready in the morning whenever it snowed enough overnight.
+//! The daemon wakes up daily at a set time, merges the latest snow statuses, and if there's enough snow runs the configured job, notifying a webhook of how it went.
+
+use crate::*;
+
+use std::path::PathBuf;
+use serde::*;
+
+/// When to run, and when not to
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Schedule {
+	/// Daily run time, `HH:MM`
+	pub at: String,
+	/// Offset of the local time from UTC, in hours (e.g. `-5`)
+	#[serde(default)]
+	pub utc_offset: f64,
+}
+
+/// Snow trigger - the job runs only when the merged snow statuses total above the threshold
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Trigger {
+	/// Snow status files, or directories of them (all `.json` files within)
+	pub snow: Vec<PathBuf>,
+	/// Minimal total of the merged snow depths
+	pub threshold: f64,
+}
+
+/// Daemon configuration
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Config {
+	pub schedule: Schedule,
+	pub trigger: Trigger,
+	/// Job command line - program and arguments
+	pub job: Vec<String>,
+	/// Webhook URL (`http://` only) notified with a JSON [`Outcome`] when the job completes or fails, see [`crate::notify::post`]
+	#[serde(default)]
+	pub webhook: Option<String>,
+}
+
+/// How a triggered job went
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Outcome {
+	/// Total of the merged snow depths that triggered the job
+	pub snow_total: f64,
+	pub success: bool,
+	/// Exit code of the job, if it exited (rather than failing to start, or being killed)
+	pub exit_code: Option<i32>,
+	pub message: String,
+}
+
+/// Parses a `HH:MM` time of day into seconds since midnight
+pub fn time_of_day(at: &str) -> Result<u64, Error> {
+	let (h, m) = at.split_once(':').ok_or_else(|| format!("Invalid time {} (HH:MM)", at))?;
+	let h: u64 = h.trim().parse().map_err(|_| format!("Invalid hour in {}", at))?;
+	let m: u64 = m.trim().parse().map_err(|_| format!("Invalid minute in {}", at))?;
+	if h < 24 && m < 60 {
+		Ok(h * 3600 + m * 60)
+	} else {
+		Err(format!("Invalid time {}", at).into())
+	}
+}
+
+impl Schedule {
+	/// Seconds from `now` (UNIX time) until the next run, a whole day if it's right now
+	pub fn until_next(&self, now: u64) -> Result<u64, Error> {
+		const DAY: i64 = 24 * 3600;
+		let at = time_of_day(&self.at)? as i64;
+		let local = (now as i64 + (self.utc_offset * 3600.0).round() as i64).rem_euclid(DAY);
+		Ok(match (at - local).rem_euclid(DAY) {
+			0 => DAY,
+			d => d,
+		} as u64)
+	}
+}
+
+/// Snow status files the trigger looks at - the files as given, and the `.json` files of the directories, by name
+pub fn snow_files(trigger: &Trigger) -> std::io::Result<Vec<PathBuf>> {
+	let mut files = Vec::new();
+	for p in &trigger.snow {
+		if p.is_dir() {
+			let mut dir: Vec<PathBuf> = std::fs::read_dir(p)?.map(|e| e.map(|e| e.path())).collect::<std::io::Result<_>>()?;
+			dir.retain(|f| f.extension().is_some_and(|e| e == "json"));
+			dir.sort();
+			files.extend(dir);
+		} else {
+			files.push(p.clone());
+		}
+	}
+	Ok(files)
+}
+
+/// Total snow depth of snow statuses
+pub fn snow_total(snow: &data::SnowStatuses) -> f64 {
+	snow.iter().map(|s| s.depth.raw()).filter(|d| *d > 0.0).sum()
+}
+
+/// Runs the job, waiting for it to finish
+pub fn run_job(job: &[String], snow_total: f64) -> Outcome {
+	let status = match job.split_first() {
+		Some((program, args)) => std::process::Command::new(program).args(args).status().map_err(|e| format!("{} failed to start: {}", program, e)),
+		None => Err("No job configured".to_string()),
+	};
+	match status {
+		Ok(s) => Outcome { snow_total, success: s.success(), exit_code: s.code(), message: format!("Job {}", s) },
+		Err(message) => Outcome { snow_total, success: false, exit_code: None, message },
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_schedule(){
+		let schedule = |at: &str, utc_offset: f64| Schedule { at: at.into(), utc_offset };
+		// 1970-01-02 01:00 UTC
+		let now = 25 * 3600;
+		assert_eq!(schedule("02:00", 0.0).until_next(now).unwrap(), 3600);
+		assert_eq!(schedule("01:00", 0.0).until_next(now).unwrap(), 24 * 3600);
+		assert_eq!(schedule("00:30", 0.0).until_next(now).unwrap(), 23 * 3600 + 1800);
+		assert_eq!(schedule("02:00", -5.0).until_next(now).unwrap(), 6 * 3600);
+		assert!(schedule("25:00", 0.0).until_next(now).is_err());
+	}
+
+}
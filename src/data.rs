@@ -1,9 +1,10 @@
 //! Crusty data types for the [Specification](https://github.com/WFBFA/Specs)
 
-use std::convert::TryFrom;
+use std::{collections::HashMap, convert::TryFrom, iter::FromIterator};
 
 use crate::*;
 
+use indexmap::IndexMap;
 use serde::*;
 
 pub trait Distance {
@@ -18,16 +19,170 @@ impl Distance for (f64, f64) {
 	}
 }
 
+/// How far apart `(lon, lat)` coordinates are, for nearest node lookups and the like
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Metric {
+	/// Euclidean on raw coordinates - skewed away from the equator, a degree of longitude being shorter than one of latitude
+	Planar,
+	/// Great-circle (haversine)
+	#[default]
+	Geodesic,
+	/// Euclidean on coordinates projected to meters around the first one (see [`project`]) - close to geodesic at city scale, and cheaper
+	Projected,
+}
+
+impl Metric {
+	pub const NAMES: [&'static str; 3] = ["Planar", "Geodesic", "Projected"];
+	/// Distance between 2 coordinates - only comparable to others by the same metric
+	pub fn distance(self, c1: (f64, f64), c2: (f64, f64)) -> f64 {
+		match self {
+			Metric::Planar => c1.distance(&c2),
+			Metric::Geodesic => crate::validate::geodesic(c1, c2),
+			Metric::Projected => {
+				let (x, y) = project(c1, c2);
+				x.hypot(y)
+			}
+		}
+	}
+}
+
+impl std::str::FromStr for Metric {
+	type Err = String;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"Planar" => Ok(Self::Planar),
+			"Geodesic" => Ok(Self::Geodesic),
+			"Projected" => Ok(Self::Projected),
+			_ => Err(format!("Unknown metric {} (Planar, Geodesic or Projected)", s)),
+		}
+	}
+}
+
+/// Projects `(lon, lat)` coordinates to local planar `(x, y)` meters (east, north) from an origin - equirectangular, good for a city's extent
+pub fn project((lon0, lat0): (f64, f64), (lon, lat): (f64, f64)) -> (f64, f64) {
+	((lon - lon0) * lat0.to_radians().cos() * 111_320.0, (lat - lat0) * 110_574.0)
+}
+
+/// Node id - an integer (e.g. an OSM node id), or a name
+///
+/// Integer ids are read and written as integers, without allocating; `1` and `"1"` are different ids.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum NodeId {
+	Int(u64),
+	Name(std::borrow::Cow<'static, str>),
+}
+
+impl NodeId {
+	/// The name, unless an integer id
+	pub fn name(&self) -> Option<&str> {
+		match self {
+			Self::Int(_) => None,
+			Self::Name(id) => Some(id),
+		}
+	}
+}
+
+impl From<u64> for NodeId {
+	fn from(id: u64) -> Self {
+		Self::Int(id)
+	}
+}
+
+impl From<&'static str> for NodeId {
+	fn from(id: &'static str) -> Self {
+		Self::Name(id.into())
+	}
+}
+
+impl From<String> for NodeId {
+	fn from(id: String) -> Self {
+		Self::Name(id.into())
+	}
+}
+
+impl std::fmt::Display for NodeId {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::Int(id) => id.fmt(f),
+			Self::Name(id) => id.fmt(f),
+		}
+	}
+}
+
+/// A road segment
+///
+/// Besides the spec'd field names, common municipal ones (`from`/`to`, `one_way`, `length`) are accepted too.
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct RoadSegment {
+	#[serde(alias="from", alias="source")]
 	pub p1: NodeId,
+	#[serde(alias="to", alias="target")]
 	pub p2: NodeId,
 	pub discriminator: Option<NodeId>,
+	/// Whether the segment is one-way, `p1`→`p2` - two-way if not given (see [`crate::infer`] to tell from its lanes and parking)
+	#[serde(default, alias="one_way", alias="oneway")]
 	pub directed: bool,
+	#[serde(alias="length")]
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
 	pub distance: N64,
+	#[serde(default)]
 	pub sidewalks: (bool, bool),
+	/// Whether there are parking lanes on the left and right, going `p1`→`p2`
+	#[serde(default)]
+	pub parking: (bool, bool),
+	/// Road surface (e.g. `asphalt`, `gravel`), unknown meaning any vehicle can clear it
+	#[serde(default)]
+	pub surface: Option<Surface>,
+	/// Street class (e.g. `arterial`, `residential`)
+	#[serde(default)]
+	pub class: Option<StreetClass>,
+	/// Whether the segment is a bridge - freezing first (see [`crate::meta::Deicing`])
+	#[serde(default, skip_serializing_if="std::ops::Not::not")]
+	pub bridge: bool,
+	/// Whether the segment is mostly shaded - freezing first too
+	#[serde(default, skip_serializing_if="std::ops::Not::not")]
+	pub shaded: bool,
+	/// Priority level (e.g. `1` for arterials), to be cleared by its deadline (see [`crate::meta::Deadlines`])
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub priority: Option<u32>,
+	/// Time windows within which the segment is one-way - reversible lanes
+	#[serde(default, skip_serializing_if="Vec::is_empty")]
+	pub windows: Vec<DirectionWindow>,
+	/// Penalty for plowing its sidewalks from the opposite side of the street, instead of [`crate::meta::Parameters::crossing`]
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<f64>"))]
+	pub crossing: Option<N64>,
+	/// Original feature properties of roads imported from GeoJSON (e.g. pavement id, ward), carried over to exported plans
+	#[serde(default, skip_serializing_if="serde_json::Map::is_empty")]
+	pub properties: serde_json::Map<String, serde_json::Value>,
 }
 
+impl RoadSegment {
+	/// A two-way segment, without sidewalks, parking or any other attributes - to be filled in with struct update syntax
+	pub fn new(p1: impl Into<NodeId>, p2: impl Into<NodeId>, distance: f64) -> Self {
+		Self { p1: p1.into(), p2: p2.into(), discriminator: None, directed: false, distance: n64(distance), sidewalks: (false, false), parking: (false, false), surface: None, class: None, bridge: false, shaded: false, priority: None, windows: vec![], crossing: None, properties: Default::default() }
+	}
+}
+
+/// Time window within which a segment is one-way, e.g. a boulevard reversing direction at rush hour
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DirectionWindow {
+	/// Start of the window, in time units since the start of the plan (as laid out by [`crate::timeline`])
+	pub start: f64,
+	pub end: f64,
+	/// Whether the one way is `p2`→`p1`, rather than `p1`→`p2`
+	#[serde(default, skip_serializing_if="std::ops::Not::not")]
+	pub reverse: bool,
+}
+
+pub type Surface = String;
+pub type StreetClass = String;
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub enum SidewalkSide {
 	#[serde(rename="left")]
@@ -37,9 +192,15 @@ pub enum SidewalkSide {
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Node {
+	#[serde(alias="node_id")]
 	pub id: NodeId,
+	#[serde(alias="coords", alias="lonlat")]
 	pub coordinates: (f64, f64),
+	/// Elevation, in meters
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub elevation: Option<f64>,
 }
 impl From<&Node> for geo::Geometry<f64> {
 	fn from(n: &Node) -> Self {
@@ -48,53 +209,762 @@ impl From<&Node> for geo::Geometry<f64> {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct RoadGraph {
 	pub roads: Vec<RoadSegment>,
 	#[serde(flatten)]
 	pub nodes: RoadGraphNodes,
 }
 
+impl RoadGraph {
+	/// Parallel index of every road segment, in order
+	///
+	/// That is its position, in the road graph, among the segments between the same nodes (either way) with the same discriminator - e.g. the carriageways of a divided road.
+	pub fn parallel_indices(&self) -> Vec<usize> {
+		let mut seen: HashMap<(&NodeId, &NodeId, &Option<NodeId>), usize> = HashMap::new();
+		self.roads.iter().map(|e| {
+			let (p1, p2) = if e.p1 <= e.p2 { (&e.p1, &e.p2) } else { (&e.p2, &e.p1) };
+			let i = seen.entry((p1, p2, &e.discriminator)).or_insert(0);
+			*i += 1;
+			*i - 1
+		}).collect()
+	}
+	/// Canonical content hash - the same for the same segments and nodes, in whatever order
+	///
+	/// GeoJSON `properties` carried over aren't part of the content. Outputs computed for a graph embed its hash (e.g. export manifests, solver statistics), to tell whether they still match it.
+	pub fn content_hash(&self) -> String {
+		let roads = self.roads.iter().map(|e| serde_json::to_vec(&RoadSegment { properties: Default::default(), ..e.clone() }));
+		let nodes = self.nodes.nodes.iter().map(serde_json::to_vec);
+		let mut hashes: Vec<(u8, u64)> = roads.map(|r| (0, fnv1a(&r.unwrap()))).chain(nodes.map(|n| (1, fnv1a(&n.unwrap())))).collect();
+		hashes.sort_unstable();
+		format!("{:016x}", fnv1a(&hashes.iter().flat_map(|(kind, h)| std::iter::once(*kind).chain(h.to_le_bytes())).collect::<Vec<_>>()))
+	}
+	/// Checks that segments only reference nodes in the graph and have a sane length, and that nodes have finite coordinates - the errors of [`validate`]
+	pub fn check(&self) -> Result<(), Error> {
+		match validate(self).into_iter().find(ValidationIssue::is_error) {
+			Some(i) => Err(Error::Validation(i)),
+			None => Ok(()),
+		}
+	}
+	/// Grade of every road segment, in order - the rise over the distance going `p1`→`p2`, if both elevations are known
+	pub fn grades(&self) -> Vec<Option<f64>> {
+		let elevations: HashMap<_, _> = self.nodes.nodes.iter().filter_map(|n| n.elevation.map(|e| (&n.id, e))).collect();
+		self.roads.iter().map(|e| match (elevations.get(&e.p1), elevations.get(&e.p2)) {
+			(Some(z1), Some(z2)) if e.distance > 0.0 => Some((z2 - z1) / e.distance.raw()),
+			_ => None,
+		}).collect()
+	}
+	/// Estimates snow depths of unsampled segments from the sampled ones within `hops` segments
+	///
+	/// The estimate is the average of sampled depths, weighted by the inverse of the number of hops to them.
+	/// The resulting statuses are the samples followed by the estimates (flagged `estimated`).
+	pub fn interpolate_snow(&self, snow: &SnowStatuses, hops: usize) -> SnowStatuses {
+		let key = |p1: &NodeId, p2: &NodeId, d: &Option<NodeId>| if p1 <= p2 { (p1.clone(), p2.clone(), d.clone()) } else { (p2.clone(), p1.clone(), d.clone()) };
+		let mut incident: HashMap<&NodeId, Vec<usize>> = HashMap::new();
+		let mut segments: IndexMap<_, Vec<usize>> = IndexMap::new();
+		for (i, e) in self.roads.iter().enumerate() {
+			incident.entry(&e.p1).or_default().push(i);
+			incident.entry(&e.p2).or_default().push(i);
+			segments.entry(key(&e.p1, &e.p2, &e.discriminator)).or_default().push(i);
+		}
+		let sampled: HashMap<_, _> = snow.iter().map(|s| (key(&s.p1, &s.p2, &s.discriminator), s.depth)).collect();
+		let mut estimates: IndexMap<usize, (N64, N64)> = IndexMap::new();
+		for (k, depth) in &sampled {
+			let mut seen: std::collections::HashSet<usize> = segments.get(k).into_iter().flatten().copied().collect();
+			let mut frontier: Vec<usize> = seen.iter().copied().collect();
+			for hop in 1..=hops {
+				frontier = frontier.into_iter()
+					.flat_map(|i| [&self.roads[i].p1, &self.roads[i].p2])
+					.flat_map(|n| incident.get(n).into_iter().flatten().copied())
+					.filter(|i| seen.insert(*i))
+					.collect();
+				let w = n64(1.0 / hop as f64);
+				for i in &frontier {
+					let (sum, weights) = estimates.entry(*i).or_insert((n64(0.0), n64(0.0)));
+					*sum += *depth * w;
+					*weights += w;
+				}
+			}
+		}
+		estimates.sort_keys();
+		let mut estimated = std::collections::HashSet::new();
+		let estimates: Vec<_> = estimates.into_iter().filter_map(|(i, (sum, weights))| {
+			let e = &self.roads[i];
+			let k = key(&e.p1, &e.p2, &e.discriminator);
+			if sampled.contains_key(&k) || !estimated.insert(k) {
+				return None;
+			}
+			Some(SnowStatusElement { p1: e.p1.clone(), p2: e.p2.clone(), discriminator: e.discriminator.clone(), parallel_index: None, depth: sum / weights, variance: None, estimated: true })
+		}).collect();
+		snow.iter().cloned().chain(estimates).collect()
+	}
+}
+
+/// Nodes with more segments than this are suspicious - likely many nodes collapsed into one
+pub const MAX_DEGREE: usize = 12;
+
+/// Something wrong with a road graph (see [`validate`])
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag="issue", rename_all="snake_case")]
+pub enum ValidationIssue {
+	/// A segment references a node not in the graph
+	MissingNode { p1: NodeId, p2: NodeId, discriminator: Option<NodeId>, node: NodeId },
+	/// A segment's length is negative or not finite
+	InvalidLength { p1: NodeId, p2: NodeId, discriminator: Option<NodeId>, distance: f64 },
+	/// A node's coordinates are not finite
+	InvalidCoordinates { node: NodeId, coordinates: (f64, f64) },
+	/// A segment of zero length
+	ZeroLength { p1: NodeId, p2: NodeId, discriminator: Option<NodeId> },
+	/// A segment from a node back to itself
+	Loop { node: NodeId, discriminator: Option<NodeId> },
+	/// Segments between the same nodes, with the same discriminator and direction
+	DuplicateSegment { p1: NodeId, p2: NodeId, discriminator: Option<NodeId>, count: usize },
+	/// A node listed more than once
+	DuplicateNode { node: NodeId, count: usize },
+	/// A node's coordinates are out of the longitude/latitude range
+	OutOfRange { node: NodeId, coordinates: (f64, f64) },
+	/// A node without segments
+	Isolated { node: NodeId },
+	/// A node with more than [`MAX_DEGREE`] segments
+	HighDegree { node: NodeId, degree: usize },
+	/// A node only one-way segments lead into (`sink`) or out of - vehicles get stuck there, or can't get there
+	OneWay { node: NodeId, sink: bool },
+	/// A connected component (ignoring directions) besides the largest - vehicles can't get there from the rest
+	Disconnected { nodes: usize, example: NodeId },
+}
+
+impl ValidationIssue {
+	/// Whether the graph can't be solved at all - the other issues are only suspicious
+	pub fn is_error(&self) -> bool {
+		matches!(self, Self::MissingNode { .. } | Self::InvalidLength { .. } | Self::InvalidCoordinates { .. })
+	}
+}
+
+impl std::fmt::Display for ValidationIssue {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::MissingNode { p1, p2, node, .. } => write!(f, "Segment {}<->{} references node {} not in the road graph", p1, p2, node),
+			Self::InvalidLength { p1, p2, distance, .. } => write!(f, "Segment {}<->{} has invalid length {}", p1, p2, distance),
+			Self::InvalidCoordinates { node, coordinates } => write!(f, "Node {} has invalid coordinates {:?}", node, coordinates),
+			Self::ZeroLength { p1, p2, .. } => write!(f, "Segment {}<->{} has zero length", p1, p2),
+			Self::Loop { node, .. } => write!(f, "Segment {}<->{} loops back to its node", node, node),
+			Self::DuplicateSegment { p1, p2, discriminator, count } => write!(f, "Segment {}<->{} ({:?}) is listed {} times", p1, p2, discriminator, count),
+			Self::DuplicateNode { node, count } => write!(f, "Node {} is listed {} times", node, count),
+			Self::OutOfRange { node, coordinates } => write!(f, "Node {} has coordinates {:?} out of the longitude/latitude range", node, coordinates),
+			Self::Isolated { node } => write!(f, "Node {} has no segments", node),
+			Self::HighDegree { node, degree } => write!(f, "Node {} has {} segments", node, degree),
+			Self::OneWay { node, sink: true } => write!(f, "Node {} can't be left, one-way segments only leading into it", node),
+			Self::OneWay { node, sink: false } => write!(f, "Node {} can't be reached, one-way segments only leading out of it", node),
+			Self::Disconnected { nodes, example } => write!(f, "{} nodes (e.g. {}) are disconnected from the rest of the graph", nodes, example),
+		}
+	}
+}
+
+/// Validates a road graph - referential integrity, lengths, coordinates, duplicates, degrees and connectivity
+///
+/// Returns: the issues found, those of segments (in order) first, then those of nodes, then the disconnected components (largest first)
+pub fn validate(g: &RoadGraph) -> Vec<ValidationIssue> {
+	use ValidationIssue::*;
+	let mut issues = Vec::new();
+	let mut counts: IndexMap<&NodeId, usize> = IndexMap::new();
+	for n in &g.nodes.nodes {
+		*counts.entry(&n.id).or_default() += 1;
+	}
+	// undirected, and incoming and outgoing one-way segments, of every node
+	let mut degrees: HashMap<&NodeId, (usize, usize, usize)> = HashMap::new();
+	let mut segments: IndexMap<(bool, &NodeId, &NodeId, &Option<NodeId>), usize> = IndexMap::new();
+	for e in &g.roads {
+		let missing: Vec<_> = [Some(&e.p1), Some(&e.p2), e.discriminator.as_ref()].iter().flatten().filter(|n| !counts.contains_key(*n)).map(|n| (*n).clone()).collect();
+		for node in missing {
+			issues.push(MissingNode { p1: e.p1.clone(), p2: e.p2.clone(), discriminator: e.discriminator.clone(), node });
+		}
+		if !(e.distance >= 0.0 && e.distance.is_finite()) {
+			issues.push(InvalidLength { p1: e.p1.clone(), p2: e.p2.clone(), discriminator: e.discriminator.clone(), distance: e.distance.raw() });
+		} else if e.distance == 0.0 {
+			issues.push(ZeroLength { p1: e.p1.clone(), p2: e.p2.clone(), discriminator: e.discriminator.clone() });
+		}
+		if e.p1 == e.p2 {
+			issues.push(Loop { node: e.p1.clone(), discriminator: e.discriminator.clone() });
+		}
+		let (p1, p2) = if e.directed || e.p1 <= e.p2 { (&e.p1, &e.p2) } else { (&e.p2, &e.p1) };
+		*segments.entry((e.directed, p1, p2, &e.discriminator)).or_default() += 1;
+		if e.directed {
+			degrees.entry(&e.p1).or_default().2 += 1;
+			degrees.entry(&e.p2).or_default().1 += 1;
+		} else {
+			degrees.entry(&e.p1).or_default().0 += 1;
+			degrees.entry(&e.p2).or_default().0 += 1;
+		}
+	}
+	issues.extend(segments.into_iter().filter(|(_, count)| *count > 1).map(|((_, p1, p2, discriminator), count)| DuplicateSegment { p1: p1.clone(), p2: p2.clone(), discriminator: discriminator.clone(), count }));
+	for n in &g.nodes.nodes {
+		let node = || n.id.clone();
+		let (lon, lat) = n.coordinates;
+		if !(lon.is_finite() && lat.is_finite()) {
+			issues.push(InvalidCoordinates { node: node(), coordinates: n.coordinates });
+		} else if lon.abs() > 180.0 || lat.abs() > 90.0 {
+			issues.push(OutOfRange { node: node(), coordinates: n.coordinates });
+		}
+		match degrees.get(&n.id).copied().unwrap_or_default() {
+			(0, 0, 0) => issues.push(Isolated { node: node() }),
+			(0, 0, _) => issues.push(OneWay { node: node(), sink: false }),
+			(0, _, 0) => issues.push(OneWay { node: node(), sink: true }),
+			(u, i, o) if u + i + o > MAX_DEGREE => issues.push(HighDegree { node: node(), degree: u + i + o }),
+			_ => (),
+		}
+	}
+	issues.extend(counts.iter().filter(|(_, c)| **c > 1).map(|(n, count)| DuplicateNode { node: (*n).clone(), count: *count }));
+	// connected components of the nodes with segments, ignoring directions
+	let index: HashMap<&NodeId, usize> = counts.keys().copied().filter(|n| degrees.contains_key(n)).enumerate().map(|(i, n)| (n, i)).collect();
+	let mut parents: Vec<usize> = (0..index.len()).collect();
+	fn root(parents: &mut [usize], mut i: usize) -> usize {
+		while parents[i] != i {
+			parents[i] = parents[parents[i]];
+			i = parents[i];
+		}
+		i
+	}
+	for e in &g.roads {
+		if let (Some(u), Some(v)) = (index.get(&e.p1), index.get(&e.p2)) {
+			let (u, v) = (root(&mut parents, *u), root(&mut parents, *v));
+			parents[u] = v;
+		}
+	}
+	let mut components: IndexMap<usize, (usize, &NodeId)> = IndexMap::new();
+	for n in counts.keys().filter(|n| index.contains_key(*n)) {
+		let r = root(&mut parents, index[n]);
+		components.entry(r).or_insert((0, n)).0 += 1;
+	}
+	let mut components: Vec<_> = components.into_values().collect();
+	components.sort_by_key(|(size, _)| std::cmp::Reverse(*size));
+	issues.extend(components.into_iter().skip(1).map(|(nodes, example)| Disconnected { nodes, example: example.clone() }));
+	issues
+}
+
+/// A road graph element, as streamed by [`RoadGraph::stream`]
+#[derive(Clone, PartialEq, Debug)]
+pub enum RoadGraphElement {
+	Segment(RoadSegment),
+	Node(Node),
+}
+
+impl RoadGraph {
+	/// Reads a JSON road graph off a reader, element by element
+	///
+	/// Deserializing it whole takes the text, then holds every node as a generic value until the end of the graph (they're flattened into it); this only ever holds the graph itself.
+	pub fn from_reader(reader: impl std::io::Read) -> serde_json::Result<Self> {
+		let mut roads = RoadGraph { roads: vec![], nodes: RoadGraphNodes { nodes: vec![] } };
+		Self::stream(reader, |e| match e {
+			RoadGraphElement::Segment(e) => roads.roads.push(e),
+			RoadGraphElement::Node(n) => roads.nodes.nodes.push(n),
+		})?;
+		Ok(roads)
+	}
+	/// Streams the segments and nodes of a JSON road graph to `f` as they're read - to build something else than a road graph out of a city-scale one
+	pub fn stream(reader: impl std::io::Read, f: impl FnMut(RoadGraphElement)) -> serde_json::Result<()> {
+		let mut de = serde_json::Deserializer::from_reader(std::io::BufReader::new(reader));
+		(&mut de).deserialize_map(StreamVisitor(f))?;
+		de.end()
+	}
+}
+
+/// Visits a road graph object, streaming its elements
+struct StreamVisitor<F>(F);
+
+impl<'de, F: FnMut(RoadGraphElement)> de::Visitor<'de> for StreamVisitor<F> {
+	type Value = ();
+	fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.write_str("a road graph")
+	}
+	fn visit_map<A: de::MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+		let (mut roads, mut nodes) = (false, false);
+		while let Some(key) = map.next_key::<String>()? {
+			match key.as_str() {
+				"roads" => {
+					map.next_value_seed(StreamElements(&mut self.0, RoadGraphElement::Segment))?;
+					roads = true;
+				},
+				"nodes" => {
+					map.next_value_seed(StreamElements(&mut self.0, RoadGraphElement::Node))?;
+					nodes = true;
+				},
+				_ => {
+					map.next_value::<de::IgnoredAny>()?;
+				},
+			}
+		}
+		if !roads {
+			return Err(de::Error::missing_field("roads"));
+		}
+		if !nodes {
+			return Err(de::Error::missing_field("nodes"));
+		}
+		Ok(())
+	}
+}
+
+/// Visits an array of road graph elements, streaming them
+struct StreamElements<'f, F, T>(&'f mut F, fn(T) -> RoadGraphElement);
+
+impl<'de, F: FnMut(RoadGraphElement), T: Deserialize<'de>> de::DeserializeSeed<'de> for StreamElements<'_, F, T> {
+	type Value = ();
+	fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+		deserializer.deserialize_seq(self)
+	}
+}
+
+impl<'de, F: FnMut(RoadGraphElement), T: Deserialize<'de>> de::Visitor<'de> for StreamElements<'_, F, T> {
+	type Value = ();
+	fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.write_str("an array")
+	}
+	fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+		while let Some(e) = seq.next_element::<T>()? {
+			(self.0)(self.1(e));
+		}
+		Ok(())
+	}
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct RoadGraphNodes {
 	pub nodes: Vec<Node>,
 }
 
 impl RoadGraphNodes {
 	/// Locates a location to the node on the graph
-	pub fn locate(&self, l: &Location) -> Option<NodeId> {
+	pub fn locate(&self, l: &Location, metric: Metric) -> Option<NodeId> {
 		match l {
-			Location::Coordinates(lon, lat) => self.nodes.iter().min_by_key(|Node {coordinates, ..}| n64((*lon, *lat).distance(coordinates))).map(|n| n.id.clone()),
+			Location::Coordinates(lon, lat) => self.nodes.iter().min_by_key(|Node {coordinates, ..}| n64(metric.distance((*lon, *lat), *coordinates))).map(|n| n.id.clone()),
 			Location::Node(n) => Some(n.clone()),
+			Location::Depot { .. } => None,
 		}
 	}
 	/// Locates a location to geographical coordinates
+	///
+	/// Depot references are to be [resolved](VehiclesConfiguration::resolve) first.
 	pub fn dislocate(&self, l: &Location) -> geo::Geometry<f64> {
 		match l {
 			Location::Coordinates(lon, lat) => geo::Point::from((*lon, *lat)).into(),
 			Location::Node(nid) => self.nodes.iter().find(|n| &n.id == nid).unwrap().into(),
+			Location::Depot { depot } => panic!("unresolved reference to depot {}", depot),
 		}
 	}
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum Location {
 	Coordinates(f64, f64),
 	Node(NodeId),
+	/// A depot of the vehicles configuration, by id (see [`VehiclesConfiguration::resolve`])
+	Depot {
+		depot: DepotId,
+	},
 }
 
 pub type Drones = Vec<Location>;
 
+/// A sensitive site drones keep clear of, e.g. a school or a hospital
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SensitiveSite {
+	#[serde(default, alias="id")]
+	pub name: Option<String>,
+	/// Outline (polygon exterior ring of coordinates)
+	pub region: Vec<(f64, f64)>,
+	/// Clearance kept around the outline, in meters
+	#[serde(default)]
+	pub buffer: f64,
+}
+
+impl SensitiveSite {
+	/// Whether a straight flight between 2 coordinates comes within the buffer of the site
+	pub fn crossed_by(&self, c1: (f64, f64), c2: (f64, f64)) -> bool {
+		use geo::euclidean_distance::EuclideanDistance;
+		let origin = match self.region.first() {
+			Some(c) => *c,
+			None => return false,
+		};
+		let project = |c| project(origin, c);
+		let region = geo::Polygon::new(self.region.iter().copied().map(project).collect::<Vec<_>>().into(), vec![]);
+		geo::Line::new(project(c1), project(c2)).euclidean_distance(&region) <= self.buffer
+	}
+}
+
+/// A flight across a sensitive site
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SiteCrossing {
+	/// Name of the site, its index among the sites if unnamed
+	pub site: String,
+	pub p1: NodeId,
+	pub p2: NodeId,
+	pub discriminator: Option<NodeId>,
+}
+
+/// Stable vehicle identifier
+pub type VehicleId = String;
+
+/// Where a vehicle should finish its route
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum RouteEnd {
+	/// back where it started
+	#[default]
+	#[serde(rename="start")]
+	Start,
+	/// wherever the last segment to clear is
+	#[serde(rename="anywhere")]
+	Anywhere,
+	/// at a specific location
+	#[serde(rename="location")]
+	Location(Location),
+	/// anywhere within a region (polygon exterior ring of coordinates)
+	#[serde(rename="region")]
+	Region(Vec<(f64, f64)>),
+}
+/// Part of the road graph a vehicle can't enter
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Exclusion {
+	/// all segments touching a node within a region (polygon exterior ring of coordinates)
+	#[serde(rename="region")]
+	Region(Vec<(f64, f64)>),
+	/// a specific road segment
+	#[serde(rename="segment")]
+	Segment {
+		p1: NodeId,
+		p2: NodeId,
+		#[serde(default)]
+		discriminator: Option<NodeId>,
+	},
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VehicleSpec {
+	#[serde(default, alias="name")]
+	pub id: Option<VehicleId>,
+	pub location: Location,
+	#[serde(default)]
+	pub end: RouteEnd,
+	#[serde(default)]
+	pub exclude: Vec<Exclusion>,
+	/// Surfaces the vehicle's equipment can clear, unspecified meaning all
+	#[serde(default)]
+	pub surfaces: Option<Vec<Surface>>,
+	#[serde(default)]
+	pub breaks: Option<BreakRule>,
+	/// Steepest grade (either way) the vehicle can clear, unspecified meaning any
+	#[serde(default)]
+	pub max_grade: Option<f64>,
+	/// Spreader load (e.g. kilograms of salt) or fuel, unspecified meaning it never runs out
+	#[serde(default)]
+	pub capacity: Option<f64>,
+	/// What the capacity is of (e.g. `salt`), for the depots to have it - any depot does, unspecified
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub resource: Option<Resource>,
+	/// Driving speed (distance per time unit) the vehicle's schedule is estimated at, instead of the `speeds` meta parameter
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub speed: Option<f64>,
+}
+
+/// A mandatory break rule, e.g. 30 minutes every 4 hours
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BreakRule {
+	/// Longest time on the route without a break, in seconds
+	pub every: f64,
+	/// Break duration, in seconds
+	pub duration: f64,
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum Vehicle {
+	Spec(VehicleSpec),
+	Location(Location),
+}
+impl Vehicle {
+	/// Identifier of the vehicle, defaulting to its (stringified) index in the configuration
+	pub fn id(&self, index: usize) -> VehicleId {
+		match self {
+			Self::Spec(VehicleSpec { id: Some(id), .. }) => id.clone(),
+			_ => index.to_string(),
+		}
+	}
+	/// Starting location
+	pub fn location(&self) -> &Location {
+		match self {
+			Self::Spec(v) => &v.location,
+			Self::Location(l) => l,
+		}
+	}
+	/// Route end preference
+	pub fn end(&self) -> RouteEnd {
+		match self {
+			Self::Spec(v) => v.end.clone(),
+			Self::Location(_) => RouteEnd::Start,
+		}
+	}
+	/// Parts of the road graph the vehicle can't enter
+	pub fn exclusions(&self) -> &[Exclusion] {
+		match self {
+			Self::Spec(v) => &v.exclude,
+			Self::Location(_) => &[],
+		}
+	}
+	/// Mandatory break rule, if any
+	pub fn breaks(&self) -> Option<BreakRule> {
+		match self {
+			Self::Spec(v) => v.breaks,
+			Self::Location(_) => None,
+		}
+	}
+	/// Whether the vehicle can clear a segment of the given surface
+	pub fn clears(&self, surface: Option<&Surface>) -> bool {
+		match (self, surface) {
+			(Self::Spec(VehicleSpec { surfaces: Some(ss), .. }), Some(s)) => ss.contains(s),
+			_ => true,
+		}
+	}
+	/// Spreader load, if limited
+	pub fn capacity(&self) -> Option<f64> {
+		match self {
+			Self::Spec(v) => v.capacity,
+			Self::Location(_) => None,
+		}
+	}
+	/// What the capacity is of, if specified
+	pub fn resource(&self) -> Option<&Resource> {
+		match self {
+			Self::Spec(v) => v.resource.as_ref(),
+			Self::Location(_) => None,
+		}
+	}
+	/// Driving speed, if its own
+	pub fn speed(&self) -> Option<f64> {
+		match self {
+			Self::Spec(v) => v.speed,
+			Self::Location(_) => None,
+		}
+	}
+	/// Whether the vehicle can clear a segment of the given grade
+	pub fn climbs(&self, grade: Option<f64>) -> bool {
+		match (self, grade) {
+			(Self::Spec(VehicleSpec { max_grade: Some(max), .. }), Some(g)) => g.abs() <= *max,
+			_ => true,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct VehiclesConfiguration {
-	pub road: Vec<Location>,
-	pub sidewalk: Vec<Location>,
+	pub road: Vec<Vehicle>,
+	pub sidewalk: Vec<Vehicle>,
+	/// Yards vehicles are based at, and where those with a capacity refill (see [`crate::meta::Parameters::consumption`])
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "Vec<AnyDepot>"))]
+	pub depots: Vec<Depot>,
+}
+
+impl VehiclesConfiguration {
+	/// The configuration with the vehicles' references to depots (as their start or end location) replaced by the depots' locations
+	///
+	/// Fails on references to depots not in the configuration, or that vehicles can't be based at.
+	pub fn resolve(&self) -> Result<Self, Error> {
+		let ids: Vec<_> = self.depots.iter().enumerate().map(|(i, d)| d.id(i)).collect();
+		if let Some(i) = self.depots.iter().position(|d| matches!(d.location, Location::Depot { .. })) {
+			return Err(format!("Depot {} is located at another depot", ids[i]).into());
+		}
+		let location = |l: &Location| match l {
+			Location::Depot { depot } => match ids.iter().position(|id| id == depot).map(|i| &self.depots[i]) {
+				Some(d) if d.bases() => Ok(d.location.clone()),
+				Some(_) => Err(format!("Vehicles can't be based at depot {}", depot)),
+				None => Err(format!("Unknown depot {}", depot)),
+			},
+			l => Ok(l.clone()),
+		};
+		let resolve = |vs: &[Vehicle]| vs.iter().map(|v| Ok(match v {
+			Vehicle::Location(l) => Vehicle::Location(location(l)?),
+			Vehicle::Spec(spec) => Vehicle::Spec(VehicleSpec {
+				location: location(&spec.location)?,
+				end: match &spec.end {
+					RouteEnd::Location(l) => RouteEnd::Location(location(l)?),
+					end => end.clone(),
+				},
+				..spec.clone()
+			}),
+		})).collect::<Result<Vec<_>, String>>();
+		Ok(Self { road: resolve(&self.road)?, sidewalk: resolve(&self.sidewalk)?, depots: self.depots.clone() })
+	}
+	/// Depots a vehicle can refill at - those that refill at all and have its resource
+	pub fn refills<'s>(&'s self, vehicle: &'s Vehicle) -> impl Iterator<Item = &'s Depot> {
+		self.depots.iter().filter(move |d| d.refills(vehicle.resource()))
+	}
+}
+
+pub type DepotId = String;
+pub type Resource = String;
+
+/// A depot - a yard vehicles are based at and refill at
+///
+/// A bare location is a depot too, doing everything at all hours.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Depot {
+	/// Identifier vehicles reference the depot by (`{"depot": ...}` locations), defaulting to its (stringified) index in the configuration
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub id: Option<DepotId>,
+	pub location: Location,
+	/// What the depot is for, unspecified meaning everything
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub capabilities: Option<Vec<Capability>>,
+	/// Resources vehicles refill there (see [`VehicleSpec::resource`]), unspecified meaning any
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub resources: Option<Vec<Resource>>,
+	/// Opening hours - `(start, end)` windows, in time units since the start of the plan (as schedules estimate them, see [`crate::timeline::schedule`]) - open at all hours without
+	#[serde(default, skip_serializing_if="Vec::is_empty")]
+	pub hours: Vec<(f64, f64)>,
+}
+
+/// What a depot is for
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all="lowercase")]
+pub enum Capability {
+	/// basing vehicles - starting and ending routes
+	Base,
+	/// refilling vehicles with a capacity
+	Refill,
+}
+
+impl Capability {
+	pub const NAMES: [&'static str; 2] = ["base", "refill"];
+}
+
+/// What a depot deserializes from, for its schema - a spec, or a bare location
+#[cfg(feature = "schema")]
+#[derive(schemars::JsonSchema)]
+#[serde(untagged)]
+#[allow(dead_code)]
+enum AnyDepot {
+	Spec(Depot),
+	Location(Location),
+}
+
+impl<'de> Deserialize<'de> for Depot {
+	fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+		#[derive(Deserialize)]
+		struct Spec {
+			#[serde(default, alias="name")]
+			id: Option<DepotId>,
+			location: Location,
+			#[serde(default)]
+			capabilities: Option<Vec<Capability>>,
+			#[serde(default)]
+			resources: Option<Vec<Resource>>,
+			#[serde(default)]
+			hours: Vec<(f64, f64)>,
+		}
+		#[derive(Deserialize)]
+		#[serde(untagged)]
+		enum AnyDepot {
+			Spec(Spec),
+			Location(Location),
+		}
+		Ok(match AnyDepot::deserialize(d)? {
+			AnyDepot::Spec(Spec { id, location, capabilities, resources, hours }) => Self { id, location, capabilities, resources, hours },
+			AnyDepot::Location(location) => location.into(),
+		})
+	}
+}
+
+impl From<Location> for Depot {
+	fn from(location: Location) -> Self {
+		Self { id: None, location, capabilities: None, resources: None, hours: vec![] }
+	}
+}
+
+impl Depot {
+	/// Identifier of the depot, defaulting to its (stringified) index in the configuration
+	pub fn id(&self, index: usize) -> DepotId {
+		self.id.clone().unwrap_or_else(|| index.to_string())
+	}
+	fn can(&self, c: Capability) -> bool {
+		self.capabilities.as_ref().is_none_or(|cs| cs.contains(&c))
+	}
+	/// Whether vehicles can be based at the depot
+	pub fn bases(&self) -> bool {
+		self.can(Capability::Base)
+	}
+	/// Whether vehicles of a resource (any, if unspecified) can refill at the depot
+	pub fn refills(&self, resource: Option<&Resource>) -> bool {
+		self.can(Capability::Refill) && match (&self.resources, resource) {
+			(Some(rs), Some(r)) => rs.contains(r),
+			_ => true,
+		}
+	}
+	/// Whether the depot is open at a time (since the start of the plan)
+	pub fn open(&self, at: f64) -> bool {
+		self.hours.is_empty() || self.hours.iter().any(|(start, end)| *start <= at && at <= *end)
+	}
+	/// When the depot is next open from a time on - `None` if it doesn't open again
+	pub fn opens(&self, at: f64) -> Option<f64> {
+		if self.hours.is_empty() {
+			return Some(at);
+		}
+		self.hours.iter().filter(|(_, end)| at <= *end).map(|(start, _)| start.max(at)).min_by(|a, b| a.total_cmp(b))
+	}
+}
+
+/// Identifiers of vehicles, in order
+///
+/// Returns: the identifiers, or the first duplicated one
+pub fn vehicle_ids(vehicles: &[Vehicle]) -> Result<Vec<VehicleId>, VehicleId> {
+	let mut ids = indexmap::IndexSet::new();
+	for (i, v) in vehicles.iter().enumerate() {
+		let id = v.id(i);
+		if !ids.insert(id.clone()) {
+			return Err(id);
+		}
+	}
+	Ok(ids.into_iter().collect())
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct PathSegment {
 	pub node: NodeId,
 	pub discriminator: Option<NodeId>,
+	/// Whether the vehicle turns around at the node, back along the segment it came by
+	#[serde(default, skip_serializing_if="std::ops::Not::not")]
+	pub reversal: bool,
+	/// What the step onto the node is part of - only drones' paths are annotated
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub leg: Option<Leg>,
+}
+
+/// Part of a drone's flight a step is
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all="lowercase")]
+pub enum Leg {
+	/// From takeoff to the first corridor surveyed
+	Outbound,
+	/// Surveying corridors, and crossing between them
+	Survey,
+	/// Back home after the last corridor surveyed
+	Return,
+	/// A stopover at home between corridors surveyed
+	Recharge,
+}
+
+impl Leg {
+	pub const NAMES: [&'static str; 4] = ["outbound", "survey", "return", "recharge"];
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
@@ -104,15 +974,415 @@ pub struct SidewalkPathSegment {
 	pub side: Option<SidewalkSide>,
 }
 
-pub type Paths = Vec<Vec<PathSegment>>;
-pub type SidewalkPaths = Vec<Vec<SidewalkPathSegment>>;
+/// Per-vehicle sequences, keyed by vehicle id
+///
+/// Positional lists are also accepted, keyed by vehicle index.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(transparent)]
+pub struct Routes<S>(pub IndexMap<VehicleId, Vec<S>>);
+impl<S> Default for Routes<S> {
+	fn default() -> Self {
+		Self(IndexMap::new())
+	}
+}
+/// What routes deserialize from
+#[derive(Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+enum AnyRoutes<S> {
+	Keyed(IndexMap<VehicleId, Vec<S>>),
+	Positional(Vec<Vec<S>>),
+}
+impl<'de, S: Deserialize<'de>> Deserialize<'de> for Routes<S> {
+	fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+		Ok(match AnyRoutes::deserialize(d)? {
+			AnyRoutes::Keyed(r) => Self(r),
+			AnyRoutes::Positional(r) => r.into_iter().enumerate().map(|(i, r)| (i.to_string(), r)).collect(),
+		})
+	}
+}
+#[cfg(feature = "schema")]
+impl<S: schemars::JsonSchema> schemars::JsonSchema for Routes<S> {
+	fn schema_name() -> String {
+		format!("Routes_of_{}", S::schema_name())
+	}
+	fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+		AnyRoutes::<S>::json_schema(gen)
+	}
+}
+impl<S> FromIterator<(VehicleId, Vec<S>)> for Routes<S> {
+	fn from_iter<I: IntoIterator<Item = (VehicleId, Vec<S>)>>(iter: I) -> Self {
+		Self(iter.into_iter().collect())
+	}
+}
+
+pub type Paths = Routes<PathSegment>;
+pub type SidewalkPaths = Routes<SidewalkPathSegment>;
 
+/// A step of a vehicle's schedule - a path segment, with how far and when the vehicle gets to its node
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ScheduledSegment {
+	#[serde(flatten)]
+	pub segment: PathSegment,
+	/// Distance driven by the node
+	pub distance: f64,
+	/// Estimated arrival at the node, in time units since the plan starts
+	pub eta: f64,
+}
+
+pub type Schedules = Routes<ScheduledSegment>;
+
+impl Schedules {
+	/// The bare paths
+	pub fn paths(&self) -> Paths {
+		self.0.iter().map(|(id, s)| (id.clone(), s.iter().map(|s| s.segment.clone()).collect())).collect()
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SnowStatusElement {
+	#[serde(alias="from", alias="source")]
 	pub p1: NodeId,
+	#[serde(alias="to", alias="target")]
 	pub p2: NodeId,
 	pub discriminator: Option<NodeId>,
+	/// Which of the parallel segments (see [`RoadGraph::parallel_indices`]) is meant, if a specific one
+	#[serde(default)]
+	pub parallel_index: Option<usize>,
+	/// Snow depth - the forecast mean, for an uncertain forecast
+	#[serde(alias="snow", alias="snow_depth")]
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
 	pub depth: N64,
+	/// Variance of the forecast depth, if uncertain (see [`crate::meta::Parameters::risk_aversion`])
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<f64>"))]
+	pub variance: Option<N64>,
+	/// Whether the depth is interpolated from nearby samples, rather than sampled
+	#[serde(default, skip_serializing_if="std::ops::Not::not")]
+	pub estimated: bool,
 }
 
 pub type SnowStatuses = Vec<SnowStatusElement>;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct GpsFix {
+	pub coordinates: (f64, f64),
+	pub time: f64,
+}
+
+pub type GpsTraces = Routes<GpsFix>;
+
+/// A road segment closure
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Closure {
+	pub p1: NodeId,
+	pub p2: NodeId,
+	#[serde(default)]
+	pub discriminator: Option<NodeId>,
+	/// Partial closure (e.g. a lane closed) - the segment stays open, this many times longer to drive
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub slowdown: Option<f64>,
+}
+
+pub type Closures = Vec<Closure>;
+
+/// Dispatch's order to clear segments right away - "clear Main St NOW"
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PriorityOverride {
+	/// Segments to clear, given as closures are
+	pub segments: Vec<Closure>,
+	/// Time by which they should be cleared, in path costs from the start of the plan
+	#[serde(default)]
+	pub deadline: Option<f64>,
+}
+
+/// How a priority override was served
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Reprioritized {
+	/// Vehicle rerouted to clear the segments first
+	pub vehicle: VehicleId,
+	/// When the last of them is cleared, in its path costs
+	pub cleared: f64,
+	/// Whether that's past the deadline
+	pub late: bool,
+}
+
+/// How a plan was replanned for a snow update
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Replanned {
+	/// Segments snowy now that weren't before
+	pub snowed: usize,
+	/// Segments snowy before that aren't anymore
+	pub cleared: usize,
+	/// Vehicles whose routes were re-solved - the others were left as they are
+	pub vehicles: Vec<VehicleId>,
+}
+
+/// A segment plowed in echelon, by several vehicles together - e.g. a lane of a multi-lane highway each
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ConvoySegment {
+	pub p1: NodeId,
+	pub p2: NodeId,
+	#[serde(default)]
+	pub discriminator: Option<NodeId>,
+	/// Number of vehicles plowing it together
+	pub vehicles: usize,
+}
+
+pub type ConvoySegments = Vec<ConvoySegment>;
+
+/// Vehicles joining up to plow a run of convoy segments together, splitting afterwards
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Convoy {
+	/// Vehicle clearing the segments, that the others join
+	pub lead: VehicleId,
+	/// Vehicles joining the lead, in echelon behind it
+	pub members: Vec<VehicleId>,
+	/// Number of vehicles the segments take - more than the convoy has, if there weren't enough to join
+	pub required: usize,
+	/// The run of segments plowed together, from where the convoy forms to where it splits
+	pub nodes: Vec<NodeId>,
+	/// Time window of the run, in the lead's path costs - from when the last vehicle gets to where the convoy forms
+	pub start: f64,
+	pub end: f64,
+	/// How long the lead, then each member, waits there for the others - not part of the paths, which don't tell time
+	#[serde(default)]
+	pub waits: Vec<f64>,
+}
+
+/// How a set of closures affects a planned route
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RouteImpact {
+	/// Number of closed (or slowed) segment traversals in the planned route
+	pub closed: usize,
+	/// Planned route length
+	pub cost_before: f64,
+	/// Revised route length, if all closed segments could be detoured around
+	pub cost_after: Option<f64>,
+	/// `cost_after - cost_before`
+	pub detour: Option<f64>,
+}
+
+/// What a spreading route spreads, and where it reloads
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct SpreadReport {
+	/// Amount spread
+	pub spread: f64,
+	/// Amount that should have been spread, but the vehicle ran out
+	pub shortfall: f64,
+	/// Depots reloaded at, in order
+	pub reloads: Vec<NodeId>,
+}
+
+/// A chokepoint of the road graph - also a [`Closure`], for what-ifs
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct CriticalSegment {
+	pub p1: NodeId,
+	pub p2: NodeId,
+	pub discriminator: Option<NodeId>,
+	/// Share of the shortest paths between nodes of the segments to clear that go through the segment
+	pub betweenness: f64,
+	/// Whether the segment is to be cleared itself
+	pub snowy: bool,
+	/// Whether there's no way around the segment when it's closed
+	pub cut: bool,
+}
+
+/// A snowy segment cleared in more than one merged plan, handed off to a single vehicle
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Handoff {
+	pub p1: NodeId,
+	pub p2: NodeId,
+	pub discriminator: Option<NodeId>,
+	/// Vehicle keeping the segment - of the cheapest route
+	pub to: VehicleId,
+	/// Vehicles no longer clearing the segment
+	pub from: Vec<VehicleId>,
+}
+
+/// A segment boosted for de-icing (see [`crate::meta::Deicing`]), and when a plan clears it
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct BoostedSegment {
+	pub p1: NodeId,
+	pub p2: NodeId,
+	pub discriminator: Option<NodeId>,
+	pub bridge: bool,
+	pub shaded: bool,
+	pub boost: N64,
+	/// Vehicle clearing it, if any
+	pub vehicle: Option<VehicleId>,
+	/// Cost of the vehicle's path up to clearing it
+	pub cleared: Option<N64>,
+}
+
+/// A snowy priority segment (see [`crate::meta::Deadlines`]), and when a plan clears it
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PrioritySegment {
+	pub p1: NodeId,
+	pub p2: NodeId,
+	pub discriminator: Option<NodeId>,
+	pub priority: u32,
+	/// Deadline of its level, if any
+	pub deadline: Option<N64>,
+	/// Vehicle clearing it, if any
+	pub vehicle: Option<VehicleId>,
+	/// Cost of the vehicle's path up to clearing it
+	pub cleared: Option<N64>,
+	/// Whether it's cleared past the deadline, or not at all
+	pub late: bool,
+}
+
+/// 64-bit FNV-1a - stable across platforms and Rust versions, unlike the std hashers
+fn fnv1a(bytes: &[u8]) -> u64 {
+	bytes.iter().fold(0xcbf29ce484222325, |h, b| (h ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_municipal_aliases(){
+		let g: RoadGraph = serde_json::from_str(r#"{"nodes": [{"node_id": "a", "coords": [0, 0]}, {"id": "b", "coordinates": [1, 0]}], "roads": [{"from": "a", "to": "b", "one_way": true, "length": 5}]}"#).unwrap();
+		assert_eq!((&g.roads[0].p1, &g.roads[0].p2, g.roads[0].directed, g.roads[0].distance, g.roads[0].sidewalks), (&NodeId::from("a"), &NodeId::from("b"), true, n64(5.0), (false, false)));
+		assert_eq!(g.nodes.nodes[0].id, NodeId::from("a"));
+		let snow: SnowStatuses = serde_json::from_str(r#"[{"source": "a", "target": "b", "snow_depth": 2}]"#).unwrap();
+		assert_eq!(snow[0].depth, n64(2.0));
+		assert!(g.check().is_ok());
+		let g: RoadGraph = serde_json::from_str(r#"{"nodes": [{"id": "a", "coordinates": [0, 0]}], "roads": [{"p1": "a", "p2": "b", "directed": false, "distance": 5}]}"#).unwrap();
+		assert_eq!(g.check().unwrap_err().to_string(), "Segment a<->b references node b not in the road graph");
+		let g: RoadGraph = serde_json::from_str(r#"{"nodes": [{"id": 1, "coordinates": [0, 0]}, {"id": "1", "coordinates": [1, 0]}], "roads": [{"p1": 1, "p2": "1", "directed": false, "distance": 5}]}"#).unwrap();
+		assert_eq!((&g.roads[0].p1, &g.roads[0].p2), (&NodeId::Int(1), &NodeId::from("1")));
+		assert!(g.check().is_ok());
+		assert_eq!(serde_json::to_string(&g.nodes.nodes[0].id).unwrap(), "1");
+	}
+
+	#[test]
+	fn test_node_ids(){
+		// integers and names round-trip as they are, `1` and `"1"` staying apart
+		let ids: Vec<NodeId> = serde_json::from_str(r#"[1, "1", 18446744073709551615, "a", ""]"#).unwrap();
+		assert_eq!(ids, vec![NodeId::Int(1), NodeId::from("1"), NodeId::Int(u64::MAX), NodeId::from("a"), NodeId::from("")]);
+		assert_eq!(serde_json::to_string(&ids).unwrap(), r#"[1,"1",18446744073709551615,"a",""]"#);
+		assert_ne!(ids[0], ids[1]);
+		assert_eq!(ids.iter().collect::<std::collections::HashSet<_>>().len(), 5);
+		assert_eq!((ids[0].to_string(), ids[1].to_string()), ("1".to_string(), "1".to_string()));
+		assert_eq!((ids[0].name(), ids[1].name()), (None, Some("1")));
+		assert!(matches!(NodeId::from("a"), NodeId::Name(std::borrow::Cow::Borrowed("a"))));
+		assert!(serde_json::from_str::<NodeId>("-1").is_err());
+		assert!(serde_json::from_str::<NodeId>("1.5").is_err());
+		// through plans and streamed road graphs too
+		let plan: Paths = serde_json::from_str(r#"{"t": [{"node": 1, "discriminator": "1"}, {"node": "1", "discriminator": null}]}"#).unwrap();
+		assert_eq!((&plan.0["t"][0].node, &plan.0["t"][0].discriminator, &plan.0["t"][1].node), (&NodeId::Int(1), &Some(NodeId::from("1")), &NodeId::from("1")));
+		assert_eq!(serde_json::from_value::<Paths>(serde_json::to_value(&plan).unwrap()).unwrap(), plan);
+		let g = RoadGraph::from_reader(r#"{"nodes": [{"id": "1", "coordinates": [0, 0]}, {"id": 2, "coordinates": [1, 0]}], "roads": [{"p1": 1, "p2": 2, "distance": 5}]}"#.as_bytes()).unwrap();
+		assert_eq!(g.nodes.nodes.iter().map(|n| n.id.clone()).collect::<Vec<_>>(), vec![NodeId::from("1"), NodeId::Int(2)]);
+		assert_eq!(g.check().unwrap_err().to_string(), "Segment 1<->2 references node 1 not in the road graph");
+	}
+
+	#[test]
+	fn test_validate(){
+		let g: RoadGraph = serde_json::from_str(r#"{"nodes": [{"id": "a", "coordinates": [0, 0]}, {"id": "b", "coordinates": [1, 0]}, {"id": "c", "coordinates": [2, 0]}, {"id": "d", "coordinates": [0, 1]}, {"id": "e", "coordinates": [0, 100]}, {"id": "e", "coordinates": [0, 2]}, {"id": "f", "coordinates": [5, 5]}],
+			"roads": [{"p1": "a", "p2": "b", "distance": 5}, {"p1": "b", "p2": "a", "distance": 5}, {"p1": "b", "p2": "c", "directed": true, "distance": 0}, {"p1": "d", "p2": "e", "distance": 1}, {"p1": "d", "p2": "x", "distance": 1}]}"#).unwrap();
+		let id = |n: &'static str| NodeId::from(n);
+		assert_eq!(validate(&g), vec![
+			ValidationIssue::ZeroLength { p1: id("b"), p2: id("c"), discriminator: None },
+			ValidationIssue::MissingNode { p1: id("d"), p2: id("x"), discriminator: None, node: id("x") },
+			ValidationIssue::DuplicateSegment { p1: id("a"), p2: id("b"), discriminator: None, count: 2 },
+			ValidationIssue::OneWay { node: id("c"), sink: true },
+			ValidationIssue::OutOfRange { node: id("e"), coordinates: (0.0, 100.0) },
+			ValidationIssue::Isolated { node: id("f") },
+			ValidationIssue::DuplicateNode { node: id("e"), count: 2 },
+			ValidationIssue::Disconnected { nodes: 2, example: id("d") },
+		]);
+		assert_eq!(g.check().unwrap_err().to_string(), "Segment d<->x references node x not in the road graph");
+		let mut g = RoadGraph { roads: g.roads[..2].to_vec(), nodes: RoadGraphNodes { nodes: g.nodes.nodes[..2].to_vec() } };
+		assert_eq!(validate(&g).len(), 1);
+		g.nodes.nodes[0].coordinates.0 = f64::NAN;
+		assert_eq!(g.check().unwrap_err().to_string(), "Node a has invalid coordinates (NaN, 0.0)");
+	}
+
+	#[test]
+	fn test_from_reader(){
+		let json = r#"{"nodes": [{"node_id": "a", "coords": [0, 0]}, {"id": 2, "coordinates": [1, 0]}], "name": {"city": "?"}, "roads": [{"from": "a", "to": 2, "length": 5, "properties": {"ward": 3}}]}"#;
+		let g = RoadGraph::from_reader(json.as_bytes()).unwrap();
+		let expected: RoadGraph = serde_json::from_str(json).unwrap();
+		assert_eq!((&g.roads, &g.nodes.nodes), (&expected.roads, &expected.nodes.nodes));
+		let mut elements = vec![];
+		RoadGraph::stream(json.as_bytes(), |e| elements.push(e)).unwrap();
+		assert!(matches!(&elements[..], [RoadGraphElement::Node(_), RoadGraphElement::Node(_), RoadGraphElement::Segment(_)]));
+		assert!(RoadGraph::from_reader(r#"{"roads": []}"#.as_bytes()).unwrap_err().to_string().starts_with("missing field `nodes`"));
+		assert!(RoadGraph::from_reader(r#"{"roads": [], "nodes": []} []"#.as_bytes()).is_err());
+	}
+
+	#[test]
+	fn test_content_hash(){
+		let g: RoadGraph = serde_json::from_str(r#"{"nodes": [{"id": "a", "coordinates": [0, 0]}, {"id": "b", "coordinates": [1, 0]}], "roads": [{"p1": "a", "p2": "b", "directed": false, "distance": 5}, {"p1": "b", "p2": "a", "directed": true, "distance": 5}]}"#).unwrap();
+		let mut shuffled = g.clone();
+		shuffled.roads.reverse();
+		shuffled.nodes.nodes.reverse();
+		shuffled.roads[0].properties.insert("ward".into(), 3.into());
+		assert_eq!(g.content_hash(), shuffled.content_hash());
+		shuffled.roads[0].distance = n64(6.0);
+		assert_ne!(g.content_hash(), shuffled.content_hash());
+	}
+
+	#[test]
+	fn test_interpolate_snow(){
+		let road = |p1: &'static str, p2: &'static str| RoadSegment::new(p1, p2, 10.0);
+		let g = RoadGraph {
+			roads: vec![road("a", "b"), road("b", "c"), road("c", "d"), road("d", "e"), road("e", "f")],
+			nodes: RoadGraphNodes { nodes: vec![] },
+		};
+		let sample = |p1: &'static str, p2: &'static str, depth: f64| SnowStatusElement { p1: p1.into(), p2: p2.into(), discriminator: None, parallel_index: None, depth: n64(depth), variance: None, estimated: false };
+		let snow = g.interpolate_snow(&vec![sample("b", "a", 3.0), sample("d", "e", 6.0)], 2);
+		let depths: Vec<_> = snow.iter().map(|s| (s.p1.name().unwrap(), s.p2.name().unwrap(), s.depth.raw(), s.estimated)).collect();
+		assert_eq!(depths, vec![("b", "a", 3.0, false), ("d", "e", 6.0, false), ("b", "c", 4.0, true), ("c", "d", 5.0, true), ("e", "f", 6.0, true)]);
+	}
+
+	#[test]
+	fn test_metric(){
+		// at 60°N a degree of longitude is half one of latitude
+		let nodes = RoadGraphNodes { nodes: vec![
+			Node { id: "north".into(), coordinates: (0.0, 60.01), elevation: None },
+			Node { id: "east".into(), coordinates: (0.015, 60.0), elevation: None },
+		] };
+		let at = Location::Coordinates(0.0, 60.0);
+		assert_eq!(nodes.locate(&at, Metric::Planar), Some("north".into()));
+		assert_eq!(nodes.locate(&at, Metric::Geodesic), Some("east".into()));
+		assert_eq!(nodes.locate(&at, Metric::Projected), Some("east".into()));
+		let (c1, c2) = ((-73.56, 45.5), (-73.55, 45.51));
+		assert!((Metric::Projected.distance(c1, c2) / Metric::Geodesic.distance(c1, c2) - 1.0).abs() < 0.005);
+		assert_eq!("Projected".parse::<Metric>(), Ok(Metric::Projected));
+	}
+
+	#[test]
+	fn test_crossed_by(){
+		// about 11m square
+		let site = SensitiveSite { name: Some("school".into()), region: vec![(0.0, 0.0), (0.0001, 0.0), (0.0001, 0.0001), (0.0, 0.0001)], buffer: 10.0 };
+		assert!(site.crossed_by((-0.001, 0.00005), (0.001, 0.00005)));
+		// passing 15m away, then 5m away
+		assert!(!site.crossed_by((-0.001, 0.00025), (0.001, 0.00025)));
+		assert!(site.crossed_by((-0.001, 0.00015), (0.001, 0.00015)));
+		assert!(!SensitiveSite { buffer: 0.0, ..site }.crossed_by((-0.001, 0.00015), (0.001, 0.00015)));
+	}
+
+	#[test]
+	fn test_depots(){
+		let vc: VehiclesConfiguration = serde_json::from_str(r#"{"road": [], "sidewalk": [{"location": {"depot": "north"}, "end": {"location": {"depot": "north"}}, "resource": "brine"}, "b"], "depots": [{"name": "north", "location": "a", "capabilities": ["base"]}, {"location": [1, 0], "resources": ["salt"], "hours": [[0, 100]]}, "c"]}"#).unwrap();
+		assert_eq!(vc.depots[2], Depot::from(Location::Node("c".into())));
+		let resolved = vc.resolve().unwrap();
+		assert_eq!((resolved.sidewalk[0].location(), resolved.sidewalk[0].end()), (&Location::Node("a".into()), RouteEnd::Location(Location::Node("a".into()))));
+		assert_eq!(resolved.sidewalk[1], vc.sidewalk[1]);
+		// the brine truck can't refill at the base-only nor at the salt depot, the other one anywhere
+		assert_eq!(vc.refills(&vc.sidewalk[0]).map(|d| d.location.clone()).collect::<Vec<_>>(), vec![Location::Node("c".into())]);
+		assert_eq!(vc.refills(&vc.sidewalk[1]).count(), 2);
+		assert!(vc.depots[1].open(50.0) && !vc.depots[1].open(150.0) && vc.depots[2].open(150.0));
+		assert_eq!((vc.depots[1].opens(50.0), vc.depots[1].opens(150.0), vc.depots[2].opens(150.0)), (Some(50.0), None, Some(150.0)));
+		let based = |depot: &str| VehiclesConfiguration { sidewalk: vec![Vehicle::Location(Location::Depot { depot: depot.into() })], ..vc.clone() }.resolve();
+		assert_eq!(based("1").unwrap(), VehiclesConfiguration { sidewalk: vec![Vehicle::Location(Location::Coordinates(1.0, 0.0))], ..vc.clone() });
+		assert_eq!(based("south").unwrap_err().to_string(), "Unknown depot south");
+		assert!(VehiclesConfiguration { depots: vec![Depot { capabilities: Some(vec![Capability::Refill]), ..vc.depots[0].clone() }], ..vc.clone() }.resolve().unwrap_err().to_string().contains("can't be based"));
+	}
+}
@@ -0,0 +1,136 @@
+//! Errors of the public entry points
+//!
+//! A message alone doesn't say which node of a graph of thousands is at fault - errors carry the node, segment, vehicle or file they're about, so callers can point at it (or skip it and retry).
+
+use crate::{config::ConfigError, data::{NodeId, VehicleId, ValidationIssue}};
+
+/// Why a load, solve or edit failed
+#[derive(Debug)]
+pub enum Error {
+	/// A config file didn't parse
+	Config(ConfigError),
+	/// A file couldn't be read or written
+	Io { file: Option<String>, source: std::io::Error },
+	/// The road graph doesn't hold together - a segment references a missing node, say
+	Validation(ValidationIssue),
+	/// Something is wrong with a node - missing from the graph, say
+	Node { id: NodeId, message: String },
+	/// Something is wrong with a segment
+	Segment { p1: NodeId, p2: NodeId, discriminator: Option<NodeId>, message: String },
+	/// Something is wrong with a vehicle
+	Vehicle { id: VehicleId, message: String },
+	/// Segments no vehicle can clear
+	Unreachable { segments: Vec<(NodeId, NodeId)>, message: String },
+	/// The experiment results database failed
+	#[cfg(feature = "sqlite")]
+	Database(rusqlite::Error),
+	/// Anything else - bad parameters, inconsistent inputs
+	Invalid(String),
+}
+
+impl Error {
+	/// An I/O error with a file
+	pub fn io(file: impl ToString, source: std::io::Error) -> Self {
+		Error::Io { file: Some(file.to_string()), source }
+	}
+	pub fn node(id: &NodeId, message: impl ToString) -> Self {
+		Error::Node { id: id.clone(), message: message.to_string() }
+	}
+	pub fn segment(p1: &NodeId, p2: &NodeId, discriminator: &Option<NodeId>, message: impl ToString) -> Self {
+		Error::Segment { p1: p1.clone(), p2: p2.clone(), discriminator: discriminator.clone(), message: message.to_string() }
+	}
+	pub fn vehicle(id: &str, message: impl ToString) -> Self {
+		Error::Vehicle { id: id.to_string(), message: message.to_string() }
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Error::Config(e) => write!(f, "{}", e),
+			Error::Validation(i) => write!(f, "{}", i),
+			Error::Io { file: Some(file), source } => write!(f, "{}: {}", file, source),
+			Error::Io { file: None, source } => write!(f, "{}", source),
+			Error::Node { id, message } => write!(f, "Node {}: {}", id, message),
+			Error::Segment { p1, p2, discriminator: None, message } => write!(f, "Segment {}<->{}: {}", p1, p2, message),
+			Error::Segment { p1, p2, discriminator: Some(d), message } => write!(f, "Segment {}<->{} ({}): {}", p1, p2, d, message),
+			Error::Vehicle { id, message } => write!(f, "Vehicle {}: {}", id, message),
+			Error::Unreachable { segments, message } => {
+				write!(f, "{} segments {}", segments.len(), message)?;
+				for (i, (p1, p2)) in segments.iter().take(10).enumerate() {
+					write!(f, "{}{}<->{}", if i == 0 { ": " } else { ", " }, p1, p2)?;
+				}
+				if segments.len() > 10 { write!(f, ", …")?; }
+				Ok(())
+			},
+			#[cfg(feature = "sqlite")]
+			Error::Database(e) => write!(f, "Results database: {}", e),
+			Error::Invalid(message) => write!(f, "{}", message),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Config(e) => Some(e),
+			Error::Io { source, .. } => Some(source),
+			#[cfg(feature = "sqlite")]
+			Error::Database(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+impl From<ConfigError> for Error {
+	fn from(e: ConfigError) -> Self {
+		Error::Config(e)
+	}
+}
+
+impl From<std::io::Error> for Error {
+	fn from(source: std::io::Error) -> Self {
+		Error::Io { file: None, source }
+	}
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+	fn from(e: rusqlite::Error) -> Self {
+		Error::Database(e)
+	}
+}
+
+impl From<String> for Error {
+	fn from(message: String) -> Self {
+		Error::Invalid(message)
+	}
+}
+
+impl From<&str> for Error {
+	fn from(message: &str) -> Self {
+		Error::Invalid(message.to_string())
+	}
+}
+
+/// For the internals still reporting plain messages
+impl From<Error> for String {
+	fn from(e: Error) -> Self {
+		e.to_string()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_display() {
+		assert_eq!(Error::segment(&1.into(), &2.into(), &Some("a".into()), "not in the road graph").to_string(), "Segment 1<->2 (a): not in the road graph");
+		let e = Error::Unreachable { segments: vec![(1.into(), 2.into()), (2.into(), 3.into())], message: "can't be reached by any vehicle".into() };
+		assert_eq!(e.to_string(), "2 segments can't be reached by any vehicle: 1<->2, 2<->3");
+		let e = Error::io("roads.json", std::io::Error::new(std::io::ErrorKind::NotFound, "gone"));
+		assert_eq!(e.to_string(), "roads.json: gone");
+		assert!(std::error::Error::source(&e).is_some());
+	}
+}
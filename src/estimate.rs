@@ -0,0 +1,86 @@
+//! Instance difficulty estimation
+//!
+//! Before committing to a multi-hour solve: how hard an instance looks, how many annealing iterations it should take to converge, and which preset's effort fits it.
+//! Iterations are regressed over the solver statistics of past runs (`--stats`), which record the features of the instances they solved.
+
+use crate::*;
+use meta::Preset;
+use plow::{Features, Stats};
+
+use serde::*;
+
+/// Scores from which an instance is deemed to take the standard and the blizzard presets' effort, without past runs to go by
+const THRESHOLDS: (f64, f64) = (50.0, 500.0);
+
+/// How hard an instance looks
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Estimate {
+	pub features: Features,
+	/// Difficulty score (see [`score`])
+	pub score: f64,
+	/// Annealing iterations until convergence expected, if there were past runs to go by
+	pub iterations: Option<f64>,
+	/// Past runs the iterations were regressed over
+	pub runs: usize,
+	/// Preset whose annealing effort fits
+	pub preset: Preset,
+}
+
+/// Difficulty score of an instance - segments to clear per vehicle, scaled up by the share of odd nodes and the imbalance of the initial clusters
+pub fn score(f: &Features) -> f64 {
+	let odd = if f.nodes > 0 { f.odd as f64 / f.nodes as f64 } else { 0.0 };
+	f.required as f64 / f.vehicles.max(1) as f64 * (1.0 + odd) * f.imbalance
+}
+
+/// Estimates the difficulty of an instance, going by the statistics of past runs (those recording features)
+///
+/// The iterations until every past annealing run converged are regressed linearly on the score of its instance, and the preset suggested is the first with as many main iterations.
+/// Without 2 different scores to go by, the preset goes by the score alone.
+pub fn estimate(features: Features, history: &[Stats]) -> Estimate {
+	let points: Vec<(f64, f64)> = history.iter().filter_map(|s| Some((score(s.features.as_ref()?), s))).flat_map(|(x, s)| s.restarts.iter().map(move |r| (x, r.converged as f64))).collect();
+	let score = score(&features);
+	let iterations = regression(&points).map(|(a, b)| (a + b * score).max(0.0));
+	let preset = match iterations {
+		Some(i) => [Preset::Light, Preset::Standard].iter().copied().find(|p| p.parameters().annealing.main_iterations as f64 >= i).unwrap_or(Preset::Blizzard),
+		None if score < THRESHOLDS.0 => Preset::Light,
+		None if score < THRESHOLDS.1 => Preset::Standard,
+		None => Preset::Blizzard,
+	};
+	Estimate { features, score, iterations, runs: points.len(), preset }
+}
+
+/// Least squares line through points
+///
+/// Returns: its intercept and slope, `None` without 2 different abscissae
+fn regression(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+	let n = points.len() as f64;
+	let (mx, my) = (points.iter().map(|p| p.0).sum::<f64>() / n, points.iter().map(|p| p.1).sum::<f64>() / n);
+	let sxx: f64 = points.iter().map(|(x, _)| (x - mx).powi(2)).sum();
+	if points.is_empty() || sxx <= 0.0 {
+		return None;
+	}
+	let slope = points.iter().map(|(x, y)| (x - mx) * (y - my)).sum::<f64>() / sxx;
+	Some((my - slope * mx, slope))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use plow::RestartSummary;
+
+	#[test]
+	fn test_estimate(){
+		let features = |required| Features { required, odd: 0, nodes: required, vehicles: 1, imbalance: 1.0 };
+		let run = |converged| RestartSummary { seed: 0, iterations: 2 * converged, converged, value: n64(0.0), max: n64(0.0), redundant: n64(0.0), cache_hits: 0, cache_misses: 0 };
+		let stats = |required, converged: &[u64]| Stats { restarts: converged.iter().map(|c| run(*c)).collect(), best: 0, graph: None, features: Some(features(required)) };
+		// 10 iterations, plus 2 per segment
+		let history = vec![stats(10, &[30, 30]), stats(40, &[85, 95]), Stats { features: None, ..stats(1000, &[1]) }];
+		let e = estimate(features(25), &history);
+		assert_eq!((e.runs, e.iterations), (4, Some(60.0)));
+		assert_eq!(e.preset, Preset::Standard);
+		assert_eq!(estimate(features(200), &history).preset, Preset::Blizzard);
+		// no past runs to go by
+		let e = estimate(Features { odd: 10, nodes: 20, vehicles: 2, imbalance: 2.0, ..features(40) }, &[]);
+		assert_eq!((e.score, e.iterations, e.preset), (60.0, None, Preset::Standard));
+	}
+}
@@ -0,0 +1,129 @@
+//! Holdout evaluation of computed plans against what actually happened
+//!
+//! Actual GPS traces are map-matched to the road graph by snapping every fix to the closest node,
+//! consecutive distinct nodes connected by a road segment make a cleared segment.
+
+use crate::*;
+use data::*;
+use view::{SegmentKey, segment_key};
+
+use std::collections::{HashMap, HashSet};
+use indexmap::IndexMap;
+use serde::*;
+
+fn node_pair(p1: &NodeId, p2: &NodeId) -> (NodeId, NodeId) {
+	if p1 <= p2 {
+		(p1.clone(), p2.clone())
+	} else {
+		(p2.clone(), p1.clone())
+	}
+}
+
+/// How well a single vehicle followed its plan
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct VehicleEvaluation {
+	/// Distinct segments in the plan
+	pub planned: usize,
+	/// Distinct segments actually cleared
+	pub cleared: usize,
+	/// Planned segments that were actually cleared
+	pub adhered: usize,
+	/// `adhered / planned`
+	pub adherence: f64,
+	/// Segments cleared that were not in the plan
+	pub unplanned: usize,
+	/// Mean absolute difference between planned and actual (first) clearing time of adhered segments, in trace time units
+	pub timing_deviation: f64,
+	/// Largest difference (actual - planned) between planned and actual clearing time
+	pub timing_deviation_max: f64,
+}
+
+/// Segments by their (unordered) end nodes
+pub(crate) fn segments_by_nodes(roads: &RoadGraph) -> HashMap<(NodeId, NodeId), Vec<&RoadSegment>> {
+	let mut segments: HashMap<(NodeId, NodeId), Vec<&RoadSegment>> = HashMap::new();
+	for e in &roads.roads {
+		segments.entry(node_pair(&e.p1, &e.p2)).or_default().push(e);
+	}
+	segments
+}
+
+/// Snap a GPS trace onto the graph, returning traversed segments with the time they were entered (the last fix at the node left) and left (the first fix at the node reached)
+pub(crate) fn map_match<'a>(roads: &RoadGraph, segments: &HashMap<(NodeId, NodeId), Vec<&'a RoadSegment>>, trace: &[GpsFix], metric: Metric) -> Vec<(&'a RoadSegment, f64, f64)> {
+	let mut traversed = Vec::new();
+	let mut last: Option<(&NodeId, f64)> = None;
+	for fix in trace {
+		let n = match roads.nodes.nodes.iter().min_by_key(|n| n64(metric.distance(fix.coordinates, n.coordinates))) {
+			Some(n) => &n.id,
+			None => break,
+		};
+		if let Some((l, t)) = last {
+			if l != n {
+				if let Some(e) = segments.get(&node_pair(l, n)).and_then(|es| es.iter().min_by_key(|e| e.distance)) {
+					traversed.push((*e, t, fix.time));
+				}
+			}
+		}
+		last = Some((n, fix.time));
+	}
+	traversed
+}
+
+/// Evaluate planned paths against actual GPS traces, matched by vehicle id (fixes snapped to the nearest node by the metric)
+pub fn evaluate(roads: &RoadGraph, plan: &Paths, actual: &GpsTraces, metric: Metric) -> IndexMap<VehicleId, VehicleEvaluation> {
+	let segments = segments_by_nodes(roads);
+	let view = view::SolutionView::new(roads, plan, None);
+	plan.0.keys().map(|id| {
+		// planned progress along the route, as a fraction of its length
+		let mut planned: HashMap<SegmentKey, f64> = HashMap::new();
+		let mut travelled = 0.0;
+		for t in view.vehicle(id) {
+			travelled = t.distance;
+			planned.entry(segment_key(t.from, &t.to.node, &t.to.discriminator)).or_insert(travelled);
+		}
+		let trace = actual.0.get(id).map(Vec::as_slice).unwrap_or(&[]);
+		let cleared: Vec<_> = map_match(roads, &segments, trace, metric).into_iter().map(|(e, _, t)| (segment_key(&e.p1, &e.p2, &e.discriminator), t)).collect();
+		let (t0, t1) = (trace.first().map_or(0.0, |f| f.time), trace.last().map_or(0.0, |f| f.time));
+		let mut first: HashMap<&SegmentKey, f64> = HashMap::new();
+		for (k, t) in &cleared {
+			first.entry(k).or_insert(*t);
+		}
+		let deviations: Vec<f64> = first.iter().filter_map(|(k, t)| planned.get(*k).map(|p| {
+			let tp = if travelled > 0.0 { t0 + (t1 - t0) * (*p / travelled) } else { t0 };
+			t - tp
+		})).collect();
+		let adhered = deviations.len();
+		let cleared: HashSet<_> = first.keys().collect();
+		(id.clone(), VehicleEvaluation {
+			planned: planned.len(),
+			cleared: cleared.len(),
+			adhered,
+			adherence: if planned.is_empty() { 1.0 } else { adhered as f64 / planned.len() as f64 },
+			unplanned: cleared.iter().filter(|k| !planned.contains_key(**k)).count(),
+			timing_deviation: if adhered > 0 { deviations.iter().map(|d| d.abs()).sum::<f64>() / adhered as f64 } else { 0.0 },
+			timing_deviation_max: deviations.iter().cloned().fold(0.0, |m, d| if d.abs() > m.abs() { d } else { m }),
+		})
+	}).collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_evaluate(){
+		let node = |id: &'static str, x: f64| Node { id: id.into(), coordinates: (x, 0.0), elevation: None };
+		let road = |p1: &'static str, p2: &'static str| RoadSegment::new(p1, p2, 1.0);
+		let roads = RoadGraph {
+			roads: vec![road("a", "b"), road("b", "c"), road("c", "d")],
+			nodes: RoadGraphNodes { nodes: vec![node("a", 0.0), node("b", 1.0), node("c", 2.0), node("d", 3.0)] },
+		};
+		let seg = |n: &'static str| PathSegment { node: n.into(), discriminator: None, reversal: false, leg: None };
+		let plan = vec![("t".to_string(), vec![seg("a"), seg("b"), seg("c"), seg("b"), seg("a")])].into_iter().collect();
+		let fix = |x: f64, time: f64| GpsFix { coordinates: (x, 0.1), time };
+		let actual = vec![("t".to_string(), vec![fix(0.0, 0.0), fix(1.1, 1.0), fix(1.9, 2.0), fix(3.0, 3.0)])].into_iter().collect();
+		let ev = evaluate(&roads, &plan, &actual, Metric::default());
+		assert_eq!(ev.len(), 1);
+		assert_eq!((ev["t"].planned, ev["t"].cleared, ev["t"].adhered, ev["t"].unplanned), (2, 3, 2, 1));
+		assert_eq!(ev["t"].adherence, 1.0);
+	}
+}
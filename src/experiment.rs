@@ -0,0 +1,188 @@
+//! Experiment runs
+//!
+//! A heuristic change is only better if it is on many instances and seeds - one lucky run proves nothing.
+//! An experiment matrix solves every instance with every parameter set, once per seed, and the results pile up in an SQLite database of records to be summarized (with the `sqlite` feature).
+
+use crate::*;
+use data::*;
+use meta::Parameters;
+use plow::Budget;
+
+use std::{path::PathBuf, time::Instant};
+use indexmap::IndexMap;
+use serde::*;
+
+/// A plowing problem instance, as files
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Instance {
+	pub roads: PathBuf,
+	pub snow: PathBuf,
+	pub vehicles: PathBuf,
+	/// Default snow depth
+	#[serde(default)]
+	pub snow_d: Option<f64>,
+}
+
+/// An experiment matrix - instances and parameter sets (meta parameter files), by name
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Matrix {
+	pub instances: IndexMap<String, Instance>,
+	pub parameters: IndexMap<String, PathBuf>,
+	/// Annealing seeds (see [`meta::Annealing::seed`])
+	pub seeds: Vec<u64>,
+}
+
+/// A loaded plowing problem instance
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Problem {
+	pub roads: RoadGraph,
+	pub snow: SnowStatuses,
+	pub snow_d: Option<f64>,
+	pub vehicles: VehiclesConfiguration,
+}
+
+/// How a single run did
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Outcome {
+	/// Annealing objective of the plan (see [`plow::PathCosts::value`])
+	pub value: N64,
+	pub total: N64,
+	pub max: N64,
+	/// Lower bound of the objective (see [`plow::road::lower_bound`])
+	pub bound: N64,
+	/// Relative gap to the bound - `(value - bound) / value`
+	pub gap: f64,
+	/// Solving time, in seconds
+	pub runtime: f64,
+}
+
+/// A database record - a single run of the matrix
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Record {
+	pub instance: String,
+	pub parameters: String,
+	pub seed: u64,
+	#[serde(flatten)]
+	pub outcome: Outcome,
+}
+
+/// Solves a road plowing problem with the given seed, and evaluates the plan
+pub fn run(problem: &Problem, params: &Parameters, seed: u64, budget: &Budget) -> Result<Outcome, Error> {
+	let mut params = params.clone();
+	params.annealing.seed = Some(seed);
+	let start = Instant::now();
+	let (paths, _) = plow::road::solve(problem.roads.clone(), problem.snow.clone(), problem.snow_d, problem.vehicles.clone(), &params, budget, |_, _| ())?;
+	let runtime = start.elapsed().as_secs_f64();
+	let costs = plow::road::evaluate(problem.roads.clone(), &problem.snow, problem.snow_d, &paths, &params)?;
+	let bound = plow::road::lower_bound(problem.roads.clone(), &problem.snow, problem.snow_d, problem.vehicles.road.len(), &params)?;
+	let gap = if costs.value > 0.0 { ((costs.value - bound) / costs.value).raw() } else { 0.0 };
+	Ok(Outcome { value: costs.value, total: costs.total, max: costs.max, bound, gap, runtime })
+}
+
+/// Statistics of the runs of an instance with a parameter set
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Summary {
+	pub instance: String,
+	pub parameters: String,
+	pub runs: usize,
+	pub best: f64,
+	pub mean: f64,
+	/// Sample standard deviation of the values
+	pub deviation: f64,
+	pub mean_gap: f64,
+	pub mean_runtime: f64,
+}
+
+/// A results database - an SQLite table of runs, keyed by instance, parameter set and seed
+///
+/// A run recorded again (e.g. by a worker retried after timing out) replaces the earlier one, in its place.
+#[cfg(feature = "sqlite")]
+pub struct Database {
+	conn: rusqlite::Connection,
+}
+
+#[cfg(feature = "sqlite")]
+impl Database {
+	/// Opens the database file, creating it (and the table) if need be - `:memory:` for a throwaway one
+	pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, Error> {
+		let conn = rusqlite::Connection::open(path)?;
+		conn.execute_batch("CREATE TABLE IF NOT EXISTS runs (
+			instance TEXT NOT NULL,
+			parameters TEXT NOT NULL,
+			seed INTEGER NOT NULL,
+			value REAL NOT NULL,
+			total REAL NOT NULL,
+			max REAL NOT NULL,
+			bound REAL NOT NULL,
+			gap REAL NOT NULL,
+			runtime REAL NOT NULL,
+			PRIMARY KEY (instance, parameters, seed)
+		)")?;
+		Ok(Self { conn })
+	}
+	/// Records a run
+	pub fn insert(&self, record: &Record) -> Result<(), Error> {
+		let o = &record.outcome;
+		self.conn.execute("INSERT INTO runs (instance, parameters, seed, value, total, max, bound, gap, runtime) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+			ON CONFLICT (instance, parameters, seed) DO UPDATE SET value = excluded.value, total = excluded.total, max = excluded.max, bound = excluded.bound, gap = excluded.gap, runtime = excluded.runtime",
+			rusqlite::params![record.instance, record.parameters, record.seed as i64, o.value.raw(), o.total.raw(), o.max.raw(), o.bound.raw(), o.gap, o.runtime])?;
+		Ok(())
+	}
+	/// Whether the run is recorded already
+	pub fn contains(&self, instance: &str, parameters: &str, seed: u64) -> Result<bool, Error> {
+		Ok(self.conn.query_row("SELECT EXISTS (SELECT 1 FROM runs WHERE instance = ?1 AND parameters = ?2 AND seed = ?3)", rusqlite::params![instance, parameters, seed as i64], |r| r.get(0))?)
+	}
+	/// Summarizes the runs by instance and parameter set (of the instance, if any), in order of the first run recorded
+	pub fn summarize(&self, instance: Option<&str>) -> Result<Vec<Summary>, Error> {
+		// no STDEV in SQLite - the sample variance from the sum of squares
+		let mut query = self.conn.prepare("SELECT instance, parameters, COUNT(*), MIN(value), AVG(value),
+			CASE WHEN COUNT(*) > 1 THEN MAX(SUM(value * value) - SUM(value) * SUM(value) / COUNT(*), 0) / (COUNT(*) - 1) ELSE 0 END,
+			AVG(gap), AVG(runtime)
+			FROM runs WHERE ?1 IS NULL OR instance = ?1 GROUP BY instance, parameters ORDER BY MIN(rowid)")?;
+		let summaries = query.query_map([instance], |r| Ok(Summary {
+			instance: r.get(0)?,
+			parameters: r.get(1)?,
+			runs: r.get::<_, i64>(2)? as usize,
+			best: r.get(3)?,
+			mean: r.get(4)?,
+			deviation: r.get::<_, f64>(5)?.sqrt(),
+			mean_gap: r.get(6)?,
+			mean_runtime: r.get(7)?,
+		}))?.collect::<Result<_, _>>()?;
+		Ok(summaries)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	#[cfg(feature = "sqlite")]
+	fn test_database(){
+		let record = |instance: &str, seed: u64, value: f64| Record {
+			instance: instance.into(),
+			parameters: "default".into(),
+			seed,
+			outcome: Outcome { value: n64(value), total: n64(value), max: n64(value), bound: n64(50.0), gap: 1.0 - 50.0 / value, runtime: 1.0 },
+		};
+		let db = Database::open(":memory:").unwrap();
+		for r in [record("a", 1, 300.0), record("b", 1, 50.0), record("a", 2, 200.0), record("a", 1, 100.0)] {
+			db.insert(&r).unwrap();
+		}
+		assert!(db.contains("a", "default", 2).unwrap());
+		assert!(!db.contains("a", "default", 3).unwrap());
+		let summaries = db.summarize(None).unwrap();
+		assert_eq!(summaries.len(), 2);
+		// the rerun of a/1 replaced the first one
+		assert_eq!((summaries[0].instance.as_str(), summaries[0].runs), ("a", 2));
+		assert_eq!((summaries[0].best, summaries[0].mean), (100.0, 150.0));
+		assert!((summaries[0].deviation - 70.71).abs() < 0.01);
+		assert!((summaries[0].mean_gap - 0.625).abs() < 1e-9);
+		assert_eq!(summaries[1].deviation, 0.0);
+		assert_eq!(db.summarize(Some("b")).unwrap().len(), 1);
+		let line = serde_json::to_string(&record("a", 1, 100.0)).unwrap();
+		assert!(line.contains("\"gap\":0.5"));
+		assert_eq!(serde_json::from_str::<Record>(&line).unwrap(), record("a", 1, 100.0));
+	}
+}
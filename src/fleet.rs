@@ -0,0 +1,159 @@
+//! Fleet sizing
+//!
+//! Given the vehicle types on hand and what an hour of each costs, searches the fleet compositions for the cheapest one
+//! clearing the snow within a completion target - every vehicle of a fleet being on shift until the last route is done.
+
+use crate::*;
+use data::*;
+use meta::Parameters;
+use plow::Budget;
+
+use indexmap::IndexMap;
+use serde::*;
+
+/// A vehicle type on hand
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct VehicleType {
+	/// Cost of a vehicle of the type, per plan time unit (hours with `speeds` per hour)
+	pub hourly_cost: f64,
+	/// Most vehicles of the type available
+	pub available: usize,
+	/// What a vehicle of the type is like - its depot, the surfaces it clears and so on (the id is generated)
+	pub vehicle: VehicleSpec,
+}
+
+/// Fleet sizing problem - the vehicle types, by name, and the completion target
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Fleet {
+	pub types: IndexMap<String, VehicleType>,
+	/// Longest route acceptable (risk-adjusted, see [`plow::PathCosts::max`]), in plan time units
+	pub target: f64,
+	/// Where vehicles with a capacity refill (see [`VehiclesConfiguration::depots`])
+	#[serde(default)]
+	pub depots: Vec<Depot>,
+}
+
+/// A fleet composition, and how it did
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Candidate {
+	/// Number of vehicles of every type
+	pub fleet: IndexMap<String, usize>,
+	/// Cost of the fleet per time unit
+	pub rate: f64,
+	/// The longest route of its plan, `None` if not solved (its bound couldn't beat the best fleet, or meet the target) or it can't clear all the snow
+	pub completion: Option<N64>,
+	/// `rate * completion`
+	pub cost: Option<N64>,
+}
+
+/// The cheapest fleet meeting the target, and its plan
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Recommendation {
+	pub best: Candidate,
+	pub paths: Paths,
+	/// All compositions considered, cheapest rate first
+	pub candidates: Vec<Candidate>,
+}
+
+impl Fleet {
+	/// Vehicles of a composition, with ids `<type>-<n>`
+	pub fn vehicles(&self, fleet: &IndexMap<String, usize>) -> Vec<Vehicle> {
+		fleet.iter().flat_map(|(name, n)| (0..*n).map(move |i| Vehicle::Spec(VehicleSpec { id: Some(format!("{}-{}", name, i)), ..self.types[name].vehicle.clone() }))).collect()
+	}
+	/// All non-empty compositions of the vehicles available, cheapest rate first
+	pub fn compositions(&self) -> Vec<Candidate> {
+		let mut compositions: Vec<IndexMap<String, usize>> = vec![IndexMap::new()];
+		for (name, t) in &self.types {
+			compositions = compositions.into_iter().flat_map(|c| (0..=t.available).map(move |n| {
+				let mut c = c.clone();
+				c.insert(name.clone(), n);
+				c
+			})).collect();
+		}
+		let mut candidates: Vec<_> = compositions.into_iter().filter(|c| c.values().any(|n| *n > 0)).map(|fleet| Candidate {
+			rate: fleet.iter().map(|(name, n)| self.types[name].hourly_cost * *n as f64).sum(),
+			fleet,
+			completion: None,
+			cost: None,
+		}).collect();
+		candidates.sort_by(|a, b| a.rate.total_cmp(&b.rate));
+		candidates
+	}
+	/// Searches the compositions for the cheapest meeting the target
+	///
+	/// Compositions whose completion bound (see [`plow::road::lower_bound`]) is already over the target, or too costly to beat the best so far, aren't solved.
+	/// Those that can't clear it all (e.g. their vehicles can't reach some snowy segment) don't meet the target, the search carrying on.
+	pub fn optimize(&self, roads: &RoadGraph, snow: &SnowStatuses, snow_d: Option<f64>, params: &Parameters, budget: &Budget) -> Result<Recommendation, Error> {
+		let most = self.types.values().map(|t| t.available).sum::<usize>();
+		let bounds: Vec<N64> = (0..=most).map(|n| plow::road::lower_bound(roads.clone(), snow, snow_d, n, &Parameters { weight_total: n64(0.0), weight_max: n64(1.0), ..params.clone() })).collect::<Result<_, _>>()?;
+		let mut candidates = self.compositions();
+		let mut best: Option<(usize, Paths)> = None;
+		for i in 0..candidates.len() {
+			let c = &candidates[i];
+			let bound = bounds[c.fleet.values().sum::<usize>()];
+			let best_cost = best.as_ref().and_then(|(b, _)| candidates[*b].cost);
+			if bound > self.target || best_cost.is_some_and(|b| n64(c.rate) * bound >= b) {
+				continue;
+			}
+			let vehicles = VehiclesConfiguration { road: self.vehicles(&c.fleet), sidewalk: vec![], depots: self.depots.clone() };
+			let name = c.fleet.iter().map(|(name, n)| format!("{} {}", n, name)).collect::<Vec<_>>().join(", ");
+			let solved = plow::road::solve(roads.clone(), snow.clone(), snow_d, vehicles, params, budget, |_, _| ())
+				.and_then(|(paths, _)| Ok((plow::road::evaluate(roads.clone(), snow, snow_d, &paths, params)?.max, paths)));
+			let (completion, paths) = match solved {
+				Ok(solved) => solved,
+				Err(e) => {
+					log::info!("{}: infeasible, {}", name, e);
+					continue;
+				}
+			};
+			let cost = n64(c.rate) * completion;
+			log::info!("{}: completion {:.1}, cost {:.1}", name, completion, cost);
+			candidates[i].completion = Some(completion);
+			candidates[i].cost = Some(cost);
+			if completion <= self.target && best_cost.is_none_or(|b| cost < b) {
+				best = Some((i, paths));
+			}
+		}
+		match best {
+			Some((i, paths)) => Ok(Recommendation { best: candidates[i].clone(), paths, candidates }),
+			None => Err(format!("No fleet of those available completes within {} (the fastest in {})", self.target,
+				candidates.iter().filter_map(|c| c.completion).min().map_or_else(|| format!("over {}", bounds[most]), |c| c.to_string())).into()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_compositions(){
+		let t = |hourly_cost: f64, available: usize| VehicleType { hourly_cost, available, vehicle: VehicleSpec {
+			id: None, location: Location::Node("a".into()), end: Default::default(), exclude: vec![], surfaces: None, breaks: None, max_grade: None, capacity: None, speed: None, resource: None,
+		} };
+		let fleet = Fleet { types: vec![("plow".to_string(), t(100.0, 2)), ("pickup".to_string(), t(30.0, 1))].into_iter().collect(), target: 1.0, depots: vec![] };
+		let rates: Vec<_> = fleet.compositions().iter().map(|c| c.rate).collect();
+		assert_eq!(rates, vec![30.0, 100.0, 130.0, 200.0, 230.0]);
+		let vehicles = fleet.vehicles(&fleet.compositions()[2].fleet);
+		assert_eq!(vehicles.iter().enumerate().map(|(i, v)| v.id(i)).collect::<Vec<_>>(), vec!["plow-0", "pickup-0"]);
+	}
+
+	#[test]
+	fn test_infeasible(){
+		// 0 - 1 - 2 all snowy, 1 - 2 gravel - the pickup can't clear it, the dearer plow can
+		let roads = RoadGraph {
+			roads: vec![RoadSegment::new(0, 1, 100.0), RoadSegment { surface: Some("gravel".to_string()), ..RoadSegment::new(1, 2, 100.0) }],
+			nodes: RoadGraphNodes { nodes: (0..3).map(|i| Node { id: i.into(), coordinates: (i as f64 * 0.001, 0.0), elevation: None }).collect() },
+		};
+		let t = |hourly_cost: f64, surfaces: Option<Vec<Surface>>| VehicleType { hourly_cost, available: 1, vehicle: VehicleSpec {
+			id: None, location: Location::Node(0.into()), end: Default::default(), exclude: vec![], surfaces, breaks: None, max_grade: None, capacity: None, speed: None, resource: None,
+		} };
+		let fleet = Fleet { types: vec![("pickup".to_string(), t(30.0, Some(vec!["asphalt".to_string()]))), ("plow".to_string(), t(100.0, None))].into_iter().collect(), target: 1000.0, depots: vec![] };
+		let mut params = meta::Preset::Light.parameters();
+		params.annealing.seed = Some(7);
+		let recommendation = fleet.optimize(&roads, &vec![], Some(1.0), &params, &Default::default()).unwrap();
+		assert_eq!(recommendation.best.fleet, vec![("pickup".to_string(), 0), ("plow".to_string(), 1)].into_iter().collect::<IndexMap<_, _>>());
+		assert_eq!(recommendation.candidates[0].rate, 30.0);
+		assert_eq!(recommendation.candidates[0].completion, None);
+	}
+}
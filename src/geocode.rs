@@ -0,0 +1,102 @@
+//! Address geocoding into [`data::Location`]s
+//!
+//! Dispatch speaks addresses, the solver speaks coordinates.
+//! Providers are pluggable through [`Geocoder`]; the only one shipped is an offline CSV [`Gazetteer`].
+
+use crate::*;
+
+use std::{collections::HashMap, io::BufRead};
+
+/// Something that can turn an address into a location
+pub trait Geocoder {
+	/// Geocode a single address, if it is known
+	fn geocode(&self, address: &str) -> Option<data::Location>;
+}
+
+/// Normalizes an address for lookups - case and whitespace insensitive
+fn normalize(address: &str) -> String {
+	address.split_whitespace().map(str::to_lowercase).collect::<Vec<_>>().join(" ")
+}
+
+/// Offline address book
+///
+/// Loaded from CSV lines of `address,lon,lat` - the address may itself contain commas (and be quoted), coordinates are always the last 2 columns.
+/// Empty lines and lines starting with `#` are ignored.
+#[derive(Clone, Debug, Default)]
+pub struct Gazetteer {
+	entries: HashMap<String, (f64, f64)>,
+}
+
+impl Gazetteer {
+	/// Load a gazetteer from CSV
+	pub fn from_csv(r: impl BufRead) -> Result<Self, Error> {
+		let mut entries = HashMap::new();
+		for (i, line) in r.lines().enumerate() {
+			let line = line.map_err(|e| e.to_string())?;
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let mut cols = line.rsplitn(3, ',');
+			let (lat, lon, address) = match (cols.next(), cols.next(), cols.next()) {
+				(Some(lat), Some(lon), Some(address)) => (lat, lon, address),
+				_ => return Err(format!("gazetteer line {}: expected `address,lon,lat`", i+1).into()),
+			};
+			let lon: f64 = match lon.trim().parse() {
+				Ok(lon) => lon,
+				Err(_) if i == 0 => continue, // header
+				Err(e) => return Err(format!("gazetteer line {}: bad longitude: {}", i+1, e).into()),
+			};
+			let lat: f64 = lat.trim().parse().map_err(|e| format!("gazetteer line {}: bad latitude: {}", i+1, e))?;
+			entries.insert(normalize(address.trim().trim_matches('"')), (lon, lat));
+		}
+		Ok(Self { entries })
+	}
+	/// Number of known addresses
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+	/// Whether the gazetteer knows no addresses
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+}
+
+impl Geocoder for Gazetteer {
+	fn geocode(&self, address: &str) -> Option<data::Location> {
+		self.entries.get(&normalize(address)).map(|(lon, lat)| data::Location::Coordinates(*lon, *lat))
+	}
+}
+
+/// Geocode all addresses, in order
+///
+/// Returns: the locations, or all addresses that couldn't be geocoded
+pub fn geocode_all<'a>(g: &impl Geocoder, addresses: impl Iterator<Item = &'a str>) -> Result<Vec<data::Location>, Vec<String>> {
+	let mut locs = Vec::new();
+	let mut unknown = Vec::new();
+	for a in addresses {
+		match g.geocode(a) {
+			Some(l) => locs.push(l),
+			None => unknown.push(a.to_string()),
+		}
+	}
+	if unknown.is_empty() {
+		Ok(locs)
+	} else {
+		Err(unknown)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_gazetteer(){
+		let g = Gazetteer::from_csv("address,lon,lat\n\"1 Rue Garage, Montreal\", -73.5, 45.5\n# nope\n\n2 Depot St,-73.6,45.4\n".as_bytes()).unwrap();
+		assert_eq!(g.len(), 2);
+		assert_eq!(g.geocode("1  rue garage, MONTREAL"), Some(data::Location::Coordinates(-73.5, 45.5)));
+		assert_eq!(geocode_all(&g, vec!["2 Depot St", "3 Nowhere"].into_iter()), Err(vec!["3 Nowhere".to_string()]));
+		assert!(Gazetteer::from_csv("a,b\nc,1,x\n".as_bytes()).is_err());
+	}
+}
@@ -7,7 +7,7 @@ use crate::*;
 use data::*;
 use geo::{GeometryCollection, intersects::Intersects};
 
-use std::{collections::HashSet, convert::{TryFrom, TryInto}};
+use std::{collections::HashSet, convert::{TryFrom, TryInto}, path::PathBuf};
 use geojson::*;
 use indexmap::{IndexMap, indexmap};
 
@@ -31,22 +31,219 @@ pub fn locations_to_geojson(g: &RoadGraphNodes, l: Vec<data::Location>) -> Featu
 	}
 }
 
-pub fn path_to_geojson(g: &Nodes, path: Vec<PathSegment>) -> Geometry {
-	Geometry::new(Value::LineString(path.into_iter().flat_map(|PathSegment { node, .. }| g.get(&node).map(|node| vec![node.coordinates.0, node.coordinates.1])).collect()))
+pub fn vehicles_to_geojson(g: &RoadGraphNodes, vs: Vec<data::Vehicle>) -> FeatureCollection {
+	FeatureCollection {
+		features: vs.into_iter().enumerate().map(|(i, v)| Feature {
+			geometry: Some((&g.dislocate(v.location())).try_into().unwrap()),
+			properties: Some(indexmap!{ "vehicle".to_string() => serde_json::Value::String(v.id(i)) }.into_iter().collect()),
+			bbox: None,
+			foreign_members: None,
+			id: None
+		}).collect(),
+		bbox: None,
+		foreign_members: None,
+	}
+}
+
+/// Depots as points, with their attributes
+pub fn depots_to_geojson(g: &RoadGraphNodes, ds: Vec<data::Depot>) -> FeatureCollection {
+	FeatureCollection {
+		features: ds.into_iter().enumerate().map(|(i, d)| Feature {
+			geometry: Some((&g.dislocate(&d.location)).try_into().unwrap()),
+			properties: Some(indexmap!{
+				"depot".to_string() => serde_json::Value::String(d.id(i)),
+				"capabilities".to_string() => serde_json::to_value(&d.capabilities).unwrap(),
+				"resources".to_string() => serde_json::to_value(&d.resources).unwrap(),
+				"hours".to_string() => serde_json::to_value(&d.hours).unwrap(),
+			}.into_iter().collect()),
+			bbox: None,
+			foreign_members: None,
+			id: None
+		}).collect(),
+		bbox: None,
+		foreign_members: None,
+	}
+}
+
+/// A path as a single line (nodes missing from the graph left out)
+pub fn path_geometry(g: &Nodes, path: &[PathSegment]) -> Geometry {
+	Geometry::new(Value::LineString(path.iter().flat_map(|PathSegment { node, .. }| g.get(node).map(|node| vec![node.coordinates.0, node.coordinates.1])).collect()))
+}
+
+/// A vehicle's path segment by segment, each with the vehicle, the `index` along the path, the `from` and `to` node ids, and the `discriminator`, `reversal` and drone `leg` of the step onto `to`
+///
+/// Segments to or from nodes missing from the graph are left out.
+pub fn path_to_geojson(g: &Nodes, vehicle: &VehicleId, path: Vec<PathSegment>) -> FeatureCollection {
+	FeatureCollection {
+		features: path.windows(2).enumerate().filter_map(|(i, w)| {
+			let (u, v) = (g.get(&w[0].node)?, g.get(&w[1].node)?);
+			Some(Feature {
+				geometry: Some(Geometry::new(Value::LineString(vec![vec![u.coordinates.0, u.coordinates.1], vec![v.coordinates.0, v.coordinates.1]]))),
+				properties: Some(indexmap!{
+					"vehicle".to_string() => serde_json::Value::String(vehicle.clone()),
+					"index".to_string() => i.into(),
+					"from".to_string() => serde_json::to_value(&w[0].node).unwrap(),
+					"to".to_string() => serde_json::to_value(&w[1].node).unwrap(),
+					"discriminator".to_string() => serde_json::to_value(&w[1].discriminator).unwrap(),
+					"reversal".to_string() => w[1].reversal.into(),
+					"leg".to_string() => serde_json::to_value(w[1].leg).unwrap(),
+				}.into_iter().collect()),
+				bbox: None,
+				foreign_members: None,
+				id: None,
+			})
+		}).collect(),
+		bbox: None,
+		foreign_members: None,
+	}
+}
+
+/// A vehicle's schedule segment by segment (see [`path_to_geojson`]), each also with the `distance` driven and the `eta` by its `to` node
+pub fn schedule_to_geojson(g: &Nodes, vehicle: &VehicleId, schedule: Vec<ScheduledSegment>) -> FeatureCollection {
+	let mut fc = path_to_geojson(g, vehicle, schedule.iter().map(|s| s.segment.clone()).collect());
+	for f in &mut fc.features {
+		if let Some(s) = f.property("index").and_then(|i| i.as_u64()).map(|i| &schedule[i as usize + 1]) {
+			let properties = f.properties.get_or_insert_with(Default::default);
+			properties.insert("distance".to_string(), s.distance.into());
+			properties.insert("eta".to_string(), s.eta.into());
+		}
+	}
+	fc
+}
+
+/// All vehicles' paths (see [`path_to_geojson`]) in a single collection
+pub fn paths_to_geojson(g: &Nodes, paths: Paths) -> FeatureCollection {
+	FeatureCollection {
+		features: paths.0.into_iter().flat_map(|(id, path)| path_to_geojson(g, &id, path).features).collect(),
+		bbox: None,
+		foreign_members: None,
+	}
+}
+
+/// A route, with its length (or travel time) as property
+pub fn route_to_geojson(g: &Nodes, route: Vec<PathSegment>, length: f64) -> Feature {
+	Feature {
+		geometry: Some(path_geometry(g, &route)),
+		properties: Some(vec![("length".to_string(), length.into())].into_iter().collect()),
+		bbox: None,
+		foreign_members: None,
+		id: None,
+	}
+}
+
+/// A path segment by segment, each with the original properties of its road segment (see [`RoadSegment::properties`]), the vehicle and the index along the path
+pub fn traversals_to_geofeatures<'a>(g: &Nodes, ts: impl Iterator<Item = view::Traversal<'a>>) -> FeatureCollection {
+	FeatureCollection {
+		features: ts.filter_map(|t| {
+			let (u, v) = (g.get(t.from)?, g.get(&t.to.node)?);
+			let mut properties = t.road.map(|e| e.properties.clone()).unwrap_or_default();
+			properties.insert("vehicle".to_string(), t.vehicle.clone().into());
+			properties.insert("index".to_string(), t.index.into());
+			Some(Feature {
+				geometry: Some(Geometry::new(Value::LineString(vec![vec![u.coordinates.0, u.coordinates.1], vec![v.coordinates.0, v.coordinates.1]]))),
+				properties: Some(properties),
+				bbox: None,
+				foreign_members: None,
+				id: None,
+			})
+		}).collect(),
+		bbox: None,
+		foreign_members: None,
+	}
+}
+
+/// A chunk's path, with its vehicle, interval, timing (wall-clock too, if any) and cast sides (one per segment) as properties
+pub fn chunk_to_geojson(g: &Nodes, c: timeline::Chunk) -> Feature {
+	Feature {
+		geometry: Some(path_geometry(g, &c.path)),
+		properties: Some(indexmap!{
+			"vehicle".to_string() => serde_json::Value::String(c.vehicle),
+			"interval".to_string() => c.interval.into(),
+			"start".to_string() => c.start.into(),
+			"end".to_string() => c.end.into(),
+			"cast".to_string() => serde_json::to_value(c.cast).unwrap(),
+		}.into_iter().chain(c.at.map(|(start, end)| vec![("start_at".to_string(), start.into()), ("end_at".to_string(), end.into())]).into_iter().flatten()).collect()),
+		bbox: None,
+		foreign_members: None,
+		id: None,
+	}
+}
+
+/// A vehicle's whole path as a single line (see [`path_geometry`]), with the vehicle as property
+pub fn path_to_line_geojson(g: &Nodes, vehicle: &VehicleId, path: &[PathSegment]) -> Feature {
+	Feature {
+		geometry: Some(path_geometry(g, path)),
+		properties: Some(indexmap!{ "vehicle".to_string() => serde_json::Value::String(vehicle.clone()) }.into_iter().collect()),
+		bbox: None,
+		foreign_members: None,
+		id: None,
+	}
+}
+
+/// Simplifies a feature's line string (Douglas-Peucker), dropping positions within `tolerance` meters of the simplified line
+///
+/// The number of segments of the original line is kept as the `segments` property, which export verification goes by.
+/// Only the exported geometry is simplified - plans keep every node.
+pub fn simplify(mut f: Feature, tolerance: f64) -> Feature {
+	if let Some(Geometry { value: Value::LineString(l), .. }) = &mut f.geometry {
+		let segments = l.len().saturating_sub(1);
+		*l = simplify_line(l, tolerance);
+		f.properties.get_or_insert_with(Default::default).insert("segments".to_string(), segments.into());
+	}
+	f
+}
+
+/// Douglas-Peucker simplification of a line, in meters (see [`data::project`]) - the ends always kept
+fn simplify_line(l: &[Vec<f64>], tolerance: f64) -> Vec<Vec<f64>> {
+	if l.len() < 3 {
+		return l.to_vec();
+	}
+	let origin = (l[0][0], l[0][1]);
+	let xy: Vec<(f64, f64)> = l.iter().map(|p| project(origin, (p[0], p[1]))).collect();
+	let mut keep = vec![false; l.len()];
+	keep[0] = true;
+	keep[l.len() - 1] = true;
+	let mut stack = vec![(0, l.len() - 1)];
+	while let Some((a, b)) = stack.pop() {
+		let (far, d) = ((a + 1)..b).map(|i| (i, distance_to_segment(xy[i], xy[a], xy[b]))).fold((a, -1.0), |m, c| if c.1 > m.1 { c } else { m });
+		if d > tolerance {
+			keep[far] = true;
+			stack.push((a, far));
+			stack.push((far, b));
+		}
+	}
+	l.iter().zip(keep).filter(|(_, k)| *k).map(|(p, _)| p.clone()).collect()
+}
+
+fn distance_to_segment((px, py): (f64, f64), (ax, ay): (f64, f64), (bx, by): (f64, f64)) -> f64 {
+	let (dx, dy) = (bx - ax, by - ay);
+	let len2 = dx * dx + dy * dy;
+	let t = if len2 > 0.0 { (((px - ax) * dx + (py - ay) * dy) / len2).clamp(0.0, 1.0) } else { 0.0 };
+	(px - ax - t * dx).hypot(py - ay - t * dy)
 }
 
 pub fn geofeatures_to_snow(g: &RoadGraph, feat: FeatureCollection) -> data::SnowStatuses {
 	let mut snow = Vec::new();
+	let parallels = g.parallel_indices();
 	for f in feat.features {
 		if let (Some(depth), Some(geometry)) = (f.property("snow").and_then(|j| j.as_f64()), f.geometry) {
-			let geometry: geo::Geometry<f64> = geometry.value.try_into().unwrap();
+			let geometry: geo::Geometry<f64> = match geometry.value.try_into() {
+				Ok(g) => g,
+				Err(e) => {
+					log::warn!("Skipping a snow feature {:?} with invalid geometry: {}", f.id, e);
+					continue;
+				},
+			};
 			let isect: HashSet<_> = g.nodes.nodes.iter().filter(|n| geometry.intersects(&geo::Geometry::<f64>::from(*n))).map(|n| &n.id).collect();
-			for e in g.roads.iter().filter(|e| isect.contains(&e.p1) || isect.contains(&e.p2)) {
+			for (e, parallel) in g.roads.iter().zip(parallels.iter()).filter(|(e, _)| isect.contains(&e.p1) || isect.contains(&e.p2)) {
 				snow.push(SnowStatusElement {
 					p1: e.p1.clone(),
 					p2: e.p2.clone(),
 					discriminator: e.discriminator.clone(),
+					parallel_index: Some(*parallel),
 					depth: n64(depth),
+					variance: None,
+					estimated: false,
 				});
 			}
 		}
@@ -54,17 +251,453 @@ pub fn geofeatures_to_snow(g: &RoadGraph, feat: FeatureCollection) -> data::Snow
 	snow
 }
 
+/// How road centerline features are read into a road graph
+#[derive(Clone, PartialEq, Debug)]
+pub struct RoadFeatures {
+	/// Property telling the direction - `true`/`yes`/`1`/`FT` along the line, `-1`/`TF`/`reverse` against it, anything else both ways
+	pub direction: String,
+	/// Decimal places of the coordinates to which the line ends are noded
+	pub precision: i32,
+}
+
+impl Default for RoadFeatures {
+	fn default() -> Self {
+		Self {
+			direction: "oneway".to_string(),
+			precision: 7,
+		}
+	}
+}
+
+/// Direction of a line, as per the direction property: `Some(true)` along, `Some(false)` against it, `None` both ways
+fn line_direction(v: Option<&serde_json::Value>) -> Option<bool> {
+	match v? {
+		serde_json::Value::Bool(b) => Some(true).filter(|_| *b),
+		serde_json::Value::Number(n) => match n.as_i64()? {
+			1 => Some(true),
+			-1 => Some(false),
+			_ => None,
+		},
+		serde_json::Value::String(s) => match s.to_lowercase().as_str() {
+			"true" | "yes" | "1" | "ft" | "f" => Some(true),
+			"-1" | "tf" | "t" | "reverse" => Some(false),
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+/// Reads a road graph out of a GeoJSON feature collection of centerline (multi)line strings
+///
+/// Lines sharing end points (to the `precision`) are noded together, with ids generated in order of appearance; the segment's distance is the geodesic length of the line, its `surface` and `class` are taken from the properties of the same name, as are the `bridge` and `shaded` flags (anything but `no`/`false`/`0` being set, as in OSM's `bridge=viaduct`), the `priority` level and the one-way `windows` (as in the spec'd JSON).
+/// All the feature's properties are kept with the segment, for exported plans to carry them over.
+/// Other geometries are skipped.
+pub fn geofeatures_to_roads(feat: FeatureCollection, opts: &RoadFeatures) -> RoadGraph {
+	let scale = 10f64.powi(opts.precision);
+	let mut nodes: IndexMap<(i64, i64), Node> = IndexMap::new();
+	let mut node = |c: &[f64]| -> NodeId {
+		let n = nodes.len();
+		nodes.entry(((c[0]*scale).round() as i64, (c[1]*scale).round() as i64)).or_insert_with(|| Node { id: format!("n{}", n).into(), coordinates: (c[0], c[1]), elevation: c.get(2).copied() }).id.clone()
+	};
+	let mut roads = Vec::new();
+	for f in feat.features {
+		let lines = match f.geometry.as_ref().map(|g| &g.value) {
+			Some(Value::LineString(l)) => vec![l.clone()],
+			Some(Value::MultiLineString(ls)) => ls.clone(),
+			_ => {
+				log::warn!("Skipping a non-line road feature {:?}", f.id);
+				continue;
+			},
+		};
+		let direction = line_direction(f.property(&opts.direction));
+		let string = |p: &str| f.property(p).and_then(|v| v.as_str()).map(str::to_string);
+		let flag = |p: &str| match f.property(p) {
+			Some(serde_json::Value::Bool(b)) => *b,
+			Some(serde_json::Value::String(s)) => !matches!(s.to_lowercase().as_str(), "" | "no" | "false" | "0"),
+			_ => false,
+		};
+		for line in lines.into_iter().filter(|l| l.len() >= 2 && l.iter().all(|c| c.len() >= 2)) {
+			let (first, last) = (node(&line[0]), node(&line[line.len()-1]));
+			let (p1, p2) = if direction == Some(false) { (last, first) } else { (first, last) };
+			roads.push(RoadSegment {
+				p1,
+				p2,
+				discriminator: None,
+				directed: direction.is_some(),
+				distance: n64(line.iter().zip(line.iter().skip(1)).map(|(u, v)| validate::geodesic((u[0], u[1]), (v[0], v[1]))).sum()),
+				sidewalks: (false, false),
+				parking: (false, false),
+				surface: string("surface"),
+				class: string("class"),
+				bridge: flag("bridge"),
+				shaded: flag("shaded"),
+				priority: f.property("priority").and_then(|p| p.as_u64()).map(|p| p as u32),
+				windows: f.property("windows").and_then(|w| serde_json::from_value(w.clone()).ok()).unwrap_or_default(),
+				crossing: f.property("crossing").and_then(|c| c.as_f64()).map(n64),
+				properties: f.properties.clone().unwrap_or_default(),
+			});
+		}
+	}
+	RoadGraph {
+		roads,
+		nodes: RoadGraphNodes { nodes: nodes.into_iter().map(|(_, n)| n).collect() },
+	}
+}
+
 pub fn snows_to_geofeatures(g: &RoadGraph, snow: data::SnowStatuses) -> FeatureCollection {
 	let coords: IndexMap<_, _> = g.nodes.nodes.iter().map(|n| (&n.id, n.coordinates)).collect();
 	FeatureCollection {
-		features: snow.into_iter().map(|s| Feature {
-			geometry: Some(Geometry::new(Value::LineString(vec![s.p1, s.p2].into_iter().map(|p| coords.get(&p).unwrap()).map(|(lon, lat)| vec![*lon, *lat]).collect()))),
-			properties: Some(indexmap!{ "snow".to_string() => serde_json::to_value(s.depth).unwrap() }.into_iter().collect()),
-			bbox: None,
-			foreign_members: None,
-			id: None,
+		features: snow.into_iter().filter_map(|s| match (coords.get(&s.p1), coords.get(&s.p2)) {
+			(Some(c1), Some(c2)) => Some(Feature {
+				geometry: Some(Geometry::new(Value::LineString(vec![vec![c1.0, c1.1], vec![c2.0, c2.1]]))),
+				properties: Some(indexmap!{ "snow".to_string() => serde_json::to_value(s.depth).unwrap() }.into_iter().collect()),
+				bbox: None,
+				foreign_members: None,
+				id: None,
+			}),
+			_ => {
+				log::warn!("Skipping snow on {}<->{}, not in the road graph", s.p1, s.p2);
+				None
+			},
 		}).collect(),
 		bbox: None,
 		foreign_members: None,
 	}
 }
+
+/// A file produced by an [`Export`]
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+pub struct ManifestEntry {
+	pub file: String,
+	pub vehicle: Option<VehicleId>,
+	/// Part number, if the output was split into several files
+	#[serde(default)]
+	pub part: Option<usize>,
+	pub bytes: u64,
+	pub features: usize,
+}
+
+/// Listing of all files produced by an [`Export`]
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+pub struct Manifest {
+	pub files: Vec<ManifestEntry>,
+	/// Content hash of the road graph the files were made from
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub graph: Option<String>,
+}
+
+/// Limits on the size of a single exported file, beyond which the output is split into numbered parts
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct SplitLimits {
+	/// Maximum number of features in a file
+	pub max_features: Option<usize>,
+	/// Maximum (approximate) size of a file, in bytes
+	pub max_size: Option<u64>,
+}
+
+impl SplitLimits {
+	fn json_size(v: &impl serde::Serialize) -> u64 {
+		serde_json::to_vec(v).map_or(0, |v| v.len() as u64)
+	}
+	/// Splits GeoJSON into parts within the limits
+	///
+	/// Feature collections are split between features, line strings between coordinates (consecutive parts sharing the end points).
+	/// A single feature that is too big on its own is not split further.
+	pub fn split(&self, gj: GeoJson) -> Vec<GeoJson> {
+		let max_size = self.max_size.unwrap_or(u64::MAX);
+		match gj {
+			GeoJson::FeatureCollection(fc) if self.max_features.is_some_and(|m| fc.features.len() > m) || Self::json_size(&fc) > max_size => {
+				let overhead = Self::json_size(&FeatureCollection { features: Vec::new(), bbox: None, foreign_members: None });
+				let mut parts: Vec<(Vec<Feature>, u64)> = Vec::new();
+				for f in fc.features {
+					let size = Self::json_size(&f) + 1;
+					match parts.last_mut() {
+						Some((fs, s)) if self.max_features.is_none_or(|m| fs.len() < m) && *s + size <= max_size => {
+							fs.push(f);
+							*s += size;
+						},
+						_ => parts.push((vec![f], overhead + size)),
+					}
+				}
+				parts.into_iter().map(|(features, _)| FeatureCollection { features, bbox: None, foreign_members: None }.into()).collect()
+			},
+			GeoJson::Geometry(Geometry { value: Value::LineString(l), .. }) if l.len() > 2 && Self::json_size(&l) > max_size => {
+				let n = Self::json_size(&l).div_ceil(max_size.max(1)) as usize;
+				let chunk = ((l.len() - 1) / n).max(1);
+				(0..l.len() - 1).step_by(chunk).map(|i| Geometry::new(Value::LineString(l[i..(i + chunk + 1).min(l.len())].to_vec())).into()).collect()
+			},
+			gj => vec![gj],
+		}
+	}
+}
+
+/// Checks GeoJSON read back from an export - coordinates finite and in range, line strings non-empty
+///
+/// Returns: the number of line segments (between consecutive line string positions) in it
+fn verify(gj: &GeoJson) -> Result<usize, String> {
+	fn position(p: &[f64]) -> Result<(), String> {
+		match p {
+			[lon, lat, ..] if lon.is_finite() && lat.is_finite() && (-180.0..=180.0).contains(lon) && (-90.0..=90.0).contains(lat) => Ok(()),
+			_ => Err(format!("invalid position {:?}", p)),
+		}
+	}
+	fn line(l: &[Vec<f64>]) -> Result<usize, String> {
+		if l.is_empty() {
+			return Err("empty line string".to_string());
+		}
+		l.iter().try_for_each(|p| position(p))?;
+		Ok(l.len() - 1)
+	}
+	fn geometry(g: &Geometry) -> Result<usize, String> {
+		match &g.value {
+			Value::Point(p) => position(p).map(|_| 0),
+			Value::MultiPoint(ps) => ps.iter().try_for_each(|p| position(p)).map(|_| 0),
+			Value::LineString(l) => line(l),
+			Value::MultiLineString(ls) | Value::Polygon(ls) => ls.iter().map(|l| line(l)).sum(),
+			Value::MultiPolygon(ps) => ps.iter().flatten().map(|l| line(l)).sum(),
+			Value::GeometryCollection(gs) => gs.iter().map(geometry).sum(),
+		}
+	}
+	match gj {
+		GeoJson::Geometry(g) => geometry(g),
+		GeoJson::Feature(f) => feature(f),
+		GeoJson::FeatureCollection(fc) => fc.features.iter().map(feature).sum(),
+	}
+}
+
+/// Checks a feature's geometry (see [`verify`])
+///
+/// Returns: the number of line segments in it - or of the original line, if [simplified](simplify)
+fn feature(f: &Feature) -> Result<usize, String> {
+	let n = f.geometry.as_ref().map_or(Ok(0), |g| verify(&GeoJson::Geometry(g.clone())))?;
+	Ok(f.property("segments").and_then(|s| s.as_u64()).map_or(n, |s| s as usize))
+}
+
+/// Staged multi-file GeoJSON export.
+///
+/// Files are written to temporaries, and only moved into place (followed by the manifest) on [`Export::commit`],
+/// so that a partial export can't be mistaken for a complete one.
+/// Before that, every file is read back and checked (see [`Export::segments`]), failing the commit on any inconsistency.
+/// Dropping an uncommitted export cleans up the temporaries.
+#[derive(Default)]
+pub struct Export {
+	staged: Vec<(PathBuf, PathBuf)>,
+	files: Vec<ManifestEntry>,
+	limits: SplitLimits,
+	graph: Option<String>,
+	segments: Option<IndexMap<VehicleId, usize>>,
+}
+
+impl Export {
+	/// An export splitting files beyond the limits into `{file}.{part}.geojson`
+	pub fn with_limits(limits: SplitLimits) -> Self {
+		Self { staged: Vec::new(), files: Vec::new(), limits, graph: None, segments: None }
+	}
+	/// Records the road graph the files are made from in the manifest, by its [content hash](RoadGraph::content_hash)
+	pub fn graph(mut self, roads: &RoadGraph) -> Self {
+		self.graph = Some(roads.content_hash());
+		self
+	}
+	/// Expects the files of every vehicle to add up to its path's segments - with a path missing nodes, for one, they don't
+	pub fn segments(mut self, paths: &Paths) -> Self {
+		self.segments = Some(paths.0.iter().map(|(id, path)| (id.clone(), path.len().saturating_sub(1))).collect());
+		self
+	}
+	/// Reads back the staged files, checking them and their segments
+	///
+	/// The segments of files of no particular vehicle go to their features' `vehicle`s, if any.
+	fn verify(&self) -> Result<(), String> {
+		let mut segments: IndexMap<VehicleId, usize> = IndexMap::new();
+		for ((tmp, _), entry) in self.staged.iter().zip(&self.files) {
+			let invalid = |e: String| format!("{} {}", entry.file, e);
+			let gj: GeoJson = serde_json::from_reader(std::io::BufReader::new(std::fs::File::open(tmp).map_err(|e| invalid(e.to_string()))?)).map_err(|e| invalid(e.to_string()))?;
+			let n = verify(&gj).map_err(invalid)?;
+			match (&entry.vehicle, &gj) {
+				(Some(v), _) => *segments.entry(v.clone()).or_default() += n,
+				(None, GeoJson::FeatureCollection(fc)) => for f in &fc.features {
+					if let Some(v) = f.property("vehicle").and_then(|v| v.as_str()) {
+						*segments.entry(v.to_string()).or_default() += feature(f).map_err(invalid)?;
+					}
+				},
+				_ => (),
+			}
+		}
+		for (v, expected) in self.segments.iter().flatten() {
+			let n = segments.get(v).copied().unwrap_or(0);
+			if n != *expected {
+				return Err(format!("vehicle {} exported with {} segments, its path has {}", v, n, expected));
+			}
+		}
+		Ok(())
+	}
+	fn staging(file: &str) -> PathBuf {
+		PathBuf::from(format!("{}.part", file))
+	}
+	/// Stage a GeoJSON file, or its parts if over the limits
+	pub fn write(&mut self, file: String, vehicle: Option<VehicleId>, gj: impl Into<GeoJson>) -> std::io::Result<()> {
+		let parts = self.limits.split(gj.into());
+		if parts.len() == 1 {
+			return self.write_part(file, vehicle, None, parts.into_iter().next().unwrap());
+		}
+		let stem = file.strip_suffix(".geojson").unwrap_or(&file).to_string();
+		for (i, gj) in parts.into_iter().enumerate() {
+			self.write_part(format!("{}.{}.geojson", stem, i), vehicle.clone(), Some(i), gj)?;
+		}
+		Ok(())
+	}
+	fn write_part(&mut self, file: String, vehicle: Option<VehicleId>, part: Option<usize>, gj: GeoJson) -> std::io::Result<()> {
+		let features = match &gj {
+			GeoJson::FeatureCollection(fc) => fc.features.len(),
+			_ => 1,
+		};
+		let tmp = Self::staging(&file);
+		serde_json::to_writer(&std::fs::File::create(&tmp)?, &gj)?;
+		let bytes = std::fs::metadata(&tmp)?.len();
+		self.staged.push((tmp, PathBuf::from(&file)));
+		self.files.push(ManifestEntry { file, vehicle, part, bytes, features });
+		Ok(())
+	}
+	/// Move all staged files into place and write the manifest
+	pub fn commit(mut self, manifest: &str) -> std::io::Result<Manifest> {
+		self.verify().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Inconsistent export: {}", e)))?;
+		let manifest_tmp = Self::staging(manifest);
+		let m = Manifest { files: std::mem::take(&mut self.files), graph: self.graph.take() };
+		serde_json::to_writer_pretty(&std::fs::File::create(&manifest_tmp)?, &m)?;
+		self.staged.push((manifest_tmp, PathBuf::from(manifest)));
+		for (tmp, file) in std::mem::take(&mut self.staged) {
+			std::fs::rename(tmp, file)?;
+		}
+		Ok(m)
+	}
+}
+
+impl Drop for Export {
+	fn drop(&mut self) {
+		for (tmp, _) in &self.staged {
+			let _ = std::fs::remove_file(tmp);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_geofeatures_to_roads() {
+		let feat: FeatureCollection = serde_json::from_str(r#"{"type": "FeatureCollection", "features": [
+			{"type": "Feature", "properties": {"oneway": "yes", "class": "arterial", "ward": 7}, "geometry": {"type": "LineString", "coordinates": [[0, 0], [0.0005, 0], [0.001, 0]]}},
+			{"type": "Feature", "properties": {"oneway": -1}, "geometry": {"type": "LineString", "coordinates": [[0.001, 0], [0.001, 0.001]]}},
+			{"type": "Feature", "properties": {}, "geometry": {"type": "LineString", "coordinates": [[0.001, 0.001], [0.00000001, 0]]}},
+			{"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [0, 0]}}
+		]}"#).unwrap();
+		let g = geofeatures_to_roads(feat, &Default::default());
+		assert_eq!(g.nodes.nodes.iter().map(|n| n.id.name().unwrap()).collect::<Vec<_>>(), vec!["n0", "n1", "n2"]);
+		assert_eq!(g.roads.iter().map(|e| (e.p1.name().unwrap(), e.p2.name().unwrap(), e.directed)).collect::<Vec<_>>(), vec![("n0", "n1", true), ("n2", "n1", true), ("n2", "n0", false)]);
+		assert_eq!(g.roads[0].class.as_deref(), Some("arterial"));
+		assert!((g.roads[0].distance.raw() - 111.2).abs() < 0.1);
+		let plan: Paths = serde_json::from_str(r#"{"t": [{"node": "n0", "discriminator": null}, {"node": "n1", "discriminator": null}]}"#).unwrap();
+		let view = view::SolutionView::new(&g, &plan, None);
+		let t = "t".to_string();
+		let fc = traversals_to_geofeatures(&roads_to_nodes(g.nodes.clone()), view.vehicle(&t));
+		assert_eq!(fc.features[0].property("ward"), Some(&serde_json::Value::from(7)));
+		assert_eq!(fc.features[0].property("vehicle"), Some(&serde_json::Value::from("t")));
+	}
+
+	#[test]
+	fn test_properties_round_trip() {
+		// imported properties survive the road graph being saved, and follow every traversal - either way - to the exported plan
+		let feat: FeatureCollection = serde_json::from_str(r#"{"type": "FeatureCollection", "features": [
+			{"type": "Feature", "properties": {"pavement": "P-12", "ward": 7, "asset": {"owner": "city"}}, "geometry": {"type": "LineString", "coordinates": [[0, 0], [0.001, 0]]}},
+			{"type": "Feature", "properties": {"pavement": "P-13", "ward": 8}, "geometry": {"type": "LineString", "coordinates": [[0.001, 0], [0.002, 0]]}},
+			{"type": "Feature", "properties": null, "geometry": {"type": "LineString", "coordinates": [[0.002, 0], [0.002, 0.001]]}}
+		]}"#).unwrap();
+		let g: RoadGraph = serde_json::from_value(serde_json::to_value(geofeatures_to_roads(feat, &Default::default())).unwrap()).unwrap();
+		assert_eq!(g.roads[0].properties.get("asset"), Some(&serde_json::json!({"owner": "city"})));
+		assert!(g.roads[2].properties.is_empty());
+		let plan: Paths = serde_json::from_str(r#"{"t": [{"node": "n0", "discriminator": null}, {"node": "n1", "discriminator": null}, {"node": "n2", "discriminator": null}, {"node": "n3", "discriminator": null}, {"node": "n2", "discriminator": null}, {"node": "n1", "discriminator": null}]}"#).unwrap();
+		let view = view::SolutionView::new(&g, &plan, None);
+		let fc = traversals_to_geofeatures(&roads_to_nodes(g.nodes.clone()), view.vehicle(&"t".to_string()));
+		let property = |i: usize, k: &str| fc.features[i].property(k).cloned();
+		assert_eq!((0..5).map(|i| property(i, "pavement")).collect::<Vec<_>>(), vec![Some("P-12".into()), Some("P-13".into()), None, None, Some("P-13".into())]);
+		assert_eq!(property(0, "asset"), Some(serde_json::json!({"owner": "city"})));
+		assert_eq!(fc.features[2].properties, serde_json::json!({"vehicle": "t", "index": 2}).as_object().cloned());
+		assert_eq!(property(4, "ward"), Some(8.into()));
+	}
+
+	#[test]
+	fn test_verify() {
+		let line = |l: Vec<Vec<f64>>| GeoJson::from(Geometry::new(Value::LineString(l)));
+		assert_eq!(verify(&line(vec![vec![0.0, 0.0], vec![0.001, 0.0], vec![0.001, 0.001]])), Ok(2));
+		assert!(verify(&line(vec![])).is_err());
+		assert!(verify(&line(vec![vec![0.0, f64::NAN]])).is_err());
+		assert!(verify(&line(vec![vec![181.0, 0.0]])).is_err());
+		let dir = std::env::temp_dir().join(format!("wfbfa-verify-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let file = |f: &str| dir.join(f).to_str().unwrap().to_string();
+		let nodes = roads_to_nodes(RoadGraphNodes { nodes: vec![Node { id: "a".into(), coordinates: (0.0, 0.0), elevation: None }, Node { id: "b".into(), coordinates: (0.001, 0.0), elevation: None }] });
+		let plan: Paths = serde_json::from_str(r#"{"t": [{"node": "a", "discriminator": null}, {"node": "b", "discriminator": null}, {"node": "x", "discriminator": null}]}"#).unwrap();
+		let mut export = Export::default().segments(&plan);
+		export.write(file("t.geojson"), Some("t".into()), path_to_geojson(&nodes, &"t".to_string(), plan.0["t"].clone())).unwrap();
+		assert!(export.commit(&file("manifest.json")).unwrap_err().to_string().contains("vehicle t exported with 1 segments, its path has 2"));
+		assert!(!dir.join("t.geojson").exists() && !dir.join("t.geojson.part").exists());
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn test_path_to_geojson() {
+		let nodes = roads_to_nodes(RoadGraphNodes { nodes: vec![Node { id: "a".into(), coordinates: (0.0, 0.0), elevation: None }, Node { id: "b".into(), coordinates: (0.001, 0.0), elevation: None }] });
+		let plan: Paths = serde_json::from_str(r#"{"t": [{"node": "a", "discriminator": null}, {"node": "b", "discriminator": "d1", "leg": "survey"}, {"node": "a", "discriminator": "d2", "reversal": true, "leg": "return"}], "u": [{"node": "b", "discriminator": null}]}"#).unwrap();
+		let fc = path_to_geojson(&nodes, &"t".to_string(), plan.0["t"].clone());
+		assert_eq!(fc.features.len(), 2);
+		assert_eq!(fc.features[1].properties, serde_json::json!({ "vehicle": "t", "index": 1, "from": "b", "to": "a", "discriminator": "d2", "reversal": true, "leg": "return" }).as_object().cloned());
+		let dir = std::env::temp_dir().join(format!("wfbfa-paths-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let file = |f: &str| dir.join(f).to_str().unwrap().to_string();
+		// a single file for all vehicles still adds up their segments
+		let mut export = Export::default().segments(&plan);
+		export.write(file("all.geojson"), None, paths_to_geojson(&nodes, plan.clone())).unwrap();
+		assert_eq!(export.commit(&file("manifest.json")).unwrap().files[0].features, 2);
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn test_simplify() {
+		// a straight line with a 5m kink, then a 50m one (~0.000045° / ~0.00045° off)
+		let nodes = roads_to_nodes(RoadGraphNodes { nodes: [(0.0, 0.0), (0.001, 0.0), (0.002, 0.000045), (0.003, 0.0), (0.004, 0.00045), (0.005, 0.0)].iter().enumerate().map(|(i, c)| Node { id: NodeId::Int(i as u64), coordinates: *c, elevation: None }).collect() });
+		let plan = Routes(indexmap!{ "t".to_string() => (0..6).map(|i| PathSegment { node: NodeId::Int(i), discriminator: None, reversal: false, leg: None }).collect() });
+		let f = simplify(path_to_line_geojson(&nodes, &"t".to_string(), &plan.0["t"]), 10.0);
+		match &f.geometry.as_ref().unwrap().value {
+			Value::LineString(l) => assert_eq!(l.iter().map(|p| p[0]).collect::<Vec<_>>(), vec![0.0, 0.003, 0.004, 0.005]),
+			_ => panic!(),
+		}
+		assert_eq!(f.property("segments"), Some(&serde_json::Value::from(5)));
+		// the export still adds up to the path's segments
+		let dir = std::env::temp_dir().join(format!("wfbfa-simplify-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let mut export = Export::default().segments(&plan);
+		export.write(dir.join("t.geojson").to_str().unwrap().to_string(), Some("t".into()), f).unwrap();
+		assert!(export.commit(dir.join("manifest.json").to_str().unwrap()).is_ok());
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn test_split() {
+		let point = |x: f64| Feature { geometry: Some(Geometry::new(Value::Point(vec![x, 0.0]))), properties: None, bbox: None, foreign_members: None, id: None };
+		let fc = FeatureCollection { features: (0..5).map(|x| point(x as f64)).collect(), bbox: None, foreign_members: None };
+		let features = |parts: Vec<GeoJson>| parts.into_iter().map(|gj| match gj {
+			GeoJson::FeatureCollection(fc) => fc.features.len(),
+			_ => 0,
+		}).collect::<Vec<_>>();
+		assert_eq!(features(SplitLimits::default().split(fc.clone().into())), vec![5]);
+		assert_eq!(features(SplitLimits { max_features: Some(2), ..Default::default() }.split(fc.clone().into())), vec![2, 2, 1]);
+		let size = SplitLimits::json_size(&point(0.0)) + 1;
+		assert_eq!(features(SplitLimits { max_size: Some(SplitLimits::json_size(&fc) - 2*size + 1), ..Default::default() }.split(fc.into())), vec![3, 2]);
+		let line: Vec<_> = (0..7).map(|x| vec![x as f64, 0.0]).collect();
+		let parts = SplitLimits { max_size: Some(SplitLimits::json_size(&line) / 3 + 1), ..Default::default() }.split(Geometry::new(Value::LineString(line)).into());
+		assert_eq!(parts.iter().map(|gj| match gj {
+			GeoJson::Geometry(Geometry { value: Value::LineString(l), .. }) => (l[0][0], l[l.len()-1][0]),
+			_ => (-1.0, -1.0),
+		}).collect::<Vec<_>>(), vec![(0.0, 2.0), (2.0, 4.0), (4.0, 6.0)]);
+	}
+}
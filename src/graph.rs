@@ -3,10 +3,17 @@
 //! Historically, this was built after the algorithms in `brr` proved themselves worthy,
 //! but the implementation was not sufficiently generic to be re-used for road, and then sidewalk, plowing.
 
-use std::{collections::{HashMap, HashSet}, hash::Hash};
+use std::{collections::hash_map::DefaultHasher, hash::{BuildHasherDefault, Hash}};
 
 use indexmap::IndexMap;
 use priority_queue::PriorityQueue;
+use rayon::prelude::*;
+
+/// Hash maps and sets of the graphs and their algorithms
+///
+/// They hash with fixed keys, rather than random ones every process, so they iterate in the same order every run - and a seeded solve is reproducible.
+pub type HashMap<K, V> = std::collections::HashMap<K, V, BuildHasherDefault<DefaultHasher>>;
+pub type HashSet<T> = std::collections::HashSet<T, BuildHasherDefault<DefaultHasher>>;
 
 /// An edge of a graph
 ///
@@ -49,6 +56,9 @@ pub trait Edge<NId: Clone + Copy + Hash + Eq> : Clone + Hash + PartialEq + Eq {
 
 /// A graph
 ///
+/// The graph is [`Send`] and [`Sync`] whenever its node ids, nodes and edges are, so it can be shared across threads by reference
+/// (e.g. for parallel solving) - it has no interior mutability, tombstones included.
+///
 /// Type Parameters:
 /// - `NId`: (lightweight) node id type
 /// - `N`: Node type (can contain arbitrary node information)
@@ -61,8 +71,72 @@ where
 {
 	nodes: HashMap<NId, N>,
 	edges: IndexMap<NId, HashSet<E>>,
-	/// An always empty set of edges (useful for [`get_edges`] on a non-existing node)
-	_empty: HashSet<E>,
+	/// Tombstoned nodes - temporarily removed, along with all of their edges
+	disabled_nodes: HashSet<NId>,
+	/// Tombstoned edges - temporarily removed
+	disabled_edges: HashSet<E>,
+}
+
+/// Reusable state of shortest path searches, sparing their allocations over repeated searching
+pub struct Scratch<'a, NId: Hash + Eq, E, Weight: Ord> {
+	dp: HashMap<NId, (Weight, Option<&'a E>)>,
+	q: PriorityQueue<NId, Weight>,
+}
+
+impl<'a, NId: Hash + Eq, E, Weight: Ord> Default for Scratch<'a, NId, E, Weight> {
+	fn default() -> Self {
+		Self { dp: HashMap::default(), q: PriorityQueue::new() }
+	}
+}
+
+/// Shortest paths from and to key nodes (e.g. depots, reload points, district boundaries), computed once per graph and looked up after
+///
+/// Holds a shortest path tree from, and one to, every key node - so costs and paths between key nodes, or between a key node and any other, need no search.
+pub struct CostMatrix<'a, NId: Hash + Eq, E, Weight> {
+	/// Cost of reaching every node from the key node, and the edge it's reached by
+	from: HashMap<NId, HashMap<NId, (Weight, Option<&'a E>)>>,
+	/// Cost of reaching the key node from every node, and the edge it leaves by
+	to: HashMap<NId, HashMap<NId, (Weight, Option<&'a E>)>>,
+}
+
+impl<'a, NId, E, Weight> CostMatrix<'a, NId, E, Weight>
+where
+	NId: Clone + Copy + Hash + Eq,
+	E: Edge<NId>,
+	Weight: Clone + Copy,
+{
+	/// The key nodes
+	pub fn keys(&self) -> impl Iterator<Item=NId> + '_ {
+		self.from.keys().copied()
+	}
+	/// Cost of the shortest path from `n1` to `n2` - either of which is to be a key node - if such exists
+	pub fn cost(&self, n1: NId, n2: NId) -> Option<Weight> {
+		self.from.get(&n1).and_then(|t| t.get(&n2)).or_else(|| self.to.get(&n2).and_then(|t| t.get(&n1))).map(|(w, _)| *w)
+	}
+	/// Edges of the shortest path from `n1` to `n2` - either of which is to be a key node - if such exists
+	pub fn path(&self, n1: NId, n2: NId) -> Option<Vec<&'a E>> {
+		let mut path = Vec::new();
+		if let Some(tree) = self.from.get(&n1).filter(|t| t.contains_key(&n2)) {
+			let mut v = n2;
+			while let Some((_, Some(e))) = tree.get(&v) {
+				path.push(*e);
+				v = e.other(v);
+			}
+			path.reverse();
+		} else {
+			let tree = self.to.get(&n2).filter(|t| t.contains_key(&n1))?;
+			let mut u = n1;
+			while let Some((_, Some(e))) = tree.get(&u) {
+				path.push(*e);
+				u = e.other(u);
+			}
+		}
+		Some(path)
+	}
+	/// The key node (satisfying `filter`) cheapest to reach from `n`, and the cost of reaching it
+	pub fn nearest(&self, n: NId, filter: impl Fn(NId) -> bool) -> Option<(NId, Weight)> where Weight: Ord {
+		self.to.iter().filter(|(k, _)| filter(**k)).filter_map(|(k, t)| t.get(&n).map(|(w, _)| (*k, *w))).min_by_key(|(_, w)| *w)
+	}
 }
 
 impl<NId, N, E> Default for Graph<NId, N, E>
@@ -74,7 +148,8 @@ where
 		Self {
 			nodes: Default::default(),
 			edges: Default::default(),
-			_empty: Default::default(),
+			disabled_nodes: Default::default(),
+			disabled_edges: Default::default(),
 		}
 	}
 }
@@ -92,41 +167,49 @@ where
 	pub fn get_node(&self, n: NId) -> Option<&N> {
 		self.nodes.get(&n)
 	}
+	/// Whether the node is not tombstoned
+	pub fn is_node_enabled(&self, n: NId) -> bool {
+		!self.disabled_nodes.contains(&n)
+	}
+	/// Whether the edge is not tombstoned, nor any of its ends
+	pub fn is_enabled(&self, e: &E) -> bool {
+		!self.disabled_edges.contains(e) && self.is_node_enabled(e.p1()) && self.is_node_enabled(e.p2())
+	}
 	/// Get all edges of a node
-	pub fn get_edges(&self, n: NId) -> &HashSet<E> {
-		self.edges.get(&n).unwrap_or(&self._empty)
+	pub fn get_edges(&self, n: NId) -> impl Iterator<Item=&E> {
+		self.edges.get(&n).into_iter().flat_map(|es| es.iter()).filter(move |e| self.is_enabled(e))
 	}
 	/// Whether the given node has no edges
 	pub fn is_orphan(&self, n: NId) -> bool {
-		self.get_edges(n).is_empty()
+		self.get_edges(n).next().is_none()
 	}
 	/// Get all edges between 2 nodes
 	pub fn get_edges_between(&self, n1: NId, n2: NId) -> Vec<&E> {
-		self.edges.get(&n1).iter().flat_map(|es| es.iter()).filter(|e| e.other(n1) == n2).collect()
+		self.get_edges(n1).filter(|e| e.other(n1) == n2).collect()
 	}
 	/// Get all nodes
 	pub fn nodes(&self) -> impl Iterator<Item=(NId,&N)> {
-		self.nodes.iter().map(|(id, n)| (*id, n))
+		self.nodes.iter().filter(move |(id, _)| self.is_node_enabled(**id)).map(|(id, n)| (*id, n))
 	}
 	/// Get all edges
 	pub fn edges(&self) -> impl Iterator<Item=&E> {
-		self.edges.iter().flat_map(|(n, es)| es.iter().filter(move |e| e.is_cyclic() || e.p1() == *n))
+		self.edges.iter().flat_map(|(n, es)| es.iter().filter(move |e| e.is_cyclic() || e.p1() == *n)).filter(move |e| self.is_enabled(e))
 	}
-	/// Number of nodes
+	/// Number of nodes, tombstoned ones aside
 	pub fn node_count(&self) -> usize {
-		self.nodes.len()
+		self.nodes.len() - self.disabled_nodes.iter().filter(|n| self.nodes.contains_key(n)).count()
 	}
 	/// Number of edges
 	pub fn edge_count(&self) -> usize {
 		self.edges().count()
 	}
-	/// Whether the graph is empty
+	/// Whether the graph is empty, tombstoned nodes aside
 	pub fn is_empty(&self) -> bool {
-		self.nodes.is_empty()
+		self.node_count() == 0
 	}
 	/// Whether the graph has no edges
 	pub fn is_edge_empty(&self) -> bool {
-		self.edges.values().all(HashSet::is_empty)
+		self.edges().next().is_none()
 	}
 	/// Adds (or replaces) a node
 	pub fn add_node(&mut self, id: NId, n: N) -> Option<N> {
@@ -156,6 +239,83 @@ where
 			false
 		}
 	}
+	/// Updates an edge's payload (e.g. its weight) in place, re-indexing it at both ends - and in the tombstones, if disabled
+	///
+	/// The edge is found by its identity (its [`Eq`]), so clones of it held elsewhere still match it afterwards unless `f` changes that too.
+	/// Should `f` change the ends, the edge moves to the new ones - provided they're nodes of the graph, the edge being left as it was otherwise.
+	///
+	/// Returns: whether the edge was updated
+	pub fn update_edge(&mut self, e: &E, f: impl FnOnce(&mut E)) -> bool {
+		let mut updated = match self.edges.get(&e.p2()).and_then(|es| es.get(e)) {
+			Some(e) => e.clone(),
+			None => return false,
+		};
+		f(&mut updated);
+		if !(self.nodes.contains_key(&updated.p1()) && self.nodes.contains_key(&updated.p2())) {
+			return false;
+		}
+		self.remove_edge(e);
+		if self.disabled_edges.remove(e) {
+			self.disabled_edges.insert(updated.clone());
+		}
+		self.add_edge(updated)
+	}
+	/// Tombstones a node (and all of its edges), until re-enabled
+	///
+	/// Returns: whether the node was enabled
+	pub fn disable_node(&mut self, n: NId) -> bool {
+		self.disabled_nodes.insert(n)
+	}
+	/// Re-enables a tombstoned node
+	///
+	/// Returns: whether the node was disabled
+	pub fn enable_node(&mut self, n: NId) -> bool {
+		self.disabled_nodes.remove(&n)
+	}
+	/// Tombstones an edge, until re-enabled
+	///
+	/// Returns: whether the edge was enabled
+	pub fn disable_edge(&mut self, e: E) -> bool {
+		self.disabled_edges.insert(e)
+	}
+	/// Re-enables a tombstoned edge
+	///
+	/// Returns: whether the edge was disabled
+	pub fn enable_edge(&mut self, e: &E) -> bool {
+		self.disabled_edges.remove(e)
+	}
+	/// Runs `f` on the graph with the `edges` tombstoned, re-enabling them afterwards
+	///
+	/// Edges that were already disabled stay so.
+	pub fn with_disabled<R>(&mut self, edges: impl IntoIterator<Item = E>, f: impl FnOnce(&Self) -> R) -> R {
+		let disabled: Vec<E> = edges.into_iter().filter(|e| self.disable_edge(e.clone())).collect();
+		let r = f(self);
+		for e in &disabled {
+			self.enable_edge(e);
+		}
+		r
+	}
+	/// The graph's own edge equal to `e` - e.g. mapping an edge of a [`Graph::subgraph`] back to the parent graph
+	pub fn get_edge(&self, e: &E) -> Option<&E> {
+		self.edges.get(&e.p2()).and_then(|es| es.get(e)).filter(|e| self.is_enabled(e))
+	}
+	/// A new graph, of the nodes matching `node` and the edges between them matching `edge`, with their payloads
+	///
+	/// Node ids and edges stay the same, so anything found on the subgraph maps back with [`Graph::get_edge`].
+	/// Tombstoned nodes and edges are left out.
+	pub fn subgraph(&self, node: impl Fn(NId, &N) -> bool, edge: impl Fn(&E) -> bool) -> Self
+	where
+		N: Clone,
+	{
+		let mut g = Self::default();
+		for (id, n) in self.nodes().filter(|(id, n)| node(*id, n)) {
+			g.add_node(id, n.clone());
+		}
+		for e in self.edges().filter(|e| edge(e)) {
+			g.add_edge(e.clone());
+		}
+		g
+	}
 	/// Retains only the nodes (and edges) matching the predicate
 	pub fn retain_nodes(&mut self, f: impl Fn(NId) -> bool){
 		self.nodes.retain(|n, _| f(*n));
@@ -168,6 +328,23 @@ where
 			es.retain(|e| f(e.other(*u)));
 		}
 	}
+	/// All nodes reachable from a node - or with `backward`, from which the node can be reached
+	///
+	/// Type Parameters:
+	/// - `DIRESPECT`: whether the directionality of edges is respected
+	pub fn reachable<const DIRESPECT: bool>(&self, n: NId, backward: bool) -> HashSet<NId> {
+		let mut seen: HashSet<NId> = std::iter::once(n).collect();
+		let mut stack = vec![n];
+		while let Some(u) = stack.pop() {
+			for e in self.get_edges(u) {
+				let traversable = if backward { e.is_incoming::<DIRESPECT>(u) } else { e.is_outgoing::<DIRESPECT>(u) };
+				if traversable && seen.insert(e.other(u)) {
+					stack.push(e.other(u));
+				}
+			}
+		}
+		seen
+	}
 	/// Find all edges going from one region to another
 	///
 	/// Arguments:
@@ -208,36 +385,20 @@ where
 		Weight: Clone + Copy + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight>,
 		FW: Fn(&E) -> Option<Weight>,
 	{
-		let mut dp: HashMap<NId, (Weight, Option<&E>)> = HashMap::new();
-		dp.insert(n1.clone(), (Weight::default(), None));
-		let mut q = PriorityQueue::new();
-		q.push(n1.clone(), Weight::default());
-		while let Some((u, _)) = q.pop() {
-			if u == n2 {
-				let mut path = Vec::new();
-				let mut v = u;
-				while let Some((_, Some(e))) = dp.get(&v) {
-					v = e.other(v);
-					path.push(e.clone());
-				}
-				path.reverse();
-				return Some(path);
-			}
-			let d = dp.get(&u).unwrap().0;
-			for e in self.get_edges(u) {
-				if !DIRESPECT || !e.directed() || e.p1() == u {
-					if let Some(ed) = weight(e){
-						let v = e.other(u);
-						let d = d + ed;
-						if dp.get(&v).map_or(true, |(vd, _)| vd > &d) {
-							dp.insert(v.clone(), (d, Some(e)));
-							q.push(v.clone(), -d);
-						}
-					}
-				}
-			}
-		}
-		None
+		let mut path = Vec::new();
+		self.pathfind_into::<_, _, DIRESPECT>(n1, n2, weight, &mut Scratch::default(), &mut path).then_some(path)
+	}
+	/// Find shortest path between 2 nodes, reusing buffers
+	///
+	/// Same as [`Self::pathfind`], but with the search state in `scratch`, and the path appended to `path`.
+	///
+	/// Returns: whether a path was found (and appended)
+	pub fn pathfind_into<'a, Weight, FW, const DIRESPECT: bool>(&'a self, n1: NId, n2: NId, weight: FW, scratch: &mut Scratch<'a, NId, E, Weight>, path: &mut Vec<&'a E>) -> bool
+	where
+		Weight: Clone + Copy + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight>,
+		FW: Fn(&E) -> Option<Weight>,
+	{
+		self.dijkstra::<_, _, DIRESPECT>(std::iter::once(n1), |u| u == n2, weight, scratch, path).is_some()
 	}
 	/// Find shortest path between 2 regions, edge-weighted by a function
 	///
@@ -254,6 +415,19 @@ where
 	///
 	/// Returns: nodes `n1` and `n2` in the 1st and 2nd regions resp and the edges path from `n1` to `n2`, if such exists
 	pub fn pathfind_regions<Weight, FW, const DIRESPECT: bool>(&self, n1: &HashSet<NId>, n2: &HashSet<NId>, weight: FW) -> Option<(NId, NId, Vec<&E>)>
+	where
+		Weight: Clone + Copy + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight>,
+		FW: Fn(&E) -> Option<Weight>,
+	{
+		let mut path = Vec::new();
+		self.pathfind_regions_into::<_, _, DIRESPECT>(n1, n2, weight, &mut Scratch::default(), &mut path).map(|(u, v)| (u, v, path))
+	}
+	/// Find shortest path between 2 regions, reusing buffers
+	///
+	/// Same as [`Self::pathfind_regions`], but with the search state in `scratch`, and the path appended to `path`.
+	///
+	/// Returns: nodes `n1` and `n2` in the 1st and 2nd regions resp, if a path was found (and appended)
+	pub fn pathfind_regions_into<'a, Weight, FW, const DIRESPECT: bool>(&'a self, n1: &HashSet<NId>, n2: &HashSet<NId>, weight: FW, scratch: &mut Scratch<'a, NId, E, Weight>, path: &mut Vec<&'a E>) -> Option<(NId, NId)>
 	where
 		Weight: Clone + Copy + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight>,
 		FW: Fn(&E) -> Option<Weight>,
@@ -261,22 +435,60 @@ where
 		if n1.is_empty() || n2.is_empty() {
 			return None;
 		}
-		let mut dp: HashMap<NId, (Weight, Option<&E>)> = HashMap::new();
-		let mut q = PriorityQueue::new();
+		self.dijkstra::<_, _, DIRESPECT>(n1.iter().copied(), |u| n2.contains(&u), weight, scratch, path)
+	}
+	/// Find shortest paths between pairs of nodes, independent of each other, in parallel - reusing buffers
+	///
+	/// The queries are split evenly among the `scratches`, a task each on the current rayon pool (none for a single one), every task keeping its search state in its own - so a query allocates nothing, paths aside.
+	/// `results` is resized to the queries: whether a path was found for each, and the path (its buffer reused).
+	pub fn pathfind_batch_into<'a, Weight, FW, const DIRESPECT: bool>(&'a self, queries: &[(NId, NId)], weight: &FW, scratches: &mut [Scratch<'a, NId, E, Weight>], results: &mut Vec<(bool, Vec<&'a E>)>)
+	where
+		NId: Send + Sync,
+		N: Sync,
+		E: Sync,
+		Weight: Clone + Copy + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight> + Send,
+		FW: Fn(&E) -> Option<Weight> + Sync,
+	{
+		results.resize_with(queries.len(), Default::default);
+		if queries.is_empty() || scratches.is_empty() {
+			return;
+		}
+		let chunk = queries.len().div_ceil(scratches.len());
+		let solve = |queries: &[(NId, NId)], results: &mut [(bool, Vec<&'a E>)], scratch: &mut Scratch<'a, NId, E, Weight>| {
+			for ((n1, n2), (found, path)) in queries.iter().zip(results) {
+				path.clear();
+				*found = self.pathfind_into::<_, _, DIRESPECT>(*n1, *n2, weight, scratch, path);
+			}
+		};
+		if chunk == queries.len() {
+			solve(queries, results, &mut scratches[0]);
+		} else {
+			queries.par_chunks(chunk).zip(results.par_chunks_mut(chunk)).zip(scratches.par_iter_mut()).for_each(|((queries, results), scratch)| solve(queries, results, scratch));
+		}
+	}
+	/// Heap-optimized Dijkstra's from the `n1` nodes to the first settled node satisfying `n2`, appending the path to `path`
+	fn dijkstra<'a, Weight, FW, const DIRESPECT: bool>(&'a self, n1: impl Iterator<Item = NId>, n2: impl Fn(NId) -> bool, weight: FW, scratch: &mut Scratch<'a, NId, E, Weight>, path: &mut Vec<&'a E>) -> Option<(NId, NId)>
+	where
+		Weight: Clone + Copy + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight>,
+		FW: Fn(&E) -> Option<Weight>,
+	{
+		let Scratch { dp, q } = scratch;
+		dp.clear();
+		q.clear();
 		for n1 in n1 {
-			dp.insert(n1.clone(), (Weight::default(), None));
-			q.push(n1.clone(), Weight::default());
+			dp.insert(n1, (Weight::default(), None));
+			q.push(n1, Weight::default());
 		}
 		while let Some((u, _)) = q.pop() {
-			if n2.contains(&u) {
-				let mut path = Vec::new();
+			if n2(u) {
+				let k = path.len();
 				let mut v = u;
 				while let Some((_, Some(e))) = dp.get(&v) {
 					v = e.other(v);
-					path.push(e.clone());
+					path.push(*e);
 				}
-				path.reverse();
-				return Some((v, u, path));
+				path[k..].reverse();
+				return Some((v, u));
 			}
 			let d = dp.get(&u).unwrap().0;
 			for e in self.get_edges(u) {
@@ -285,8 +497,8 @@ where
 						let v = e.other(u);
 						let d = d + ed;
 						if dp.get(&v).map_or(true, |(vd, _)| vd > &d) {
-							dp.insert(v.clone(), (d, Some(e)));
-							q.push(v.clone(), -d);
+							dp.insert(v, (d, Some(e)));
+							q.push(v, -d);
 						}
 					}
 				}
@@ -294,6 +506,122 @@ where
 		}
 		None
 	}
+	/// Shortest path tree from (or, `backward`, to) a node - the cost of every node reachable, and the edge it's reached by (or leaves by)
+	fn tree<'a, Weight, FW, const DIRESPECT: bool>(&'a self, n: NId, backward: bool, weight: &FW) -> HashMap<NId, (Weight, Option<&'a E>)>
+	where
+		Weight: Clone + Copy + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight>,
+		FW: Fn(&E) -> Option<Weight>,
+	{
+		let mut dp = HashMap::default();
+		let mut q = PriorityQueue::new();
+		dp.insert(n, (Weight::default(), None));
+		q.push(n, Weight::default());
+		while let Some((u, _)) = q.pop() {
+			let d = dp.get(&u).unwrap().0;
+			for e in self.get_edges(u) {
+				if !DIRESPECT || !e.directed() || (if backward { e.p2() } else { e.p1() }) == u {
+					if let Some(ed) = weight(e) {
+						let v = e.other(u);
+						let d = d + ed;
+						if dp.get(&v).is_none_or(|(vd, _)| vd > &d) {
+							dp.insert(v, (d, Some(e)));
+							q.push(v, -d);
+						}
+					}
+				}
+			}
+		}
+		dp
+	}
+	/// Precomputes shortest paths from and to key nodes, edge-weighted by a function
+	///
+	/// Type Parameters:
+	/// - `Weight`: weight of an edge
+	/// - `DIRESPECT`: whether the directionality of edges is respected
+	///
+	/// Arguments:
+	/// - `keys`: the key nodes
+	/// - `weight`: filtering weight function - returns the weight of the edge, iff it can be traversed
+	pub fn cost_matrix<'a, Weight, FW, const DIRESPECT: bool>(&'a self, keys: impl IntoIterator<Item = NId>, weight: FW) -> CostMatrix<'a, NId, E, Weight>
+	where
+		Weight: Clone + Copy + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight>,
+		FW: Fn(&E) -> Option<Weight>,
+	{
+		let mut matrix = CostMatrix { from: HashMap::default(), to: HashMap::default() };
+		for k in keys {
+			matrix.from.insert(k, self.tree::<_, _, DIRESPECT>(k, false, &weight));
+			matrix.to.insert(k, self.tree::<_, _, DIRESPECT>(k, true, &weight));
+		}
+		matrix
+	}
+	/// Edge betweenness - on how many shortest paths between the nodes of a region each edge lies
+	///
+	/// Currently uses Brandes' algorithm, with heap-optimized Dijkstra's from every node of the region.
+	/// Shortest paths of equal weight share the pair between them; undirected pairs are counted both ways.
+	///
+	/// Type Parameters:
+	/// - `Weight`: weight of an edge
+	/// - `DIRESPECT`: whether the directionality of edges is respected
+	///
+	/// Arguments:
+	/// - `among`: nodes of the region, paths between which are counted
+	/// - `weight`: filtering weight function - returns the weight of the edge, iff it can be traversed
+	///
+	/// Returns: the (fractional) number of shortest paths through every edge that's on any
+	pub fn edge_betweenness<Weight, FW, const DIRESPECT: bool>(&self, among: &HashSet<NId>, weight: FW) -> HashMap<&E, f64>
+	where
+		Weight: Clone + Copy + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight>,
+		FW: Fn(&E) -> Option<Weight>,
+	{
+		let mut betweenness: HashMap<&E, f64> = HashMap::default();
+		for s in among.iter().cloned() {
+			let mut dp: HashMap<NId, (Weight, f64)> = HashMap::default();
+			let mut preds: HashMap<NId, Vec<(NId, &E)>> = HashMap::default();
+			dp.insert(s, (Weight::default(), 1.0));
+			let mut settled = Vec::new();
+			let mut seen = HashSet::default();
+			let mut q = PriorityQueue::new();
+			q.push(s, Weight::default());
+			while let Some((u, _)) = q.pop() {
+				if !seen.insert(u) {
+					continue;
+				}
+				settled.push(u);
+				let (d, paths) = dp[&u];
+				for e in self.get_edges(u) {
+					if e.is_outgoing::<DIRESPECT>(u) && !e.is_cyclic() {
+						if let Some(ed) = weight(e) {
+							let v = e.other(u);
+							let d = d + ed;
+							match dp.get_mut(&v) {
+								Some((vd, vp)) if *vd == d && !seen.contains(&v) => {
+									*vp += paths;
+									preds.entry(v).or_default().push((u, e));
+								}
+								Some((vd, _)) if *vd <= d => (),
+								_ => {
+									dp.insert(v, (d, paths));
+									preds.insert(v, vec![(u, e)]);
+									q.push(v, -d);
+								}
+							}
+						}
+					}
+				}
+			}
+			let mut dependency: HashMap<NId, f64> = HashMap::default();
+			while let Some(w) = settled.pop() {
+				let paths = dp[&w].1;
+				let dw = dependency.get(&w).copied().unwrap_or(0.0) + if w != s && among.contains(&w) { 1.0 } else { 0.0 };
+				for (v, e) in preds.remove(&w).unwrap_or_default() {
+					let c = dp[&v].1 / paths * dw;
+					*betweenness.entry(e).or_default() += c;
+					*dependency.entry(v).or_default() += c;
+				}
+			}
+		}
+		betweenness
+	}
 	/// Detect all strongly connected components in the graph
 	///
 	/// Currently uses unrecursed Tarjan's SCC algorithm.
@@ -307,14 +635,14 @@ where
 		let mut sccs = Vec::new();
 		let mut index = 0usize;
 		let mut stack = Vec::new();
-		let mut inf: HashMap<_, (bool, usize, usize)> = HashMap::new();
+		let mut inf: HashMap<_, (bool, usize, usize)> = HashMap::default();
 		let mut q = Vec::new();
-		for u in self.nodes.keys().into_iter().cloned() {
+		for (u, _) in self.nodes() {
 			if self.is_orphan(u) && !ORPHANS {
 				continue;
 			}
 			if !inf.contains_key(&u) {
-				q.push((u, self.get_edges(u).iter().collect::<Vec<_>>(), false));
+				q.push((u, self.get_edges(u).collect::<Vec<_>>(), false));
 				// "strongconnect"
 				'unrec: while let Some((u, es, jr)) = q.last_mut() {
 					let u = *u;
@@ -334,7 +662,7 @@ where
 								// v has not yet been visited
 								None => {
 									*jr = true;
-									q.push((v, self.get_edges(v).iter().collect::<Vec<_>>(), false));
+									q.push((v, self.get_edges(v).collect::<Vec<_>>(), false));
 									continue 'unrec;
 								},
 								// v was just visited
@@ -354,7 +682,7 @@ where
 					// generate scc
 					let (_, idx, ll) = inf.get(&u).cloned().unwrap();
 					if idx == ll {
-						let mut scc = HashSet::new();
+						let mut scc = HashSet::default();
 						loop {
 							let v = stack.pop().unwrap();
 							inf.get_mut(&v).unwrap().0 = false;
@@ -371,6 +699,29 @@ where
 		}
 		sccs
 	}
+	/// Detect all connected components in the graph, largest first, orphan nodes left out
+	///
+	/// Arguments:
+	/// - `DIRESPECT`: whether the directionality of edges is respected - components are then strongly connected (see [`Self::strongly_connected_components`]), otherwise weakly
+	pub fn connected_components<const DIRESPECT: bool>(&self) -> Vec<HashSet<NId>>
+	where NId: std::fmt::Display {
+		let mut components = if DIRESPECT {
+			self.strongly_connected_components::<true, false>()
+		} else {
+			let mut seen = HashSet::default();
+			let mut components = Vec::new();
+			for (u, _) in self.nodes() {
+				if !self.is_orphan(u) && !seen.contains(&u) {
+					let component = self.reachable::<false>(u, false);
+					seen.extend(component.iter().copied());
+					components.push(component);
+				}
+			}
+			components
+		};
+		components.sort_by_key(|c| std::cmp::Reverse(c.len()));
+		components
+	}
 	/// Patches weak links between regions
 	///
 	/// _SCCs together stronk!_
@@ -384,7 +735,7 @@ where
 		FD: Fn(E) -> E,
 	{
 		if DIRESPECT {
-			let mut redir = HashSet::new();
+			let mut redir = HashSet::default();
 			for i in 0..regions.len() {
 				for j in (i+1)..regions.len() {
 					for (.., e) in self.get_edges_between_regions::<false>(&regions[i], &regions[j]) {
@@ -414,6 +765,93 @@ where
 		}
 		vs
 	}
+	/// Converts a path to successively visited nodes, like [`Self::path_to_nodes`] without the edges, into a reused buffer
+	pub fn path_nodes_into<'a>(path: impl Iterator<Item = &'a E>, n: NId, nodes: &mut Vec<NId>) where E: 'a {
+		nodes.clear();
+		nodes.push(n);
+		for e in path {
+			nodes.push(e.other(*nodes.last().unwrap()));
+		}
+	}
+}
+
+/// Debug snapshots of graphs, for comparing internal states between runs
+#[cfg(feature = "serde")]
+pub mod dump {
+	use super::*;
+	use std::fmt::Debug;
+
+	/// Structural snapshot of a graph (and its id mappings), in a deterministic order
+	#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug, Default)]
+	pub struct GraphDump {
+		/// Node ids, sorted
+		pub nodes: Vec<String>,
+		/// Edges, sorted
+		pub edges: Vec<String>,
+		/// Id mappings (heavy id, graph node id), sorted
+		pub ids: Vec<(String, String)>,
+	}
+
+	impl GraphDump {
+		/// Structural differences from `self` to `other`, as human-readable lines
+		///
+		/// Lines start with `-` for things only in `self`, `+` for things only in `other`, and `~` for changed id mappings.
+		pub fn diff(&self, other: &Self) -> Vec<String> {
+			fn sorted_diff(tag: &str, a: &[String], b: &[String], out: &mut Vec<String>) {
+				let (sa, sb): (HashSet<_>, HashSet<_>) = (a.iter().collect(), b.iter().collect());
+				out.extend(a.iter().filter(|x| !sb.contains(x)).map(|x| format!("- {} {}", tag, x)));
+				out.extend(b.iter().filter(|x| !sa.contains(x)).map(|x| format!("+ {} {}", tag, x)));
+			}
+			let mut out = Vec::new();
+			sorted_diff("node", &self.nodes, &other.nodes, &mut out);
+			sorted_diff("edge", &self.edges, &other.edges, &mut out);
+			let (ia, ib): (HashMap<_, _>, HashMap<_, _>) = (self.ids.iter().map(|(k, v)| (k, v)).collect(), other.ids.iter().map(|(k, v)| (k, v)).collect());
+			for (k, v) in &self.ids {
+				match ib.get(k) {
+					None => out.push(format!("- id {} => {}", k, v)),
+					Some(v2) if *v2 != v => out.push(format!("~ id {} => {} / {}", k, v, v2)),
+					_ => {},
+				}
+			}
+			out.extend(other.ids.iter().filter(|(k, _)| !ia.contains_key(k)).map(|(k, v)| format!("+ id {} => {}", k, v)));
+			out
+		}
+	}
+
+	impl<NId, N, E> Graph<NId, N, E>
+	where 
+		NId: Clone + Copy + Hash + Eq + Ord + Debug,
+		E: Edge<NId> + Debug,
+	{
+		/// Dump the graph structure
+		pub fn dump(&self) -> GraphDump {
+			let mut nodes: Vec<_> = self.nodes.keys().cloned().collect();
+			nodes.sort_unstable();
+			let mut edges: Vec<_> = self.edges().map(|e| format!("{:?}", e)).collect();
+			edges.sort_unstable();
+			GraphDump {
+				nodes: nodes.into_iter().map(|n| format!("{:?}", n)).collect(),
+				edges,
+				ids: Vec::new(),
+			}
+		}
+	}
+
+	impl<NId, N, E, IdAcc, Gen> adapt::GraphAdapter<NId, N, E, IdAcc, Gen>
+	where
+		NId: Clone + Copy + Hash + Eq + Ord + Debug,
+		E: Edge<NId> + Debug,
+		N: adapt::IdentifiableNode,
+		N::Id: std::fmt::Display,
+		Gen: Fn(&N::Id, IdAcc) -> (NId, IdAcc),
+	{
+		/// Dump the graph structure, with id mappings
+		pub fn dump(&self) -> GraphDump {
+			let mut ids: Vec<_> = self.fwd.iter().map(|(id, nid)| (id.to_string(), format!("{:?}", nid))).collect();
+			ids.sort_unstable();
+			GraphDump { ids, ..self.graph.dump() }
+		}
+	}
 }
 
 /// Graph construction adapters, for when your ids don't copy
@@ -432,6 +870,8 @@ pub mod adapt {
 	/// However that is not always the case.
 	/// [`GraphAdapter`] hence allows you to construct a graph, by providing a stored "your node id" ↔ "graph node id" mapping.
 	///
+	/// Like [`Graph`], it is [`Send`] and [`Sync`] whenever all of its type parameters (the id generator included) are.
+	///
 	/// Type Parameters:
 	/// - `NId`: (lightweight) node id, used by the [`Graph`]
 	/// - `E`: edge type
@@ -445,7 +885,7 @@ pub mod adapt {
 		Gen: Fn(&N::Id, IdAcc) -> (NId, IdAcc),
 	{
 		pub graph: Graph<NId, N, E>,
-		fwd: HashMap<N::Id, NId>,
+		pub(super) fwd: HashMap<N::Id, NId>,
 		last_id: IdAcc,
 		next_id: Gen,
 	}
@@ -495,12 +935,65 @@ pub mod adapt {
 			self.graph.add_edge(e);
 			self
 		}
+		/// A new adapter of a subgraph, see [`Graph::subgraph`] - with the id mappings of the nodes kept
+		pub fn subgraph(&self, node: impl Fn(NId, &N) -> bool, edge: impl Fn(&E) -> bool) -> Self
+		where
+			N: Clone,
+			IdAcc: Clone,
+			Gen: Clone,
+		{
+			let graph = self.graph.subgraph(node, edge);
+			Self {
+				fwd: self.fwd.iter().filter(|(_, nid)| graph.get_node(**nid).is_some()).map(|(id, nid)| (id.clone(), *nid)).collect(),
+				graph,
+				last_id: self.last_id.clone(),
+				next_id: self.next_id.clone(),
+			}
+		}
+		/// Runs `f` with the `edges` tombstoned, see [`Graph::with_disabled`]
+		pub fn with_disabled<R>(&mut self, edges: impl IntoIterator<Item = E>, f: impl FnOnce(&Self) -> R) -> R {
+			let disabled: Vec<E> = edges.into_iter().filter(|e| self.graph.disable_edge(e.clone())).collect();
+			let r = f(self);
+			for e in &disabled {
+				self.graph.enable_edge(e);
+			}
+			r
+		}
 	}
 }
 
 /// Heuristic graph algorithms
 pub mod heuristics {
 	use super::*;
+	use std::time::Instant;
+
+	/// Bounds on how long a heuristic may run
+	#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+	pub struct Limits {
+		/// Wall-clock deadline
+		pub deadline: Option<Instant>,
+		/// Maximum number of iterations
+		pub iterations: Option<usize>,
+	}
+	impl Limits {
+		/// No limits
+		pub fn none() -> Self {
+			Self::default()
+		}
+		/// Whether the limits are exceeded, after `iterations` iterations
+		pub fn exceeded(&self, iterations: usize) -> bool {
+			self.iterations.is_some_and(|i| iterations >= i) || self.deadline.is_some_and(|d| Instant::now() >= d)
+		}
+	}
+
+	/// Failure to solve PWRP
+	#[derive(Clone, Debug)]
+	pub enum PwrpError<'a, E> {
+		/// Some allocated edges can't be reached
+		Unreachable(HashSet<&'a E>),
+		/// Limits were exceeded - with the partial (closed) path so far, and the allocated edges not yet visited
+		Interrupted(Vec<&'a E>, HashSet<&'a E>),
+	}
 	
 	/// Solve Positioned Windy Rural Postman
 	///
@@ -509,63 +1002,289 @@ pub mod heuristics {
 	/// - `g`: eulirian graph
 	/// - `sp`: starting node
 	/// - `alloc`: set of edges that need to be visited
+	/// - `end`: where the path should end - `None` for a closed tour back to `sp`, otherwise the path is opened after the last allocated edge and connected to the region (empty region being anywhere)
 	/// - `weight`: filtering weight function
 	///
 	/// Returns: the path visiting all allocated edges on success, or the allocated edges that can't be reached otherwise
-	pub fn solve_pwrp<'a, NId, N, E, Weight, FW, const DIRESPECT: bool>(g: &'a Graph<NId, N, E>, sp: NId, mut alloc: HashSet<&'a E>, weight: FW) -> Result<Vec<&'a E>, HashSet<&'a E>>
+	pub fn solve_pwrp<'a, NId, N, E, Weight, FW, const DIRESPECT: bool>(g: &'a Graph<NId, N, E>, sp: NId, alloc: HashSet<&'a E>, end: Option<&HashSet<NId>>, weight: FW) -> Result<Vec<&'a E>, HashSet<&'a E>>
 	where 
-		NId: Clone + Copy + Hash + Eq,
-		E: Edge<NId>,
-		Weight: Clone + Copy + PartialEq + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight>,
-		FW: Fn(&E) -> Option<Weight>,
+		NId: Clone + Copy + Hash + Eq + Send + Sync,
+		N: Sync,
+		E: Edge<NId> + Sync,
+		Weight: Clone + Copy + PartialEq + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight> + Send,
+		FW: Fn(&E) -> Option<Weight> + Sync,
+	{
+		solve_pwrp_bounded::<_, _, _, _, _, _, DIRESPECT>(g, sp, alloc, end, weight, Limits::none(), |_| {}).map_err(|e| match e {
+			PwrpError::Unreachable(es) | PwrpError::Interrupted(_, es) => es,
+		})
+	}
+
+	/// Solve Positioned Windy Rural Postman, within limits
+	///
+	/// Same as [`solve_pwrp`], with additional arguments:
+	/// - `limits`: bounds on the running time, an iteration being a single injection of a cycle into the path
+	/// - `progress`: called after every iteration with the number of allocated edges remaining
+	///
+	/// Returns: the path visiting all allocated edges on success, or why not otherwise
+	pub fn solve_pwrp_bounded<'a, NId, N, E, Weight, FW, FP, const DIRESPECT: bool>(g: &'a Graph<NId, N, E>, sp: NId, alloc: HashSet<&'a E>, end: Option<&HashSet<NId>>, weight: FW, limits: Limits, progress: FP) -> Result<Vec<&'a E>, PwrpError<'a, E>>
+	where 
+		NId: Clone + Copy + Hash + Eq + Send + Sync,
+		N: Sync,
+		E: Edge<NId> + Sync,
+		Weight: Clone + Copy + PartialEq + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight> + Send,
+		FW: Fn(&E) -> Option<Weight> + Sync,
+		FP: FnMut(usize),
+	{
+		solve_pwrp_buffered::<_, _, _, _, _, _, DIRESPECT>(g, sp, alloc, end, weight, limits, progress, &mut Buffers::default())
+	}
+
+	/// Paths a [`PathCache`] holds by default
+	pub const PATH_CACHE_CAPACITY: usize = 4096;
+
+	/// Endpoints of a path, and the weight mode it's found with
+	type PathKey<NId> = (NId, NId, usize);
+
+	/// Shortest paths between 2 nodes, as found with a weight mode - the least recently used evicted beyond capacity
+	///
+	/// Solves keep looking for the same paths back from where they strayed, iteration after iteration. A path is only looked up among those found with the same
+	/// weight mode (e.g. a vehicle, and the segments it can't drive); when the weights of a mode change, the cache is to be invalidated.
+	pub struct PathCache<'a, NId: Hash + Eq, E> {
+		mode: usize,
+		/// path (if any) by endpoints and mode, none kept without capacity
+		paths: Option<lru::LruCache<PathKey<NId>, Option<Vec<&'a E>>>>,
+		pub hits: u64,
+		pub misses: u64,
+	}
+	impl<'a, NId: Clone + Copy + Hash + Eq, E: Edge<NId>> PathCache<'a, NId, E> {
+		/// An empty cache of up to `capacity` paths - none at all for 0
+		pub fn with_capacity(capacity: usize) -> Self {
+			Self { mode: 0, paths: std::num::NonZeroUsize::new(capacity).map(lru::LruCache::new), hits: 0, misses: 0 }
+		}
+		/// Switches weight modes - paths are found and looked up with the mode's weights from then on
+		pub fn mode(&mut self, mode: usize) {
+			self.mode = mode;
+		}
+		/// Forgets every path, e.g. when the weights of a mode change
+		pub fn invalidate(&mut self) {
+			if let Some(paths) = &mut self.paths {
+				paths.clear();
+			}
+		}
+		/// Share of the lookups that hit, 0 before any
+		pub fn hit_rate(&self) -> f64 {
+			self.hits as f64 / (self.hits + self.misses).max(1) as f64
+		}
+		/// Same as [`Graph::pathfind_into`], looking the path up before searching
+		pub fn pathfind_into<N, Weight, FW, const DIRESPECT: bool>(&mut self, g: &'a Graph<NId, N, E>, n1: NId, n2: NId, weight: FW, scratch: &mut Scratch<'a, NId, E, Weight>, path: &mut Vec<&'a E>) -> bool
+		where
+			Weight: Clone + Copy + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight>,
+			FW: Fn(&E) -> Option<Weight>,
+		{
+			let paths = match &mut self.paths {
+				Some(paths) => paths,
+				None => return g.pathfind_into::<_, _, DIRESPECT>(n1, n2, weight, scratch, path),
+			};
+			let key = (n1, n2, self.mode);
+			if let Some(found) = paths.get(&key) {
+				self.hits += 1;
+				return found.as_ref().map(|p| path.extend(p.iter().copied())).is_some();
+			}
+			self.misses += 1;
+			let k = path.len();
+			let found = g.pathfind_into::<_, _, DIRESPECT>(n1, n2, weight, scratch, path);
+			paths.put(key, found.then(|| path[k..].to_vec()));
+			found
+		}
+	}
+
+	/// Scratch buffers of [`solve_pwrp_buffered`], reused between solves to spare their allocations
+	pub struct Buffers<'a, NId: Hash + Eq, E, Weight: Ord> {
+		scratch: Scratch<'a, NId, E, Weight>,
+		nodes: Vec<NId>,
+		starts: HashMap<NId, usize>,
+		from: HashSet<NId>,
+		to: HashSet<NId>,
+		cycle: Vec<&'a E>,
+		paths: Vec<Vec<&'a E>>,
+		/// Paths found back to where solves strayed
+		pub cache: PathCache<'a, NId, E>,
+		batch: Batch<'a, NId, E, Weight>,
+	}
+	impl<'a, NId: Clone + Copy + Hash + Eq, E: Edge<NId>, Weight: Ord> Default for Buffers<'a, NId, E, Weight> {
+		fn default() -> Self {
+			Self { scratch: Default::default(), nodes: Vec::new(), starts: HashMap::default(), from: HashSet::default(), to: HashSet::default(), cycle: Vec::new(), paths: Vec::new(), cache: PathCache::with_capacity(PATH_CACHE_CAPACITY), batch: Default::default() }
+		}
+	}
+	impl<'a, NId: Clone + Copy + Hash + Eq, E: Edge<NId>, Weight: Ord> Buffers<'a, NId, E, Weight> {
+		/// Buffers of solves connecting to distant isles in batches, on `threads` threads
+		///
+		/// Once the nearest isle turns out to have no way back, rather than searching from the path anew for every next one, a single search reaches them all, and the ways back from the nearest are looked for a batch at a time (see [`Graph::pathfind_batch_into`]).
+		/// Solves come out the same (but for ties); it pays off on graphs where many allocated edges lead where there's no way back from, as one-way dead ends.
+		pub fn batched(threads: usize) -> Self {
+			Self { batch: Batch { scratches: (0..threads.max(1)).map(|_| Default::default()).collect(), ..Default::default() }, ..Default::default() }
+		}
+		/// Give a path back, for its buffer to be reused by the next solve
+		pub fn recycle(&mut self, mut path: Vec<&'a E>) {
+			path.clear();
+			self.paths.push(path);
+		}
+	}
+
+	/// Buffers of connecting to distant isles in batches - a scratch per thread, the isles and the ways back from them
+	struct Batch<'a, NId: Hash + Eq, E, Weight: Ord> {
+		scratches: Vec<Scratch<'a, NId, E, Weight>>,
+		targets: Vec<(Weight, NId)>,
+		candidates: Vec<(NId, &'a E)>,
+		queries: Vec<(NId, NId)>,
+		returns: Vec<(bool, Vec<&'a E>)>,
+	}
+	impl<NId: Hash + Eq, E, Weight: Ord> Default for Batch<'_, NId, E, Weight> {
+		fn default() -> Self {
+			Self { scratches: Vec::new(), targets: Vec::new(), candidates: Vec::new(), queries: Vec::new(), returns: Vec::new() }
+		}
+	}
+	impl<'a, NId: Clone + Copy + Hash + Eq + Send + Sync, E: Edge<NId> + Sync, Weight: Clone + Copy + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight> + Send> Batch<'a, NId, E, Weight> {
+		/// Connects a path (its `nodes`) to the nearest of the `to` nodes with an allocated edge out of it, and a way back from that edge
+		///
+		/// Returns: the node of the path the cycle (appended to `cycle`) starts from, if any
+		#[allow(clippy::too_many_arguments)]
+		fn connect<N: Sync, FW: Fn(&E) -> Option<Weight> + Sync, const DIRESPECT: bool>(&mut self, g: &'a Graph<NId, N, E>, nodes: &[NId], to: &HashSet<NId>, alloc: &HashSet<&'a E>, weight: &FW, scratch: &mut Scratch<'a, NId, E, Weight>, cycle: &mut Vec<&'a E>) -> Option<NId> {
+			let Batch { scratches, targets, candidates, queries, returns } = self;
+			// a single search reaching every isle, the nearest first
+			g.dijkstra::<_, _, DIRESPECT>(nodes.iter().copied(), |_| false, weight, scratch, cycle);
+			let tree = &scratch.dp;
+			targets.clear();
+			targets.extend(to.iter().filter_map(|v| tree.get(v).map(|(d, _)| (*d, *v))));
+			targets.sort_by_key(|(d, _)| *d);
+			let origin = |mut u: NId| {
+				while let Some((_, Some(e))) = tree.get(&u) {
+					u = e.other(u);
+				}
+				u
+			};
+			candidates.clear();
+			candidates.extend(targets.iter().flat_map(|&(_, v)| g.get_edges(v).filter(move |e| e.is_outgoing::<DIRESPECT>(v) && alloc.contains(e)).map(move |e| (v, e))));
+			for round in candidates.chunks(scratches.len()) {
+				queries.clear();
+				queries.extend(round.iter().map(|(v, e)| (e.other(*v), origin(*v))));
+				g.pathfind_batch_into::<_, _, DIRESPECT>(queries, weight, scratches, returns);
+				if let Some(i) = returns.iter().position(|(back, _)| *back) {
+					let (v, e) = round[i];
+					let k = cycle.len();
+					let mut u = v;
+					while let Some((_, Some(e))) = tree.get(&u) {
+						cycle.push(*e);
+						u = e.other(u);
+					}
+					cycle[k..].reverse();
+					cycle.push(e);
+					cycle.append(&mut returns[i].1);
+					return Some(u);
+				}
+			}
+			None
+		}
+	}
+
+	/// Solve Positioned Windy Rural Postman, within limits, reusing buffers
+	///
+	/// Same as [`solve_pwrp_bounded`], taking the scratch space (and the returned path) from `buffers` - [`Buffers::recycle`] paths no longer needed to close the loop.
+	#[allow(clippy::too_many_arguments)]
+	pub fn solve_pwrp_buffered<'a, NId, N, E, Weight, FW, FP, const DIRESPECT: bool>(g: &'a Graph<NId, N, E>, sp: NId, mut alloc: HashSet<&'a E>, end: Option<&HashSet<NId>>, weight: FW, limits: Limits, mut progress: FP, buffers: &mut Buffers<'a, NId, E, Weight>) -> Result<Vec<&'a E>, PwrpError<'a, E>>
+	where 
+		NId: Clone + Copy + Hash + Eq + Send + Sync,
+		N: Sync,
+		E: Edge<NId> + Sync,
+		Weight: Clone + Copy + PartialEq + Ord + Default + std::ops::Add<Weight, Output = Weight> + std::ops::Neg<Output = Weight> + Send,
+		FW: Fn(&E) -> Option<Weight> + Sync,
+		FP: FnMut(usize),
 	{
 		log::trace!("Solving PWRP, starting with {}", alloc.len());
-		let mut sol: Vec<&E> = Vec::new();
+		let Buffers { scratch, nodes, starts, from, to, cycle, paths, cache, batch } = buffers;
+		let mut iterations = 0usize;
+		let allocated = if end.is_some() { alloc.clone() } else { HashSet::default() };
+		let mut sol: Vec<&E> = paths.pop().unwrap_or_default();
 		macro_rules! sol_inject {
-			($inj:expr,$y:expr) => {
-				log::trace!("of {}", $inj.len());
-				for e in &$inj {
+			($y:expr) => {
+				log::trace!("of {}", cycle.len());
+				for e in cycle.iter() {
 					alloc.remove(e);
 				}
 				log::trace!("remaining {}", alloc.len());
-				sol.splice($y..$y, $inj);
+				sol.splice($y..$y, cycle.drain(..));
+				iterations += 1;
+				progress(alloc.len());
 			}
 		}
 		while !alloc.is_empty() {
-			if let Some((u, y, e)) = Graph::<NId, N, E>::path_to_nodes(sol.iter().map(|e| *e), sp).into_iter().enumerate().find_map(|(i, (u, _))| if let Some(e) = g.get_edges(u).iter().find(|e| e.is_outgoing::<DIRESPECT>(u) && alloc.contains(e)) { Some((u, i, e)) } else { None }) {
+			if limits.exceeded(iterations) {
+				log::trace!("interrupted with {} remaining", alloc.len());
+				return Err(PwrpError::Interrupted(sol, alloc));
+			}
+			Graph::<NId, N, E>::path_nodes_into(sol.iter().copied(), sp, nodes);
+			cycle.clear();
+			if let Some((u, y, e)) = nodes.iter().enumerate().find_map(|(i, u)| g.get_edges(*u).find(|e| e.is_outgoing::<DIRESPECT>(*u) && alloc.contains(e)).map(|e| (*u, i, e))) {
 				log::trace!("injecting a cycle");
 				let v = e.other(u);
-				if let Some(mut p) = g.pathfind::<_, _, DIRESPECT>(v, u, |e| weight(e)) {
-					p.insert(0, e);
-					sol_inject!(p, y);
+				cycle.push(e);
+				if cache.pathfind_into::<_, _, _, DIRESPECT>(g, v, u, |e| weight(e), scratch, cycle) {
+					sol_inject!(y);
 				} else {
 					panic!("it's a trap!");
 				}
 			} else {
 				log::trace!("connecting to a distant isle");
-				let mut vs: HashSet<_> = alloc.iter().flat_map(|e| if !DIRESPECT || !e.directed() { vec![e.p1(), e.p2()] } else { vec![e.p1()] }).collect();
-				let us: IndexMap<_, _> = Graph::<NId, N, E>::path_to_nodes(sol.iter().map(|e| *e), sp).into_iter().enumerate().map(|(i, (u, _))| (u, i)).collect();
-				if let Some((inj, y)) = loop {
-					if let Some((u, v, mut p)) = g.pathfind_regions::<_, _, DIRESPECT>(&us.keys().cloned().collect(), &vs, |e| weight(e)) {
-						if let Some((e, mut pb)) = g.get_edges(v).iter().find_map(|e| if e.is_outgoing::<DIRESPECT>(v) && alloc.contains(e) {
-							g.pathfind::<_, _, DIRESPECT>(e.other(v), u, |e| weight(e)).map(|path| (e, path))
-						} else { None }) {
-							p.push(e);
-							p.append(&mut pb);
+				to.clear();
+				to.extend(alloc.iter().flat_map(|e| std::iter::once(e.p1()).chain((!DIRESPECT || !e.directed()).then_some(e.p2()))));
+				starts.clear();
+				starts.extend(nodes.iter().enumerate().map(|(i, u)| (*u, i)));
+				if let Some(y) = loop {
+					// searching from the path's nodes in order, for ties not to depend on how big the buffers have grown
+					if let Some((u, v)) = g.dijkstra::<_, _, DIRESPECT>(nodes.iter().copied(), |u| to.contains(&u), |e| weight(e), scratch, cycle) {
+						let k = cycle.len();
+						if g.get_edges(v).any(|e| e.is_outgoing::<DIRESPECT>(v) && alloc.contains(e) && {
+							cycle.truncate(k);
+							cycle.push(e);
+							cache.pathfind_into::<_, _, _, DIRESPECT>(g, e.other(v), u, |e| weight(e), scratch, cycle)
+						}) {
 							// log::trace!("connecting {} to {} to {} to {}", u, v, e.other(v), u);
-							break Some((p, *us.get(&u).unwrap()));
+							break Some(*starts.get(&u).unwrap());
+						} else if !batch.scratches.is_empty() {
+							log::trace!("Can go from u to v, but not back; looking for the ways back from all the others at once");
+							cycle.clear();
+							to.remove(&v);
+							break batch.connect::<_, _, DIRESPECT>(g, nodes, to, &alloc, &weight, scratch, cycle).map(|u| *starts.get(&u).unwrap());
 						} else {
 							log::trace!("Can go from u to v, but not back; discarding v");
-							vs.remove(&v);
+							cycle.clear();
+							to.remove(&v);
 						}
 					} else {
 						break None;
 					}
 				} {
-					sol_inject!(inj, y);
+					sol_inject!(y);
 				} else {
 					log::trace!("failed to reach");
-					return Err(alloc);
+					return Err(PwrpError::Unreachable(alloc));
+				}
+			}
+		}
+		if let Some(end) = end {
+			// after the last allocated edge is first traversed - a two-way one may well be traversed back
+			let mut cleared = HashSet::default();
+			let open = sol.iter().enumerate().filter(|(_, e)| allocated.contains(*e) && cleared.insert(*e)).last().map_or(0, |(i, _)| i + 1);
+			if end.is_empty() {
+				sol.truncate(open);
+			} else {
+				Graph::<NId, N, E>::path_nodes_into(sol[..open].iter().copied(), sp, nodes);
+				from.clear();
+				from.insert(*nodes.last().unwrap());
+				let closed = sol.len();
+				if g.pathfind_regions_into::<_, _, DIRESPECT>(from, end, |e| weight(e), scratch, &mut sol).is_some() {
+					sol.drain(open..closed);
+				} else {
+					log::trace!("end region unreachable, keeping the tour closed");
 				}
 			}
 		}
@@ -601,6 +1320,22 @@ mod test {
 		}
 	}
 
+	/// Two-way edge
+	#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+	struct TwoWay(u64, u64);
+
+	impl Edge<u64> for TwoWay {
+		fn p1(&self) -> u64 {
+			self.0
+		}
+		fn p2(&self) -> u64 {
+			self.1
+		}
+		fn directed(&self) -> bool {
+			false
+		}
+	}
+
 	macro_rules! graph {
 		($edges:expr) => {
 			{
@@ -633,6 +1368,322 @@ mod test {
 		};
 	}
 
+	#[test]
+	fn test_pwrp_ends(){
+		let g = graph!(vec![(0, 1), (1, 2), (2, 0), (2, 3), (3, 2)]);
+		let alloc = || vec![(0, 1)].iter().map(|e| g.get_edges_between(e.0, e.1)[0]).collect::<HashSet<_>>();
+		assert_eq!(heuristics::solve_pwrp::<_, _, _, _, _, true>(&g, 0, alloc(), None, |_| Some(1i64)), Ok(vec![&(0, 1), &(1, 2), &(2, 0)]));
+		assert_eq!(heuristics::solve_pwrp::<_, _, _, _, _, true>(&g, 0, alloc(), Some(&HashSet::default()), |_| Some(1i64)), Ok(vec![&(0, 1)]));
+		assert_eq!(heuristics::solve_pwrp::<_, _, _, _, _, true>(&g, 0, alloc(), Some(&vec![3].into_iter().collect()), |_| Some(1i64)), Ok(vec![&(0, 1), &(1, 2), &(2, 3)]));
+		// a two-way edge, cleared going and traversed back
+		let g = graph!([TwoWay(0, 1)]);
+		let alloc: HashSet<_> = g.edges().collect();
+		assert_eq!(heuristics::solve_pwrp::<_, _, _, _, _, true>(&g, 0, alloc.clone(), None, |_| Some(1i64)).map(|p| p.len()), Ok(2));
+		assert_eq!(heuristics::solve_pwrp::<_, _, _, _, _, true>(&g, 0, alloc, Some(&HashSet::default()), |_| Some(1i64)).map(|p| p.len()), Ok(1));
+	}
+
+	#[test]
+	fn test_pwrp_limits(){
+		let g = graph!(vec![(0, 1), (1, 0), (1, 2), (2, 1)]);
+		let alloc: HashSet<_> = g.edges().collect();
+		let mut remaining = Vec::new();
+		assert_eq!(heuristics::solve_pwrp_bounded::<_, _, _, _, _, _, true>(&g, 0, alloc.clone(), None, |_| Some(1i64), heuristics::Limits::none(), |r| remaining.push(r)).map(|p| p.len()).ok(), Some(4));
+		assert_eq!(remaining, vec![2, 0]);
+		match heuristics::solve_pwrp_bounded::<_, _, _, _, _, _, true>(&g, 0, alloc, None, |_| Some(1i64), heuristics::Limits { iterations: Some(1), ..Default::default() }, |_| {}) {
+			Err(heuristics::PwrpError::Interrupted(p, rem)) => assert_eq!((p, rem.len()), (vec![&(0, 1), &(1, 0)], 2)),
+			r => panic!("not interrupted: {:?}", r),
+		}
+	}
+
+	#[test]
+	fn test_pwrp_batched(){
+		// a line, with one-way dead ends off 1 and 2
+		let g = graph!(vec![(0, 1), (1, 0), (1, 2), (2, 1), (2, 3), (3, 2), (1, 4), (4, 5), (2, 6), (6, 7)]);
+		let queries = vec![(0, 3), (3, 0), (0, 5), (5, 0), (7, 7)];
+		let mut results = vec![(true, vec![&(0, 1)]); 7];
+		g.pathfind_batch_into::<_, _, true>(&queries, &|_| Some(1i64), &mut (0..2).map(|_| Default::default()).collect::<Vec<_>>(), &mut results);
+		assert_eq!(results, queries.iter().map(|(u, v)| g.pathfind::<_, _, true>(*u, *v, |_| Some(1i64)).map_or((false, vec![]), |p| (true, p))).collect::<Vec<_>>());
+		let alloc: HashSet<_> = [(2, 3), (4, 5), (6, 7)].iter().map(|e| g.get_edges_between(e.0, e.1)[0]).collect();
+		for mut buffers in [Default::default(), heuristics::Buffers::batched(1), heuristics::Buffers::batched(2)] {
+			match heuristics::solve_pwrp_buffered::<_, _, _, _, _, _, true>(&g, 0, alloc.clone(), None, |_| Some(1i64), heuristics::Limits::none(), |_| {}, &mut buffers) {
+				Err(heuristics::PwrpError::Unreachable(es)) => assert_eq!(es, [&(4, 5), &(6, 7)].iter().copied().collect()),
+				r => panic!("not unreachable: {:?}", r),
+			}
+		}
+		let alloc: HashSet<_> = [&(2, 3), &(3, 2)].iter().copied().collect();
+		assert_eq!(heuristics::solve_pwrp_buffered::<_, _, _, _, _, _, true>(&g, 0, alloc, None, |_| Some(1i64), heuristics::Limits::none(), |_| {}, &mut heuristics::Buffers::batched(2)).unwrap(), vec![&(0, 1), &(1, 2), &(2, 3), &(3, 2), &(2, 1), &(1, 0)]);
+	}
+
+	#[test]
+	fn test_pwrp_buffers(){
+		// a two-way 5×5 grid, with various allocations, starts and ends
+		let mut edges = Vec::new();
+		for u in 0..25 {
+			if u % 5 < 4 {
+				edges.extend([(u, u + 1), (u + 1, u)].iter().copied());
+			}
+			if u < 20 {
+				edges.extend([(u, u + 5), (u + 5, u)].iter().copied());
+			}
+		}
+		let g = graph!(edges.clone());
+		let alloc = |k: usize| edges.iter().enumerate().filter(|(i, _)| i % k == 0).map(|(_, e)| g.get_edges_between(e.0, e.1)[0]).collect::<HashSet<_>>();
+		let ends: Vec<Option<HashSet<u64>>> = vec![None, Some(HashSet::default()), Some(vec![24].into_iter().collect())];
+		// the same buffers reused throughout, solves recycling their paths and switching cache modes, come out the same as fresh ones
+		let mut buffers = heuristics::Buffers::default();
+		for (i, (k, (sp, end))) in (2..6).flat_map(|k| [0, 12, 19].iter().flat_map(|sp| ends.iter().map(move |end| (*sp, end))).map(move |se| (k, se))).enumerate() {
+			buffers.cache.mode(i % 2);
+			let weight = |e: &(u64, u64)| Some(if i % 2 == 0 { 1i64 } else { 1 + (e.0 % 3) as i64 });
+			let fresh = heuristics::solve_pwrp::<_, _, _, _, _, true>(&g, sp, alloc(k), end.as_ref(), weight).unwrap();
+			let buffered = heuristics::solve_pwrp_buffered::<_, _, _, _, _, _, true>(&g, sp, alloc(k), end.as_ref(), weight, heuristics::Limits::none(), |_| {}, &mut buffers).unwrap();
+			assert_eq!(buffered, fresh, "allocation {} from {} to {:?}", k, sp, end);
+			buffers.recycle(buffered);
+		}
+		assert!(buffers.cache.hits > 0);
+	}
+
+	#[test]
+	fn test_path_cache(){
+		let g = graph!(vec![(0, 1), (1, 2), (2, 0), (0, 2)]);
+		let mut cache = heuristics::PathCache::with_capacity(2);
+		let mut scratch = Scratch::default();
+		macro_rules! find {
+			($cache:expr, $n1:expr, $n2:expr, $w:expr) => {{
+				let mut path = vec![&(9, 9)];
+				$cache.pathfind_into::<_, _, _, true>(&g, $n1, $n2, |e| Some(if *e == (0, 2) { $w } else { 1i64 }), &mut scratch, &mut path).then_some(path)
+			}};
+		}
+		assert_eq!(find!(cache, 0, 2, 1), Some(vec![&(9, 9), &(0, 2)]));
+		assert_eq!(find!(cache, 0, 2, 1), Some(vec![&(9, 9), &(0, 2)]));
+		assert_eq!((cache.hits, cache.misses), (1, 1));
+		// another mode's weights
+		cache.mode(1);
+		assert_eq!(find!(cache, 0, 2, 5), Some(vec![&(9, 9), &(0, 1), &(1, 2)]));
+		// the least recently used path of the first mode is evicted
+		find!(cache, 2, 1, 1);
+		cache.mode(0);
+		assert_eq!(find!(cache, 0, 2, 5), Some(vec![&(9, 9), &(0, 1), &(1, 2)]));
+		assert_eq!((cache.hits, cache.misses), (1, 4));
+		// stale until invalidated
+		assert_eq!(find!(cache, 0, 2, 1), Some(vec![&(9, 9), &(0, 1), &(1, 2)]));
+		cache.invalidate();
+		assert_eq!(find!(cache, 0, 2, 1), Some(vec![&(9, 9), &(0, 2)]));
+		assert_eq!(cache.hit_rate(), 2.0 / 7.0);
+	}
+
+	#[test]
+	fn test_path_cache_modes(){
+		let g = graph!([(0, 1), (1, 2), (0, 2)]);
+		let mut cache = heuristics::PathCache::with_capacity(8);
+		let mut scratch = Scratch::default();
+		// vehicle 1 can't drive the shortcut, until it can
+		macro_rules! find {
+			($shortcut:expr) => {{
+				let mut path = Vec::new();
+				cache.pathfind_into::<_, _, _, true>(&g, 0, 2, |e| if *e == (0, 2) && !$shortcut { None } else { Some(1i64) }, &mut scratch, &mut path);
+				path
+			}};
+		}
+		cache.mode(0);
+		assert_eq!(find!(true), vec![&(0, 2)]);
+		// a mode switch doesn't get vehicle 0's path
+		cache.mode(1);
+		assert_eq!(find!(false), vec![&(0, 1), &(1, 2)]);
+		cache.mode(0);
+		assert_eq!(find!(true), vec![&(0, 2)]);
+		assert_eq!((cache.hits, cache.misses), (1, 2));
+		// nor, once invalidated, the path found with vehicle 1's former weights
+		cache.invalidate();
+		cache.mode(1);
+		assert_eq!(find!(true), vec![&(0, 2)]);
+		assert_eq!((cache.hits, cache.misses), (1, 3));
+	}
+
+	#[test]
+	#[cfg(feature = "serde")]
+	fn test_dump_diff(){
+		let g1 = graph!(vec![(0, 1), (1, 2)]);
+		let g2 = graph!(vec![(0, 1), (1, 3)]);
+		assert_eq!(g1.dump().diff(&g1.dump()), Vec::<String>::new());
+		assert_eq!(g1.dump().diff(&g2.dump()), vec!["- node 2", "+ node 3", "- edge (1, 2)", "+ edge (1, 3)"]);
+	}
+
+	#[test]
+	fn test_reachable(){
+		let g = graph!(vec![(0, 1), (1, 2), (2, 1), (3, 0)]);
+		assert_eq!(g.reachable::<true>(0, false), vec![0, 1, 2].into_iter().collect());
+		assert_eq!(g.reachable::<true>(0, true), vec![0, 3].into_iter().collect());
+		assert_eq!(g.reachable::<false>(2, true), vec![0, 1, 2, 3].into_iter().collect());
+	}
+
+	#[test]
+	fn test_update_edge(){
+		let mut g = graph!(vec![(0, 1, 1i64), (1, 2, 1), (0, 2, 5)]);
+		assert!(g.update_edge(&(0, 2, 5), |e| e.2 = 1));
+		assert_eq!(g.pathfind::<_, _, true>(0, 2, |e| Some(e.2)), Some(vec![&(0, 2, 1)]));
+		assert_eq!((g.get_edges(0).count(), g.get_edges(2).count(), g.edge_count()), (2, 2, 3));
+		// tombstones follow
+		g.disable_edge((0, 2, 1));
+		assert!(g.update_edge(&(0, 2, 1), |e| e.2 = 2));
+		assert!(!g.is_enabled(&(0, 2, 2)));
+		g.enable_edge(&(0, 2, 2));
+		// moved to other ends, but not to missing ones
+		assert!(g.update_edge(&(0, 2, 2), |e| e.0 = 1));
+		assert_eq_unordered!(g.get_edges_between(1, 2), vec![&(1, 2, 1), &(1, 2, 2)]);
+		assert!(!g.update_edge(&(1, 2, 2), |e| e.1 = 7));
+		assert!(!g.update_edge(&(3, 4, 1), |_| ()));
+		assert_eq!((g.get_edges(0).count(), g.edge_count()), (1, 3));
+	}
+
+	#[test]
+	fn test_cost_matrix(){
+		let g = graph!(vec![(0, 1, 1i64), (1, 2, 1), (2, 0, 5), (2, 3, 1), (3, 0, 1), (4, 0, 1)]);
+		let m = g.cost_matrix::<_, _, true>(vec![0, 2], |e| Some(e.2));
+		assert_eq!((m.cost(0, 2), m.cost(2, 0), m.cost(4, 2), m.cost(2, 4)), (Some(2), Some(2), Some(3), None));
+		assert_eq!(m.path(2, 0), Some(vec![&(2, 3, 1), &(3, 0, 1)]));
+		assert_eq!(m.path(4, 2), Some(vec![&(4, 0, 1), &(0, 1, 1), &(1, 2, 1)]));
+		assert_eq!(m.path(0, 0), Some(vec![]));
+		assert_eq!(m.nearest(3, |_| true), Some((0, 1)));
+		assert_eq!(m.nearest(3, |k| k != 0), Some((2, 3)));
+	}
+
+	#[test]
+	fn test_edge_betweenness(){
+		let g = graph!(vec![(0, 1), (1, 0), (1, 2), (2, 1), (2, 3), (3, 2), (1, 3), (3, 1), (3, 4)]);
+		let among = vec![0, 2, 3, 4].into_iter().collect();
+		let b = g.edge_betweenness::<_, _, true>(&among, |_| Some(1i64));
+		assert_eq!(b[&(0, 1)], 3.0);
+		assert_eq!(b[&(1, 2)], 1.0);
+		assert_eq!(b[&(1, 3)], 2.0);
+		assert_eq!(b[&(3, 4)], 3.0);
+		assert_eq!((b[&(2, 1)], b[&(1, 0)]), (1.0, 2.0));
+		assert_eq!(b.get(&(4, 3)), None);
+	}
+
+	#[test]
+	fn test_subgraph(){
+		let mut g = graph!(vec![(0, 1), (1, 2), (2, 0), (0, 2), (2, 3)]);
+		g.disable_edge((1, 2));
+		let sub = g.subgraph(|n, _| n != 3, |e| e.0 != 2);
+		assert_eq!(sub.node_count(), 3);
+		assert_eq_unordered!(sub.edges().collect::<Vec<_>>(), vec![&(0, 1), &(0, 2)]);
+		assert!(sub.get_edges(1).all(|e| *e != (1, 2)));
+		let path = sub.pathfind::<_, _, true>(0, 2, |_| Some(1i64)).unwrap();
+		assert_eq!(path.into_iter().map(|e| g.get_edge(e)).collect::<Vec<_>>(), vec![Some(&(0, 2))]);
+		assert_eq!(g.get_edge(&(1, 2)), None);
+		assert_eq!(g.edge_count(), 4);
+	}
+
+	#[test]
+	fn test_tombstones(){
+		let mut g = graph!(vec![(0, 1), (1, 2), (2, 0), (0, 2)]);
+		assert_eq!(g.pathfind::<_, _, true>(0, 2, |_| Some(1i64)), Some(vec![&(0, 2)]));
+		let detour = g.with_disabled(vec![(0, 2)], |g| g.pathfind::<_, _, true>(0, 2, |_| Some(1i64)).map(|p| p.into_iter().cloned().collect::<Vec<_>>()));
+		assert_eq!(detour, Some(vec![(0, 1), (1, 2)]));
+		assert_eq!((g.node_count(), g.edge_count()), (3, 4));
+		g.disable_node(1);
+		assert_eq!((g.node_count(), g.edge_count()), (2, 2));
+		assert_eq!(g.pathfind::<_, _, true>(2, 1, |_| Some(1i64)), None);
+		assert_eq!(g.strongly_connected_components::<true, false>().len(), 1);
+		g.enable_node(1);
+		assert_eq!(g.node_count(), 3);
+		assert_eq!(g.get_edges_between(1, 2), vec![&(1, 2)]);
+	}
+
+	#[test]
+	fn test_send_sync(){
+		struct Node(String);
+		impl adapt::IdentifiableNode for Node {
+			type Id = String;
+			fn id(&self) -> &String {
+				&self.0
+			}
+		}
+		fn assert_send_sync<T: Send + Sync>() {}
+		assert_send_sync::<Graph<u64, (), (u64, u64)>>();
+		assert_send_sync::<adapt::GraphAdapter<u64, Node, (u64, u64), u64, fn(&String, u64) -> (u64, u64)>>();
+	}
+
+	/// An edge that may or may not be directed
+	#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+	struct Mixed(u64, u64, bool);
+	impl Edge<u64> for Mixed {
+		fn p1(&self) -> u64 {
+			self.0
+		}
+		fn p2(&self) -> u64 {
+			self.1
+		}
+		fn directed(&self) -> bool {
+			self.2
+		}
+	}
+
+	/// Invariants that must hold after any sequence of operations
+	fn check_invariants(g: &Graph<u64, (), Mixed>) {
+		let nodes: HashSet<u64> = g.nodes().map(|(n, _)| n).collect();
+		let edges: Vec<&Mixed> = g.edges().collect();
+		assert_eq!(g.edge_count(), edges.len());
+		assert_eq!(edges.iter().collect::<HashSet<_>>().len(), edges.len(), "edges listed twice");
+		for e in &edges {
+			assert!(nodes.contains(&e.p1()) && nodes.contains(&e.p2()), "{:?} dangles", e);
+			assert!(g.get_edges(e.p1()).any(|f| f == *e) && g.get_edges(e.p2()).any(|f| f == *e), "{:?} not incident", e);
+			assert!(g.get_edges_between(e.p1(), e.p2()).contains(e));
+		}
+		assert_eq!(nodes.iter().map(|n| g.get_edges(*n).filter(|e| !e.is_cyclic()).count()).sum::<usize>(), 2 * edges.iter().filter(|e| !e.is_cyclic()).count());
+		for sccs in [g.strongly_connected_components::<true, true>(), g.strongly_connected_components::<false, true>()] {
+			assert_eq!(sccs.iter().map(|c| c.len()).sum::<usize>(), nodes.len());
+			assert_eq!(sccs.iter().flatten().copied().collect::<HashSet<_>>(), nodes);
+		}
+	}
+
+	#[test]
+	fn test_random_operations(){
+		// xorshift, to stay dependency free and reproducible
+		let mut state = 0x2545F4914F6CDD1Du64;
+		let mut rand = |n: u64| {
+			state ^= state << 13;
+			state ^= state >> 7;
+			state ^= state << 17;
+			state % n
+		};
+		for _ in 0..50 {
+			let mut g: Graph<u64, (), Mixed> = Default::default();
+			let mut model: HashSet<Mixed> = HashSet::default();
+			for _ in 0..200 {
+				let (u, v, d) = (rand(8), rand(8), rand(2) == 0);
+				match rand(8) {
+					0 | 1 => {
+						g.add_node(u, ());
+					},
+					2 | 3 => if g.add_edge(Mixed(u, v, d)) {
+						model.insert(Mixed(u, v, d));
+					},
+					4 => if g.remove_edge(&Mixed(u, v, d)) {
+						model.remove(&Mixed(u, v, d));
+					},
+					5 => {
+						g.disable_node(u);
+					},
+					6 => {
+						g.enable_node(u);
+					},
+					_ => {
+						let (nodes, edges) = (g.node_count(), g.edge_count());
+						let sccs = g.strongly_connected_components::<true, true>();
+						g.patch_sccs::<_, true>(&sccs, |Mixed(u, v, _)| Mixed(u, v, false));
+						model = model.into_iter().map(|e| if g.get_edges(e.0).any(|f| *f == e) || !g.is_enabled(&e) { e } else { Mixed(e.0, e.1, false) }).collect();
+						assert_eq!(g.node_count(), nodes);
+						assert!(g.edge_count() <= edges);
+					},
+				}
+				check_invariants(&g);
+				let enabled: HashSet<_> = model.iter().filter(|e| g.is_enabled(e)).collect();
+				assert_eq!(g.edges().collect::<HashSet<_>>(), enabled);
+			}
+		}
+	}
+
 	#[test]
 	fn test_sccs(){
 		let g = graph!(vec![(0, 1)]);
@@ -642,4 +1693,16 @@ mod test {
 		assert_eq_unordered!(g.strongly_connected_components::<true, false>(), vec![vec![0, 1, 2].into_iter().collect(), vec![3].into_iter().collect(), vec![4, 5].into_iter().collect()]);
 		assert_eq_unordered!(g.strongly_connected_components::<false, false>(), vec![vec![0, 1, 2, 3].into_iter().collect(), vec![4, 5].into_iter().collect()]);
 	}
+
+	#[test]
+	fn test_connected_components(){
+		let mut g = graph!([(0, 1), (1, 2), (2, 0), (3, 1), (4, 5), (5, 4), (6, 7)]);
+		g.add_node(8, ());
+		let set = |ns: &[u64]| ns.iter().copied().collect::<HashSet<_>>();
+		assert_eq!(g.connected_components::<false>()[..2], [set(&[0, 1, 2, 3]), set(&[4, 5])]);
+		assert_eq!(g.connected_components::<false>().len(), 3);
+		let strong = g.connected_components::<true>();
+		assert_eq!(strong[0], set(&[0, 1, 2]));
+		assert_eq!(strong.len(), 5);
+	}
 }
@@ -0,0 +1,125 @@
+//! Direction inference
+//!
+//! Some datasets leave out which streets are one-way, but their lanes, parking and sidewalks tell - a single lane between two parking lanes
+//! is no two-way street. Inferred directions come with a confidence and the hints they're based on, for a person to accept or reject before solving.
+
+use crate::*;
+use data::*;
+use patch::Change;
+
+use std::collections::HashSet;
+use serde::*;
+
+/// A segment inferred one-way, `p1`→`p2` (the way it's drawn, as OSM's `oneway=yes`)
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Inference {
+	pub p1: NodeId,
+	pub p2: NodeId,
+	pub discriminator: Option<NodeId>,
+	/// From 0 to 1
+	pub confidence: f64,
+	/// What the inference is based on
+	pub hints: Vec<String>,
+	/// Whether to make the segment one-way - edit to accept or reject
+	pub accepted: bool,
+}
+
+impl Inference {
+	/// The change making the segment one-way
+	pub fn change(&self) -> Change {
+		Change::ModifySegment {
+			p1: self.p1.clone(),
+			p2: self.p2.clone(),
+			discriminator: self.discriminator.clone(),
+			set: vec![("directed".to_string(), true.into())].into_iter().collect(),
+		}
+	}
+}
+
+/// A numeric property, given as a number or a string (as OSM tags are)
+fn number(e: &RoadSegment, key: &str) -> Option<f64> {
+	match e.properties.get(key)? {
+		serde_json::Value::Number(n) => n.as_f64(),
+		serde_json::Value::String(s) => s.trim().parse().ok(),
+		_ => None,
+	}
+}
+
+/// Hints of a segment being one-way, weighted - negative ones being of it being two-way
+fn hints(e: &RoadSegment) -> Vec<(f64, String)> {
+	let mut hints = Vec::new();
+	match number(e, "lanes") {
+		Some(1.0) => hints.push((0.5, "a single lane".to_string())),
+		Some(l) if l >= 2.0 && number(e, "lanes:forward").is_none() => hints.push((-0.3, format!("{} lanes", l))),
+		_ => {},
+	}
+	if number(e, "lanes:forward").is_some_and(|l| l > 0.0) && number(e, "lanes:backward").unwrap_or(0.0) == 0.0 {
+		hints.push((0.4, "forward lanes only".to_string()));
+	}
+	if e.parking == (true, true) && number(e, "lanes").is_some_and(|l| l <= 1.0) {
+		hints.push((0.2, "parking on both sides of its lane".to_string()));
+	}
+	if e.sidewalks.0 != e.sidewalks.1 {
+		hints.push((0.1, "a sidewalk on one side only".to_string()));
+	}
+	hints
+}
+
+/// Infers which two-way segments are really one-way, from their hints - those with some, accepted if confident enough
+///
+/// Segments with a twin the other way are two-way as given, and left alone.
+pub fn infer(roads: &RoadGraph, min_confidence: f64) -> Vec<Inference> {
+	let directed: HashSet<_> = roads.roads.iter().filter(|e| e.directed).map(|e| (&e.p1, &e.p2, &e.discriminator)).collect();
+	roads.roads.iter()
+		.filter(|e| !e.directed && !directed.contains(&(&e.p2, &e.p1, &e.discriminator)))
+		.filter_map(|e| {
+			let hints = hints(e);
+			let confidence = hints.iter().map(|(w, _)| w).sum::<f64>().clamp(0.0, 1.0);
+			(confidence > 0.0).then(|| Inference {
+				p1: e.p1.clone(),
+				p2: e.p2.clone(),
+				discriminator: e.discriminator.clone(),
+				confidence,
+				hints: hints.into_iter().map(|(_, h)| h).collect(),
+				accepted: confidence >= min_confidence,
+			})
+		})
+		.collect()
+}
+
+impl RoadGraph {
+	/// Makes the segments of the accepted inferences one-way
+	///
+	/// Returns: the number made one-way
+	pub fn accept(&mut self, inferences: &[Inference]) -> Result<usize, Error> {
+		let changes: Vec<_> = inferences.iter().filter(|i| i.accepted).map(Inference::change).collect();
+		self.apply(&changes)?;
+		Ok(changes.len())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_infer(){
+		let road = |p1: &'static str, p2: &'static str, properties: serde_json::Value| RoadSegment { parking: (true, true), properties: serde_json::from_value(properties).unwrap(), ..RoadSegment::new(p1, p2, 10.0) };
+		let node = |id: &'static str| Node { id: id.into(), coordinates: (0.0, 0.0), elevation: None };
+		let mut g = RoadGraph {
+			roads: vec![
+				road("a", "b", serde_json::json!({"lanes": "1"})),
+				road("b", "c", serde_json::json!({"lanes": 2})),
+				road("c", "d", serde_json::json!({"lanes:forward": 1})),
+				road("d", "a", serde_json::json!({"lanes": 1})),
+				RoadSegment { directed: true, ..road("a", "d", serde_json::json!({})) },
+			],
+			nodes: RoadGraphNodes { nodes: vec![node("a"), node("b"), node("c"), node("d")] },
+		};
+		let inferences = infer(&g, 0.6);
+		let inferred: Vec<_> = inferences.iter().map(|i| (i.p1.name().unwrap(), i.confidence, i.accepted)).collect();
+		assert_eq!(inferred, vec![("a", 0.7, true), ("c", 0.4, false)]);
+		assert_eq!(g.accept(&inferences).unwrap(), 1);
+		assert_eq!(g.roads.iter().map(|e| e.directed).collect::<Vec<_>>(), vec![true, false, false, false, true]);
+	}
+}
@@ -0,0 +1,89 @@
+//! # _make 'em fly & make 'em plow_
+//!
+//! Graph algorithms for flight & plow problems, and everything around solving them.
+//!
+//! Only [`graph`] is always there, the rest comes with features:
+//! - `serde`: (de)serializable graph dumps
+//! - `solver`: spec'd data types, the solvers and plan tooling
+//! - `geojson`: GeoJSON conversions
+//! - `geocode`: address geocoding
+//! - `schema`: JSON Schemas of the input/output types
+//! - `osm`: OpenStreetMap import
+//! - `notify`: notification hooks, the scheduling daemon and remote workers
+//! - `live`: live route updates over WebSocket
+//! - `sqlite`: experiment results databases
+//! - `png`: PNG plan maps
+//! - `cli`: the `wfbfa-solver-cli` binary
+
+pub mod graph;
+#[cfg(feature = "solver")]
+pub mod data;
+#[cfg(feature = "solver")]
+pub mod meta;
+#[cfg(feature = "solver")]
+pub mod plow;
+#[cfg(feature = "geojson")]
+pub mod gj;
+#[cfg(feature = "solver")]
+pub mod view;
+#[cfg(feature = "solver")]
+pub mod eval;
+#[cfg(feature = "solver")]
+pub mod validate;
+#[cfg(feature = "solver")]
+pub mod timeline;
+#[cfg(feature = "solver")]
+pub mod conflict;
+#[cfg(feature = "solver")]
+pub mod storm;
+#[cfg(feature = "solver")]
+pub mod spread;
+#[cfg(feature = "solver")]
+pub mod render;
+#[cfg(feature = "solver")]
+pub mod experiment;
+#[cfg(feature = "solver")]
+pub mod calibrate;
+#[cfg(feature = "solver")]
+pub mod repl;
+#[cfg(feature = "solver")]
+pub mod weather;
+#[cfg(feature = "solver")]
+pub mod fleet;
+#[cfg(feature = "solver")]
+pub mod patch;
+#[cfg(feature = "solver")]
+pub mod orient;
+#[cfg(feature = "solver")]
+pub mod infer;
+#[cfg(feature = "solver")]
+pub mod config;
+#[cfg(feature = "solver")]
+pub mod error;
+#[cfg(feature = "solver")]
+pub mod scrub;
+#[cfg(feature = "solver")]
+pub mod estimate;
+#[cfg(feature = "osm")]
+pub mod osm;
+#[cfg(feature = "notify")]
+pub mod notify;
+#[cfg(feature = "notify")]
+pub mod daemon;
+#[cfg(feature = "live")]
+pub mod live;
+#[cfg(feature = "notify")]
+pub mod remote;
+#[cfg(feature = "geocode")]
+pub mod geocode;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(feature = "solver")]
+pub use try_all::{TryAll, TryMapAll};
+#[cfg(feature = "solver")]
+pub use noisy_float::prelude::{N64, n64, Float};
+
+#[cfg(feature = "solver")]
+pub use data::NodeId;
+#[cfg(feature = "solver")]
+pub use error::Error;
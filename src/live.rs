@@ -0,0 +1,213 @@
+//! Live route updates over WebSocket
+//!
+//! In-cab tablets shouldn't have to poll checkpoint files: they subscribe to a vehicle's topic (`ws://host:port/<vehicle>`, or `/` for all of them),
+//! and get its route whenever a newer plan changes it, sequence numbered. The protocol is `tungstenite`'s, over std's TCP - a thread per subscriber
+//! takes dispatch's priority overrides back.
+
+use crate::*;
+use data::*;
+
+use std::{net::{SocketAddr, TcpListener, TcpStream}, sync::{Arc, Mutex, mpsc}, time::Duration};
+use indexmap::IndexMap;
+use serde::*;
+use tungstenite::{Message, WebSocket, handshake::server::{Request, Response}};
+
+/// How long reading a subscriber's frame may block
+const POLL: Duration = Duration::from_millis(50);
+
+type Socket = Arc<Mutex<WebSocket<TcpStream>>>;
+
+/// A route update
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Update {
+	/// Sequence number, across all vehicles - a subscriber missing one knows it missed an update
+	pub seq: u64,
+	pub vehicle: VehicleId,
+	/// Solving time of the plan, in seconds
+	pub at: f64,
+	/// Whether it's the final plan's
+	#[serde(rename="final")]
+	pub last: bool,
+	pub route: serde_json::Value,
+}
+
+#[derive(Default)]
+struct State {
+	seq: u64,
+	/// Latest update of every vehicle
+	latest: IndexMap<VehicleId, Update>,
+	/// Subscribers, with the vehicle subscribed to (all if `None`)
+	subscribers: Vec<(Option<VehicleId>, Socket)>,
+	/// Where priority overrides go, when watched for
+	overrides: Option<mpsc::Sender<PriorityOverride>>,
+}
+
+/// A WebSocket channel publishing route updates
+#[derive(Clone)]
+pub struct Channel {
+	state: Arc<Mutex<State>>,
+	addr: SocketAddr,
+}
+
+impl Channel {
+	/// Starts accepting subscribers, from a thread of its own
+	pub fn bind(addr: &str) -> std::io::Result<Self> {
+		let listener = TcpListener::bind(addr)?;
+		let channel = Self { state: Default::default(), addr: listener.local_addr()? };
+		let c = channel.clone();
+		std::thread::spawn(move || {
+			for stream in listener.incoming().flatten() {
+				match handshake(stream) {
+					Ok((socket, topic)) => c.subscribe(topic, socket),
+					Err(e) => log::warn!("Rejected live subscriber: {}", e),
+				}
+			}
+		});
+		Ok(channel)
+	}
+	/// The address bound to
+	pub fn local_addr(&self) -> SocketAddr {
+		self.addr
+	}
+	fn subscribe(&self, topic: Option<VehicleId>, mut socket: WebSocket<TcpStream>) {
+		let mut state = self.state.lock().unwrap();
+		// catch up on the latest routes
+		let caught_up = state.latest.values().filter(|u| topic.as_ref().is_none_or(|v| v == &u.vehicle)).all(|u| socket.send(Message::Text(serde_json::to_string(u).unwrap())).is_ok());
+		if caught_up && socket.get_ref().set_read_timeout(Some(POLL)).is_ok() {
+			log::info!("Live subscriber {} on {}", socket.get_ref().peer_addr().map_or_else(|_| "?".to_string(), |a| a.to_string()), topic.as_deref().unwrap_or("all vehicles"));
+			let socket = Arc::new(Mutex::new(socket));
+			let (c, s) = (self.clone(), socket.clone());
+			std::thread::spawn(move || c.read(s));
+			state.subscribers.push((topic, socket));
+		}
+	}
+	/// Takes the priority overrides subscribers send from now on - a receiver at a time
+	pub fn overrides(&self) -> mpsc::Receiver<PriorityOverride> {
+		let (tx, rx) = mpsc::channel();
+		self.state.lock().unwrap().overrides = Some(tx);
+		rx
+	}
+	/// Forwards a subscriber's priority overrides, until it goes away
+	fn read(&self, socket: Socket) {
+		let stream = match socket.lock().unwrap().get_ref().try_clone() {
+			Ok(stream) => stream,
+			Err(_) => return,
+		};
+		let peer = stream.peer_addr().map_or_else(|_| "?".to_string(), |a| a.to_string());
+		let timed_out = |e: &std::io::Error| matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut);
+		// whether a frame may already be buffered in the socket, with nothing left to peek at
+		let mut buffered = false;
+		loop {
+			// wait for the next frame without holding the socket, publishing needs it
+			if !buffered {
+				match stream.peek(&mut [0]) {
+					Ok(0) => break,
+					Ok(_) => {},
+					Err(e) if timed_out(&e) => continue,
+					Err(_) => break,
+				}
+			}
+			// the socket's released before the state's locked, publishing locks them the other way around
+			let text = match socket.lock().unwrap().read() {
+				Ok(Message::Text(text)) => text,
+				Ok(Message::Close(_)) => break,
+				Ok(_) => continue,
+				Err(tungstenite::Error::Io(e)) if timed_out(&e) => {
+					buffered = false;
+					continue;
+				},
+				Err(_) => break,
+			};
+			buffered = true;
+			match serde_json::from_str::<PriorityOverride>(&text) {
+				Ok(o) => match &self.state.lock().unwrap().overrides {
+					Some(tx) if tx.send(o).is_ok() => log::info!("Priority override from {}", peer),
+					_ => log::warn!("Priority override from {} ignored, not watching for any", peer),
+				},
+				Err(e) => log::warn!("Invalid priority override from {}: {}", peer, e),
+			}
+		}
+		self.state.lock().unwrap().subscribers.retain(|(_, s)| !Arc::ptr_eq(s, &socket));
+	}
+	/// Publishes the routes of a plan that changed since the previous one
+	///
+	/// Returns: the number of updates
+	pub fn publish<S: Serialize>(&self, routes: &Routes<S>, at: Duration, last: bool) -> usize {
+		let mut state = self.state.lock().unwrap();
+		let mut updates = Vec::new();
+		for (vehicle, route) in &routes.0 {
+			let route = serde_json::to_value(route).unwrap();
+			if !last && state.latest.get(vehicle).is_some_and(|u| u.route == route) {
+				continue;
+			}
+			state.seq += 1;
+			let update = Update { seq: state.seq, vehicle: vehicle.clone(), at: at.as_secs_f64(), last, route };
+			state.latest.insert(vehicle.clone(), update.clone());
+			updates.push(update);
+		}
+		for u in &updates {
+			let text = serde_json::to_string(u).unwrap();
+			state.subscribers.retain(|(topic, socket)| topic.as_ref().is_some_and(|v| v != &u.vehicle) || socket.lock().unwrap().send(Message::Text(text.clone())).is_ok());
+		}
+		updates.len()
+	}
+}
+
+/// Answers a WebSocket opening handshake
+///
+/// Returns: the socket, and the vehicle subscribed to (`None` for all)
+#[allow(clippy::result_large_err)] // tungstenite's callback signature
+fn handshake(stream: TcpStream) -> Result<(WebSocket<TcpStream>, Option<VehicleId>), String> {
+	stream.set_read_timeout(Some(Duration::from_secs(10))).map_err(|e| e.to_string())?;
+	let mut path = String::new();
+	let socket = tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+		path = request.uri().path().to_string();
+		Ok(response)
+	}).map_err(|e| e.to_string())?;
+	let topic = path.trim_matches('/');
+	Ok((socket, Some(topic.to_string()).filter(|t| !t.is_empty())))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn connect(channel: &Channel, path: &str) -> WebSocket<TcpStream> {
+		let stream = TcpStream::connect(channel.local_addr()).unwrap();
+		stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+		tungstenite::client(format!("ws://{}{}", channel.local_addr(), path), stream).unwrap().0
+	}
+
+	#[test]
+	fn test_subscribe(){
+		let channel = Channel::bind("127.0.0.1:0").unwrap();
+		let route = |nodes: &[&'static str]| nodes.iter().map(|n| PathSegment { node: (*n).into(), discriminator: None, reversal: false, leg: None }).collect::<Vec<_>>();
+		let plan = |a, b| Routes(vec![("a".to_string(), route(a)), ("b".to_string(), route(b))].into_iter().collect());
+		assert_eq!(channel.publish(&plan(&["x", "y"], &["y", "z"]), Duration::from_secs(1), false), 2);
+		let mut client = connect(&channel, "/b");
+		let mut next = || -> Update {
+			match client.read().unwrap() {
+				Message::Text(text) => serde_json::from_str(&text).unwrap(),
+				m => panic!("Not a text message: {:?}", m),
+			}
+		};
+		let caught_up = next();
+		assert_eq!((caught_up.seq, caught_up.vehicle.as_str()), (2, "b"));
+		// only the changed route of a is published, which b's subscriber doesn't get
+		assert_eq!(channel.publish(&plan(&["x"], &["y", "z"]), Duration::from_secs(2), false), 1);
+		assert_eq!(channel.publish(&plan(&["x"], &["z"]), Duration::from_secs(3), true), 2);
+		let update = next();
+		assert_eq!((update.seq, update.last), (5, true));
+		assert_eq!(update.route, serde_json::to_value(route(&["z"])).unwrap());
+	}
+
+	#[test]
+	fn test_override(){
+		let channel = Channel::bind("127.0.0.1:0").unwrap();
+		let overrides = channel.overrides();
+		let mut client = connect(&channel, "/");
+		client.send(Message::Text(r#"{"segments": [{"p1": "a", "p2": "b"}], "deadline": 30}"#.to_string())).unwrap();
+		let o = overrides.recv_timeout(Duration::from_secs(10)).unwrap();
+		assert_eq!((o.segments[0].p2.name(), o.deadline), (Some("b"), Some(30.0)));
+	}
+}
@@ -2,25 +2,18 @@
 //!
 //! Universal CLI for solving flight & plow problems, as well as converting spec'd data to/from GeoJSON.
 
-use std::borrow::Cow;
-
-use clap::{App, Arg, SubCommand, crate_version};
-mod data;
-mod graph;
-mod meta;
-mod plow;
-mod gj;
-pub use try_all::{TryAll, TryMapAll};
-pub use noisy_float::prelude::{N64, n64, Float};
-
-pub type NodeId = Cow<'static, str>;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand, crate_version};
+use std::io::Write;
+use wfbfa_solver_cli::*;
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
 #[serde(untagged)]
 enum Wut {
+	Vehicles(data::VehiclesConfiguration),
+	// before paths, which schedules would pass for too
+	Schedules(data::Schedules),
 	Paths(data::Paths),
 	Drones(data::Drones),
-	Vehicles(data::VehiclesConfiguration),
 	Snow(data::SnowStatuses),
 }
 
@@ -31,29 +24,456 @@ enum SnuwDapg {
 	Geo(geojson::FeatureCollection),
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(untagged)]
+enum Roadz {
+	Formal(data::RoadGraph),
+	Geo(geojson::FeatureCollection),
+}
+
+/// Loads the road graph - spec'd JSON, or GeoJSON centerlines
+fn load_roads(matches: &ArgMatches) -> Result<data::RoadGraph, Error> {
+	read_roads(matches.value_of("road-graph").unwrap(), matches)
+}
+
+/// Reads a road graph file - spec'd JSON, or GeoJSON centerlines
+///
+/// Spec'd JSON is streamed, city-scale graphs not having to fit in memory twice; only when it isn't is the whole text read, to tell where it's invalid or for GeoJSON.
+fn read_roads(path: impl AsRef<std::path::Path>, matches: &ArgMatches) -> Result<data::RoadGraph, Error> {
+	if let Ok(roads) = data::RoadGraph::from_reader(std::fs::File::open(&path).map_err(|e| Error::io(path.as_ref().display(), e))?) {
+		return Ok(roads);
+	}
+	Ok(match load_or_geo(path, Roadz::Formal, Roadz::Geo)? {
+		Roadz::Formal(roads) => roads,
+		Roadz::Geo(feat) => gj::geofeatures_to_roads(feat, &gj::RoadFeatures {
+			direction: matches.value_of("oneway-property").unwrap_or("oneway").to_string(),
+			..Default::default()
+		}),
+	})
+}
+
+/// Reads a whole file, telling which one it couldn't
+fn read(path: impl AsRef<std::path::Path>) -> Result<String, Error> {
+	std::fs::read_to_string(&path).map_err(|e| Error::io(path.as_ref().display(), e))
+}
+
+/// Loads a JSON file, telling where it's invalid if it is
+fn load<T: serde::de::DeserializeOwned>(path: impl AsRef<std::path::Path>) -> Result<T, Error> {
+	Ok(config::from_str(&path.as_ref().display().to_string(), &read(&path)?)?)
+}
+
+/// Loads a YAML (or JSON) file, telling where it's invalid if it is
+fn load_yaml<T: serde::de::DeserializeOwned>(path: impl AsRef<std::path::Path>) -> Result<T, Error> {
+	parse_yaml(&path.as_ref().display().to_string(), &read(&path)?)
+}
+
+/// Parses YAML (or JSON) read from a file, telling where it's invalid if it is
+fn parse_yaml<T: serde::de::DeserializeOwned>(file: &str, text: &str) -> Result<T, Error> {
+	let value: serde_json::Value = serde_yaml::from_str(text).map_err(|e| config::ConfigError::document(file, e))?;
+	Ok(config::from_value(file, &value)?)
+}
+
+/// Loads a spec'd JSON file, or else GeoJSON - invalid, it's the spec'd type's errors that tell where
+fn load_or_geo<T: serde::de::DeserializeOwned, R>(path: impl AsRef<std::path::Path>, formal: fn(T) -> R, geo: fn(geojson::FeatureCollection) -> R) -> Result<R, Error> {
+	let text = read(&path)?;
+	match config::from_str(&path.as_ref().display().to_string(), &text) {
+		Ok(t) => Ok(formal(t)),
+		Err(e) => serde_json::from_str(&text).map(geo).map_err(|_| e.into()),
+	}
+}
+
 /// Merge snow samplings with following rules:
 /// - between a sample without snow and a sample with some snow, sampling with snow wins
 /// - depths of all samples for given road segment are averaged
 fn merge_snow_statuses(snows: impl Iterator<Item = data::SnowStatusElement>) -> data::SnowStatuses {
 	let mut keyed = indexmap::IndexMap::new();
 	for s in snows {
-		let entry = keyed.entry((s.p1, s.p2, s.discriminator)).or_insert(n64(0.0));
+		let entry = keyed.entry((s.p1, s.p2, s.discriminator, s.parallel_index)).or_insert(n64(0.0));
 		if *entry <= n64(0.0) || s.depth <= n64(0.0) {
 			*entry = std::cmp::max(*entry, s.depth);
 		} else {
 			*entry = (*entry + s.depth) / n64(2.0);
 		}
 	}
-	keyed.into_iter().map(|((p1, p2, discriminator), depth)| data::SnowStatusElement { p1, p2, discriminator, depth }).collect()
+	keyed.into_iter().map(|((p1, p2, discriminator, parallel_index), depth)| data::SnowStatusElement { p1, p2, discriminator, parallel_index, depth, variance: None, estimated: false }).collect()
+}
+
+/// Parses a duration, in seconds, minutes or hours (e.g. `90s`, `5m`, `1.5h`)
+fn parse_duration(s: &str) -> Result<std::time::Duration, String> {
+	let (n, unit) = s.split_at(s.find(|c: char| c.is_alphabetic()).unwrap_or(s.len()));
+	let n: f64 = n.parse().map_err(|e| format!("bad duration {}: {}", s, e))?;
+	let secs = match unit {
+		"" | "s" => n,
+		"m" => n * 60.0,
+		"h" => n * 3600.0,
+		_ => return Err(format!("bad duration unit {}", unit)),
+	};
+	Ok(std::time::Duration::from_secs_f64(secs))
+}
+
+/// Parses `lon,lat` coordinates
+fn parse_lonlat(s: &str) -> Result<(f64, f64), String> {
+	match s.split(',').map(|c| c.trim().parse()).collect::<Result<Vec<f64>, _>>() {
+		Ok(c) if c.len() == 2 => Ok((c[0], c[1])),
+		_ => Err(format!("bad coordinates {} (expected lon,lat)", s)),
+	}
+}
+
+/// Parses a size, in bytes, kilobytes, megabytes or gigabytes (e.g. `800K`, `50MB`, `1G`)
+fn parse_size(s: &str) -> Result<u64, String> {
+	let (n, unit) = s.split_at(s.find(|c: char| c.is_alphabetic()).unwrap_or(s.len()));
+	let n: f64 = n.parse().map_err(|e| format!("bad size {}: {}", s, e))?;
+	let scale = match unit.trim_end_matches('B') {
+		"" => 1.0,
+		"K" => 1e3,
+		"M" => 1e6,
+		"G" => 1e9,
+		_ => return Err(format!("bad size unit {}", unit)),
+	};
+	Ok((n * scale) as u64)
+}
+
+/// Timing arguments, shared by timeline subcommands
+fn timing_args<'a, 'b>() -> [Arg<'a, 'b>; 7] {
+	[
+		Arg::with_name("snow")
+			.short("s")
+			.takes_value(true)
+			.help("Snow status - only snowy segments are worked (all are without)"),
+		Arg::with_name("speed")
+			.long("speed")
+			.takes_value(true)
+			.default_value("10")
+			.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+			.help("Driving speed, in meters per second"),
+		Arg::with_name("slowdown")
+			.long("slowdown")
+			.takes_value(true)
+			.default_value("2")
+			.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+			.help("Clearing slowdown factor"),
+		Arg::with_name("vehicles")
+			.short("v")
+			.takes_value(true)
+			.help("Vehicles configuration, for their break rules"),
+		Arg::with_name("sidewalks")
+			.short("w")
+			.takes_value(false)
+			.help("Paths are of sidewalk vehicles"),
+		Arg::with_name("rest-spots")
+			.short("r")
+			.takes_value(true)
+			.help("Rest spot nodes JSON, where vehicles can take their breaks (besides their start)"),
+		Arg::with_name("start-time")
+			.long("start-time")
+			.takes_value(true)
+			.validator(|s| timeline::Clock::parse(&s).map(|_| ()).map_err(|e| e.to_string()))
+			.help("Wall-clock start of the plan (RFC3339, e.g. 2024-01-15T02:00:00-05:00), for absolute timestamps"),
+	]
+}
+
+/// Lays out the planned paths in time, as per the timing arguments
+fn plan_timeline(matches: &ArgMatches, roads: &data::RoadGraph, paths: &data::Paths, bucket: std::time::Duration) -> Result<timeline::Timeline, Error> {
+	let snow: Option<data::SnowStatuses> = matches.value_of("snow").map(load).transpose()?;
+	let pace = timeline::Pace {
+		speed: matches.value_of("speed").unwrap().parse().unwrap(),
+		slowdown: matches.value_of("slowdown").unwrap().parse().unwrap(),
+		bucket: bucket.as_secs_f64(),
+	};
+	let mut breaks = timeline::Breaks::default();
+	if let Some(f) = matches.value_of("vehicles") {
+		let vehicles: data::VehiclesConfiguration = load(f)?;
+		let vehicles = if matches.is_present("sidewalks") { vehicles.sidewalk } else { vehicles.road };
+		breaks.rules = vehicles.iter().enumerate().filter_map(|(i, v)| v.breaks().map(|b| (v.id(i), b))).collect();
+	}
+	if let Some(f) = matches.value_of("rest-spots") {
+		breaks.rest_spots = load(f)?;
+	}
+	log::info!("Loaded configuration");
+	let mut tl = timeline::timeline(roads, paths, snow.as_ref(), pace, &breaks);
+	if let Some(s) = matches.value_of("start-time") {
+		tl.stamp(timeline::Clock::parse(s).unwrap());
+	}
+	Ok(tl)
+}
+
+/// Storm severity preset argument, shared by solving subcommands
+fn preset_arg<'a, 'b>() -> Arg<'a, 'b> {
+	Arg::with_name("preset")
+		.long("preset")
+		.takes_value(true)
+		.possible_values(&meta::Preset::NAMES)
+		.help("Storm severity preset the meta parameters start from - they then only need the overrides")
+}
+
+/// Metric argument, shared by subcommands locating coordinates without meta parameters
+fn metric_arg<'a, 'b>() -> Arg<'a, 'b> {
+	Arg::with_name("metric")
+		.long("metric")
+		.takes_value(true)
+		.possible_values(&data::Metric::NAMES)
+		.default_value("Geodesic")
+		.help("Metric coordinates are located to the nearest node by (as the `metric` meta parameter)")
+}
+
+fn metric(matches: &ArgMatches) -> data::Metric {
+	matches.value_of("metric").unwrap().parse().unwrap()
+}
+
+/// Simplification tolerance argument, shared by subcommands exporting lines to GeoJSON
+fn simplify_arg<'a, 'b>() -> Arg<'a, 'b> {
+	Arg::with_name("simplify")
+		.long("simplify")
+		.takes_value(true)
+		.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+		.help("Simplify the exported lines (Douglas-Peucker), to within this many meters - the original segment count kept as `segments`")
+}
+
+/// Simplifies an exported feature, if asked to
+fn simplify(matches: &ArgMatches, f: geojson::Feature) -> geojson::Feature {
+	match matches.value_of("simplify") {
+		Some(t) => gj::simplify(f, t.parse().unwrap()),
+		None => f,
+	}
+}
+
+/// Solving threads argument, shared by solving subcommands
+fn threads_arg<'a, 'b>() -> Arg<'a, 'b> {
+	Arg::with_name("threads")
+		.long("threads")
+		.takes_value(true)
+		.validator(|s| s.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+		.help("Threads to solve on, overriding the meta parameters' parallelism (all the cores by default)")
+}
+
+/// Annealing seed argument, shared by solving subcommands
+fn seed_arg<'a, 'b>() -> Arg<'a, 'b> {
+	Arg::with_name("seed")
+		.long("seed")
+		.takes_value(true)
+		.validator(|s| s.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()))
+		.help("Random seed of the annealing, overriding the meta parameters' - the same inputs and seed make the same plan")
+}
+
+/// Meta parameters, over the preset if any - with the threads and seed arguments'
+fn load_params(matches: &ArgMatches) -> Result<meta::Parameters, Error> {
+	// without meta parameters, the preset's (the standard one's without either)
+	let mut params: meta::Parameters = match (matches.value_of("meta"), matches.value_of("preset")) {
+		(Some(file), Some(preset)) => {
+			let overrides = read(file)?;
+			let overrides = if overrides.trim().is_empty() { serde_json::Value::Null } else { parse_yaml(file, &overrides)? };
+			config::from_value(file, &preset.parse::<meta::Preset>().unwrap().merged(overrides))?
+		},
+		(Some(file), None) => load_yaml(file)?,
+		(None, Some(preset)) => preset.parse::<meta::Preset>().unwrap().parameters(),
+		(None, None) => Default::default(),
+	};
+	if let Some(t) = matches.value_of("threads") {
+		params.parallelism = Some(t.parse().unwrap());
+	}
+	if let Some(seed) = matches.value_of("seed") {
+		params.annealing.seed = Some(seed.parse().unwrap());
+	}
+	Ok(params)
+}
+
+/// Anytime mode arguments, shared by solving subcommands
+fn budget_args<'a, 'b>() -> [Arg<'a, 'b>; 3] {
+	[
+		Arg::with_name("budget")
+			.short("b")
+			.long("budget")
+			.takes_value(true)
+			.validator(|s| parse_duration(&s).map(|_| ()))
+			.help("Wall-clock budget (e.g. 90s, 5m, 1h) - annealing carries on until it's spent"),
+		Arg::with_name("checkpoints")
+			.short("c")
+			.long("checkpoints")
+			.takes_value(true)
+			.use_delimiter(true)
+			.validator(|s| parse_duration(&s).map(|_| ()))
+			.help(r#"Elapsed times (e.g. 1m,2m,5m) at which the best plan so far is written to "{output}.{seconds}s.json""#),
+		Arg::with_name("stats")
+			.long("stats")
+			.takes_value(true)
+			.help("Solver statistics JSON output file, with a summary of every annealing restart"),
+	]
+}
+
+/// Anytime mode settings from arguments
+fn budget(matches: &ArgMatches) -> plow::Budget {
+	plow::Budget {
+		time: matches.value_of("budget").map(|b| parse_duration(b).unwrap()),
+		checkpoints: matches.values_of("checkpoints").map(|cs| cs.map(|c| parse_duration(c).unwrap()).collect()).unwrap_or_default(),
+	}
+}
+
+/// Logs the annealing restarts, and writes the solver statistics if asked to
+fn write_stats(matches: &ArgMatches, mut stats: plow::Stats, graph: String) -> Result<(), Error> {
+	stats.graph = Some(graph);
+	if stats.restarts.len() > 1 {
+		let values: Vec<f64> = stats.restarts.iter().map(|r| r.value.raw()).collect();
+		log::info!("Best of {} restarts: {:.1} (worst {:.1})", values.len(), values[stats.best], values.iter().cloned().fold(f64::NEG_INFINITY, f64::max));
+	}
+	if let Some(best) = stats.restarts.get(stats.best).filter(|r| r.redundant > 0.0) {
+		log::info!("Redundant coverage: {:.1}", best.redundant);
+	}
+	let (hits, misses) = stats.restarts.iter().fold((0, 0), |(h, m), r| (h + r.cache_hits, m + r.cache_misses));
+	if hits + misses > 0 {
+		log::info!("Path cache hit rate: {:.1}% of {} lookups", 100.0 * hits as f64 / (hits + misses) as f64, hits + misses);
+	}
+	if let Some(f) = matches.value_of("stats") {
+		serde_json::to_writer_pretty(&std::fs::File::create(f)?, &stats).unwrap();
+	}
+	Ok(())
+}
+
+/// Runs a solving job, firing the notification hooks under `notify` in its meta parameters when it completes, turns out infeasible or gets cancelled
+fn solved<T>(matches: &ArgMatches, command: &str, graph: &str, solve: impl FnOnce() -> Result<(T, plow::Stats), Error>) -> Result<(T, plow::Stats), Error> {
+	let mut fields = serde_json::Map::new();
+	fields.insert("command".to_string(), command.into());
+	fields.insert("meta".to_string(), serde_json::json!(matches.value_of("meta")));
+	fields.insert("output".to_string(), serde_json::json!(matches.value_of("output")));
+	fields.insert("graph".to_string(), graph.into());
+	#[cfg(feature = "notify")]
+	let hooks = {
+		#[derive(serde::Deserialize)]
+		struct Job {
+			#[serde(default)]
+			notify: notify::Hooks,
+		}
+		// a bare preset has no meta parameters to speak of, if any are given at all
+		let job = matches.value_of("meta").map(read).transpose()?.unwrap_or_default();
+		let job: Job = if job.trim().is_empty() { Job { notify: Default::default() } } else { parse_yaml(matches.value_of("meta").unwrap(), &job)? };
+		#[cfg(unix)]
+		if !job.notify.is_empty() {
+			let (hooks, fields) = (job.notify.clone(), fields.clone());
+			notify::on_interrupt(move |sig| {
+				log::warn!("Cancelled");
+				for (url, e) in notify::fire(&hooks, notify::Event::Cancelled, fields) {
+					log::error!("Notifying {} failed: {}", url, e);
+				}
+				std::process::exit(128 + sig);
+			});
+		}
+		job.notify
+	};
+	let start = std::time::Instant::now();
+	let solution = solve();
+	fields.insert("elapsed".to_string(), start.elapsed().as_secs_f64().into());
+	#[cfg(feature = "notify")]
+	let failed = match &solution {
+		Ok((_, stats)) => {
+			let best = &stats.restarts[stats.best];
+			fields.insert("value".to_string(), best.value.raw().into());
+			fields.insert("max".to_string(), best.max.raw().into());
+			fields.insert("restarts".to_string(), stats.restarts.len().into());
+			notify::fire(&hooks, notify::Event::Completed, fields)
+		},
+		Err(e) => {
+			fields.insert("error".to_string(), e.to_string().into());
+			notify::fire(&hooks, notify::Event::Infeasible, fields)
+		},
+	};
+	#[cfg(feature = "notify")]
+	for (url, e) in failed {
+		log::error!("Notifying {} failed: {}", url, e);
+	}
+	#[cfg(not(feature = "notify"))]
+	let _ = fields;
+	solution
+}
+
+/// Live route updates channel, if asked for with `--live`
+#[cfg(feature = "live")]
+type Live = Option<live::Channel>;
+#[cfg(not(feature = "live"))]
+type Live = ();
+
+fn live_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+	#[cfg(feature = "live")]
+	return vec![Arg::with_name("live")
+		.long("live")
+		.takes_value(true)
+		.help("Serve route updates over WebSocket at this address (e.g. 0.0.0.0:8080) as checkpoints change them - ws://address/<vehicle> for a vehicle's, / for all"),
+		Arg::with_name("watch")
+		.long("watch")
+		.requires("live")
+		.conflicts_with("sidewalks")
+		.help("Keep serving once solved, replanning for the priority overrides subscribers send until killed")];
+	#[cfg(not(feature = "live"))]
+	Vec::new()
+}
+
+fn live_channel(matches: &ArgMatches) -> Result<Live, Error> {
+	#[cfg(feature = "live")]
+	return matches.value_of("live").map(|addr| {
+		// the accepting thread leaving interrupts to the notification hooks (see `solved`)
+		#[cfg(all(feature = "notify", unix))]
+		let channel = notify::without_interrupts(|| live::Channel::bind(addr))?;
+		#[cfg(not(all(feature = "notify", unix)))]
+		let channel = live::Channel::bind(addr)?;
+		log::info!("Serving live route updates at ws://{}", channel.local_addr());
+		Ok(channel)
+	}).transpose();
+	#[cfg(not(feature = "live"))]
+	{
+		let _ = matches;
+		Ok(())
+	}
+}
+
+/// Takes the priority overrides live subscribers send, when watching for them
+fn live_overrides(matches: &ArgMatches, live: &Live) -> Option<std::sync::mpsc::Receiver<data::PriorityOverride>> {
+	#[cfg(feature = "live")]
+	return live.as_ref().filter(|_| matches.is_present("watch")).map(live::Channel::overrides);
+	#[cfg(not(feature = "live"))]
+	{
+		let _ = (matches, live);
+		None
+	}
+}
+
+/// Publishes a plan's changed routes to the live subscribers
+fn publish<S: serde::Serialize>(live: &Live, t: std::time::Duration, routes: &data::Routes<S>, last: bool) {
+	#[cfg(feature = "live")]
+	if let Some(channel) = live {
+		log::debug!("Published {} live route updates", channel.publish(routes, t, last));
+	}
+	#[cfg(not(feature = "live"))]
+	let _ = (live, t, routes, last);
+}
+
+/// Writes an intermediate plan next to the output
+fn write_checkpoint(output: &str, t: std::time::Duration, paths: &impl serde::Serialize) {
+	let f = format!("{}.{}s.json", output.strip_suffix(".json").unwrap_or(output), t.as_secs_f64());
+	match std::fs::File::create(&f).map_err(|e| e.to_string()).and_then(|w| serde_json::to_writer(w, paths).map_err(|e| e.to_string())) {
+		Ok(()) => log::info!("Checkpoint {:?} written to {}", t, f),
+		Err(e) => log::error!("Failed to write checkpoint {}: {}", f, e),
+	}
 }
 
-fn main() -> std::io::Result<()> {
+fn main() {
 	env_logger::init_from_env(env_logger::Env::default().filter_or(env_logger::DEFAULT_FILTER_ENV, "info"));
-	let matches = App::new("Flight Paths Compute")
+	// errors are for operators, not a debug dump
+	if let Err(e) = run() {
+		log::error!("{}", e);
+		std::process::exit(1);
+	}
+}
+
+fn run() -> Result<(), Error> {
+	let app = App::new("Flight Paths Compute")
 							.version(crate_version!())
 							.about("Make it fly!")
+							.arg(Arg::with_name("oneway-property")
+									.long("oneway-property")
+									.takes_value(true)
+									.global(true)
+									.help("Property telling the direction of GeoJSON road graph lines (default oneway)"))
 							.subcommand(SubCommand::with_name("fly")
 								.about("Compute flight paths")
+								.setting(AppSettings::AllowMissingPositional)
 								.arg(Arg::with_name("road-graph")
 										.takes_value(true)
 										.required(true)
@@ -66,17 +486,39 @@ fn main() -> std::io::Result<()> {
 										.help("Drones configuration JSON"))
 								.arg(Arg::with_name("meta")
 										.takes_value(true)
-										.required(true)
 										.index(3)
-										.help("Meta parameters"))
+										.help("Meta parameters - the standard preset's (or --preset's) without"))
 								.arg(Arg::with_name("output")
 										.takes_value(true)
 										.required(true)
 										.index(4)
 										.help("Output JSON"))
+								.arg(Arg::with_name("sites")
+										.long("sites")
+										.takes_value(true)
+										.help("Sensitive sites JSON - schools, hospitals... to keep the drones away from"))
+								.arg(Arg::with_name("crossings")
+										.long("crossings")
+										.takes_value(true)
+										.requires("sites")
+										.help("Write the crossings of sensitive sites left, by drone, to this JSON file"))
+								.arg(Arg::with_name("schedule")
+										.long("schedule")
+										.takes_value(true)
+										.help("Write the drones' schedules - distance flown and estimated arrival at every node - to this JSON file"))
+								.arg(Arg::with_name("objective")
+										.long("objective")
+										.takes_value(true)
+										.possible_values(&["total", "max"])
+										.help("Minimize the total distance flown, or that of the busiest drone - instead of the meta parameters' weights"))
+								.arg(preset_arg())
+								.arg(threads_arg())
+								.arg(seed_arg())
+								.args(&budget_args())
 							)
 							.subcommand(SubCommand::with_name("snows")
 								.about("Merge multiple snow status updates")
+								.setting(AppSettings::SubcommandsNegateReqs)
 								.arg(Arg::with_name("road-graph")
 										.takes_value(true)
 										.required(true)
@@ -91,9 +533,61 @@ fn main() -> std::io::Result<()> {
 										.takes_value(true)
 										.required(true)
 										.multiple(true)
-										.help("Let it snow let it snow let it go")))
+										.help("Let it snow let it snow let it go"))
+								.arg(Arg::with_name("interpolate")
+										.long("interpolate")
+										.takes_value(true)
+										.validator(|s| s.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Estimate depths of unsampled segments from samples within this many hops"))
+								.subcommand(SubCommand::with_name("simulate")
+									.about("Simulate a storm's snow status, for drills")
+									.arg(Arg::with_name("road-graph")
+											.takes_value(true)
+											.required(true)
+											.index(1)
+											.help("Road Graph JSON"))
+									.arg(Arg::with_name("output")
+											.takes_value(true)
+											.required(true)
+											.index(2)
+											.help("Simulated snow status output JSON"))
+									.arg(Arg::with_name("center")
+											.long("center")
+											.takes_value(true)
+											.validator(|s| parse_lonlat(&s).map(|_| ()))
+											.help("Storm center as lon,lat (default the road graph centroid)"))
+									.arg(Arg::with_name("intensity")
+											.long("intensity")
+											.takes_value(true)
+											.default_value("1")
+											.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+											.help("Snowfall rate at the center, in depth per hour"))
+									.arg(Arg::with_name("gradient")
+											.long("gradient")
+											.takes_value(true)
+											.default_value("0.1")
+											.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+											.help("Fraction of the center snowfall rate lost per kilometer away from it"))
+									.arg(Arg::with_name("duration")
+											.long("duration")
+											.takes_value(true)
+											.default_value("6")
+											.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+											.help("Storm duration, in hours"))
+									.arg(Arg::with_name("noise")
+											.long("noise")
+											.takes_value(true)
+											.default_value("0.2")
+											.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+											.help("Relative amplitude of random depth variation"))
+									.arg(Arg::with_name("seed")
+											.long("seed")
+											.takes_value(true)
+											.validator(|s| s.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()))
+											.help("Random seed, for a reproducible storm"))))
 							.subcommand(SubCommand::with_name("plow")
 								.about("Plow dat snow!")
+								.setting(AppSettings::AllowMissingPositional)
 								.arg(Arg::with_name("road-graph")
 										.takes_value(true)
 										.required(true)
@@ -111,9 +605,8 @@ fn main() -> std::io::Result<()> {
 										.help("Vehicles configuration"))
 								.arg(Arg::with_name("meta")
 										.takes_value(true)
-										.required(true)
 										.index(4)
-										.help("Meta parameters"))
+										.help("Meta parameters - the standard preset's (or --preset's) without"))
 								.arg(Arg::with_name("output")
 										.takes_value(true)
 										.required(true)
@@ -128,87 +621,1789 @@ fn main() -> std::io::Result<()> {
 								.arg(Arg::with_name("sidewalks")
 									.short("w")
 									.takes_value(false)
-									.help("Clean sidewalks")))
-							.subcommand(SubCommand::with_name("geojson")
-								.about("Convert anything into GeoJSONs")
+									.help("Clean sidewalks"))
+								.arg(Arg::with_name("temperature")
+										.long("temperature")
+										.takes_value(true)
+										.allow_hyphen_values(true)
+										.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Ambient temperature, overriding the meta parameters'"))
+								.arg(Arg::with_name("boosted")
+										.long("boosted")
+										.takes_value(true)
+										.conflicts_with("sidewalks")
+										.help("Write the segments boosted for de-icing, and when they are cleared, to this JSON"))
+								.arg(Arg::with_name("priorities")
+										.long("priorities")
+										.takes_value(true)
+										.conflicts_with("sidewalks")
+										.help("Write the snowy priority segments, when they are cleared and whether past their deadline, to this JSON"))
+								.arg(Arg::with_name("schedule")
+										.long("schedule")
+										.takes_value(true)
+										.conflicts_with("sidewalks")
+										.help("Write the vehicles' schedules - distance driven and estimated arrival at every node - to this JSON"))
+								.arg(Arg::with_name("convoys")
+										.long("convoys")
+										.takes_value(true)
+										.conflicts_with("sidewalks")
+										.help("Segments plowed in echelon, and by how many vehicles (JSON)"))
+								.arg(Arg::with_name("convoy-report")
+										.long("convoy-report")
+										.takes_value(true)
+										.requires("convoys")
+										.help("Write the convoys formed, and their members, to this JSON"))
+								.arg(preset_arg())
+								.arg(threads_arg())
+								.arg(seed_arg())
+								.args(&budget_args())
+								.args(&live_args()))
+							.subcommand(SubCommand::with_name("spread")
+								.about("Spread salt/abrasives where the rules say")
+								.setting(AppSettings::AllowMissingPositional)
 								.arg(Arg::with_name("road-graph")
 										.takes_value(true)
 										.required(true)
 										.index(1)
 										.help("Road Graph JSON"))
-								.arg(Arg::with_name("wut")
+								.arg(Arg::with_name("spreading")
 										.takes_value(true)
 										.required(true)
 										.index(2)
-										.help("Produced thingy that you want to convert (currently supported: flight paths)"))
+										.help("Spreading configuration (coverage rules, rate and depots)"))
+								.arg(Arg::with_name("vehicles")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Vehicles configuration"))
+								.arg(Arg::with_name("meta")
+										.takes_value(true)
+										.index(4)
+										.help("Meta parameters - the standard preset's (or --preset's) without"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(5)
+										.help("Output JSON"))
+								.arg(Arg::with_name("report")
+										.short("r")
+										.takes_value(true)
+										.help("Write what each vehicle spreads and where it reloads to this JSON"))
+								.arg(preset_arg())
+								.arg(threads_arg())
+								.arg(seed_arg())
+								.args(&budget_args()))
+							.subcommand(SubCommand::with_name("storm")
+								.about("Plow dat snow, day after day")
+								.setting(AppSettings::AllowMissingPositional)
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("forecast")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Snow fall forecast - a JSON list of snow statuses, one per round"))
+								.arg(Arg::with_name("vehicles")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Vehicles configuration"))
+								.arg(Arg::with_name("meta")
+										.takes_value(true)
+										.index(4)
+										.help("Meta parameters - the standard preset's without"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(5)
+										.help("Output plan bundle JSON"))
+								.arg(threads_arg())
+								.arg(seed_arg())
+								.args(&budget_args()[..1]))
+							.subcommand(SubCommand::with_name("evaluate-actual")
+								.about("Evaluate plan adherence against actual GPS traces")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("paths")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Planned paths JSON"))
+								.arg(Arg::with_name("traces")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Actual GPS traces JSON, in the same vehicle order as the paths"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(4)
+										.help("Evaluation report output JSON"))
+								.arg(metric_arg())
+							)
+							.subcommand(SubCommand::with_name("calibrate")
+								.about("Estimate driving speeds and clearing slowdowns from past GPS traces")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("snow")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Snow status before the traces"))
+								.arg(Arg::with_name("traces")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("GPS traces JSON"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(4)
+										.help("Meta parameters fragments YAML output, by vehicle type"))
+								.arg(Arg::with_name("types")
+										.short("t")
+										.takes_value(true)
+										.help("Vehicle types JSON (vehicle id to type) - all vehicles are of the same type otherwise"))
+								.arg(Arg::with_name("bucket")
+										.long("bucket")
+										.takes_value(true)
+										.default_value("5")
+										.validator(|s| s.parse::<f64>().map_err(|e| e.to_string()).and_then(|b| if b > 0.0 { Ok(()) } else { Err("must be positive".to_string()) }))
+										.help("Width of the snow depth intervals the slowdown is estimated on"))
+								.arg(metric_arg())
+							)
+							.subcommand(SubCommand::with_name("gantt")
+								.about("Export a per-vehicle timeline of planned paths")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("paths")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Planned paths JSON"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Timeline output JSON"))
+								.args(&timing_args())
+								.arg(Arg::with_name("bucket")
+										.long("bucket")
+										.takes_value(true)
+										.default_value("15m")
+										.validator(|s| parse_duration(&s).map(|_| ()))
+										.help("Histogram bucket width"))
+								.arg(Arg::with_name("svg")
+										.long("svg")
+										.takes_value(true)
+										.help("Also render the timeline as an SVG Gantt chart"))
+							)
+							.subcommand(SubCommand::with_name("check-windows")
+								.about("Validate that planned paths respect the direction windows of reversible segments")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("paths")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Planned paths JSON"))
+								.args(&timing_args())
+								.arg(Arg::with_name("output")
+										.short("o")
+										.takes_value(true)
+										.help("Write the violations to this JSON"))
+							)
+							.subcommand(SubCommand::with_name("chunk")
+								.about("Cut planned paths into time intervals, for dispatch")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("paths")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Planned paths JSON"))
 								.arg(Arg::with_name("prefix")
 										.takes_value(true)
 										.required(true)
 										.index(3)
-										.help(r#"GeoJSON files prefix - the generated files will be named alike "{prefix}.{...}.geojson""#))
+										.help(r#"GeoJSON files prefix - the generated files will be named alike "{prefix}.{vehicle}.{interval}.geojson""#))
+								.arg(Arg::with_name("interval")
+										.long("interval")
+										.takes_value(true)
+										.default_value("1h")
+										.validator(|s| parse_duration(&s).map(|_| ()))
+										.help("Time interval width"))
+								.arg(Arg::with_name("csv")
+										.long("csv")
+										.takes_value(true)
+										.help("Also write the chunks as CSV (vehicle,interval,start,end,node,discriminator,cast,start_at,end_at lines)"))
+								.args(&timing_args())
+								.arg(Arg::with_name("cast-side")
+										.long("cast-side")
+										.takes_value(true)
+										.possible_values(&["left", "right"])
+										.default_value("right")
+										.help("Side to cast snow to"))
+								.arg(Arg::with_name("cast-onto")
+										.long("cast-onto")
+										.takes_value(true)
+										.multiple(true)
+										.use_delimiter(true)
+										.possible_values(&["sidewalks", "parking"])
+										.help("Curbside features that may be cast onto - otherwise the cast goes to the other side if it's clear of them"))
+								.arg(simplify_arg())
 							)
-							.get_matches();
-	log::info!("Loading...");
-	if let Some(matches) = matches.subcommand_matches("fly") {
-		log::trace!("tracing enabled");
-		let drones: data::Drones = serde_json::from_reader(&std::fs::File::open(matches.value_of("drones").unwrap())?).expect("Drones config invalid JSON");
-		let roads: data::RoadGraph = serde_json::from_reader(&std::fs::File::open(matches.value_of("road-graph").unwrap())?).expect("Road graph invalid JSON");
-		let params: meta::Parameters = serde_yaml::from_reader(&std::fs::File::open(matches.value_of("meta").unwrap())?).expect("Meta parameters invalid JSON");
-		log::info!("Loaded configuration");
-		let paths = plow::fly::solve(roads, drones, &params).unwrap();
-		log::info!("Constructed paths");
-		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &paths).unwrap();
-	} else if let Some(matches) = matches.subcommand_matches("snows") {
-		let roads: data::RoadGraph = serde_json::from_reader(&std::fs::File::open(matches.value_of("road-graph").unwrap())?).expect("Road graph invalid JSON");
-		log::info!("Loaded configuration");
-		let mut snu: Vec<SnuwDapg> = Vec::new();
-		for f in matches.values_of("snows").unwrap() {
-			snu.push(serde_json::from_reader(&std::fs::File::open(f)?).expect("Snow status invalid JSON"));
-		}
-		log::info!("Loaded ❄");
-		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &merge_snow_statuses(snu.into_iter().map(|s| match s {
-			SnuwDapg::Formal(s) => s,
-			SnuwDapg::Geo(feat) => gj::geofeatures_to_snow(&roads, feat),
-		}).flatten())).unwrap();
-	} else if let Some(matches) = matches.subcommand_matches("plow") {
-		log::trace!("tracing enabled");
-		let roads: data::RoadGraph = serde_json::from_reader(&std::fs::File::open(matches.value_of("road-graph").unwrap())?).expect("Road graph config invalid JSON");
-		let snow: data::SnowStatuses = serde_json::from_reader(&std::fs::File::open(matches.value_of("snow").unwrap())?).expect("Snow status config invalid JSON");
-		let vehicles: data::VehiclesConfiguration = serde_json::from_reader(&std::fs::File::open(matches.value_of("vehicles").unwrap())?).expect("Meta parameters invalid JSON");
-		let params: meta::Parameters = serde_yaml::from_reader(&std::fs::File::open(matches.value_of("meta").unwrap())?).expect("Meta parameters invalid JSON");
-		log::info!("Loaded configuration");
-		if matches.is_present("sidewalks") {
-			let paths = plow::sidewalk::solve(roads, snow, matches.value_of("snow-d").map(|f| f.parse().unwrap()), vehicles, &params).unwrap();
-			log::info!("Constructed paths");
-			serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &paths).unwrap();
-		} else {
-			let paths = plow::road::solve(roads, snow, matches.value_of("snow-d").map(|f| f.parse().unwrap()), vehicles, &params).unwrap();
-			log::info!("Constructed paths");
-			serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &paths).unwrap();
-		}
-	} else if let Some(matches) = matches.subcommand_matches("geojson") {
-		let roads: data::RoadGraph = serde_json::from_reader(&std::fs::File::open(matches.value_of("road-graph").unwrap())?).expect("Road graph config invalid JSON");
-		let pref = matches.value_of("prefix").unwrap();
-		let wut = serde_json::from_reader(&std::fs::File::open(matches.value_of("wut").unwrap())?).expect("WUT invalid JSON");
-		log::info!("Loaded configuration");
-		match wut {
-			Wut::Paths(paths) => {
-				let g = gj::roads_to_nodes(roads.nodes);
-				for (i, path) in (0..paths.len()).zip(paths.into_iter()) {
-					serde_json::to_writer(&std::fs::File::create(format!("{}.{}.geojson", pref, i))?, &gj::path_to_geojson(&g, path)).unwrap();
-				}
-			}
-			Wut::Drones(drones) => {
-				serde_json::to_writer(&std::fs::File::create(format!("{}.geojson", pref))?, &gj::locations_to_geojson(&roads.nodes, drones)).unwrap();
-			}
-			Wut::Vehicles(vc) => {
-				serde_json::to_writer(&std::fs::File::create(format!("{}.road.geojson", pref))?, &gj::locations_to_geojson(&roads.nodes, vc.road)).unwrap();
-				serde_json::to_writer(&std::fs::File::create(format!("{}.sidewalk.geojson", pref))?, &gj::locations_to_geojson(&roads.nodes, vc.sidewalk)).unwrap();
-			}
+							.subcommand(SubCommand::with_name("conflicts")
+								.about("Detect vehicles crossing each other on planned paths, and optionally delay their starts to avoid it")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("paths")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Planned paths JSON"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Conflicts report output JSON"))
+								.args(&timing_args())
+								.arg(Arg::with_name("window")
+										.long("window")
+										.takes_value(true)
+										.default_value("30s")
+										.validator(|s| parse_duration(&s).map(|_| ()))
+										.help("Time window within which vehicles on the same node or segment conflict"))
+								.arg(Arg::with_name("deconflict")
+										.long("deconflict")
+										.takes_value(false)
+										.help("Delay vehicle starts to avoid conflicts"))
+								.arg(Arg::with_name("step")
+										.long("step")
+										.takes_value(true)
+										.default_value("1m")
+										.validator(|s| parse_duration(&s).map(|_| ()))
+										.help("Start delay step"))
+								.arg(Arg::with_name("max-offset")
+										.long("max-offset")
+										.takes_value(true)
+										.default_value("15m")
+										.validator(|s| parse_duration(&s).map(|_| ()))
+										.help("Maximum start delay"))
+							)
+							.subcommand(SubCommand::with_name("render")
+								.about("Draw a plan map")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("paths")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Paths JSON"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Output SVG, or PNG by the .png extension"))
+								.arg(Arg::with_name("snow")
+										.short("s")
+										.takes_value(true)
+										.help("Snow status, to highlight the snowy segments"))
+							)
+							.subcommand(SubCommand::with_name("check-lengths")
+								.about("Validate road segment lengths against node coordinates")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("tolerance")
+										.short("t")
+										.takes_value(true)
+										.default_value("0.1")
+										.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Tolerated relative deviation from the geodesic length"))
+								.arg(Arg::with_name("fix")
+										.short("f")
+										.takes_value(true)
+										.help("Write the road graph with deviating lengths replaced by geodesic ones to this JSON"))
+							)
+							.subcommand(SubCommand::with_name("check-surfaces")
+								.about("Validate that every snowy segment has a vehicle with compatible equipment")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("snow")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Snow status"))
+								.arg(Arg::with_name("vehicles")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Vehicles configuration"))
+								.arg(Arg::with_name("sidewalks")
+										.short("w")
+										.takes_value(false)
+										.help("Check sidewalk vehicles"))
+								.arg(Arg::with_name("snow-d")
+										.short("d")
+										.takes_value(true)
+										.default_value("0")
+										.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Default snow depth"))
+							)
+							.subcommand(SubCommand::with_name("validate")
+								.about("Validate a road graph - missing nodes, invalid lengths and coordinates, duplicates, degree anomalies and disconnected components")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.index(2)
+										.help("Write the report (errors and warnings) to this JSON, standard output otherwise"))
+							)
+							.subcommand(SubCommand::with_name("what-if")
+								.about("Evaluate the impact of hypothetical road closures on a plan")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("paths")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Planned paths JSON"))
+								.arg(Arg::with_name("closures")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Closed road segments JSON"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(4)
+										.help("Impact report output JSON"))
+								.arg(Arg::with_name("revised")
+										.takes_value(true)
+										.required(true)
+										.index(5)
+										.help("Revised paths of affected vehicles output JSON"))
+							)
+							.subcommand(SubCommand::with_name("replan")
+								.about("Update a road plan for new snow statuses, re-solving only the routes affected")
+								.setting(AppSettings::AllowMissingPositional)
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("paths")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Planned paths JSON"))
+								.arg(Arg::with_name("before")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Snow status the paths were planned for"))
+								.arg(Arg::with_name("snow")
+										.takes_value(true)
+										.required(true)
+										.index(4)
+										.help("New snow status"))
+								.arg(Arg::with_name("vehicles")
+										.takes_value(true)
+										.required(true)
+										.index(5)
+										.help("Vehicles configuration"))
+								.arg(Arg::with_name("meta")
+										.takes_value(true)
+										.index(6)
+										.help("Meta parameters - the standard preset's (or --preset's) without"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(7)
+										.help("Updated paths output JSON"))
+								.arg(Arg::with_name("snow-d")
+										.short("d")
+										.takes_value(true)
+										.default_value("0")
+										.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Default snow depth"))
+								.arg(Arg::with_name("report")
+										.short("r")
+										.takes_value(true)
+										.help("Write what was replanned to this JSON"))
+								.arg(preset_arg())
+							)
+							.subcommand(SubCommand::with_name("merge-plans")
+								.about("Merge the plans of adjacent districts, handing shared segments off to a single vehicle")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON, of all the districts"))
+								.arg(Arg::with_name("snow")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Snow status"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Merged paths output JSON"))
+								.arg(Arg::with_name("plans")
+										.takes_value(true)
+										.required(true)
+										.multiple(true)
+										.min_values(2)
+										.index(4)
+										.help("Paths JSONs of the districts"))
+								.arg(Arg::with_name("meta")
+										.short("m")
+										.long("meta")
+										.takes_value(true)
+										.help("Meta parameters - the standard preset's (or --preset's) without (an option, as the plans come last)"))
+								.arg(Arg::with_name("report")
+										.short("r")
+										.takes_value(true)
+										.help("Write the handoffs of shared segments to this JSON"))
+								.arg(Arg::with_name("snow-d")
+										.short("d")
+										.takes_value(true)
+										.default_value("0")
+										.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Default snow depth"))
+								.arg(preset_arg())
+							)
+							.subcommand(SubCommand::with_name("critical-segments")
+								.about("Report the chokepoints of plow routing, by betweenness")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("snow")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Snow status"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Critical segments output JSON"))
+								.arg(Arg::with_name("top")
+										.short("n")
+										.long("top")
+										.takes_value(true)
+										.default_value("20")
+										.validator(|s| s.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Number of segments reported"))
+								.arg(Arg::with_name("snow-d")
+										.short("d")
+										.takes_value(true)
+										.default_value("0")
+										.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Default snow depth"))
+							)
+							.subcommand(SubCommand::with_name("score")
+								.about("Score road paths with the solver's cost function")
+								.setting(AppSettings::AllowMissingPositional)
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("snow")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Snow status"))
+								.arg(Arg::with_name("meta")
+										.takes_value(true)
+										.index(3)
+										.help("Meta parameters - the standard preset's without"))
+								.arg(Arg::with_name("paths")
+										.takes_value(true)
+										.required(true)
+										.index(4)
+										.help("Paths JSON"))
+								.arg(Arg::with_name("output")
+										.short("o")
+										.takes_value(true)
+										.help("Write the costs to this JSON"))
+								.arg(Arg::with_name("snow-d")
+										.short("d")
+										.takes_value(true)
+										.default_value("0")
+										.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Default snow depth"))
+							)
+							.subcommand(SubCommand::with_name("experiments")
+								.about("Run experiment matrices of plowing instances, parameters and seeds, and summarize the results")
+								.subcommand(SubCommand::with_name("run")
+									.about("Solve every instance with every parameter set and seed, appending to the results database")
+									.arg(Arg::with_name("matrix")
+											.takes_value(true)
+											.required(true)
+											.index(1)
+											.help("Experiment matrix YAML (paths relative to it)"))
+									.arg(Arg::with_name("database")
+											.takes_value(true)
+											.required(true)
+											.index(2)
+											.help("Results database (SQLite, created if need be) - runs already in it are skipped"))
+									.arg(Arg::with_name("budget")
+											.short("b")
+											.long("budget")
+											.takes_value(true)
+											.validator(|s| parse_duration(&s).map(|_| ()))
+											.help("Wall-clock budget of every run (e.g. 90s, 5m, 1h)"))
+									.arg(Arg::with_name("workers")
+											.long("workers")
+											.takes_value(true)
+											.use_delimiter(true)
+											.help("Hand the runs out to these workers (host:port, comma-separated) instead of solving them here")))
+								.subcommand(SubCommand::with_name("summary")
+									.about("Summarize the results by instance and parameter set")
+									.arg(Arg::with_name("database")
+											.takes_value(true)
+											.required(true)
+											.index(1)
+											.help("Results database (SQLite)"))
+									.arg(Arg::with_name("instance")
+											.short("i")
+											.takes_value(true)
+											.help("Only summarize runs of this instance"))
+									.arg(Arg::with_name("output")
+											.short("o")
+											.takes_value(true)
+											.help("Write the summaries to this JSON")))
+							)
+							.subcommand(SubCommand::with_name("tune")
+								.about("Interactively tweak meta parameters and re-solve a road plowing problem, loaded once")
+								.setting(AppSettings::AllowMissingPositional)
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("snow")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Snow status"))
+								.arg(Arg::with_name("vehicles")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Vehicles configuration"))
+								.arg(Arg::with_name("meta")
+										.takes_value(true)
+										.index(4)
+										.help("Meta parameters to start from - the standard preset's (or --preset's) without"))
+								.arg(Arg::with_name("snow-d")
+										.short("d")
+										.takes_value(true)
+										.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Default snow depth"))
+								.arg(Arg::with_name("budget")
+										.short("b")
+										.long("budget")
+										.takes_value(true)
+										.validator(|s| parse_duration(&s).map(|_| ()))
+										.help("Wall-clock budget of every solve (e.g. 90s, 5m, 1h)"))
+								.arg(preset_arg())
+							)
+							.subcommand(SubCommand::with_name("estimate")
+								.about("Estimate how hard a road plowing instance is to solve, before solving it")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("snow")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Snow status"))
+								.arg(Arg::with_name("vehicles")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Vehicles configuration"))
+								.arg(Arg::with_name("meta")
+										.takes_value(true)
+										.index(4)
+										.help("Meta parameters - the standard preset's (or --preset's) without"))
+								.arg(Arg::with_name("history")
+										.long("history")
+										.takes_value(true)
+										.multiple(true)
+										.help("Solver statistics JSONs of past runs (see --stats), to regress the iterations to convergence over"))
+								.arg(Arg::with_name("output")
+										.short("o")
+										.takes_value(true)
+										.help("Write the estimate to this JSON"))
+								.arg(Arg::with_name("snow-d")
+										.short("d")
+										.takes_value(true)
+										.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Default snow depth"))
+								.arg(preset_arg())
+							)
+							.subcommand(SubCommand::with_name("fleet")
+								.about("Find the cheapest fleet of the vehicle types available clearing the snow within a completion target")
+								.setting(AppSettings::AllowMissingPositional)
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("snow")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Snow status"))
+								.arg(Arg::with_name("fleet")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Vehicle types and completion target"))
+								.arg(Arg::with_name("meta")
+										.takes_value(true)
+										.index(4)
+										.help("Meta parameters - the standard preset's (or --preset's) without"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(5)
+										.help("Output JSON - the recommended fleet's plan"))
+								.arg(Arg::with_name("snow-d")
+										.short("d")
+										.takes_value(true)
+										.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Default snow depth"))
+								.arg(Arg::with_name("budget")
+										.short("b")
+										.long("budget")
+										.takes_value(true)
+										.validator(|s| parse_duration(&s).map(|_| ()))
+										.help("Wall-clock budget of every solve (e.g. 90s, 5m, 1h)"))
+								.arg(Arg::with_name("report")
+										.long("report")
+										.takes_value(true)
+										.help("Write every fleet composition considered, and how it did, to this JSON file"))
+								.arg(preset_arg())
+							)
+							.subcommand(SubCommand::with_name("hash")
+								.about("Print the content hash of the road graph, or check it against an output's")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("check")
+										.short("c")
+										.long("check")
+										.takes_value(true)
+										.help("Expected hash, or a JSON output embedding one (manifest, solver statistics) - fails on a mismatch"))
+							)
+							.subcommand(SubCommand::with_name("apply-patch")
+								.about("Apply changes (added, removed or modified nodes and segments) to a road graph")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("patch")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Patch JSON"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("Patched road graph output JSON"))
+							)
+							.subcommand(SubCommand::with_name("route")
+								.about("Compute the shortest route between 2 locations")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("from")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Start - node id, or lon,lat"))
+								.arg(Arg::with_name("to")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help("End - node id, or lon,lat"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(4)
+										.help("Route output JSON"))
+								.arg(Arg::with_name("geojson")
+										.long("geojson")
+										.takes_value(true)
+										.help("Write the route to this GeoJSON file too"))
+								.arg(Arg::with_name("speeds")
+										.long("speeds")
+										.takes_value(true)
+										.help("Speeds (as the `speeds` meta parameter) JSON - the route is then the fastest rather than the shortest"))
+								.arg(Arg::with_name("ignore-directions")
+										.long("ignore-directions")
+										.help("Go one-way segments the wrong way too"))
+								.arg(metric_arg())
+								.arg(simplify_arg())
+							)
+							.subcommand(SubCommand::with_name("infer-directions")
+								.about("Infer which two-way road segments are one-way, from their lanes, parking and sidewalks")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Road graph output JSON, with the accepted directions"))
+								.arg(Arg::with_name("report")
+										.long("report")
+										.takes_value(true)
+										.help("Write the inferences, with their confidence and hints, to this JSON file - to review"))
+								.arg(Arg::with_name("min-confidence")
+										.long("min-confidence")
+										.takes_value(true)
+										.default_value("0.6")
+										.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Confidence from which inferences are accepted"))
+								.arg(Arg::with_name("accept")
+										.long("accept")
+										.takes_value(true)
+										.conflicts_with("report")
+										.help("Apply the accepted inferences of a (reviewed) report instead of inferring"))
+							)
+							.subcommand(SubCommand::with_name("scrub")
+								.about("Anonymize an instance, to attach it to a public bug report - same topology, lengths and snow, but opaque node ids and jittered coordinates")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("prefix")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help(r#"Output files prefix - the scrubbed files will be named alike "{prefix}.{roads,snow,vehicles}.json""#))
+								.arg(Arg::with_name("snow")
+										.long("snow")
+										.takes_value(true)
+										.help("Snow status to scrub along"))
+								.arg(Arg::with_name("vehicles")
+										.long("vehicles")
+										.takes_value(true)
+										.help("Vehicles configuration to scrub along"))
+								.arg(Arg::with_name("jitter")
+										.long("jitter")
+										.takes_value(true)
+										.default_value("25")
+										.validator(|s| s.parse::<f64>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Tolerance coordinates are jittered within, in meters"))
+								.arg(Arg::with_name("seed")
+										.long("seed")
+										.takes_value(true)
+										.validator(|s| s.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Random seed - for the same tokens and jitter every time"))
+							)
+							.subcommand(SubCommand::with_name("graph-dump")
+								.about("[dev] Dump the internal solver graph")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Graph dump output JSON"))
+								.arg(Arg::with_name("solver")
+										.short("s")
+										.takes_value(true)
+										.possible_values(&["fly", "road", "sidewalk"])
+										.default_value("road")
+										.help("Solver whose graph to dump"))
+							)
+							.subcommand(SubCommand::with_name("graph-diff")
+								.about("[dev] Report structural differences between 2 graph dumps")
+								.arg(Arg::with_name("dump1")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("First graph dump JSON"))
+								.arg(Arg::with_name("dump2")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Second graph dump JSON"))
+							)
+							.subcommand(SubCommand::with_name("geojson")
+								.about("Convert anything into GeoJSONs")
+								.arg(Arg::with_name("road-graph")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Road Graph JSON"))
+								.arg(Arg::with_name("wut")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Produced thingy that you want to convert (currently supported: flight paths)"))
+								.arg(Arg::with_name("prefix")
+										.takes_value(true)
+										.required(true)
+										.index(3)
+										.help(r#"GeoJSON files prefix - the generated files will be named alike "{prefix}.{...}.geojson""#))
+								.arg(Arg::with_name("max-features")
+										.long("max-features")
+										.takes_value(true)
+										.validator(|s| s.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+										.help("Split files with more features into numbered parts"))
+								.arg(Arg::with_name("max-size")
+										.long("max-size")
+										.takes_value(true)
+										.validator(|s| parse_size(&s).map(|_| ()))
+										.help("Split files bigger than this (e.g. 50MB) into numbered parts"))
+								.arg(Arg::with_name("combined")
+										.long("combined")
+										.help("Write all vehicles' paths to a single {prefix}.geojson"))
+								.arg(simplify_arg()
+										.help("Write every vehicle's path as a single line, simplified (Douglas-Peucker) to within this many meters - the original segment count kept as `segments`"))
+							);
+	#[cfg(feature = "geocode")]
+	let app = app.subcommand(SubCommand::with_name("geocode")
+								.about("Geocode addresses into locations")
+								.arg(Arg::with_name("addresses")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Addresses JSON (list of strings)"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Locations output JSON"))
+								.arg(Arg::with_name("gazetteer")
+										.short("g")
+										.takes_value(true)
+										.required(true)
+										.help("Offline gazetteer CSV (address,lon,lat)"))
+							);
+	#[cfg(feature = "notify")]
+	let app = app.subcommand(SubCommand::with_name("daemon")
+								.about("Run a job daily at a scheduled time, whenever the merged snow statuses total above a threshold")
+								.arg(Arg::with_name("config")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("Daemon configuration YAML"))
+								.arg(Arg::with_name("once")
+										.long("once")
+										.help("Check the trigger and run right away, once, instead of on schedule"))
+							);
+	#[cfg(feature = "osm")]
+	let app = app.subcommand(SubCommand::with_name("import-osm")
+								.about("Import a road graph from an OpenStreetMap extract")
+								.arg(Arg::with_name("extract")
+										.takes_value(true)
+										.required(true)
+										.index(1)
+										.help("OSM extract - .osm.pbf, or Overpass API JSON"))
+								.arg(Arg::with_name("output")
+										.takes_value(true)
+										.required(true)
+										.index(2)
+										.help("Road graph output JSON"))
+								.arg(Arg::with_name("highways")
+										.long("highways")
+										.takes_value(true)
+										.use_delimiter(true)
+										.help("Highway types to import (comma-separated) - the streets, motorways to service roads, by default"))
+							);
+	#[cfg(feature = "notify")]
+	let app = app.subcommand(SubCommand::with_name("worker")
+								.about("Serve experiment runs to a coordinator (experiments run --workers)")
+								.arg(Arg::with_name("address")
+										.takes_value(true)
+										.default_value("0.0.0.0:7878")
+										.index(1)
+										.help("Address to listen at"))
+							);
+	#[cfg(feature = "schema")]
+	let app = app.subcommand(SubCommand::with_name("schema")
+								.about("Print the JSON Schemas of the input/output types")
+								.arg(Arg::with_name("type")
+										.takes_value(true)
+										.index(1)
+										.possible_values(&["RoadGraph", "SnowStatuses", "VehiclesConfiguration", "Parameters", "Paths", "Schedules"])
+										.help("Only print the schema of this type"))
+							);
+	let matches = app.get_matches();
+	log::info!("Loading...");
+	if let Some(matches) = matches.subcommand_matches("fly") {
+		log::trace!("tracing enabled");
+		let drones: data::Drones = load(matches.value_of("drones").unwrap())?;
+		let roads = load_roads(matches)?;
+		let graph = roads.content_hash();
+		let sites: Vec<data::SensitiveSite> = match matches.value_of("sites") {
+			Some(f) => load(f)?,
+			None => vec![],
+		};
+		let params = load_params(matches)?;
+		log::info!("Loaded configuration");
+		let output = matches.value_of("output").unwrap();
+		let mut planner = plow::fly::FlightPlanner::new(params.clone()).sites(sites).budget(budget(matches));
+		match matches.value_of("objective") {
+			Some("total") => planner = planner.objective(plow::fly::Objective::Total),
+			Some("max") => planner = planner.objective(plow::fly::Objective::Max),
+			_ => {},
+		}
+		let (plan, stats) = solved(matches, "fly", &graph, || planner.plan(roads.clone(), drones, |t, paths| write_checkpoint(output, t, &paths)).map(|plan| {
+			let stats = plan.stats.clone();
+			(plan, stats)
+		}))?;
+		log::info!("Constructed paths");
+		for (drone, c) in plan.crossings.iter().filter(|(_, c)| !c.is_empty()) {
+			log::warn!("Drone {} crosses sensitive sites {} times, there's no way around: {}", drone, c.len(), c.iter().map(|c| c.site.as_str()).collect::<indexmap::IndexSet<_>>().into_iter().collect::<Vec<_>>().join(", "));
+		}
+		if let Some(f) = matches.value_of("crossings") {
+			serde_json::to_writer(&std::fs::File::create(f)?, &plan.crossings).unwrap();
+		}
+		if let Some(f) = matches.value_of("schedule") {
+			serde_json::to_writer(&std::fs::File::create(f)?, &timeline::schedule(&roads, &plan.paths, &vec![], None, &params, &Default::default())).unwrap();
+		}
+		write_stats(matches, stats, graph)?;
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &plan.paths).unwrap();
+	} else if let Some(matches) = matches.subcommand_matches("snows").and_then(|m| m.subcommand_matches("simulate")) {
+		let roads = load_roads(matches)?;
+		let center = matches.value_of("center").map(|c| parse_lonlat(c).unwrap());
+		let num = |arg| matches.value_of(arg).unwrap().parse().unwrap();
+		let storm = weather::StormModel {
+			center,
+			intensity: num("intensity"),
+			gradient: num("gradient"),
+			duration: num("duration"),
+			noise: num("noise"),
+			seed: matches.value_of("seed").map(|s| s.parse().unwrap()),
+		};
+		let snow = storm.simulate(&roads)?;
+		log::info!("Simulated ❄ on {} segments", snow.len());
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &snow).unwrap();
+	} else if let Some(matches) = matches.subcommand_matches("snows") {
+		let roads = load_roads(matches)?;
+		log::info!("Loaded configuration");
+		let mut snu: Vec<SnuwDapg> = Vec::new();
+		for f in matches.values_of("snows").unwrap() {
+			snu.push(load_or_geo(f, SnuwDapg::Formal, SnuwDapg::Geo)?);
+		}
+		log::info!("Loaded ❄");
+		let mut snow = merge_snow_statuses(snu.into_iter().map(|s| match s {
+			SnuwDapg::Formal(s) => s,
+			SnuwDapg::Geo(feat) => gj::geofeatures_to_snow(&roads, feat),
+		}).flatten());
+		if let Some(hops) = matches.value_of("interpolate") {
+			let sampled = snow.len();
+			snow = roads.interpolate_snow(&snow, hops.parse().unwrap());
+			log::info!("Estimated {} segments", snow.len() - sampled);
+		}
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &snow).unwrap();
+	} else if let Some(matches) = matches.subcommand_matches("plow") {
+		log::trace!("tracing enabled");
+		let roads = load_roads(matches)?;
+		let graph = roads.content_hash();
+		let snow: data::SnowStatuses = load(matches.value_of("snow").unwrap())?;
+		let vehicles: data::VehiclesConfiguration = load(matches.value_of("vehicles").unwrap())?;
+		let speeds: std::collections::HashMap<_, _> = vehicles.road.iter().enumerate().filter_map(|(i, v)| v.speed().map(|s| (v.id(i), s))).collect();
+		let mut params = load_params(matches)?;
+		if let Some(t) = matches.value_of("temperature") {
+			params.temperature = Some(n64(t.parse().unwrap()));
+		}
+		log::info!("Loaded configuration");
+		let output = matches.value_of("output").unwrap();
+		let live = live_channel(matches)?;
+		let overrides = live_overrides(matches, &live);
+		let start = std::time::Instant::now();
+		if matches.is_present("sidewalks") {
+			let (paths, stats) = solved(matches, "plow", &graph, || plow::sidewalk::solve(roads, snow, matches.value_of("snow-d").map(|f| f.parse().unwrap()), vehicles, &params, &budget(matches), |t, paths| {
+				write_checkpoint(output, t, &paths);
+				publish(&live, t, &paths, false);
+			}))?;
+			log::info!("Constructed paths");
+			publish(&live, start.elapsed(), &paths, true);
+			write_stats(matches, stats, graph)?;
+			serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &paths).unwrap();
+		} else {
+			let snow_d = matches.value_of("snow-d").map(|f| f.parse().unwrap());
+			let (paths, stats) = solved(matches, "plow", &graph, || plow::road::solve(roads.clone(), snow.clone(), snow_d, vehicles, &params, &budget(matches), |t, paths| {
+				write_checkpoint(output, t, &paths);
+				publish(&live, t, &paths, false);
+			}))?;
+			log::info!("Constructed paths");
+			let (paths, removed) = orient::orient(&roads, &snow, snow_d, &paths, &params)?;
+			log::info!("Reoriented routes, {} turnarounds fewer", removed);
+			let paths = match matches.value_of("convoys") {
+				Some(f) => {
+					let convoys: data::ConvoySegments = load(f)?;
+					let (paths, formed) = plow::road::convoys(roads.clone(), &snow, snow_d, paths, &convoys, &params)?;
+					for c in &formed {
+						log::info!("{} -> {}: {}-vehicle convoy led by {}, joined by {}", c.nodes[0], c.nodes[c.nodes.len() - 1], c.required, c.lead, c.members.join(", "));
+					}
+					log::info!("Formed {} convoys", formed.len());
+					if let Some(f) = matches.value_of("convoy-report") {
+						serde_json::to_writer(&std::fs::File::create(f)?, &formed).unwrap();
+					}
+					paths
+				},
+				None => paths,
+			};
+			publish(&live, start.elapsed(), &paths, true);
+			write_stats(matches, stats, graph)?;
+			serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &paths).unwrap();
+			if let Some(f) = matches.value_of("boosted") {
+				let boosted = plow::road::boosted(roads.clone(), &snow, snow_d, &paths, &params)?;
+				log::info!("{} segments boosted for de-icing", boosted.len());
+				serde_json::to_writer(&std::fs::File::create(f)?, &boosted).unwrap();
+			}
+			if let Some(f) = matches.value_of("schedule") {
+				serde_json::to_writer(&std::fs::File::create(f)?, &timeline::schedule(&roads, &paths, &snow, snow_d, &params, &speeds)).unwrap();
+			}
+			if let Some(f) = matches.value_of("priorities") {
+				let priorities = plow::road::priorities(roads.clone(), &snow, snow_d, &paths, &params)?;
+				match priorities.iter().filter(|p| p.late).count() {
+					0 => log::info!("{} priority segments, none late", priorities.len()),
+					late => log::warn!("{} of {} priority segments cleared past their deadline", late, priorities.len()),
+				}
+				serde_json::to_writer(&std::fs::File::create(f)?, &priorities).unwrap();
+			}
+			if let Some(overrides) = overrides {
+				log::info!("Watching for priority overrides");
+				let mut paths = paths;
+				for o in overrides {
+					match plow::road::reprioritize(roads.clone(), &snow, snow_d, &paths, &o, &params) {
+						Ok((revised, r)) => {
+							match (r.late, o.deadline) {
+								(true, Some(d)) => log::warn!("{} priority segments rerouted to vehicle {}, cleared by {:.1} - past the {:.1} deadline", o.segments.len(), r.vehicle, r.cleared, d),
+								_ => log::info!("{} priority segments rerouted to vehicle {}, cleared by {:.1}", o.segments.len(), r.vehicle, r.cleared),
+							}
+							paths = revised;
+							serde_json::to_writer(&std::fs::File::create(output)?, &paths).unwrap();
+							publish(&live, start.elapsed(), &paths, false);
+						},
+						Err(e) => log::error!("Priority override not served: {}", e),
+					}
+				}
+			}
+		}
+	} else if let Some(matches) = matches.subcommand_matches("spread") {
+		let roads = load_roads(matches)?;
+		let graph = roads.content_hash();
+		let spreading: spread::Spreading = load_yaml(matches.value_of("spreading").unwrap())?;
+		let vehicles: data::VehiclesConfiguration = load(matches.value_of("vehicles").unwrap())?;
+		let params = load_params(matches)?;
+		log::info!("Loaded configuration");
+		let output = matches.value_of("output").unwrap();
+		let ((paths, reports), stats) = solved(matches, "spread", &graph, || spread::plan(roads, vehicles, &spreading, &params, &budget(matches), |t, paths| write_checkpoint(output, t, &paths)).map(|(paths, reports, stats)| ((paths, reports), stats)))?;
+		log::info!("Constructed paths");
+		for (id, r) in &reports {
+			log::info!("vehicle {}: spreads {:.1}, {} reloads{}", id, r.spread, r.reloads.len(), if r.shortfall > 0.0 { format!(", {:.1} short", r.shortfall) } else { String::new() });
+		}
+		write_stats(matches, stats, graph)?;
+		serde_json::to_writer(&std::fs::File::create(output)?, &paths).unwrap();
+		if let Some(f) = matches.value_of("report") {
+			serde_json::to_writer(&std::fs::File::create(f)?, &reports).unwrap();
+		}
+	} else if let Some(matches) = matches.subcommand_matches("storm") {
+		let roads = load_roads(matches)?;
+		let forecast: Vec<data::SnowStatuses> = load(matches.value_of("forecast").unwrap())?;
+		let vehicles: data::VehiclesConfiguration = load(matches.value_of("vehicles").unwrap())?;
+		let params = load_params(matches)?;
+		log::info!("Loaded configuration");
+		let rounds = storm::plan(&roads, forecast, vehicles, &params, &budget(matches))?;
+		for (r, round) in rounds.iter().enumerate() {
+			log::info!("Round {}: {} snowy segments, {} left over", r, round.snow.len(), round.carryover.len());
+		}
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &rounds).unwrap();
+	} else if let Some(matches) = matches.subcommand_matches("evaluate-actual") {
+		let roads = load_roads(matches)?;
+		let paths: data::Paths = load(matches.value_of("paths").unwrap())?;
+		let traces: data::GpsTraces = load(matches.value_of("traces").unwrap())?;
+		log::info!("Loaded configuration");
+		for id in paths.0.keys().filter(|id| !traces.0.contains_key(*id)) {
+			log::warn!("No GPS trace for vehicle {}", id);
+		}
+		let report = eval::evaluate(&roads, &paths, &traces, metric(matches));
+		for (i, ev) in &report {
+			log::info!("vehicle {}: adherence {:.1}% ({}/{}), {} unplanned, timing deviation {:.1}", i, ev.adherence*100.0, ev.adhered, ev.planned, ev.unplanned, ev.timing_deviation);
+		}
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &report).unwrap();
+	} else if let Some(matches) = matches.subcommand_matches("calibrate") {
+		let roads = load_roads(matches)?;
+		let snow: data::SnowStatuses = load(matches.value_of("snow").unwrap())?;
+		let traces: data::GpsTraces = load(matches.value_of("traces").unwrap())?;
+		let types: indexmap::IndexMap<data::VehicleId, String> = match matches.value_of("types") {
+			Some(f) => load(f)?,
+			None => Default::default(),
+		};
+		log::info!("Loaded configuration");
+		let calibrations = calibrate::calibrate(&roads, &snow, &traces, &types, matches.value_of("bucket").unwrap().parse().unwrap(), metric(matches));
+		for (t, c) in &calibrations {
+			log::info!("{}: {:.2} by default, {} classes", t, c.speeds.default, c.speeds.classes.len());
+		}
+		serde_yaml::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &calibrations).unwrap();
+	} else if let Some(matches) = matches.subcommand_matches("gantt") {
+		let roads = load_roads(matches)?;
+		let paths: data::Paths = load(matches.value_of("paths").unwrap())?;
+		let tl = plan_timeline(matches, &roads, &paths, parse_duration(matches.value_of("bucket").unwrap()).unwrap())?;
+		for (c, t) in &tl.completion {
+			log::info!("{} done after {:.0}s", c, t);
+		}
+		for (id, v) in tl.vehicles.iter().filter(|(_, v)| !v.breaks.is_empty()) {
+			log::info!("vehicle {}: {} breaks, at {}", id, v.breaks.len(), v.breaks.iter().map(|b| b.node.to_string()).collect::<Vec<_>>().join(", "));
+		}
+		log::info!("All done after {:.0}s", tl.horizon);
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &tl).unwrap();
+		if let Some(f) = matches.value_of("svg") {
+			std::fs::write(f, timeline::to_svg(&tl))?;
+		}
+	} else if let Some(matches) = matches.subcommand_matches("check-windows") {
+		let roads = load_roads(matches)?;
+		let paths: data::Paths = load(matches.value_of("paths").unwrap())?;
+		let tl = plan_timeline(matches, &roads, &paths, std::time::Duration::from_secs(15 * 60))?;
+		let violations = timeline::check_windows(&view::SolutionView::new(&roads, &paths, None), &tl);
+		for v in &violations {
+			log::warn!("vehicle {}: {}->{} at {:.0}s-{:.0}s, one way {}-{}s", v.vehicle, v.from, v.to.node, v.start, v.end, v.window.start, v.window.end);
+		}
+		log::info!("{} direction window violations", violations.len());
+		if let Some(f) = matches.value_of("output") {
+			serde_json::to_writer(&std::fs::File::create(f)?, &violations).unwrap();
+		}
+	} else if let Some(matches) = matches.subcommand_matches("chunk") {
+		let roads = load_roads(matches)?;
+		let paths: data::Paths = load(matches.value_of("paths").unwrap())?;
+		let interval = parse_duration(matches.value_of("interval").unwrap()).unwrap();
+		let tl = plan_timeline(matches, &roads, &paths, interval)?;
+		let onto: Vec<_> = matches.values_of("cast-onto").into_iter().flatten().collect();
+		let cast = view::CastPolicy {
+			side: if matches.value_of("cast-side") == Some("left") { view::CastSide::Left } else { view::CastSide::Right },
+			avoid_sidewalks: !onto.contains(&"sidewalks"),
+			avoid_parking: !onto.contains(&"parking"),
+		};
+		let chunks = timeline::chunks(&view::SolutionView::new(&roads, &paths, None).cast(cast), &tl, interval.as_secs_f64());
+		if let Some(f) = matches.value_of("csv") {
+			let mut csv = String::from("vehicle,interval,start,end,node,discriminator,cast,start_at,end_at\n");
+			for c in &chunks {
+				let (start_at, end_at) = c.at.as_ref().map_or(("", ""), |(s, e)| (s, e));
+				let cast = std::iter::once(None).chain(c.cast.iter().copied());
+				for (s, cast) in c.path.iter().zip(cast) {
+					let cast = match cast {
+						Some(view::CastSide::Left) => "left",
+						Some(view::CastSide::Right) => "right",
+						None => "",
+					};
+					csv += &format!("{},{},{},{},{},{},{},{},{}\n", c.vehicle, c.interval, c.start, c.end, s.node, s.discriminator.as_ref().map_or_else(String::new, |d| d.to_string()), cast, start_at, end_at);
+				}
+			}
+			std::fs::write(f, csv)?;
+		}
+		let pref = matches.value_of("prefix").unwrap();
+		let mut export = gj::Export::default().graph(&roads).segments(&paths);
+		let g = gj::roads_to_nodes(roads.nodes);
+		for c in chunks {
+			export.write(format!("{}.{}.{}.geojson", pref, c.vehicle, c.interval), Some(c.vehicle.clone()), simplify(matches, gj::chunk_to_geojson(&g, c)))?;
+		}
+		let manifest = export.commit(&format!("{}.manifest.json", pref))?;
+		log::info!("Exported {} chunks", manifest.files.len());
+	} else if let Some(matches) = matches.subcommand_matches("conflicts") {
+		let roads = load_roads(matches)?;
+		let paths: data::Paths = load(matches.value_of("paths").unwrap())?;
+		let tl = plan_timeline(matches, &roads, &paths, std::time::Duration::from_secs(900))?;
+		let window = parse_duration(matches.value_of("window").unwrap()).unwrap().as_secs_f64();
+		let report = if matches.is_present("deconflict") {
+			let step = parse_duration(matches.value_of("step").unwrap()).unwrap().as_secs_f64();
+			let max_offset = parse_duration(matches.value_of("max-offset").unwrap()).unwrap().as_secs_f64();
+			conflict::deconflict(&paths, &tl, window, step, max_offset)
+		} else {
+			conflict::Report { offsets: paths.0.keys().map(|id| (id.clone(), 0.0)).collect(), conflicts: conflict::detect(&paths, &tl, window) }
+		};
+		for (id, o) in report.offsets.iter().filter(|(_, o)| **o > 0.0) {
+			log::info!("vehicle {}: start delayed {:.0}s", id, o);
+		}
+		for c in &report.conflicts {
+			log::warn!("{} and {} conflict on {:?} at {:.0}s/{:.0}s", c.a, c.b, c.place, c.a_time.0, c.b_time.0);
+		}
+		log::info!("{} conflicts", report.conflicts.len());
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &report).unwrap();
+	} else if let Some(matches) = matches.subcommand_matches("render") {
+		let roads = load_roads(matches)?;
+		let paths: data::Paths = load(matches.value_of("paths").unwrap())?;
+		let snow: Option<data::SnowStatuses> = matches.value_of("snow").map(load).transpose()?;
+		log::info!("Loaded configuration");
+		let output = matches.value_of("output").unwrap();
+		let svg = render::to_svg(&roads, snow.as_ref(), &paths);
+		if output.ends_with(".png") {
+			#[cfg(feature = "png")]
+			std::fs::write(output, render::to_png(&svg)?)?;
+			#[cfg(not(feature = "png"))]
+			return Err("PNG plan maps need the png feature".into());
+		} else {
+			std::fs::write(output, svg)?;
+		}
+	} else if let Some(matches) = matches.subcommand_matches("check-lengths") {
+		let mut roads = load_roads(matches)?;
+		let tolerance: f64 = matches.value_of("tolerance").unwrap().parse().unwrap();
+		log::info!("Loaded configuration");
+		let issues = if matches.is_present("fix") { validate::fix_lengths(&mut roads, tolerance) } else { validate::check_lengths(&roads, tolerance) };
+		for i in &issues {
+			log::warn!("{}<->{} ({:?}): length {:.1} but geodesic {:.1} ({:+.0}%)", i.p1, i.p2, i.discriminator, i.distance, i.computed, (i.distance.raw()/i.computed - 1.0)*100.0);
+		}
+		log::info!("{}/{} segments deviate more than {:.0}%", issues.len(), roads.roads.len(), tolerance*100.0);
+		if let Some(f) = matches.value_of("fix") {
+			serde_json::to_writer(&std::fs::File::create(f)?, &roads).unwrap();
+			log::info!("Replaced {} lengths", issues.len());
+		}
+	} else if let Some(matches) = matches.subcommand_matches("check-surfaces") {
+		let roads = load_roads(matches)?;
+		let snow: data::SnowStatuses = load(matches.value_of("snow").unwrap())?;
+		let vehicles: data::VehiclesConfiguration = load(matches.value_of("vehicles").unwrap())?;
+		log::info!("Loaded configuration");
+		let everywhere = matches.value_of("snow-d").unwrap().parse::<f64>().unwrap() > 0.0;
+		let issues = validate::check_surfaces(&roads, Some(&snow).filter(|_| !everywhere), if matches.is_present("sidewalks") { &vehicles.sidewalk } else { &vehicles.road });
+		for e in &issues {
+			log::warn!("{}<->{} ({:?}): no vehicle can clear {:?}", e.p1, e.p2, e.discriminator, e.surface);
+		}
+		log::info!("{}/{} segments can't be cleared", issues.len(), roads.roads.len());
+	} else if let Some(matches) = matches.subcommand_matches("validate") {
+		let roads = load_roads(matches)?;
+		log::info!("Loaded configuration");
+		let (errors, warnings): (Vec<_>, Vec<_>) = data::validate(&roads).into_iter().partition(data::ValidationIssue::is_error);
+		for i in errors.iter().chain(&warnings) {
+			log::warn!("{}", i);
+		}
+		let report = serde_json::json!({ "errors": errors, "warnings": warnings });
+		match matches.value_of("output") {
+			Some(f) => serde_json::to_writer(&std::fs::File::create(f)?, &report).unwrap(),
+			None => println!("{}", report),
+		}
+		log::info!("{} errors, {} warnings over {} segments and {} nodes", errors.len(), warnings.len(), roads.roads.len(), roads.nodes.nodes.len());
+		if !errors.is_empty() {
+			return Err(format!("{} errors in the road graph", errors.len()).into());
+		}
+	} else if let Some(matches) = matches.subcommand_matches("what-if") {
+		let roads = load_roads(matches)?;
+		let paths: data::Paths = load(matches.value_of("paths").unwrap())?;
+		let closures: data::Closures = load(matches.value_of("closures").unwrap())?;
+		log::info!("Loaded configuration");
+		let (report, revised) = plow::road::what_if(roads, paths, closures)?;
+		for (i, imp) in &report {
+			match imp.detour {
+				Some(d) => log::info!("vehicle {}: {} closed segments, detour {:+.1} ({:.1} -> {:.1})", i, imp.closed, d, imp.cost_before, imp.cost_after.unwrap()),
+				None => log::warn!("vehicle {}: {} closed segments, no detour possible", i, imp.closed),
+			}
+		}
+		log::info!("{} routes affected", report.len());
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &report).unwrap();
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("revised").unwrap())?, &revised).unwrap();
+	} else if let Some(matches) = matches.subcommand_matches("replan") {
+		let roads = load_roads(matches)?;
+		let paths: data::Paths = load(matches.value_of("paths").unwrap())?;
+		let before: data::SnowStatuses = load(matches.value_of("before").unwrap())?;
+		let snow: data::SnowStatuses = load(matches.value_of("snow").unwrap())?;
+		let vehicles: data::VehiclesConfiguration = load(matches.value_of("vehicles").unwrap())?;
+		let params = load_params(matches)?;
+		log::info!("Loaded configuration");
+		let (paths, replanned) = plow::road::replan(roads, &before, &snow, matches.value_of("snow-d").map(|f| f.parse().unwrap()), &paths, vehicles, &params)?;
+		log::info!("{} segments newly snowed, {} cleared: {}/{} routes replanned", replanned.snowed, replanned.cleared, replanned.vehicles.len(), paths.0.len());
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &paths).unwrap();
+		if let Some(f) = matches.value_of("report") {
+			serde_json::to_writer(&std::fs::File::create(f)?, &replanned).unwrap();
+		}
+	} else if let Some(matches) = matches.subcommand_matches("merge-plans") {
+		let roads = load_roads(matches)?;
+		let snow: data::SnowStatuses = load(matches.value_of("snow").unwrap())?;
+		let params = load_params(matches)?;
+		let plans: Vec<data::Paths> = matches.values_of("plans").unwrap().map(load).collect::<Result<_, Error>>()?;
+		log::info!("Loaded configuration");
+		let (merged, handoffs) = plow::road::merge(roads, &snow, matches.value_of("snow-d").map(|f| f.parse().unwrap()), plans, &params)?;
+		for h in &handoffs {
+			log::info!("{}<->{}: handed off to {} from {}", h.p1, h.p2, h.to, h.from.join(", "));
+		}
+		log::info!("{} shared segments handed off", handoffs.len());
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &merged).unwrap();
+		if let Some(f) = matches.value_of("report") {
+			serde_json::to_writer(&std::fs::File::create(f)?, &handoffs).unwrap();
+		}
+	} else if let Some(matches) = matches.subcommand_matches("critical-segments") {
+		let roads = load_roads(matches)?;
+		let snow: data::SnowStatuses = load(matches.value_of("snow").unwrap())?;
+		log::info!("Loaded configuration");
+		let critical = plow::road::critical_segments(roads, &snow, matches.value_of("snow-d").map(|f| f.parse().unwrap()), matches.value_of("top").unwrap().parse().unwrap())?;
+		for c in &critical {
+			log::info!("{}<->{}: betweenness {:.3}{}{}", c.p1, c.p2, c.betweenness, if c.snowy { ", snowy" } else { "" }, if c.cut { ", no way around" } else { "" });
+		}
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &critical).unwrap();
+	} else if let Some(matches) = matches.subcommand_matches("score") {
+		let roads = load_roads(matches)?;
+		let snow: data::SnowStatuses = load(matches.value_of("snow").unwrap())?;
+		let params = load_params(matches)?;
+		let paths: data::Paths = load(matches.value_of("paths").unwrap())?;
+		log::info!("Loaded configuration");
+		let costs = plow::road::evaluate(roads, &snow, matches.value_of("snow-d").map(|f| f.parse().unwrap()), &paths, &params)?;
+		for (id, c) in paths.0.keys().zip(costs.costs.iter()) {
+			log::info!("vehicle {}: cost {:.1}", id, c);
+		}
+		log::info!("total {:.1}, max {:.1}, value {:.1}", costs.total, costs.max, costs.value);
+		if let Some(f) = matches.value_of("output") {
+			serde_json::to_writer(&std::fs::File::create(f)?, &costs).unwrap();
+		}
+	} else if let Some(matches) = matches.subcommand_matches("experiments") {
+		#[cfg(not(feature = "sqlite"))]
+		{
+			let _ = matches;
+			return Err("Experiment databases need the sqlite feature".into());
+		}
+		#[cfg(feature = "sqlite")]
+		if let Some(matches) = matches.subcommand_matches("run") {
+			let path = std::path::Path::new(matches.value_of("matrix").unwrap());
+			let matrix: experiment::Matrix = load_yaml(path)?;
+			let dir = path.parent().unwrap_or_else(|| std::path::Path::new("."));
+			let db = experiment::Database::open(matches.value_of("database").unwrap())?;
+			let parameters: Vec<(&String, meta::Parameters)> = matrix.parameters.iter().map(|(name, f)| Ok((name, load_yaml(dir.join(f))?))).collect::<Result<_, Error>>()?;
+			let load_problem = |i: &experiment::Instance| -> Result<_, Error> {
+				Ok(experiment::Problem {
+					roads: read_roads(dir.join(&i.roads), matches)?,
+					snow: load(dir.join(&i.snow))?,
+					snow_d: i.snow_d,
+					vehicles: load(dir.join(&i.vehicles))?,
+				})
+			};
+			let pending = |instance: &String, name: &String, seed: u64| -> Result<bool, Error> {
+				let pending = !db.contains(instance, name, seed)?;
+				if !pending {
+					log::info!("{} / {} / {}: already done", instance, name, seed);
+				}
+				Ok(pending)
+			};
+			if let Some(workers) = matches.values_of("workers") {
+				#[cfg(feature = "notify")]
+				{
+					use std::borrow::Cow;
+					let workers: Vec<String> = workers.map(str::to_string).collect();
+					let problems = matrix.instances.iter().map(|(instance, i)| Ok((instance, load_problem(i)?))).collect::<Result<Vec<_>, Error>>()?;
+					let budget = budget(matches).time.map(|t| t.as_secs_f64());
+					let mut jobs = Vec::new();
+					for (instance, problem) in &problems {
+						for (name, params) in &parameters {
+							for seed in &matrix.seeds {
+								if pending(instance, name, *seed)? {
+									jobs.push(remote::Job { instance: instance.to_string(), parameters: name.to_string(), seed: *seed, problem: Cow::Borrowed(problem), params: Cow::Borrowed(params), budget });
+								}
+							}
+						}
+					}
+					// the runs that went through are kept either way, and the first failure reported after
+					let mut failed = None;
+					remote::distribute(&workers, jobs, |job, outcome| match outcome {
+						Ok(record) => {
+							log::info!("{} / {} / {}: value {:.1} (gap {:.1}%) in {:.1}s", job.instance, job.parameters, job.seed, record.outcome.value, record.outcome.gap * 100.0, record.outcome.runtime);
+							if let Err(e) = db.insert(&record) {
+								failed.get_or_insert(e.to_string());
+							}
+						}
+						Err(e) => {
+							log::error!("{} / {} / {}: {}", job.instance, job.parameters, job.seed, e);
+							failed.get_or_insert(e);
+						}
+					}).and_then(|_| failed.map_or(Ok(()), |e| Err(Error::Invalid(e))))?;
+				}
+				#[cfg(not(feature = "notify"))]
+				{
+					let _ = workers;
+					return Err("Remote workers need the notify feature".into());
+				}
+			} else {
+				for (instance, i) in &matrix.instances {
+					let problem = load_problem(i)?;
+					for (name, params) in &parameters {
+						for seed in &matrix.seeds {
+							if !pending(instance, name, *seed)? {
+								continue;
+							}
+							let outcome = experiment::run(&problem, params, *seed, &budget(matches))?;
+							log::info!("{} / {} / {}: value {:.1} (gap {:.1}%) in {:.1}s", instance, name, seed, outcome.value, outcome.gap * 100.0, outcome.runtime);
+							let record = experiment::Record { instance: instance.clone(), parameters: name.to_string(), seed: *seed, outcome };
+							db.insert(&record)?;
+						}
+					}
+				}
+			}
+		} else if let Some(matches) = matches.subcommand_matches("summary") {
+			let summaries = experiment::Database::open(matches.value_of("database").unwrap())?.summarize(matches.value_of("instance"))?;
+			println!("{:<20} {:<20} {:>5} {:>12} {:>12} {:>10} {:>8} {:>9}", "instance", "parameters", "runs", "best", "mean", "deviation", "gap", "runtime");
+			for s in &summaries {
+				println!("{:<20} {:<20} {:>5} {:>12.1} {:>12.1} {:>10.1} {:>7.1}% {:>8.1}s", s.instance, s.parameters, s.runs, s.best, s.mean, s.deviation, s.mean_gap * 100.0, s.mean_runtime);
+			}
+			if let Some(f) = matches.value_of("output") {
+				serde_json::to_writer(&std::fs::File::create(f)?, &summaries).unwrap();
+			}
+		}
+	} else if let Some(matches) = matches.subcommand_matches("tune") {
+		let problem = experiment::Problem {
+			roads: load_roads(matches)?,
+			snow: load(matches.value_of("snow").unwrap())?,
+			snow_d: matches.value_of("snow-d").map(|f| f.parse().unwrap()),
+			vehicles: load(matches.value_of("vehicles").unwrap())?,
+		};
+		let params = load_params(matches)?;
+		log::info!("Loaded configuration");
+		let mut session = repl::Session::new(problem, params, budget(matches));
+		let stdin = std::io::stdin();
+		loop {
+			print!("> ");
+			std::io::stdout().flush()?;
+			let mut line = String::new();
+			if stdin.read_line(&mut line)? == 0 {
+				break;
+			}
+			match repl::Command::parse(&line) {
+				Ok(Some(repl::Command::Quit)) => break,
+				Ok(Some(cmd)) => match session.run(cmd) {
+					Ok(out) => println!("{}", out),
+					Err(e) => println!("error: {}", e),
+				},
+				Ok(None) => (),
+				Err(e) => println!("error: {}", e),
+			}
+		}
+	} else if let Some(matches) = matches.subcommand_matches("estimate") {
+		let roads = load_roads(matches)?;
+		let snow: data::SnowStatuses = load(matches.value_of("snow").unwrap())?;
+		let vehicles: data::VehiclesConfiguration = load(matches.value_of("vehicles").unwrap())?;
+		let params = load_params(matches)?;
+		let history: Vec<plow::Stats> = matches.values_of("history").into_iter().flatten().map(load).collect::<Result<_, Error>>()?;
+		log::info!("Loaded configuration");
+		let features = plow::road::features(roads, &snow, matches.value_of("snow-d").map(|f| f.parse().unwrap()), &vehicles, &params)?;
+		let e = estimate::estimate(features, &history);
+		println!("{} segments to clear, {} odd nodes of {}, {} vehicles, clusters {:.2} imbalanced", e.features.required, e.features.odd, e.features.nodes, e.features.vehicles, e.features.imbalance);
+		match e.iterations {
+			Some(i) => println!("difficulty {:.1}, ~{:.0} iterations to converge (over {} past runs): {} preset", e.score, i, e.runs, meta::Preset::NAMES[e.preset as usize]),
+			None => println!("difficulty {:.1}: {} preset", e.score, meta::Preset::NAMES[e.preset as usize]),
+		}
+		if let Some(f) = matches.value_of("output") {
+			serde_json::to_writer(&std::fs::File::create(f)?, &e).unwrap();
+		}
+	} else if let Some(matches) = matches.subcommand_matches("fleet") {
+		let roads = load_roads(matches)?;
+		let snow: data::SnowStatuses = load(matches.value_of("snow").unwrap())?;
+		let fleet: fleet::Fleet = load_yaml(matches.value_of("fleet").unwrap())?;
+		let params = load_params(matches)?;
+		log::info!("Loaded configuration");
+		let rec = fleet.optimize(&roads, &snow, matches.value_of("snow-d").map(|f| f.parse().unwrap()), &params, &budget(matches))?;
+		println!("{:<20} {:>5}", "type", "count");
+		for (name, n) in &rec.best.fleet {
+			println!("{:<20} {:>5}", name, n);
+		}
+		println!("completion {:.1}, cost {:.1} ({:.1} per time unit)", rec.best.completion.unwrap(), rec.best.cost.unwrap(), rec.best.rate);
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &rec.paths).unwrap();
+		if let Some(f) = matches.value_of("report") {
+			serde_json::to_writer(&std::fs::File::create(f)?, &rec.candidates).unwrap();
+		}
+	} else if let Some(matches) = matches.subcommand_matches("apply-patch") {
+		let mut roads = load_roads(matches)?;
+		let patch: patch::Patch = load(matches.value_of("patch").unwrap())?;
+		let hash = roads.content_hash();
+		let affected = roads.apply(&patch)?;
+		log::info!("Applied {} changes affecting {} nodes, graph {} -> {}", patch.len(), affected.len(), hash, roads.content_hash());
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &roads).unwrap();
+	} else if let Some(matches) = matches.subcommand_matches("route") {
+		let roads = load_roads(matches)?;
+		let speeds: Option<meta::Speeds> = matches.value_of("speeds").map(load).transpose()?;
+		let location = |s: &str| parse_lonlat(s).map_or_else(|_| data::Location::Node(s.parse::<u64>().map_or_else(|_| s.to_string().into(), data::NodeId::from)), |(lon, lat)| data::Location::Coordinates(lon, lat));
+		let (from, to) = (location(matches.value_of("from").unwrap()), location(matches.value_of("to").unwrap()));
+		let nodes = gj::roads_to_nodes(roads.nodes.clone());
+		let (route, length) = plow::road::route(roads, &from, &to, speeds.as_ref(), !matches.is_present("ignore-directions"), metric(matches))?;
+		log::info!("Route of {} segments, {} {:.1}", route.len().saturating_sub(1), if speeds.is_some() { "time" } else { "length" }, length);
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &route).unwrap();
+		if let Some(f) = matches.value_of("geojson") {
+			serde_json::to_writer(&std::fs::File::create(f)?, &geojson::GeoJson::from(simplify(matches, gj::route_to_geojson(&nodes, route, length.raw())))).unwrap();
+		}
+	} else if let Some(matches) = matches.subcommand_matches("infer-directions") {
+		let mut roads = load_roads(matches)?;
+		let inferences: Vec<infer::Inference> = match matches.value_of("accept") {
+			Some(f) => load(f)?,
+			None => infer::infer(&roads, matches.value_of("min-confidence").unwrap().parse().unwrap()),
+		};
+		for i in &inferences {
+			log::info!("{}->{}: one-way with confidence {:.2} ({}){}", i.p1, i.p2, i.confidence, i.hints.join(", "), if i.accepted { "" } else { " - rejected" });
+		}
+		if let Some(f) = matches.value_of("report") {
+			serde_json::to_writer_pretty(&std::fs::File::create(f)?, &inferences).unwrap();
+		}
+		let accepted = roads.accept(&inferences)?;
+		log::info!("Made {} of {} segments one-way", accepted, roads.roads.len());
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &roads).unwrap();
+	} else if let Some(matches) = matches.subcommand_matches("scrub") {
+		let roads = load_roads(matches)?;
+		let pref = matches.value_of("prefix").unwrap();
+		let mut scrubber = scrub::Scrubber::new(&roads, matches.value_of("jitter").unwrap().parse().unwrap(), matches.value_of("seed").map(|s| s.parse().unwrap()));
+		serde_json::to_writer(&std::fs::File::create(format!("{}.roads.json", pref))?, &scrubber.roads(&roads)?).unwrap();
+		if let Some(f) = matches.value_of("snow") {
+			let snow: data::SnowStatuses = load(f)?;
+			serde_json::to_writer(&std::fs::File::create(format!("{}.snow.json", pref))?, &scrubber.snow(&snow)?).unwrap();
+		}
+		if let Some(f) = matches.value_of("vehicles") {
+			let vehicles: data::VehiclesConfiguration = load(f)?;
+			serde_json::to_writer(&std::fs::File::create(format!("{}.vehicles.json", pref))?, &scrubber.vehicles(&vehicles)?).unwrap();
+		}
+		log::info!("Scrubbed {} nodes and {} segments", roads.nodes.nodes.len(), roads.roads.len());
+	} else if let Some(matches) = matches.subcommand_matches("hash") {
+		let hash = load_roads(matches)?.content_hash();
+		if let Some(check) = matches.value_of("check") {
+			let expected = if std::path::Path::new(check).is_file() {
+				let output: serde_json::Value = load(check)?;
+				output["graph"].as_str().map(str::to_string).ok_or_else(|| Error::Invalid(format!("{} has no graph hash", check)))?
+			} else {
+				check.to_string()
+			};
+			if hash != expected {
+				return Err(format!("Road graph hash {} doesn't match {}", hash, expected).into());
+			}
+			log::info!("Road graph hash matches");
+		}
+		println!("{}", hash);
+	} else if let Some(matches) = matches.subcommand_matches("graph-dump") {
+		let roads = load_roads(matches)?;
+		log::info!("Loaded configuration");
+		let dump = match matches.value_of("solver").unwrap() {
+			"fly" => plow::fly::dump(roads),
+			"sidewalk" => plow::sidewalk::dump(roads),
+			_ => plow::road::dump(roads),
+		}?;
+		log::info!("Dumped {} nodes and {} edges", dump.nodes.len(), dump.edges.len());
+		serde_json::to_writer_pretty(&std::fs::File::create(matches.value_of("output").unwrap())?, &dump).unwrap();
+	} else if let Some(matches) = matches.subcommand_matches("graph-diff") {
+		let dump1: graph::dump::GraphDump = load(matches.value_of("dump1").unwrap())?;
+		let dump2: graph::dump::GraphDump = load(matches.value_of("dump2").unwrap())?;
+		let diff = dump1.diff(&dump2);
+		for line in &diff {
+			println!("{}", line);
+		}
+		log::info!("{} differences", diff.len());
+	} else if let Some(matches) = matches.subcommand_matches("geojson") {
+		let roads = load_roads(matches)?;
+		let pref = matches.value_of("prefix").unwrap();
+		let wut = load(matches.value_of("wut").unwrap())?;
+		log::info!("Loaded configuration");
+		let mut export = gj::Export::with_limits(gj::SplitLimits {
+			max_features: matches.value_of("max-features").map(|m| m.parse().unwrap()),
+			max_size: matches.value_of("max-size").map(|m| parse_size(m).unwrap()),
+		}).graph(&roads);
+		match wut {
+			Wut::Paths(paths) if matches.is_present("simplify") => {
+				export = export.segments(&paths);
+				let g = gj::roads_to_nodes(roads.nodes);
+				let lines = paths.0.iter().map(|(id, path)| (id, simplify(matches, gj::path_to_line_geojson(&g, id, path))));
+				if matches.is_present("combined") {
+					export.write(format!("{}.geojson", pref), None, geojson::FeatureCollection { features: lines.map(|(_, f)| f).collect(), bbox: None, foreign_members: None })?;
+				} else {
+					for (id, f) in lines {
+						export.write(format!("{}.{}.geojson", pref, id), Some(id.clone()), f)?;
+					}
+				}
+			}
+			Wut::Paths(paths) if roads.roads.iter().any(|e| !e.properties.is_empty()) => {
+				export = export.segments(&paths);
+				let g = gj::roads_to_nodes(roads.nodes.clone());
+				let view = view::SolutionView::new(&roads, &paths, None);
+				if matches.is_present("combined") {
+					export.write(format!("{}.geojson", pref), None, gj::traversals_to_geofeatures(&g, paths.0.keys().flat_map(|id| view.vehicle(id))))?;
+				} else {
+					for id in paths.0.keys() {
+						export.write(format!("{}.{}.geojson", pref, id), Some(id.clone()), gj::traversals_to_geofeatures(&g, view.vehicle(id)))?;
+					}
+				}
+			}
+			Wut::Paths(paths) => {
+				export = export.segments(&paths);
+				let g = gj::roads_to_nodes(roads.nodes);
+				if matches.is_present("combined") {
+					export.write(format!("{}.geojson", pref), None, gj::paths_to_geojson(&g, paths))?;
+				} else {
+					for (id, path) in paths.0 {
+						let gj = gj::path_to_geojson(&g, &id, path);
+						export.write(format!("{}.{}.geojson", pref, id), Some(id), gj)?;
+					}
+				}
+			}
+			Wut::Schedules(schedules) => {
+				export = export.segments(&schedules.paths());
+				let g = gj::roads_to_nodes(roads.nodes);
+				if matches.is_present("combined") {
+					export.write(format!("{}.geojson", pref), None, geojson::FeatureCollection { features: schedules.0.into_iter().flat_map(|(id, s)| gj::schedule_to_geojson(&g, &id, s).features).collect(), bbox: None, foreign_members: None })?;
+				} else {
+					for (id, s) in schedules.0 {
+						let gj = gj::schedule_to_geojson(&g, &id, s);
+						export.write(format!("{}.{}.geojson", pref, id), Some(id), gj)?;
+					}
+				}
+			}
+			Wut::Drones(drones) => {
+				export.write(format!("{}.geojson", pref), None, gj::locations_to_geojson(&roads.nodes, drones))?;
+			}
+			Wut::Vehicles(vc) => {
+				let vc = vc.resolve()?;
+				if !vc.depots.is_empty() {
+					export.write(format!("{}.depots.geojson", pref), None, gj::depots_to_geojson(&roads.nodes, vc.depots))?;
+				}
+				export.write(format!("{}.road.geojson", pref), None, gj::vehicles_to_geojson(&roads.nodes, vc.road))?;
+				export.write(format!("{}.sidewalk.geojson", pref), None, gj::vehicles_to_geojson(&roads.nodes, vc.sidewalk))?;
+			}
 			Wut::Snow(snows) => {
-				serde_json::to_writer(&std::fs::File::create(format!("{}.geojson", pref))?, &gj::snows_to_geofeatures(&roads, snows)).unwrap();
+				export.write(format!("{}.geojson", pref), None, gj::snows_to_geofeatures(&roads, snows))?;
+			}
+		}
+		let manifest = export.commit(&format!("{}.manifest.json", pref))?;
+		log::info!("Exported {} files", manifest.files.len());
+	}
+	#[cfg(feature = "geocode")]
+	if let Some(matches) = matches.subcommand_matches("geocode") {
+		let addresses: Vec<String> = load(matches.value_of("addresses").unwrap())?;
+		let gazetteer = geocode::Gazetteer::from_csv(std::io::BufReader::new(std::fs::File::open(matches.value_of("gazetteer").unwrap()).map_err(|e| Error::io(matches.value_of("gazetteer").unwrap(), e))?)).map_err(|e| Error::Invalid(format!("{}: {}", matches.value_of("gazetteer").unwrap(), e)))?;
+		if gazetteer.is_empty() {
+			log::warn!("Gazetteer is empty, nothing will be geocoded");
+		}
+		log::info!("Loaded {} addresses and {} gazetteer entries", addresses.len(), gazetteer.len());
+		let locations: data::Drones = geocode::geocode_all(&gazetteer, addresses.iter().map(String::as_str)).map_err(|unknown| Error::Invalid(format!("Couldn't geocode {} addresses: {}", unknown.len(), unknown.join("; "))))?;
+		log::info!("Geocoded");
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &locations).unwrap();
+	}
+	#[cfg(feature = "osm")]
+	if let Some(matches) = matches.subcommand_matches("import-osm") {
+		let path = matches.value_of("extract").unwrap();
+		let extract = osm::Extract::read(path)?;
+		log::info!("Loaded {} nodes and {} ways", extract.nodes.len(), extract.ways.len());
+		let highways: Vec<&str> = matches.values_of("highways").map_or_else(|| osm::HIGHWAYS.to_vec(), Iterator::collect);
+		let roads = extract.roads(&highways);
+		log::info!("Imported {} segments between {} nodes", roads.roads.len(), roads.nodes.nodes.len());
+		serde_json::to_writer(&std::fs::File::create(matches.value_of("output").unwrap())?, &roads).unwrap();
+	}
+	#[cfg(feature = "notify")]
+	if let Some(matches) = matches.subcommand_matches("worker") {
+		let listener = std::net::TcpListener::bind(matches.value_of("address").unwrap())?;
+		log::info!("Serving experiment runs at {}", listener.local_addr()?);
+		remote::serve(listener);
+	}
+	#[cfg(feature = "notify")]
+	if let Some(matches) = matches.subcommand_matches("daemon") {
+		let path = std::path::Path::new(matches.value_of("config").unwrap());
+		let mut config: daemon::Config = load_yaml(path)?;
+		let dir = path.parent().unwrap_or_else(|| std::path::Path::new("."));
+		config.trigger.snow = config.trigger.snow.iter().map(|f| dir.join(f)).collect();
+		config.schedule.until_next(0)?;
+		loop {
+			if !matches.is_present("once") {
+				let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+				let wait = config.schedule.until_next(now)?;
+				log::info!("Next run in {}h {}m", wait / 3600, wait % 3600 / 60);
+				std::thread::sleep(std::time::Duration::from_secs(wait));
+			}
+			// a snow file unreadable (e.g. half-written) fails the run, not the daemon
+			let snow = daemon::snow_files(&config.trigger).map_err(Error::from).and_then(|files| {
+				let mut snows = Vec::new();
+				for f in files {
+					let snow: data::SnowStatuses = load(f)?;
+					snows.extend(snow);
+				}
+				Ok(merge_snow_statuses(snows.into_iter()))
+			});
+			let outcome = match snow.map(|snow| daemon::snow_total(&snow)) {
+				Ok(total) if total > config.trigger.threshold => {
+					log::info!("Snow total {:.1} above {:.1}, running the job", total, config.trigger.threshold);
+					Some(daemon::run_job(&config.job, total))
+				},
+				Ok(total) => {
+					log::info!("Snow total {:.1} not above {:.1}, skipping", total, config.trigger.threshold);
+					None
+				},
+				Err(e) => Some(daemon::Outcome { snow_total: 0.0, success: false, exit_code: None, message: format!("Reading the snow statuses failed: {}", e) }),
+			};
+			if let Some(outcome) = outcome {
+				if outcome.success {
+					log::info!("{}", outcome.message);
+				} else {
+					log::error!("{}", outcome.message);
+				}
+				if let Some(url) = &config.webhook {
+					if let Err(e) = notify::post(url, &outcome) {
+						log::error!("Webhook failed: {}", e);
+					}
+				}
+			}
+			if matches.is_present("once") {
+				break;
 			}
 		}
 	}
+	#[cfg(feature = "schema")]
+	if let Some(matches) = matches.subcommand_matches("schema") {
+		let mut schemas = schema::schemas();
+		let schema = match matches.value_of("type") {
+			Some(t) => schemas.swap_remove(t).unwrap(),
+			None => serde_json::to_value(schemas).unwrap(),
+		};
+		println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+	}
 	Ok(())
 }
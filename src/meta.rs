@@ -4,6 +4,7 @@ use crate::*;
 use serde::*;
 
 #[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Recycle {
 	/// do not move cycles
 	No,
@@ -12,6 +13,7 @@ pub enum Recycle {
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Clearing {
 	/// the vehicle clears only the allocated edges
 	OnlyAllocated,
@@ -20,6 +22,7 @@ pub enum Clearing {
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Reorder {
 	/// don't reorder
 	No,
@@ -29,9 +32,14 @@ pub enum Reorder {
 	RandomReorder,
 	/// swap most and least used
 	Swap2MostLeast,
+	/// swap most and least expensive (by cost in the previous iteration)
+	Swap2MostLeastCost,
+	/// rotate the order by one
+	RoundRobin,
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Realloc {
 	/// don't
 	No,
@@ -41,22 +49,388 @@ pub enum Realloc {
 	MostToLeast,
 }
 
+/// How a snow report not addressing a specific one of parallel segments is distributed among them
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Parallels {
+	/// only the first parallel segment gets the snow
+	#[default]
+	First,
+	/// every parallel segment gets the whole depth
+	All,
+	/// the depth is split evenly among the parallel segments
+	Split,
+}
+
+/// Clearing slowdown factor - a constant, or a piecewise linear function of the snow depth (and temperature)
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum Slowdown {
+	Constant(#[cfg_attr(feature = "schema", schemars(with = "f64"))] N64),
+	Curve {
+		/// `[depth, factor]` points, by increasing depth
+		#[cfg_attr(feature = "schema", schemars(with = "Vec<(f64, f64)>"))]
+		depth: Vec<(N64, N64)>,
+		/// `[temperature, multiplier]` points, by increasing temperature
+		#[serde(default)]
+		#[cfg_attr(feature = "schema", schemars(with = "Vec<(f64, f64)>"))]
+		temperature: Vec<(N64, N64)>,
+	},
+}
+
+/// Linearly interpolates between the points, constant beyond the first and the last
+fn piecewise(points: &[(N64, N64)], x: N64) -> Option<N64> {
+	let i = points.iter().position(|(px, _)| *px > x).unwrap_or(points.len());
+	match (i.checked_sub(1).map(|i| points[i]), points.get(i).copied()) {
+		(Some((x1, y1)), Some((x2, y2))) => Some(y1 + (y2 - y1) * (x - x1) / (x2 - x1)),
+		(Some((_, y)), None) | (None, Some((_, y))) => Some(y),
+		(None, None) => None,
+	}
+}
+
+impl Slowdown {
+	/// Slowdown factor clearing snow of `depth`, at `temperature` if known
+	pub fn factor(&self, depth: N64, temperature: Option<N64>) -> N64 {
+		match self {
+			Self::Constant(s) => *s,
+			Self::Curve { depth: d, temperature: t } => piecewise(d, depth).unwrap_or_else(|| n64(1.0)) * temperature.and_then(|x| piecewise(t, x)).unwrap_or_else(|| n64(1.0)),
+		}
+	}
+}
+
+/// Driving speeds (distance per time unit), making road segment costs travel times rather than distances
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Speeds {
+	/// Speed on unlisted classes, and unclassified segments
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
+	pub default: N64,
+	/// Speeds by street class
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "indexmap::IndexMap<String, f64>"))]
+	pub classes: indexmap::IndexMap<data::StreetClass, N64>,
+}
+
+impl Speeds {
+	/// Speed on a segment of `class`
+	pub fn of(&self, class: Option<&data::StreetClass>) -> N64 {
+		class.and_then(|c| self.classes.get(c)).copied().unwrap_or(self.default)
+	}
+}
+
+/// De-icing priority - bridges and shaded segments freeze first, so near freezing they are to be cleared early
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Deicing {
+	/// `[min, max]` temperatures at which segments are boosted (e.g. `[-5, 2]`)
+	#[cfg_attr(feature = "schema", schemars(with = "(f64, f64)"))]
+	pub band: (N64, N64),
+	/// Boost of bridges
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
+	pub bridge: N64,
+	/// Boost of shaded segments
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
+	pub shaded: N64,
+}
+
+impl Deicing {
+	/// Boost of a segment at `temperature` - none out of the band (or unknown temperature), the larger one for shaded bridges
+	pub fn boost(&self, temperature: Option<N64>, bridge: bool, shaded: bool) -> N64 {
+		match temperature {
+			Some(t) if self.band.0 <= t && t <= self.band.1 => std::cmp::max(if bridge { self.bridge } else { n64(0.0) }, if shaded { self.shaded } else { n64(0.0) }),
+			_ => n64(0.0),
+		}
+	}
+}
+
+/// Clearing deadlines of priority segments (see [`data::RoadSegment::priority`]) - e.g. arterials within 4 hours of the snowfall
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Deadlines {
+	/// Deadline of every priority level, in path costs (travel times with `speeds`) since the start of the plan
+	#[cfg_attr(feature = "schema", schemars(with = "indexmap::IndexMap<u32, f64>"))]
+	pub levels: indexmap::IndexMap<u32, N64>,
+	/// Penalty per unit of cost a priority segment is cleared past its deadline
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
+	pub lateness: N64,
+}
+
+impl Deadlines {
+	/// Lateness penalty of a segment of `priority` cleared at `cleared` - none without a deadline for its level
+	pub fn penalty(&self, priority: Option<u32>, cleared: N64) -> N64 {
+		match priority.and_then(|p| self.levels.get(&p)) {
+			Some(d) if cleared > *d => self.lateness * (cleared - *d),
+			_ => n64(0.0),
+		}
+	}
+}
+
+/// Tolerance comparing objective values - a plan only beats another by more than float noise, so late in a run equivalent plans don't keep swapping
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Tolerance {
+	#[serde(default)]
+	pub absolute: f64,
+	/// Relative to the larger of the values compared
+	#[serde(default)]
+	pub relative: f64,
+}
+
+impl Default for Tolerance {
+	fn default() -> Self {
+		Self { absolute: 0.0, relative: 1e-9 }
+	}
+}
+
+impl Tolerance {
+	fn epsilon(&self, a: f64, b: f64) -> f64 {
+		self.absolute.max(self.relative * a.abs().max(b.abs()))
+	}
+	/// Whether `a` is less than `b` by more than the tolerance - an infinite `b` being beaten by anything finite
+	pub fn lt(&self, a: N64, b: N64) -> bool {
+		let (a, b) = (a.raw(), b.raw());
+		if !(a.is_finite() && b.is_finite()) {
+			return a < b;
+		}
+		a < b - self.epsilon(a, b)
+	}
+	/// Whether `a` is no more than `b`, within the tolerance
+	pub fn le(&self, a: N64, b: N64) -> bool {
+		let (a, b) = (a.raw(), b.raw());
+		if !(a.is_finite() && b.is_finite()) {
+			return a <= b;
+		}
+		a <= b + self.epsilon(a, b)
+	}
+}
+
 #[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Annealing {
 	pub main_iterations: u64, //MI
 	pub ft_iterations: u64, //II
 	pub starting_temperature: f64, //ST
 	pub cooling_factor: f64, //RC
+	/// Independent annealing runs, of which the best plan is kept - a single one by default
+	#[serde(default)]
+	pub restarts: usize,
+	/// Random generator seed of the first run (the following ones take the next seeds) - a random one by default
+	#[serde(default)]
+	pub seed: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Parameters {
 	pub recycle: Recycle, //IV
 	pub clearing: Clearing, //MD
 	pub reorder: Reorder, //ChV
 	pub realloc: Realloc, //MV
 	pub annealing: Annealing,
-	pub slowdown: N64,
+	pub slowdown: Slowdown,
+	/// Ambient temperature, for a temperature dependent slowdown
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "Option<f64>"))]
+	pub temperature: Option<N64>,
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
 	pub weight_total: N64,
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
 	pub weight_max: N64,
+	#[serde(default)]
+	pub parallels: Parallels,
+	/// Clearing slowdown per unit of grade - a segment being cleared costs `1 + grade_multiplier * |grade|` times more
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
+	pub grade_multiplier: N64,
+	/// Penalty per standard deviation of a path's cost under an uncertain snow forecast - the most expensive path is judged by `cost + risk_aversion * deviation`
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
+	pub risk_aversion: N64,
+	/// Penalty per unit of cost of a snowy segment covered by more than one vehicle able to clear it (under `OnlyAllocated`, routes overlapping on snow), for every vehicle beyond the first
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
+	pub redundancy: N64,
+	/// Penalty for plowing a sidewalk from the opposite side of the street - going `p1`→`p2` along the left one, or `p2`→`p1` along the right one - having to cross (segments can have their own)
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
+	pub crossing: N64,
+	/// Cost multiplier of drone corridors across sensitive sites (within their buffer) - crossing one costs `1 + sensitive` times more, so drones only do where there's no way around
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
+	pub sensitive: N64,
+	/// Load a road vehicle uses up per unit of cost (distance, or time with `speeds`) of the segments it clears - vehicles with a [`data::VehicleSpec::capacity`] refill at the depots when it runs out.
+	/// None by default, capacities not limiting plowing
+	#[serde(default)]
+	#[cfg_attr(feature = "schema", schemars(with = "f64"))]
+	pub consumption: N64,
+	/// How far apart coordinates are, locating vehicles and allocating them segments - geodesic by default
+	#[serde(default)]
+	pub metric: data::Metric,
+	/// Driving speeds of road vehicles - costs are distances without
+	#[serde(default)]
+	pub speeds: Option<Speeds>,
+	/// De-icing priority of bridges and shaded segments, at the ambient `temperature`
+	#[serde(default)]
+	pub deicing: Option<Deicing>,
+	/// Clearing deadlines of priority segments
+	#[serde(default)]
+	pub deadlines: Option<Deadlines>,
+	/// Tolerance of the annealing acceptance and improvement checks
+	#[serde(default)]
+	pub tolerance: Tolerance,
+	/// Threads solving may use - for restarts, then for the vehicles' paths of each run (see [`Clearing::OnlyAllocated`]), then for connecting paths to distant isles (see [`crate::graph::heuristics::Buffers::batched`]); all the cores by default
+	#[serde(default)]
+	pub parallelism: Option<usize>,
+}
+
+/// Storm severity preset - slowdown (with its depth thresholds), clearing mode and annealing effort set together
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all="lowercase")]
+pub enum Preset {
+	/// light dusting - clearing barely slows down, and a quick plan will do
+	Light,
+	/// standard storm
+	Standard,
+	/// blizzard - deep snow crawling, every vehicle clearing what it passes, and the longest route counting most
+	Blizzard,
+}
+
+impl std::str::FromStr for Preset {
+	type Err = String;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"light" => Ok(Self::Light),
+			"standard" => Ok(Self::Standard),
+			"blizzard" => Ok(Self::Blizzard),
+			_ => Err(format!("Unknown preset {} (light, standard or blizzard)", s)),
+		}
+	}
+}
+
+/// The standard preset's - a typical city-scale storm
+impl Default for Parameters {
+	fn default() -> Self {
+		Preset::Standard.parameters()
+	}
+}
+
+impl Preset {
+	pub const NAMES: [&'static str; 3] = ["light", "standard", "blizzard"];
+	/// The preset's meta parameters
+	pub fn parameters(self) -> Parameters {
+		let curve = |points: &[(f64, f64)]| Slowdown::Curve { depth: points.iter().map(|(d, f)| (n64(*d), n64(*f))).collect(), temperature: vec![] };
+		let annealing = |main_iterations, ft_iterations, starting_temperature, cooling_factor, restarts| Annealing { main_iterations, ft_iterations, starting_temperature, cooling_factor, restarts, seed: None };
+		let (clearing, slowdown, annealing, weight_max) = match self {
+			Self::Light => (Clearing::All, curve(&[(1.0, 1.0), (5.0, 1.5)]), annealing(20, 5, 100.0, 0.5, 1), 1.0),
+			Self::Standard => (Clearing::All, curve(&[(2.0, 1.0), (10.0, 2.0), (25.0, 4.0)]), annealing(100, 10, 1000.0, 0.9, 2), 2.0),
+			Self::Blizzard => (Clearing::OnlyAllocated, curve(&[(5.0, 1.5), (20.0, 3.0), (50.0, 8.0)]), annealing(300, 20, 2000.0, 0.95, 4), 5.0),
+		};
+		Parameters {
+			recycle: Recycle::ExpensiveToCheap,
+			clearing,
+			reorder: Reorder::Swap2MostLeastCost,
+			realloc: Realloc::No,
+			annealing,
+			slowdown,
+			temperature: None,
+			weight_total: n64(1.0),
+			weight_max: n64(weight_max),
+			parallels: Parallels::default(),
+			grade_multiplier: n64(0.0),
+			risk_aversion: n64(0.0),
+			redundancy: n64(0.0),
+			crossing: n64(0.0),
+			sensitive: n64(0.0),
+			consumption: n64(0.0),
+			metric: Default::default(),
+			speeds: None,
+			deicing: None,
+			deadlines: None,
+			tolerance: Default::default(),
+			parallelism: None,
+		}
+	}
+	/// The preset's meta parameters, overridden by those given (e.g. a meta parameters file with only some of them)
+	pub fn with(self, overrides: serde_json::Value) -> Result<Parameters, Error> {
+		serde_json::from_value(self.merged(overrides)).map_err(|e| format!("Invalid meta parameters: {}", e).into())
+	}
+	/// The preset's meta parameters, overridden by those given, yet to be deserialized
+	pub fn merged(self, overrides: serde_json::Value) -> serde_json::Value {
+		fn merge(base: &mut serde_json::Value, over: serde_json::Value) {
+			match (base, over) {
+				(serde_json::Value::Object(base), serde_json::Value::Object(over)) => for (k, v) in over {
+					merge(base.entry(k).or_insert(serde_json::Value::Null), v);
+				},
+				(base, over) => *base = over,
+			}
+		}
+		let mut params = serde_json::to_value(self.parameters()).unwrap();
+		if !overrides.is_null() {
+			merge(&mut params, overrides);
+		}
+		params
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_slowdown() {
+		let s: Slowdown = serde_json::from_str(r#"{"depth": [[2, 1], [10, 2], [25, 6]], "temperature": [[-20, 1.5], [0, 1]]}"#).unwrap();
+		assert_eq!(s.factor(n64(0.0), None), 1.0);
+		assert_eq!(s.factor(n64(6.0), None), 1.5);
+		assert_eq!(s.factor(n64(40.0), None), 6.0);
+		assert_eq!(s.factor(n64(10.0), Some(n64(-10.0))), 2.5);
+		assert_eq!(serde_json::from_str::<Slowdown>("2").unwrap().factor(n64(40.0), None), 2.0);
+	}
+
+	#[test]
+	fn test_deicing() {
+		let d: Deicing = serde_json::from_str(r#"{"band": [-5, 2], "bridge": 3, "shaded": 1}"#).unwrap();
+		assert_eq!(d.boost(Some(n64(0.0)), true, true), 3.0);
+		assert_eq!(d.boost(Some(n64(-5.0)), false, true), 1.0);
+		assert_eq!(d.boost(Some(n64(0.0)), false, false), 0.0);
+		assert_eq!(d.boost(Some(n64(-10.0)), true, false), 0.0);
+		assert_eq!(d.boost(None, true, false), 0.0);
+	}
+
+	#[test]
+	fn test_deadlines() {
+		let d: Deadlines = serde_json::from_str(r#"{"levels": {"1": 100, "2": 300}, "lateness": 2}"#).unwrap();
+		assert_eq!(d.penalty(Some(1), n64(150.0)), 100.0);
+		assert_eq!(d.penalty(Some(2), n64(150.0)), 0.0);
+		assert_eq!(d.penalty(Some(3), n64(1000.0)), 0.0);
+		assert_eq!(d.penalty(None, n64(1000.0)), 0.0);
+	}
+
+	#[test]
+	fn test_tolerance() {
+		let t = Tolerance { absolute: 0.0, relative: 1e-6 };
+		assert!(!t.lt(n64(1000.0 - 1e-6), n64(1000.0)));
+		assert!(t.lt(n64(999.0), n64(1000.0)));
+		assert!(t.le(n64(1000.0 + 1e-6), n64(1000.0)));
+		assert!(!t.le(n64(1001.0), n64(1000.0)));
+		assert!(t.lt(n64(1e12), N64::infinity()));
+		assert!(!t.lt(N64::infinity(), N64::infinity()));
+		let t = Tolerance { absolute: 0.5, relative: 0.0 };
+		assert!(!t.lt(n64(0.6), n64(1.0)) && t.lt(n64(0.4), n64(1.0)));
+		assert_eq!(serde_json::from_str::<Tolerance>("{}").unwrap(), Tolerance { absolute: 0.0, relative: 0.0 });
+	}
+
+	#[test]
+	fn test_presets() {
+		for name in Preset::NAMES {
+			let preset: Preset = name.parse().unwrap();
+			assert_eq!(preset.with(serde_json::Value::Null).unwrap(), preset.parameters());
+		}
+		let params = Preset::Blizzard.with(serde_json::json!({"weight_max": 3, "annealing": {"restarts": 1}})).unwrap();
+		assert_eq!((params.weight_max, params.annealing.restarts, params.annealing.main_iterations, params.clearing), (n64(3.0), 1, 300, Clearing::OnlyAllocated));
+		assert!(Preset::Light.with(serde_json::json!({"clearing": "Some"})).is_err());
+		assert!("hail".parse::<Preset>().is_err());
+	}
 }
@@ -0,0 +1,172 @@
+//! Notification hooks
+//!
+//! Long solves finish at odd hours - hooks let whoever's waiting know when they complete, turn out infeasible or get cancelled.
+//! Webhooks are POSTed over std's TCP, plain `http://` only, so no HTTP client is pulled in (and minimal builds leave the feature out altogether).
+
+use std::{io::{Read, Write}, net::TcpStream, time::Duration};
+use serde::*;
+use serde_json::{Map, Value};
+use crate::Error;
+
+/// What happened to the job
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+	#[serde(rename="completed")]
+	Completed,
+	/// the solver gave up, e.g. on unreachable segments
+	#[serde(rename="infeasible")]
+	Infeasible,
+	/// interrupted (`Ctrl+C`, `SIGTERM`)
+	#[serde(rename="cancelled")]
+	Cancelled,
+}
+
+impl Event {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Event::Completed => "completed",
+			Event::Infeasible => "infeasible",
+			Event::Cancelled => "cancelled",
+		}
+	}
+}
+
+fn all_events() -> Vec<Event> {
+	vec![Event::Completed, Event::Infeasible, Event::Cancelled]
+}
+
+/// A webhook to notify
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Hook {
+	/// Webhook URL, `http://` only
+	pub webhook: String,
+	/// Events to fire on, all by default
+	#[serde(default = "all_events")]
+	pub on: Vec<Event>,
+	/// Payload template, see [`render`] - all the fields as an object by default
+	#[serde(default)]
+	pub template: Option<Value>,
+}
+
+pub type Hooks = Vec<Hook>;
+
+/// Fills a payload template in with fields
+///
+/// A string that's just `{{field}}` becomes the field's value (of whatever type, `null` if there's no such field), `{{field}}`s within longer strings are replaced with its text.
+pub fn render(template: &Value, fields: &Map<String, Value>) -> Value {
+	match template {
+		Value::String(s) => match s.strip_prefix("{{").and_then(|s| s.strip_suffix("}}")).filter(|f| !f.contains("{{")) {
+			Some(f) => fields.get(f.trim()).cloned().unwrap_or(Value::Null),
+			None => Value::String(fields.iter().fold(s.clone(), |s, (f, v)| s.replace(&format!("{{{{{}}}}}", f), &v.as_str().map_or_else(|| v.to_string(), str::to_string)))),
+		},
+		Value::Array(vs) => Value::Array(vs.iter().map(|v| render(v, fields)).collect()),
+		Value::Object(o) => Value::Object(o.iter().map(|(k, v)| (k.clone(), render(v, fields))).collect()),
+		v => v.clone(),
+	}
+}
+
+/// Notifies the hooks firing on the event, with the fields (and the `event` itself)
+///
+/// Returns: the failures, by webhook
+pub fn fire(hooks: &[Hook], event: Event, mut fields: Map<String, Value>) -> Vec<(String, Error)> {
+	fields.insert("event".to_string(), event.name().into());
+	hooks.iter().filter(|h| h.on.contains(&event)).filter_map(|h| {
+		let payload = h.template.as_ref().map_or_else(|| Value::Object(fields.clone()), |t| render(t, &fields));
+		post(&h.webhook, &payload).err().map(|e| (h.webhook.clone(), e))
+	}).collect()
+}
+
+/// POSTs a JSON body to a plain HTTP URL, expecting a 2xx response
+pub fn post(url: &str, body: &impl Serialize) -> Result<(), Error> {
+	let rest = url.strip_prefix("http://").ok_or_else(|| format!("Only http:// webhooks are supported, not {}", url))?;
+	let (authority, path) = rest.find('/').map_or((rest, "/"), |i| (&rest[..i], &rest[i..]));
+	let host = authority.rsplit_once(':').map_or(authority, |(h, _)| h);
+	let address = if authority.contains(':') { authority.to_string() } else { format!("{}:80", authority) };
+	let body = serde_json::to_string(body).map_err(|e| e.to_string())?;
+	let mut stream = TcpStream::connect(&address).map_err(|e| format!("Can't connect to {}: {}", address, e))?;
+	stream.set_read_timeout(Some(Duration::from_secs(30))).map_err(|e| e.to_string())?;
+	write!(stream, "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", path, host, body.len(), body).map_err(|e| e.to_string())?;
+	let mut response = String::new();
+	stream.read_to_string(&mut response).map_err(|e| e.to_string())?;
+	let status = response.lines().next().unwrap_or_default();
+	match status.split_whitespace().nth(1) {
+		Some(code) if code.starts_with('2') => Ok(()),
+		_ => Err(format!("Webhook responded {}", status).into()),
+	}
+}
+
+/// The interrupt signals, `SIGINT` and `SIGTERM`
+#[cfg(unix)]
+fn interrupts() -> libc::sigset_t {
+	unsafe {
+		let mut set: libc::sigset_t = std::mem::zeroed();
+		libc::sigemptyset(&mut set);
+		libc::sigaddset(&mut set, libc::SIGINT);
+		libc::sigaddset(&mut set, libc::SIGTERM);
+		set
+	}
+}
+
+/// Calls `f` on the first interrupt (`SIGINT`, `SIGTERM`) instead of terminating, from a thread of its own
+///
+/// Must be called before spawning any other threads, for them to leave the signals to it - or those spawned [`without_interrupts`].
+#[cfg(unix)]
+pub fn on_interrupt(f: impl FnOnce(i32) + Send + 'static) {
+	let set = interrupts();
+	unsafe {
+		libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut());
+		std::thread::spawn(move || {
+			let mut sig = 0;
+			libc::sigwait(&set, &mut sig);
+			f(sig);
+		});
+	}
+}
+
+/// Runs `f` with the interrupts blocked, for the threads it spawns to leave them to the main thread (or [`on_interrupt`]'s, once called)
+#[cfg(unix)]
+pub fn without_interrupts<T>(f: impl FnOnce() -> T) -> T {
+	let set = interrupts();
+	unsafe {
+		let mut old: libc::sigset_t = std::mem::zeroed();
+		libc::pthread_sigmask(libc::SIG_BLOCK, &set, &mut old);
+		let result = f();
+		libc::pthread_sigmask(libc::SIG_SETMASK, &old, std::ptr::null_mut());
+		result
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_render(){
+		let fields: Map<String, Value> = serde_json::from_str(r#"{"event": "completed", "value": 12.5, "job": "east"}"#).unwrap();
+		let template = serde_json::json!({ "text": "Plan {{job}} {{event}}: {{value}}", "value": "{{value}}", "missing": "{{nope}}", "tags": ["{{job}}", 1] });
+		assert_eq!(render(&template, &fields), serde_json::json!({ "text": "Plan east completed: 12.5", "value": 12.5, "missing": null, "tags": ["east", 1] }));
+	}
+
+	#[test]
+	fn test_post(){
+		let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+		let url = format!("http://{}/hook", listener.local_addr().unwrap());
+		let server = std::thread::spawn(move || {
+			let (mut s, _) = listener.accept().unwrap();
+			// the head and the body may come in separate reads
+			let (mut request, mut buf) = (Vec::new(), [0; 4096]);
+			while !request.ends_with(b"}") {
+				let n = s.read(&mut buf).unwrap();
+				assert!(n > 0);
+				request.extend_from_slice(&buf[..n]);
+			}
+			s.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+			String::from_utf8_lossy(&request).to_string()
+		});
+		assert!(post(&url, &serde_json::json!({ "success": true })).is_ok());
+		let request = server.join().unwrap();
+		assert!(request.starts_with("POST /hook HTTP/1.1\r\n"));
+		assert!(request.ends_with("{\"success\":true}"));
+		assert!(post("https://example.com", &()).is_err());
+	}
+}
@@ -0,0 +1,119 @@
+//! Route orientation
+//!
+//! The solver doesn't care which way a vehicle goes around a loop of two-way streets, so routes may turn around where going round
+//! the other way would have carried on forward. A post-pass reorients such loops - only where it costs nothing - and marks the turnarounds left.
+
+use crate::*;
+use data::*;
+use meta::Parameters;
+
+use std::collections::HashSet;
+
+/// Whether the vehicle turns around at the `k`th node of the path, back along the segment it came by
+fn uturn(path: &[PathSegment], k: usize) -> bool {
+	k > 0 && k + 1 < path.len() && path[k-1].node == path[k+1].node && path[k].discriminator == path[k+1].discriminator
+}
+
+/// Reverses closed stretches of the path (from a node back to it) where it removes turnarounds, and every segment of the stretch can be driven the other way
+///
+/// Returns: the number of turnarounds removed
+pub fn reorient(path: &mut [PathSegment], drivable: impl Fn(&NodeId, &NodeId, &Option<NodeId>) -> bool) -> usize {
+	let mut removed = 0;
+	loop {
+		// reversing [i, j] only changes whether the vehicle turns around at its ends
+		let gain = |path: &[PathSegment], i: usize, j: usize| {
+			let before = uturn(path, i) as i32 + uturn(path, j) as i32;
+			let after = (i > 0 && path[i-1].node == path[j-1].node && path[i].discriminator == path[j].discriminator) as i32
+				+ (j + 1 < path.len() && path[i+1].node == path[j+1].node && path[i+1].discriminator == path[j+1].discriminator) as i32;
+			before - after
+		};
+		let p = &*path;
+		let best = (0..p.len())
+			.flat_map(|i| (i+3..p.len()).filter(move |j| p[i].node == p[*j].node).map(move |j| (i, j)))
+			.filter(|(i, j)| gain(p, *i, *j) > 0)
+			.filter(|(i, j)| (i+1..=*j).all(|k| drivable(&p[k].node, &p[k-1].node, &p[k].discriminator)))
+			.max_by_key(|(i, j)| gain(p, *i, *j));
+		match best {
+			Some((i, j)) => {
+				removed += gain(path, i, j) as usize;
+				path[i..=j].reverse();
+				// the stretch's segments are now entered from their other end, and it is still entered by the same one
+				let mut discriminators: Vec<_> = path[i..=j].iter().map(|s| s.discriminator.clone()).collect();
+				discriminators.rotate_right(1);
+				for (s, d) in path[i..=j].iter_mut().zip(discriminators) {
+					s.discriminator = d;
+				}
+			},
+			None => return removed,
+		}
+	}
+}
+
+/// Marks where the vehicles turn around
+///
+/// Returns: the number of turnarounds
+pub fn mark_reversals(paths: &mut Paths) -> usize {
+	paths.0.values_mut().map(|path| {
+		let uturns: Vec<_> = (0..path.len()).map(|k| uturn(path, k)).collect();
+		for (s, u) in path.iter_mut().zip(&uturns) {
+			s.reversal = *u;
+		}
+		uturns.into_iter().filter(|u| *u).count()
+	}).sum()
+}
+
+/// Reorients the routes of a road plan (see [`reorient`]) where it doesn't make them any more expensive, and marks the turnarounds left
+///
+/// Returns: the plan, and the number of turnarounds removed
+pub fn orient(roads: &RoadGraph, snow: &SnowStatuses, snow_d: Option<f64>, paths: &Paths, params: &Parameters) -> Result<(Paths, usize), Error> {
+	let mut drivable = HashSet::new();
+	for e in &roads.roads {
+		drivable.insert((&e.p1, &e.p2, &e.discriminator));
+		if !e.directed {
+			drivable.insert((&e.p2, &e.p1, &e.discriminator));
+		}
+	}
+	let mut oriented = paths.clone();
+	let removed: Vec<_> = oriented.0.values_mut().map(|path| reorient(path, |u, v, d| drivable.contains(&(u, v, d)))).collect();
+	let before = plow::road::evaluate(roads.clone(), snow, snow_d, paths, params)?;
+	let after = plow::road::evaluate(roads.clone(), snow, snow_d, &oriented, params)?;
+	let mut total = 0;
+	for (i, (id, path)) in paths.0.iter().enumerate() {
+		if after.costs[i] > before.costs[i] {
+			oriented.0.insert(id.clone(), path.clone());
+		} else {
+			total += removed[i];
+		}
+	}
+	mark_reversals(&mut oriented);
+	Ok((oriented, total))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_reorient(){
+		let seg = |n: &'static str| PathSegment { node: n.into(), discriminator: None, reversal: false, leg: None };
+		fn nodes(path: &[PathSegment]) -> Vec<&str> {
+			path.iter().map(|s| s.node.name().unwrap()).collect()
+		}
+		// a -> b, around the b c d loop, and back to a: coming back from d to b then turns around at b rather than carrying on
+		let mut path = vec![seg("a"), seg("b"), seg("a"), seg("b"), seg("c"), seg("d"), seg("b"), seg("e")];
+		for k in [1, 2, 3] {
+			path[k].discriminator = Some("x".into());
+		}
+		path[5].discriminator = Some("y".into());
+		assert_eq!(reorient(&mut path, |_, _, _| true), 1);
+		assert_eq!(nodes(&path), vec!["a", "b", "d", "c", "b", "a", "b", "e"]);
+		let discriminators: Vec<_> = path.iter().map(|s| s.discriminator.as_ref().and_then(|d| d.name())).collect();
+		assert_eq!(discriminators, vec![None, Some("x"), None, Some("y"), None, Some("x"), Some("x"), None]);
+		// ... unless the loop is one way
+		let mut path = vec![seg("a"), seg("b"), seg("a"), seg("b"), seg("c"), seg("d"), seg("b"), seg("e")];
+		assert_eq!(reorient(&mut path, |u, v, _| !(u == &NodeId::from("c") && v == &NodeId::from("b"))), 0);
+		let mut paths: Paths = vec![("0".to_string(), path)].into_iter().collect();
+		assert_eq!(mark_reversals(&mut paths), 2);
+		assert_eq!(paths.0["0"].iter().map(|s| s.reversal).collect::<Vec<_>>(), vec![false, true, true, false, false, false, false, false]);
+	}
+}
@@ -0,0 +1,242 @@
+//! OpenStreetMap import
+//!
+//! Road graphs straight from OSM extracts - `.osm.pbf` files, or Overpass API JSON - rather than hand conversions.
+//! Highway ways are split at intersections into segments, keeping the OSM node ids (as integers) of their ends.
+//! PBF files are decoded with `osmpbf`, of which only the nodes and ways are kept.
+
+use crate::*;
+use data::*;
+
+use std::{collections::{BTreeMap, HashMap}, io::Read};
+use indexmap::IndexMap;
+use serde::*;
+
+/// Highway types imported by default - the streets, not paths, tracks or footways
+pub const HIGHWAYS: [&str; 16] = [
+	"motorway", "motorway_link", "trunk", "trunk_link", "primary", "primary_link", "secondary", "secondary_link",
+	"tertiary", "tertiary_link", "unclassified", "residential", "living_street", "service", "road", "busway",
+];
+
+/// A way, with its nodes and tags
+#[derive(Clone, PartialEq, Debug)]
+pub struct Way {
+	pub id: i64,
+	pub nodes: Vec<i64>,
+	pub tags: BTreeMap<String, String>,
+}
+
+/// The nodes' coordinates and the ways of an extract
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Extract {
+	pub nodes: HashMap<i64, (f64, f64)>,
+	pub ways: Vec<Way>,
+}
+
+#[derive(Deserialize)]
+struct Overpass {
+	elements: Vec<Element>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Element {
+	Node {
+		id: i64,
+		lat: f64,
+		lon: f64,
+	},
+	Way {
+		id: i64,
+		#[serde(default)]
+		nodes: Vec<i64>,
+		#[serde(default)]
+		tags: BTreeMap<String, String>,
+	},
+	#[serde(other)]
+	Other,
+}
+
+impl Extract {
+	/// Reads Overpass API JSON (`[out:json]`) - with the ways' nodes included, as `(way[highway];>;);out;` makes them
+	pub fn from_overpass(reader: impl Read) -> Result<Self, Error> {
+		let overpass: Overpass = serde_json::from_reader(reader).map_err(|e| e.to_string())?;
+		let mut extract = Self::default();
+		for e in overpass.elements {
+			match e {
+				Element::Node { id, lat, lon } => {
+					extract.nodes.insert(id, (lon, lat));
+				}
+				Element::Way { id, nodes, tags } => extract.ways.push(Way { id, nodes, tags }),
+				Element::Other => (),
+			}
+		}
+		Ok(extract)
+	}
+
+	/// Reads an `.osm.pbf` file
+	pub fn from_pbf(reader: impl Read + Send) -> Result<Self, Error> {
+		let mut extract = Self::default();
+		osmpbf::ElementReader::new(reader).for_each(|e| match e {
+			osmpbf::Element::Node(n) => {
+				extract.nodes.insert(n.id(), (n.lon(), n.lat()));
+			}
+			osmpbf::Element::DenseNode(n) => {
+				extract.nodes.insert(n.id(), (n.lon(), n.lat()));
+			}
+			osmpbf::Element::Way(w) => extract.ways.push(Way {
+				id: w.id(),
+				nodes: w.refs().collect(),
+				tags: w.tags().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+			}),
+			osmpbf::Element::Relation(_) => (),
+		}).map_err(|e| format!("Invalid PBF: {}", e))?;
+		Ok(extract)
+	}
+
+	/// Reads an extract file, by its extension - `.pbf` or else Overpass JSON
+	pub fn read(path: impl AsRef<std::path::Path>) -> Result<Self, Error> {
+		let file = std::io::BufReader::new(std::fs::File::open(&path).map_err(|e| e.to_string())?);
+		if path.as_ref().extension().is_some_and(|e| e == "pbf") {
+			Self::from_pbf(file)
+		} else {
+			Self::from_overpass(file)
+		}
+	}
+
+	/// Builds the road graph of the highways of the given types
+	///
+	/// Ways are split at every node they share with another (or visit twice) and at their ends, closed ways also halfway round; distances are the geodesic lengths along the ways.
+	/// The segment's `class` is its `highway` type and its `surface` the tag's, `oneway` (and roundabouts and motorways) make it directed - against the way for `oneway=-1` - and `sidewalk` (`both`, `left`, `right`, or `sidewalk:left`/`sidewalk:right`) tells its sidewalks, `parking:left`/`parking:right` (or `parking:lane:*`, `parking:both`) its parking lanes.
+	/// The `bridge` flag is set unless `bridge=no`, and the way's id (`osm_way`) and tags are kept as the segment's properties.
+	/// Ways with nodes missing from the extract are skipped.
+	pub fn roads(&self, highways: &[&str]) -> RoadGraph {
+		let ways: Vec<&Way> = self.ways.iter().filter(|w| w.tags.get("highway").is_some_and(|h| highways.contains(&h.as_str())) && w.tags.get("area").is_none_or(|a| a != "yes")).filter(|w| {
+			let missing = w.nodes.iter().any(|n| !self.nodes.contains_key(n));
+			if missing {
+				log::warn!("Skipping way {}, with nodes missing from the extract", w.id);
+			}
+			!missing && w.nodes.len() >= 2
+		}).collect();
+		let mut uses: HashMap<i64, usize> = HashMap::new();
+		for w in &ways {
+			for n in &w.nodes {
+				*uses.entry(*n).or_default() += 1;
+			}
+		}
+		let mut nodes: IndexMap<i64, Node> = IndexMap::new();
+		let mut node = |n: i64| -> NodeId {
+			let coordinates = self.nodes[&n];
+			nodes.entry(n).or_insert_with(|| Node { id: NodeId::Int(n as u64), coordinates, elevation: None }).id.clone()
+		};
+		let mut roads = Vec::new();
+		for w in ways {
+			let tag = |k: &str| w.tags.get(k).map(String::as_str);
+			let yes = |v: Option<&str>| matches!(v, Some("yes" | "true" | "1"));
+			let reverse = tag("oneway") == Some("-1");
+			let directed = reverse || yes(tag("oneway")) || (tag("oneway").is_none() && (tag("junction") == Some("roundabout") || tag("highway") == Some("motorway")));
+			let side = |side: &str| {
+				let sidewalk = tag("sidewalk").is_some_and(|s| s == "both" || s == side) || tag("sidewalk:both").is_some_and(|s| s == "yes") || tag(&format!("sidewalk:{}", side)).is_some_and(|s| s == "yes");
+				let parking = [format!("parking:{}", side), format!("parking:lane:{}", side), "parking:both".to_string(), "parking:lane:both".to_string()].iter().any(|k| tag(k).is_some_and(|p| !matches!(p, "no" | "none" | "separate" | "no_parking" | "no_stopping")));
+				(sidewalk, parking)
+			};
+			let ((sl, pl), (sr, pr)) = (side("left"), side("right"));
+			let mut properties: serde_json::Map<String, serde_json::Value> = w.tags.iter().map(|(k, v)| (k.clone(), v.clone().into())).collect();
+			properties.insert("osm_way".to_string(), w.id.into());
+			// where the way is split
+			let mut splits: Vec<usize> = (0..w.nodes.len()).filter(|i| *i == 0 || *i == w.nodes.len() - 1 || uses[&w.nodes[*i]] > 1).collect();
+			if w.nodes.first() == w.nodes.last() && splits.len() == 2 && w.nodes.len() > 2 {
+				splits.insert(1, w.nodes.len() / 2);
+			}
+			for (i, j) in splits.iter().zip(splits.iter().skip(1)) {
+				let run = &w.nodes[*i..=*j];
+				let distance = run.iter().zip(run.iter().skip(1)).map(|(u, v)| validate::geodesic(self.nodes[u], self.nodes[v])).sum::<f64>();
+				let (first, last) = (node(run[0]), node(run[run.len() - 1]));
+				let (p1, p2, sidewalks, parking) = if reverse { (last, first, (sr, sl), (pr, pl)) } else { (first, last, (sl, sr), (pl, pr)) };
+				roads.push(RoadSegment {
+					p1,
+					p2,
+					discriminator: None,
+					directed,
+					distance: n64(distance),
+					sidewalks,
+					parking,
+					surface: tag("surface").map(str::to_string),
+					class: tag("highway").map(str::to_string),
+					bridge: tag("bridge").is_some_and(|b| b != "no"),
+					shaded: false,
+					priority: None,
+					windows: vec![],
+					crossing: None,
+					properties: properties.clone(),
+				});
+			}
+		}
+		RoadGraph {
+			roads,
+			nodes: RoadGraphNodes { nodes: nodes.into_values().collect() },
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// A cross of a two-way residential street and a one-way (against the way) primary, and a footway
+	fn extract() -> Extract {
+		Extract::from_overpass(r#"{"elements": [
+			{"type": "node", "id": 1, "lat": 45.5, "lon": -73.501},
+			{"type": "node", "id": 2, "lat": 45.5, "lon": -73.5},
+			{"type": "node", "id": 3, "lat": 45.5, "lon": -73.499},
+			{"type": "node", "id": 4, "lat": 45.501, "lon": -73.5},
+			{"type": "node", "id": 5, "lat": 45.499, "lon": -73.5},
+			{"type": "way", "id": 10, "nodes": [1, 2, 3], "tags": {"highway": "residential", "sidewalk": "left", "surface": "asphalt"}},
+			{"type": "way", "id": 11, "nodes": [4, 2, 5], "tags": {"highway": "primary", "oneway": "-1", "sidewalk": "left", "bridge": "viaduct"}},
+			{"type": "way", "id": 12, "nodes": [1, 4], "tags": {"highway": "footway"}},
+			{"type": "relation", "id": 20, "members": []}
+		]}"#.as_bytes()).unwrap()
+	}
+
+	#[test]
+	fn test_roads(){
+		let g = extract().roads(&HIGHWAYS);
+		assert_eq!(g.nodes.nodes.len(), 5);
+		let segments: Vec<_> = g.roads.iter().map(|e| (e.p1.clone(), e.p2.clone(), e.directed, e.sidewalks)).collect();
+		let id = |n: u64| NodeId::Int(n);
+		assert_eq!(segments, vec![
+			(id(1), id(2), false, (true, false)),
+			(id(2), id(3), false, (true, false)),
+			// against the way, sides swapped
+			(id(2), id(4), true, (false, true)),
+			(id(5), id(2), true, (false, true)),
+		]);
+		assert!((g.roads[0].distance.raw() - 78.0).abs() < 1.0);
+		assert_eq!((g.roads[0].surface.as_deref(), g.roads[2].class.as_deref(), g.roads[2].bridge), (Some("asphalt"), Some("primary"), true));
+		assert_eq!(g.roads[3].properties.get("osm_way"), Some(&serde_json::Value::from(11)));
+		// closed ways are split halfway round
+		let mut e = extract();
+		e.ways = vec![Way { id: 13, nodes: vec![1, 2, 4, 1], tags: vec![("highway".to_string(), "service".to_string())].into_iter().collect() }];
+		assert_eq!(e.roads(&HIGHWAYS).roads.iter().map(|e| (e.p1.clone(), e.p2.clone())).collect::<Vec<_>>(), vec![(id(1), id(4)), (id(4), id(1))]);
+	}
+
+	#[test]
+	fn test_filter(){
+		let tags = |kvs: &[(&str, &str)]| kvs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+		let e = Extract {
+			nodes: vec![(1, (-73.501, 45.5)), (2, (-73.5, 45.5)), (3, (-73.499, 45.5))].into_iter().collect(),
+			ways: vec![
+				Way { id: 10, nodes: vec![1, 2], tags: tags(&[("highway", "residential")]) },
+				// a pedestrian square, a node missing from the extract, and a single node way
+				Way { id: 11, nodes: vec![2, 3], tags: tags(&[("highway", "residential"), ("area", "yes")]) },
+				Way { id: 12, nodes: vec![2, 4], tags: tags(&[("highway", "residential")]) },
+				Way { id: 13, nodes: vec![3], tags: tags(&[("highway", "residential")]) },
+				Way { id: 14, nodes: vec![2, 3], tags: tags(&[("highway", "service")]) },
+			],
+		};
+		let ways = |g: RoadGraph| g.roads.iter().map(|e| e.properties["osm_way"].as_i64().unwrap()).collect::<Vec<_>>();
+		assert_eq!(ways(e.roads(&HIGHWAYS)), vec![10, 14]);
+		assert_eq!(ways(e.roads(&["service"])), vec![14]);
+		// 2 isn't shared with another imported way, so 10 isn't split there
+		assert_eq!(e.roads(&["residential"]).nodes.nodes.len(), 2);
+	}
+}
@@ -0,0 +1,152 @@
+//! Road graph patches
+//!
+//! Road networks change a little week to week - a new cul-de-sac, a street made one way. Rather than regenerating the whole road graph,
+//! a patch lists the changes, applied in order.
+
+use crate::*;
+use data::*;
+
+use std::collections::BTreeSet;
+use serde::*;
+
+/// A single road graph change
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag="op", rename_all="snake_case")]
+pub enum Change {
+	/// A new node
+	AddNode(Node),
+	/// Removes a node, and the segments touching it
+	RemoveNode { id: NodeId },
+	/// Moves a node, or sets its elevation
+	ModifyNode {
+		id: NodeId,
+		#[serde(default)]
+		coordinates: Option<(f64, f64)>,
+		#[serde(default)]
+		elevation: Option<f64>,
+	},
+	/// A new segment
+	AddSegment(RoadSegment),
+	/// Removes the segments between 2 nodes (either way) with the discriminator
+	RemoveSegment {
+		p1: NodeId,
+		p2: NodeId,
+		#[serde(default)]
+		discriminator: Option<NodeId>,
+	},
+	/// Sets fields of the segments between 2 nodes (either way) with the discriminator, e.g. `{"directed": true}`
+	ModifySegment {
+		p1: NodeId,
+		p2: NodeId,
+		#[serde(default)]
+		discriminator: Option<NodeId>,
+		set: serde_json::Map<String, serde_json::Value>,
+	},
+}
+
+/// A road graph patch - changes applied in order
+pub type Patch = Vec<Change>;
+
+/// Whether a segment is between 2 nodes (either way) with the discriminator
+fn between(e: &RoadSegment, p1: &NodeId, p2: &NodeId, discriminator: &Option<NodeId>) -> bool {
+	((&e.p1 == p1 && &e.p2 == p2) || (&e.p1 == p2 && &e.p2 == p1)) && &e.discriminator == discriminator
+}
+
+impl RoadGraph {
+	/// Applies a patch, all of it or (on error) none of it
+	///
+	/// Returns: the nodes affected - changed, or ends of segments changed - for whatever was derived from them to be recomputed
+	pub fn apply(&mut self, patch: &[Change]) -> Result<BTreeSet<NodeId>, Error> {
+		let mut g = self.clone();
+		let mut affected = BTreeSet::new();
+		for change in patch {
+			match change {
+				Change::AddNode(n) => {
+					if g.nodes.nodes.iter().any(|m| m.id == n.id) {
+						return Err(Error::node(&n.id, "already in the road graph"));
+					}
+					g.nodes.nodes.push(n.clone());
+					affected.insert(n.id.clone());
+				},
+				Change::RemoveNode { id } => {
+					let before = g.nodes.nodes.len();
+					g.nodes.nodes.retain(|n| &n.id != id);
+					if g.nodes.nodes.len() == before {
+						return Err(Error::node(id, "not in the road graph"));
+					}
+					g.roads.retain(|e| if [Some(&e.p1), Some(&e.p2), e.discriminator.as_ref()].contains(&Some(id)) {
+						affected.extend([e.p1.clone(), e.p2.clone()]);
+						false
+					} else {
+						true
+					});
+					affected.insert(id.clone());
+				},
+				Change::ModifyNode { id, coordinates, elevation } => {
+					let n = g.nodes.nodes.iter_mut().find(|n| &n.id == id).ok_or_else(|| format!("Node {} not in the road graph", id))?;
+					if let Some(c) = coordinates {
+						n.coordinates = *c;
+					}
+					if elevation.is_some() {
+						n.elevation = *elevation;
+					}
+					affected.insert(id.clone());
+				},
+				Change::AddSegment(e) => {
+					g.roads.push(e.clone());
+					affected.extend([e.p1.clone(), e.p2.clone()]);
+				},
+				Change::RemoveSegment { p1, p2, discriminator } => {
+					let before = g.roads.len();
+					g.roads.retain(|e| !between(e, p1, p2, discriminator));
+					if g.roads.len() == before {
+						return Err(Error::segment(p1, p2, discriminator, "not in the road graph"));
+					}
+					affected.extend([p1.clone(), p2.clone()]);
+				},
+				Change::ModifySegment { p1, p2, discriminator, set } => {
+					let mut found = false;
+					for e in g.roads.iter_mut().filter(|e| between(e, p1, p2, discriminator)) {
+						let mut v = serde_json::to_value(&*e).map_err(|e| e.to_string())?;
+						v.as_object_mut().unwrap().extend(set.clone());
+						*e = serde_json::from_value(v).map_err(|err| format!("Invalid change of segment {}<->{}: {}", p1, p2, err))?;
+						affected.extend([e.p1.clone(), e.p2.clone()]);
+						found = true;
+					}
+					if !found {
+						return Err(Error::segment(p1, p2, discriminator, "not in the road graph"));
+					}
+				},
+			}
+		}
+		g.check()?;
+		*self = g;
+		Ok(affected)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_apply(){
+		let mut g: RoadGraph = serde_json::from_str(r#"{"nodes": [{"id": "a", "coordinates": [0, 0]}, {"id": "b", "coordinates": [0, 1]}, {"id": "c", "coordinates": [1, 1]}],
+			"roads": [{"p1": "a", "p2": "b", "discriminator": null, "directed": false, "distance": 10}, {"p1": "b", "p2": "c", "discriminator": null, "directed": false, "distance": 10}]}"#).unwrap();
+		let patch: Patch = serde_json::from_str(r#"[
+			{"op": "add_node", "id": "d", "coordinates": [1, 0]},
+			{"op": "add_segment", "p1": "c", "p2": "d", "discriminator": null, "directed": false, "distance": 12},
+			{"op": "modify_segment", "p1": "b", "p2": "a", "set": {"directed": true, "surface": "gravel"}},
+			{"op": "remove_node", "id": "c"}
+		]"#).unwrap();
+		let affected = g.apply(&patch).unwrap();
+		assert_eq!(affected.iter().map(|n| n.name().unwrap()).collect::<Vec<_>>(), vec!["a", "b", "c", "d"]);
+		assert_eq!(g.roads.len(), 1);
+		assert!(g.roads[0].directed);
+		assert_eq!(g.nodes.nodes.len(), 3);
+		let before = g.content_hash();
+		assert!(g.apply(&[Change::AddSegment(RoadSegment { p1: "a".into(), p2: "x".into(), ..g.roads[0].clone() })]).is_err());
+		assert!(g.apply(&[Change::RemoveSegment { p1: "a".into(), p2: "d".into(), discriminator: None }]).is_err());
+		assert_eq!(g.content_hash(), before);
+	}
+}
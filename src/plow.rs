@@ -3,24 +3,384 @@
 use crate::*;
 use graph::*;
 use graph::adapt::*;
-use data::Distance;
 use meta::*;
 
-use std::{collections::HashSet, convert::TryFrom};
+use std::{convert::TryFrom, hash::Hash, time::{Duration, Instant}};
+use indexmap::IndexMap;
 use itertools::Itertools;
-use rand::{Rng, prelude::SliceRandom};
+use rand::{Rng, SeedableRng, prelude::SliceRandom, rngs::StdRng};
+use rayon::prelude::*;
+use std::sync::{Mutex, mpsc};
 
 type SID = u64;
+/// Light node id generator of the solver graphs
+type IdGen = fn(&NodeId, SID) -> (SID, SID);
 type Coords = (f64, f64);
 
 trait Positioned {
 	fn pos(&self) -> Coords;
 }
 
-trait Weighted {
+/// An edge with a cost to traverse
+pub trait Weighted {
 	fn weight(&self) -> N64;
+	/// Distance, the weight being a travel time with speeds
+	fn distance(&self) -> N64 {
+		self.weight()
+	}
+	/// Steepness (rise over run, either way), slowing clearing down
+	fn grade(&self) -> N64 {
+		n64(0.0)
+	}
+	/// Whether the edge is a bridge, and whether it's shaded - clearing being boosted near freezing
+	fn exposure(&self) -> (bool, bool) {
+		(false, false)
+	}
+	/// Penalty for clearing the edge going `p1`→`p2` (`forward`) or the other way
+	fn crossing(&self, _forward: bool) -> N64 {
+		n64(0.0)
+	}
+	/// Priority level, clearing having a deadline
+	fn priority(&self) -> Option<u32> {
+		None
+	}
+}
+
+trait Discriminated {
+	fn discriminator(&self) -> Option<SID>;
+}
+
+/// Distinguishes parallel edges (see [`data::RoadGraph::parallel_indices`])
+trait Parallel {
+	fn parallel(&self) -> usize;
+}
+
+/// Costs of paths, the way the solver sees them
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+pub struct PathCosts {
+	/// Cost of every path
+	pub costs: Vec<N64>,
+	/// Risk-adjusted cost of every path (see [`Parameters::risk_aversion`]) - the same as the cost, unless the snow forecast is uncertain
+	pub adjusted: Vec<N64>,
+	/// Sum of path costs
+	pub total: N64,
+	/// Risk-adjusted cost of the most expensive path
+	pub max: N64,
+	/// The annealing objective - `weight_total * total + weight_max * max`
+	pub value: N64,
+}
+
+impl PathCosts {
+	fn new(costs: Vec<(N64, N64)>, params: &Parameters) -> Self {
+		let (costs, adjusted): (Vec<_>, Vec<_>) = costs.into_iter().unzip();
+		let total = costs.iter().cloned().sum();
+		let max = adjusted.iter().cloned().max().unwrap_or_else(|| n64(0.0));
+		Self { value: params.weight_total*total + params.weight_max*max, costs, adjusted, total, max }
+	}
+}
+
+/// Snow on an edge
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Snow {
+	/// Depth - the expected one, for an uncertain forecast
+	pub depth: N64,
+	/// Standard deviation of the depth
+	pub deviation: N64,
+}
+
+impl Snow {
+	fn certain(depth: N64) -> Self {
+		Self { depth, deviation: n64(0.0) }
+	}
+}
+
+/// Snowy edges, with the snow on them
+pub type Snowy<'a, E> = HashMap<&'a E, Snow>;
+
+/// A disconnected region of the graph: indices of the vehicles starting in it, those vehicles, and its snowy edges
+type Region<'a, E> = (Vec<usize>, Vec<Vehicle<'a, E>>, Snowy<'a, E>);
+
+/// Collects snowy edges from located snow, the deepest report of an edge winning
+fn snow_depths<'a, E: Hash + Eq>(located: impl Iterator<Item = (&'a E, Snow)>) -> Snowy<'a, E> {
+	let mut snowy = HashMap::default();
+	for (e, snow) in located.filter(|(_, snow)| snow.depth > 0.0) {
+		let s = snowy.entry(e).or_insert(snow);
+		if snow.depth > s.depth {
+			*s = snow;
+		}
+	}
+	snowy
+}
+
+/// Clearing slowdown of an edge, as per the parameters - the steeper the more
+fn clearing_factor<E: Weighted>(e: &E, depth: N64, params: &Parameters) -> N64 {
+	params.slowdown.factor(depth, params.temperature) * (params.grade_multiplier * e.grade() + 1.0)
+}
+
+/// De-icing boost of an edge (see [`Deicing::boost`])
+fn boost<E: Weighted>(e: &E, params: &Parameters) -> N64 {
+	let (bridge, shaded) = e.exposure();
+	params.deicing.as_ref().map_or_else(|| n64(0.0), |d| d.boost(params.temperature, bridge, shaded))
+}
+
+/// Lateness penalty of an edge cleared at `cleared` (see [`Parameters::deadlines`])
+fn lateness<E: Weighted>(e: &E, cleared: N64, params: &Parameters) -> N64 {
+	params.deadlines.as_ref().map_or_else(|| n64(0.0), |d| d.penalty(e.priority(), cleared))
+}
+
+/// Cost of a path - its length, with edges being cleared (of the snow depth) slowed down as per the parameters
+///
+/// Boosted edges (see [`Parameters::deicing`]) add their boost times the cost of the path up to clearing them, for them to be cleared early.
+/// Priority edges cleared past their deadline (see [`Parameters::deadlines`]) add their lateness penalty, for them to be cleared first.
+///
+/// Returns: the expected cost, and the risk-adjusted one (see [`Parameters::risk_aversion`]).
+/// Forecast errors of the edges are taken as independent, each deviating the cost by what clearing a standard deviation deeper would.
+fn path_cost<E: Weighted>(path: &[&E], params: &Parameters, clearing: impl Fn(&E) -> Option<Snow>) -> (N64, N64) {
+	let (cost, variance, delay) = path.iter().fold((n64(0.0), n64(0.0), n64(0.0)), |(cost, variance, delay), e| match clearing(e) {
+		None => (cost + e.weight(), variance, delay),
+		Some(snow) => {
+			let f = clearing_factor(*e, snow.depth, params);
+			let d = e.weight() * (clearing_factor(*e, snow.depth + snow.deviation, params) - f);
+			let cost = cost + e.weight() * f;
+			(cost, variance + d * d, delay + boost(*e, params) * cost + lateness(*e, cost, params))
+		}
+	});
+	(cost + delay, cost + delay + params.risk_aversion * variance.sqrt())
+}
+
+/// Crossing penalties of a path from `start` (see [`Weighted::crossing`]), for the edges being cleared
+fn crossings<E: graph::Edge<SID> + Weighted>(path: &[&E], start: SID, clearing: impl Fn(&E) -> bool) -> N64 {
+	let mut u = start;
+	path.iter().fold(n64(0.0), |penalty, e| {
+		let forward = e.p1() == u;
+		u = if forward { e.p2() } else { e.p1() };
+		if clearing(e) { penalty + e.crossing(forward) } else { penalty }
+	})
+}
+
+/// Time a vehicle takes over an edge, as its schedule estimates it (see [`crate::timeline::schedule`]) - at its own speed if it has one, slowed down clearing snow
+fn pace<E: Weighted>(e: &E, speed: Option<N64>, clearing: Option<Snow>, params: &Parameters) -> N64 {
+	let slowdown = clearing.map_or_else(|| n64(1.0), |s| params.slowdown.factor(s.depth, params.temperature));
+	speed.map_or_else(|| e.weight(), |s| e.distance() / s) * slowdown
+}
+
+/// A depot trip - before which edge of a path it's made, from which node, to which depot, and its cost there and back (with any wait for the depot to open)
+type Trip = (usize, SID, SID, N64);
+
+/// Depot trips of a vehicle's path, the load running out clearing the edges being cleared - on their first traversal, driving them again being free
+///
+/// Only depots open (see [`data::Depot::hours`]) by the vehicle's estimated arrival there, as per its schedule, are refilled at - waiting for one to open adding to the trip.
+///
+/// Returns: the trips, and before which edge the vehicle runs out with no depot opening again, if it does
+fn refills<E: graph::Edge<SID> + Weighted + Hash + Eq>(path: &[&E], start: SID, refill: &Refill<E>, params: &Parameters, clearing: impl Fn(&E) -> Option<Snow>) -> (Vec<Trip>, Option<usize>) {
+	let mut trips = Vec::new();
+	let mut dun = HashSet::default();
+	let drive = |from: SID, to: SID| refill.depots.path(from, to).into_iter().flatten().map(|e| pace(e, refill.speed, None, params)).sum::<N64>();
+	let (mut u, mut load, mut eta) = (start, refill.capacity, n64(0.0));
+	for (i, e) in path.iter().enumerate() {
+		let snow = clearing(e).filter(|_| dun.insert(*e));
+		if snow.is_some() {
+			let need = params.consumption * e.weight();
+			if need > load {
+				let trip = refill.stops.iter().filter_map(|(d, depot)| {
+					let cost = refill.depots.cost(u, *d)? + refill.depots.cost(*d, u)?;
+					let there = eta + drive(u, *d);
+					let wait = n64(depot.opens(there.raw())?) - there;
+					Some((*d, cost + wait, there - eta + wait + drive(*d, u)))
+				}).min_by_key(|(_, c, _)| *c);
+				match trip {
+					Some((d, cost, time)) => {
+						trips.push((i, u, d, cost));
+						load = refill.capacity;
+						eta += time;
+					},
+					None => return (trips, Some(i)),
+				}
+			}
+			load = (load - need).max(n64(0.0));
+		}
+		eta += pace(*e, refill.speed, snow, params);
+		u = e.other(u);
+	}
+	(trips, None)
+}
+
+/// Penalties of a vehicle's path, for the edges being cleared - crossings, and the deadheading of depot trips
+///
+/// Running out with no depot opening again costs the rest of the path once more, for the solver to plan the route around it.
+fn penalties<E: graph::Edge<SID> + Weighted + Hash + Eq>(path: &[&E], vehicle: &Vehicle<E>, params: &Parameters, clearing: impl Fn(&E) -> Option<Snow>) -> N64 {
+	let trips = vehicle.refill.as_ref().map_or(n64(0.0), |r| {
+		let (trips, stranded) = refills(path, vehicle.sp, r, params, &clearing);
+		trips.into_iter().map(|(.., c)| c).sum::<N64>() + stranded.map_or_else(|| n64(0.0), |i| path[i..].iter().map(|e| e.weight()).sum())
+	});
+	crossings(path, vehicle.sp, |e| clearing(e).is_some()) + trips
+}
+
+/// A vehicle's path, with its depot trips (see [`refills`]) along shortest paths
+///
+/// Returns: the path, or `None` if the vehicle runs out with no depot opening again
+fn with_refills<'a, E: graph::Edge<SID> + Weighted + Hash + Eq>(path: &[&'a E], vehicle: &Vehicle<'a, E>, params: &Parameters, clearing: impl Fn(&E) -> Option<Snow>) -> Option<Vec<&'a E>> {
+	let refill = match &vehicle.refill {
+		Some(r) => r,
+		None => return Some(path.to_vec()),
+	};
+	let (trips, stranded) = refills(path, vehicle.sp, refill, params, clearing);
+	if stranded.is_some() {
+		return None;
+	}
+	let mut trips = trips.into_iter().peekable();
+	let mut route = Vec::with_capacity(path.len());
+	for (i, e) in path.iter().enumerate() {
+		while let Some((_, u, d, _)) = trips.next_if(|(j, ..)| *j == i) {
+			route.extend(refill.depots.path(u, d).into_iter().flatten());
+			route.extend(refill.depots.path(d, u).into_iter().flatten());
+		}
+		route.push(*e);
+	}
+	Some(route)
+}
+
+/// Evaluates paths with the same cost function the solver optimizes
+///
+/// Every snowy edge is cleared by the first path (in order) traversing it, i.e. is allocated as the solver would after accepting the paths.
+/// Under the parameters' [`Clearing::OnlyAllocated`], the paths after it drive it still snowy, their redundant coverage (see [`Parameters::redundancy`]) adding to the value as it does solving.
+pub fn evaluate_paths<E: Weighted + Hash + Eq>(paths: &[Vec<&E>], snowy: &Snowy<E>, params: &Parameters) -> PathCosts {
+	let mut dun: HashSet<&E> = HashSet::default();
+	let mut costs = PathCosts::new(paths.iter().map(|path| {
+		let cost = path_cost(path, params, |e| snowy.get(e).copied().filter(|_| !dun.contains(e)));
+		dun.extend(path.iter().filter(|e| snowy.contains_key(*e)));
+		cost
+	}).collect(), params);
+	costs.value += params.redundancy * redundant(paths, snowy, params, |_, _| true);
+	costs
+}
+
+/// Redundant coverage of paths - the cost of every snowy edge traversed by more than one vehicle able to clear it, once per vehicle beyond the first
+///
+/// Only happens under [`Clearing::OnlyAllocated`], vehicles not allocated an edge driving it still snowy; under [`Clearing::All`] the first one clears it.
+fn redundant<E: Weighted + Hash + Eq>(paths: &[Vec<&E>], snowy: &Snowy<E>, params: &Parameters, clears: impl Fn(usize, &E) -> bool) -> N64 {
+	if params.clearing == Clearing::All {
+		return n64(0.0);
+	}
+	let mut covered: HashMap<&E, (usize, usize)> = HashMap::default();
+	for (i, path) in paths.iter().enumerate() {
+		for e in path.iter().filter(|e| snowy.contains_key(**e) && clears(i, e)) {
+			let (last, n) = covered.entry(*e).or_insert((i, 0));
+			if *n == 0 || *last != i {
+				*last = i;
+				*n += 1;
+			}
+		}
+	}
+	covered.into_iter().map(|(e, (_, n))| e.weight() * n64((n - 1) as f64)).sum()
+}
+
+/// A vehicle, as seen by the solver
+#[derive(Clone)]
+struct Vehicle<'a, E> {
+	/// starting location, on the graph
+	sp: SID,
+	/// starting location, geographically
+	loc: Coords,
+	/// where the route should end (see [`graph::heuristics::solve_pwrp`])
+	end: Option<HashSet<SID>>,
+	/// edges the vehicle can't traverse
+	excluded: HashSet<&'a E>,
+	/// edges the vehicle can traverse, but not clear (e.g. of an incompatible surface)
+	incompatible: HashSet<&'a E>,
+	/// load, and where it's refilled - `None` if it never runs out
+	refill: Option<Refill<'a, E>>,
+}
+
+/// A vehicle's load (see [`Parameters::consumption`]), and the depots it refills at
+#[derive(Clone)]
+struct Refill<'a, E> {
+	capacity: N64,
+	/// own driving speed, the schedule being estimated at (see [`data::VehicleSpec::speed`])
+	speed: Option<N64>,
+	/// shortest paths from and to the depots
+	depots: &'a CostMatrix<'a, SID, E, N64>,
+	/// the located depots serving the vehicle's resource
+	stops: Vec<(SID, &'a data::Depot)>,
+}
+
+impl<E: std::hash::Hash + Eq> Vehicle<'_, E> {
+	/// Whether the vehicle can clear an edge
+	fn clears(&self, e: &E) -> bool {
+		!self.excluded.contains(e) && !self.incompatible.contains(e)
+	}
+}
+
+/// Chance of an edge moving to a random vehicle, in the initial allocation of restarts
+const PERTURBATION: f64 = 0.2;
+
+/// Summary of a single annealing run
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+pub struct RestartSummary {
+	/// Random generator seed of the run
+	pub seed: u64,
+	pub iterations: u64,
+	/// Iterations until the run's plan was last improved
+	#[serde(default)]
+	pub converged: u64,
+	/// Annealing objective of the run's plan (see [`PathCosts::value`])
+	pub value: N64,
+	/// Cost of the most expensive path of the run's plan
+	pub max: N64,
+	/// Redundant coverage of the run's plan - the cost (length, without speeds) of snowy segments covered by more than one vehicle, once per vehicle beyond the first
+	#[serde(default)]
+	pub redundant: N64,
+	/// Lookups of paths back to where solves strayed that hit the cache, and that missed it
+	#[serde(default)]
+	pub cache_hits: u64,
+	#[serde(default)]
+	pub cache_misses: u64,
+}
+
+/// Solver statistics
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct Stats {
+	/// Every annealing run, in order
+	pub restarts: Vec<RestartSummary>,
+	/// Index of the run whose plan was kept
+	pub best: usize,
+	/// Content hash of the road graph solved
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub graph: Option<String>,
+	/// What made the instance hard
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub features: Option<Features>,
+}
+
+/// Features of an instance telling how hard it is to solve, before solving it
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+pub struct Features {
+	/// Segments to clear
+	pub required: usize,
+	/// Nodes with an odd number of segments to clear - every pair of them forcing some deadheading
+	pub odd: usize,
+	/// Nodes with segments to clear
+	pub nodes: usize,
+	pub vehicles: usize,
+	/// Length to clear of the biggest initial allocation cluster, relative to the average (1 being balanced)
+	pub imbalance: f64,
+}
+
+/// Anytime mode settings
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Budget {
+	/// Wall-clock budget, after which the best plan so far is returned.
+	/// With a budget, annealing carries on past the main iterations until the budget is spent.
+	pub time: Option<Duration>,
+	/// Elapsed times at which the best plan so far is reported
+	pub checkpoints: Vec<Duration>,
 }
 
+/// Compile-time check that a type can be shared across threads by reference
+///
+/// The solver graphs (and everything in them) must be, for parallel solving.
+const fn assert_send_sync<T: ?Sized + Send + Sync>() {}
+
 /// Solver with a graph attached.
 ///
 /// For ~~no~~ a number of reasons, graph node id is forced to `u64`.
@@ -31,12 +391,15 @@ where
 	Gen: Fn(&N::Id, SID) -> (SID, SID),
 {
 	graph: GraphAdapter<SID, N, E, SID, Gen>,
+	/// how far apart coordinates are, locating and allocating
+	metric: data::Metric,
 }
 /// Construct new generic plow solver, with incremental node ids generation
 macro_rules! plow_solver {
 	() => {
 		PlowSolver {
 			graph: GraphAdapter::new(0, |_, id| (id, id+1)),
+			metric: Default::default(),
 		}
 	}
 }
@@ -50,67 +413,305 @@ where
 	/// Allocates all snowy edges to some vehicle.
 	///
 	/// Uses positions of vehicles as gravicenters of allocation clusters.
-	fn initial_allocation<'a>(&'a self, locs: &Vec<Coords>, snowy: impl Iterator<Item = &'a E>) -> Vec<HashSet<&'a E>> {
-		let closest = |c: &(f64, f64)| (0..locs.len()).zip(locs.iter()).min_by_key(|(_, c2)| n64(c.distance(*c2))).unwrap().0;
-		let mut allocations: Vec<_> = (0..locs.len()).map(|_| HashSet::new()).collect();
+	/// Edges a vehicle can't clear are allocated to the closest vehicle that can.
+	/// Vehicles starting at the same node split their cluster by direction (of the edge from the start).
+	fn initial_allocation<'a>(&'a self, vehicles: &[Vehicle<'a, E>], snowy: impl Iterator<Item = &'a E>) -> Vec<HashSet<&'a E>> {
+		let closest = |e: &E, c: &(f64, f64)| vehicles.iter().enumerate().filter(|(_, v)| v.clears(e)).min_by_key(|(_, v)| n64(self.metric.distance(*c, v.loc))).map(|(i, _)| i);
+		let sector = |e: &E, mid: &(f64, f64), i: usize| {
+			let group: Vec<_> = vehicles.iter().enumerate().filter(|(_, v)| v.sp == vehicles[i].sp && v.clears(e)).map(|(j, _)| j).collect();
+			let bearing = (mid.1 - vehicles[i].loc.1).atan2(mid.0 - vehicles[i].loc.0);
+			let k = ((bearing + std::f64::consts::PI) / std::f64::consts::TAU * group.len() as f64) as usize;
+			group[k.min(group.len() - 1)]
+		};
+		let mut allocations: Vec<_> = vehicles.iter().map(|_| HashSet::default()).collect();
 		for e in snowy {
-			let lv1 = closest(&self.graph.nid2node(e.p1()).unwrap().pos());
-			let lv2 = closest(&self.graph.nid2node(e.p2()).unwrap().pos());
-			let lv = if lv1 == lv2 || allocations[lv2].len() > allocations[lv1].len() { lv1 } else { lv2 };
-			allocations[lv].insert(e);
+			let (c1, c2) = (self.graph.nid2node(e.p1()).unwrap().pos(), self.graph.nid2node(e.p2()).unwrap().pos());
+			let mid = ((c1.0 + c2.0) / 2.0, (c1.1 + c2.1) / 2.0);
+			let lv1 = closest(e, &c1).map(|i| sector(e, &mid, i));
+			let lv2 = closest(e, &c2).map(|i| sector(e, &mid, i));
+			if let (Some(lv1), Some(lv2)) = (lv1, lv2) {
+				let lv = if lv1 == lv2 || allocations[lv2].len() > allocations[lv1].len() { lv1 } else { lv2 };
+				allocations[lv].insert(e);
+			}
 		}
 		allocations
 	}
+	/// Features of the instance (see [`Features`])
+	fn features<'a>(&'a self, vehicles: &[Vehicle<'a, E>], snowy: &Snowy<'a, E>) -> Features {
+		let mut degrees: HashMap<SID, usize> = HashMap::default();
+		for e in snowy.keys() {
+			*degrees.entry(e.p1()).or_default() += 1;
+			*degrees.entry(e.p2()).or_default() += 1;
+		}
+		let lengths: Vec<N64> = self.initial_allocation(vehicles, snowy.keys().copied()).iter().map(|a| a.iter().map(|e| e.weight()).sum()).collect();
+		let total: N64 = lengths.iter().copied().sum();
+		let max = lengths.iter().copied().max().unwrap_or_else(|| n64(0.0));
+		Features {
+			required: snowy.len(),
+			odd: degrees.values().filter(|d| *d % 2 == 1).count(),
+			nodes: degrees.len(),
+			vehicles: vehicles.len(),
+			imbalance: if total > 0.0 { (max * n64(vehicles.len() as f64) / total).raw() } else { 1.0 },
+		}
+	}
+	/// Checks that every snowy edge can be cleared by some vehicle
+	fn check_exclusions<'a>(&'a self, vehicles: &[Vehicle<'a, E>], snowy: &Snowy<'a, E>) -> Result<(), Error>
+	where
+		N: IdentifiableNode<Id = NodeId>,
+	{
+		let unassignable: Vec<_> = snowy.keys().filter(|e| !vehicles.iter().any(|v| v.clears(e))).collect();
+		if unassignable.is_empty() {
+			Ok(())
+		} else {
+			Err(Error::Unreachable { segments: self.segment_ids(unassignable.into_iter().copied()), message: "can't be cleared by any vehicle".to_string() })
+		}
+	}
+	/// Node ids of the ends of edges, for errors
+	fn segment_ids<'a>(&self, edges: impl IntoIterator<Item = &'a E>) -> Vec<(NodeId, NodeId)>
+	where
+		E: 'a,
+		N: IdentifiableNode<Id = NodeId>,
+	{
+		edges.into_iter().map(|e| (self.graph.nid2id(e.p1()).unwrap().clone(), self.graph.nid2id(e.p2()).unwrap().clone())).collect()
+	}
 	/// updates allocation from solution
-	fn sol_to_alloc<'a>(&'a self, order: impl Iterator<Item = usize>, sols: &Vec<Vec<&'a E>>, allocs: &mut Vec<HashSet<&'a E>>, snowy: impl Fn(&E) -> bool){
+	fn sol_to_alloc<'a>(&'a self, order: impl Iterator<Item = usize>, sols: &[Vec<&'a E>], allocs: &mut [HashSet<&'a E>], clears: impl Fn(usize, &E) -> bool){
 		for i in order {
 			for e in &sols[i] {
-				if snowy(e) {
-					if allocs[i].insert(e) {
-						for a in 0..allocs.len() {
-							if a != i {
-								allocs[a].remove(e);
+				if clears(i, e) && allocs[i].insert(e) {
+					for (a, alloc) in allocs.iter_mut().enumerate() {
+						if a != i {
+							alloc.remove(e);
+						}
+					}
+				}
+			}
+		}
+	}
+	/// Iterative annealing solver, with restarts.
+	///
+	/// Runs `params.annealing.restarts` independent annealing runs - in parallel, on a rayon pool of `params.parallelism` threads - and keeps the best plan.
+	/// With a time budget, the runs that don't fit in parallel split it; threads left over once every run has one solve the vehicles' paths of a run in parallel, and those left over then connect paths to distant isles in batches.
+	///
+	/// Arguments:
+	/// - `DIRESPECT`
+	/// - `vehicles`: the vehicles
+	/// - `snowy`: edges that need to be cleared, with the snow depth
+	/// - `params`: meta parameters
+	/// - `budget`: anytime mode settings
+	/// - `checkpoint`: called with the best paths so far (of any run) at every checkpoint
+	///
+	/// Returns: paths, for each vehicle, and the runs' statistics
+	fn solve<'a, const DIRESPECT: bool>(&'a self, vehicles: &[Vehicle<'a, E>], snowy: &Snowy<'a, E>, params: &Parameters, budget: &Budget, mut checkpoint: impl FnMut(Duration, &[Vec<&'a E>])) -> Result<(Vec<Vec<&'a E>>, Stats), Error>
+	where
+		N: Sync,
+		N: IdentifiableNode<Id = NodeId>,
+		E: std::fmt::Debug + Sync,
+		Gen: Sync,
+	{
+		self.check_exclusions(vehicles, snowy)?;
+		let features = Some(self.features(vehicles, snowy));
+		let start = Instant::now();
+		let restarts = params.annealing.restarts.max(1);
+		let seed = |r: usize| params.annealing.seed.map_or_else(rand::random, |s| s.wrapping_add(r as u64));
+		let parallelism = params.parallelism.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get())).max(1);
+		let workers = parallelism.min(restarts);
+		let threads = parallelism / workers;
+		let time = budget.time.map(|t| t / restarts.div_ceil(workers) as u32);
+		log::info!("Annealing {} restarts on {} threads", restarts, workers);
+		let pool = rayon::ThreadPoolBuilder::new().num_threads(parallelism).build().map_err(|e| e.to_string())?;
+		let best: Mutex<(N64, Vec<Vec<&'a E>>)> = Mutex::new((N64::infinity(), vehicles.iter().map(|_| Vec::new()).collect()));
+		let (done, finished) = mpsc::channel();
+		let mut runs = pool.in_place_scope(|s| {
+			for r in 0..restarts {
+				let (done, best, seed) = (done.clone(), &best, &seed);
+				s.spawn(move |_| {
+					let mut offered = N64::infinity();
+					let run = self.anneal::<DIRESPECT>(vehicles, snowy, params, time.map(|t| Instant::now() + t), seed(r), r > 0, threads, |value, solution| {
+						if value < offered {
+							offered = value;
+							let mut best = best.lock().unwrap();
+							if params.tolerance.lt(value, best.0) {
+								*best = (value, solution.to_vec());
 							}
 						}
+					});
+					if let Ok((_, summary)) = &run {
+						log::info!("Restart {}: value {:.1} after {} iterations", r, summary.value, summary.iterations);
+					}
+					let _ = done.send(run.map(|(solution, summary)| (r, solution, summary)));
+				});
+			}
+			drop(done);
+			let mut runs = Vec::new();
+			for c in budget.checkpoints.iter().sorted() {
+				// wait for the checkpoint, or for all the runs to be over
+				while let Some(left) = c.checked_sub(start.elapsed()) {
+					match finished.recv_timeout(left) {
+						Ok(run) => runs.push(run),
+						Err(mpsc::RecvTimeoutError::Timeout) => {},
+						Err(mpsc::RecvTimeoutError::Disconnected) => break,
 					}
 				}
+				checkpoint(*c, &best.lock().unwrap().1);
+			}
+			runs.extend(finished.iter());
+			runs.into_iter().collect::<Result<Vec<_>, Error>>()
+		})?;
+		runs.sort_by_key(|(r, ..)| *r);
+		let best = runs.iter().position_min_by_key(|(.., summary)| summary.value).unwrap_or(0);
+		let restarts = runs.iter().map(|(.., summary)| summary.clone()).collect();
+		Ok((runs.swap_remove(best).1, Stats { restarts, best, graph: None, features }))
+	}
+	/// Solves every connected region of the graph as a subproblem of its own (see [`Self::solve`]), in parallel.
+	///
+	/// Vehicles can never leave the region they start in, so every region is solved with its vehicles and the snowy edges in it - a graph of a single region is solved as a whole.
+	/// Regions split the parallelism, each having the whole time budget, and checkpoints report the plans of all of them at once.
+	///
+	/// Returns: paths, for each vehicle, and the statistics - run `r` summing up run `r` of every region (values and costs summed, the max being the largest), the plan kept being the best of every region
+	fn solve_regions<'a, const DIRESPECT: bool>(&'a self, vehicles: &[Vehicle<'a, E>], snowy: &Snowy<'a, E>, params: &Parameters, budget: &Budget, mut checkpoint: impl FnMut(Duration, &[Vec<&'a E>])) -> Result<(Vec<Vec<&'a E>>, Stats), Error>
+	where
+		N: Sync,
+		N: IdentifiableNode<Id = NodeId>,
+		E: std::fmt::Debug + Clone + Sync,
+		Gen: Sync,
+	{
+		// weakly - a region where one-way segments lead out of is still one region
+		let regions: Vec<_> = self.graph.graph.connected_components::<false>().into_iter().filter(|c| vehicles.iter().any(|v| c.contains(&v.sp))).collect();
+		if regions.len() < 2 {
+			return self.solve::<DIRESPECT>(vehicles, snowy, params, budget, checkpoint);
+		}
+		let parts: Vec<Region<'a, E>> = regions.iter().map(|c| {
+			let (is, fleet) = vehicles.iter().enumerate().filter(|(_, v)| c.contains(&v.sp)).map(|(i, v)| (i, v.clone())).unzip();
+			(is, fleet, snowy.iter().filter(|(e, _)| c.contains(&e.p1())).map(|(e, s)| (*e, *s)).collect())
+		}).collect();
+		log::info!("Solving {} disconnected regions separately, with {:?} vehicles", parts.len(), parts.iter().map(|(is, ..)| is.len()).collect::<Vec<_>>());
+		let parallelism = params.parallelism.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+		let params = Parameters { parallelism: Some((parallelism / parts.len()).max(1)), ..params.clone() };
+		let pool = rayon::ThreadPoolBuilder::new().num_threads(parts.len()).build().map_err(|e| e.to_string())?;
+		let (reports, reported) = mpsc::channel();
+		let outcomes: Mutex<Vec<(usize, Result<_, Error>)>> = Mutex::new(Vec::new());
+		pool.in_place_scope(|s| {
+			for (k, (_, fleet, snowy)) in parts.iter().enumerate() {
+				let (reports, params, outcomes) = (reports.clone(), &params, &outcomes);
+				s.spawn(move |_| {
+					let outcome = self.solve::<DIRESPECT>(fleet, snowy, params, budget, |_, solution| reports.send((k, solution.to_vec())).unwrap());
+					outcomes.lock().unwrap().push((k, outcome));
+				});
+			}
+			drop(reports);
+			// every region reports every checkpoint, in order - a checkpoint is over once all of them did
+			let checkpoints: Vec<_> = budget.checkpoints.iter().copied().sorted().collect();
+			let mut merged: Vec<(usize, Vec<Vec<&'a E>>)> = checkpoints.iter().map(|_| (0, vehicles.iter().map(|_| Vec::new()).collect())).collect();
+			let (mut next, mut counts) = (0, vec![0; parts.len()]);
+			for (k, solution) in reported {
+				let (count, paths) = &mut merged[counts[k]];
+				counts[k] += 1;
+				*count += 1;
+				for (i, path) in parts[k].0.iter().zip(solution) {
+					paths[*i] = path;
+				}
+				while merged.get(next).is_some_and(|(count, _)| *count == parts.len()) {
+					checkpoint(checkpoints[next], &merged[next].1);
+					next += 1;
+				}
+			}
+		});
+		let mut outcomes = outcomes.into_inner().unwrap();
+		outcomes.sort_by_key(|(k, _)| *k);
+		let outcomes = outcomes.into_iter().map(|(_, outcome)| outcome).collect::<Result<Vec<_>, Error>>()?;
+		let runs = outcomes.iter().map(|(_, stats)| stats.restarts.len()).min().unwrap_or(0);
+		let restarts: Vec<_> = (0..runs).map(|r| {
+			let runs: Vec<_> = outcomes.iter().map(|(_, stats)| &stats.restarts[r]).collect();
+			RestartSummary {
+				seed: runs[0].seed,
+				iterations: runs.iter().map(|s| s.iterations).sum(),
+				converged: runs.iter().map(|s| s.converged).max().unwrap_or(0),
+				value: runs.iter().map(|s| s.value).sum(),
+				max: runs.iter().map(|s| s.max).max().unwrap_or_else(|| n64(0.0)),
+				redundant: runs.iter().map(|s| s.redundant).sum(),
+				cache_hits: runs.iter().map(|s| s.cache_hits).sum(),
+				cache_misses: runs.iter().map(|s| s.cache_misses).sum(),
+			}
+		}).collect();
+		let best = restarts.iter().position_min_by_key(|s| s.value).unwrap_or(0);
+		let mut paths: Vec<Vec<&'a E>> = vehicles.iter().map(|_| Vec::new()).collect();
+		for ((is, ..), (solution, _)) in parts.iter().zip(outcomes) {
+			for (i, path) in is.iter().zip(solution) {
+				paths[*i] = path;
 			}
 		}
+		Ok((paths, Stats { restarts, best, graph: None, features: Some(self.features(vehicles, snowy)) }))
 	}
-	/// Iterative annealing solver.
+	/// A single annealing run.
 	///
 	/// Arguments:
 	/// - `DIRESPECT`
-	/// - `sps`: starting locations, on the graph, of each vehicle
-	/// - `locs`: starting locations, geographically, of each vehicle
-	/// - `snowy`: set of edges that need to be cleared
+	/// - `vehicles`: the vehicles
+	/// - `snowy`: edges that need to be cleared, with the snow depth
 	/// - `params`: meta parameters
+	/// - `deadline`: when to stop, instead of after the main iterations
+	/// - `seed`: random generator seed
+	/// - `perturb`: whether to shake up the initial allocation, for the run to start elsewhere
+	/// - `threads`: threads to solve the vehicles' paths on - only used when clearing `OnlyAllocated`, paths otherwise depending on those solved before
+	/// - `iteration`: called with the best value and paths so far after every iteration
 	///
-	/// Returns: paths, for each vehicle
-	fn solve<'a, const DIRESPECT: bool>(&'a self, sps: &Vec<SID>, locs: &Vec<Coords>, snowy: &HashSet<&'a E>, params: &Parameters) -> Vec<Vec<&'a E>>
+	/// Returns: paths, for each vehicle, and the run's summary - an error if a vehicle can't reach its segments
+	#[allow(clippy::too_many_arguments)]
+	fn anneal<'a, const DIRESPECT: bool>(&'a self, vehicles: &[Vehicle<'a, E>], snowy: &Snowy<'a, E>, params: &Parameters, deadline: Option<Instant>, seed: u64, perturb: bool, threads: usize, mut iteration: impl FnMut(N64, &[Vec<&'a E>])) -> Result<(Vec<Vec<&'a E>>, RestartSummary), Error>
 	where
-		N::Id: std::fmt::Display,
-		E: std::fmt::Debug,
+		N: Sync,
+		N: IdentifiableNode<Id = NodeId>,
+		E: std::fmt::Debug + Sync,
+		Gen: Sync,
 	{
-		let vs = locs.len();
-		let mut alloc = self.initial_allocation(locs, snowy.iter().map(|e| *e));
+		let vs = vehicles.len();
+		let mut rng = StdRng::seed_from_u64(seed);
+		let mut alloc = self.initial_allocation(vehicles, snowy.keys().copied());
+		if perturb {
+			for e in snowy.keys() {
+				let to = rng.gen_range(0..vs);
+				if rng.gen_bool(PERTURBATION) && vehicles[to].clears(e) {
+					for a in alloc.iter_mut() {
+						a.remove(e);
+					}
+					alloc[to].insert(*e);
+				}
+			}
+		}
 		let mut solution: Vec<Vec<&'a E>> = (0..vs).map(|_| Vec::new()).collect();
 		log::debug!("Initialized allocations: {}", alloc.iter().map(|a| a.len()).join("/"));
-		let mut rng = rand::thread_rng();
 		let mut cost_max_best = N64::infinity();
 		let mut value_best = N64::infinity();
+		let mut redundant_best = n64(0.0);
 		let mut temperature: f64 = params.annealing.starting_temperature;
 		let mut ii = 0u64;
 		let mut order: Vec<_> = (0..vs).collect();
-		macro_rules! cycle_cost_compute {
-			($sol:expr,$alloc:expr,$dun:expr) => {
-				$sol.iter().map(|e| e.weight() * if snowy.contains(e) && if params.clearing == Clearing::All { !$dun.contains(e) } else { $alloc.contains(e) } { params.slowdown } else { n64(1.0) }).sum()
-			};
-			($sol:expr,$alloc:expr) => {
-				$sol.iter().map(|e| e.weight() * if snowy.contains(e) && $alloc.contains(e) { params.slowdown } else { n64(1.0) }).sum()
-			};
-		}
-		for _mi in 0..params.annealing.main_iterations {
+		let mut costs_last: Vec<N64> = (0..vs).map(|_| n64(0.0)).collect();
+		let (mut iterations, mut converged) = (0, 0);
+		// vehicles' paths are solved in parallel when they don't depend on each other, the threads left over connecting to distant isles in batches
+		let (threads, batch) = match params.clearing {
+			Clearing::OnlyAllocated => (threads.min(vs).max(1), threads / threads.min(vs).max(1)),
+			Clearing::All => (1, threads),
+		};
+		let new_buffers = || if batch > 1 { graph::heuristics::Buffers::batched(batch) } else { Default::default() };
+		// buffers reused across iterations, sparing the hot loop from reallocating paths
+		let mut buffers = new_buffers();
+		// and those of the other threads solving paths in parallel, if any
+		let mut thread_buffers: Vec<graph::heuristics::Buffers<'a, SID, E, N64>> = (1..threads).map(|_| new_buffers()).collect();
+		let mut presolved: Vec<Option<Result<Vec<&'a E>, graph::heuristics::PwrpError<'a, E>>>> = Vec::new();
+		let mut sol_next: Vec<Vec<&'a E>> = (0..vs).map(|_| Vec::new()).collect();
+		let mut sol_improv: Vec<Vec<&'a E>> = (0..vs).map(|_| Vec::new()).collect();
+		let mut vycles: Vec<Vec<SID>> = (0..vs).map(|_| Vec::new()).collect();
+		let (mut moved, mut moved_nodes) = (Vec::new(), Vec::new());
+		// where vehicles can get to, and the vehicles that couldn't reach an edge - for reallocating it, once
+		let mut reach: Vec<Option<HashSet<SID>>> = (0..vs).map(|_| None).collect();
+		let mut unreached: HashMap<&'a E, HashSet<usize>> = HashMap::default();
+		'anneal: for _mi in 0.. {
+			// not before a first solution, which reallocating unreachable segments may put off
+			if value_best.is_finite() && deadline.map_or(_mi >= params.annealing.main_iterations, |d| Instant::now() >= d) {
+				break;
+			}
 			log::debug!("iteration {} current best {:.1}", _mi, value_best);
 			//Try to improve allocations
 			//TODO? change alloc
@@ -123,58 +724,127 @@ where
 						order.swap(i, j);
 					}
 				},
+				Reorder::Swap2MostLeastCost => {
+					if let itertools::MinMaxResult::MinMax(i, j) = (0..vs).minmax_by_key(|i| costs_last[order[*i]]) {
+						order.swap(i, j);
+					}
+				},
 				Reorder::RandomReorder => order.shuffle(&mut rng),
+				Reorder::RoundRobin => order.rotate_left(1),
 			}
 			log::debug!(" new order: {:?}", order);
 			//Provide new solutions
-			let mut sol_next: Vec<_> = (0..vs).map(|_| Vec::new()).collect();
+			for p in sol_next.iter_mut() {
+				buffers.recycle(std::mem::take(p));
+			}
 			let mut cost_next_all = n64(0.0);
 			let mut cost_next_max = n64(0.0);
 			let mut costs_next = Vec::new();
 			costs_next.resize(vs, n64(0.0));
-			let mut dun = HashSet::new();
+			let mut dun = HashSet::default();
+			// the first solution is seen through, whatever the budget
+			let limits = graph::heuristics::Limits { deadline: deadline.filter(|_| value_best.is_finite()), ..Default::default() };
+			// paths are found with the vehicle's exclusions
+			let solve = |i: usize, alloc: &HashSet<&'a E>, dun: &HashSet<&'a E>, buffers: &mut graph::heuristics::Buffers<'a, SID, E, N64>| {
+				buffers.cache.mode(i);
+				graph::heuristics::solve_pwrp_buffered::<_, _, _, _, _, _, DIRESPECT>(&self.graph.graph, vehicles[i].sp, alloc.iter().copied().filter(|e| !dun.contains(e)).collect(), vehicles[i].end.as_ref(), |e| if vehicles[i].excluded.contains(e) { None } else { Some(e.weight()) }, limits, |_| {}, buffers)
+			};
+			if threads > 1 {
+				// vehicles only clear what's allocated to them, so their paths don't depend on each other's
+				let (solve, alloc, dun) = (&solve, &alloc, &dun);
+				let solved: Vec<_> = std::iter::once(&mut buffers).chain(thread_buffers.iter_mut()).collect::<Vec<_>>().into_par_iter().enumerate().map(|(t, buffers)| {
+					(t..vs).step_by(threads).map(|i| (i, solve(i, &alloc[i], dun, buffers))).collect::<Vec<_>>()
+				}).collect();
+				presolved.clear();
+				presolved.resize_with(vs, || None);
+				for (i, sol) in solved.into_iter().flatten() {
+					presolved[i] = Some(sol);
+				}
+			}
 			for i in &order {
 				let i = *i;
 				log::debug!(" solving {}", i);
-				match graph::heuristics::solve_pwrp::<_, _, _, _, _, DIRESPECT>(&self.graph.graph, sps[i], alloc[i].iter().map(|e| *e).filter(|e| !dun.contains(e)).collect(), |e| Some(e.weight())) {
+				let sol = match presolved.get_mut(i).and_then(Option::take) {
+					Some(sol) => sol,
+					None => solve(i, &alloc[i], &dun, &mut buffers),
+				};
+				match sol {
 					Ok(sol) => {
-						let cost = cycle_cost_compute!(sol, alloc[i], dun);
+						let clearing = |e: &E| snowy.get(e).copied().filter(|_| if params.clearing == Clearing::All { !dun.contains(e) } else { alloc[i].contains(e) });
+						let penalty = penalties(&sol, &vehicles[i], params, clearing);
+						let (cost, adjusted) = path_cost(&sol, params, clearing);
+						let (cost, adjusted) = (cost + penalty, adjusted + penalty);
 						if params.clearing == Clearing::All {
 							for e in &sol {
-								dun.insert(*e);
+								if vehicles[i].clears(e) {
+									dun.insert(*e);
+								}
 							}
 						}
-						costs_next[i] = cost;
+						costs_next[i] = adjusted;
 						cost_next_all = cost_next_all + cost;
-						if cost > cost_next_max {
-							cost_next_max = cost;
+						if adjusted > cost_next_max {
+							cost_next_max = adjusted;
 						}
 						sol_next[i] = sol;
 					}
-					Err(_es) => panic!("Can't reach everywhere :( ({}) {}", _es.len(), _es.into_iter().take(50).map(|e| format!("{:?} ({}<->{})", e, self.graph.nid2id(e.p1()).unwrap(), self.graph.nid2id(e.p2()).unwrap())).join(", ")) //TODO instead of panicking, try to reallocate unreachable sections first
+					Err(graph::heuristics::PwrpError::Interrupted(_p, _es)) => {
+						log::debug!(" budget spent, abandoning iteration (with a partial path of {} segments, {} remaining)", _p.len(), _es.len());
+						break 'anneal;
+					}
+					Err(graph::heuristics::PwrpError::Unreachable(es)) => {
+						// hand the segments to vehicles which can get to them, and start the iteration over
+						let mut stranded = Vec::new();
+						for e in es {
+							let tried = unreached.entry(e).or_default();
+							tried.insert(i);
+							let to = (0..vs).filter(|j| !tried.contains(j) && vehicles[*j].clears(e)).filter(|j| {
+								let reach = reach[*j].get_or_insert_with(|| self.graph.graph.reachable::<DIRESPECT>(vehicles[*j].sp, false));
+								reach.contains(&e.p1()) || (!e.directed() && reach.contains(&e.p2()))
+							}).min_by_key(|j| alloc[*j].len());
+							match to {
+								Some(j) => {
+									alloc[i].remove(e);
+									alloc[j].insert(e);
+								},
+								None => stranded.push(e),
+							}
+						}
+						if !stranded.is_empty() {
+							return Err(Error::Unreachable { segments: self.segment_ids(stranded), message: "can't be reached by any vehicle".to_string() });
+						}
+						log::debug!(" reallocated segments vehicle {} can't reach", i);
+						continue 'anneal;
+					},
 				}
 			}
 			//Evaluate
-			let sol_next = sol_next;
 			let (cost_next_all, cost_next_max, costs_next) = (cost_next_all, cost_next_max, costs_next);
-			let value_next = params.weight_total*cost_next_all + params.weight_max*cost_next_max;
+			let redundant_next = redundant(&sol_next, snowy, params, |i, e| vehicles[i].clears(e));
+			let value_next = params.weight_total*cost_next_all + params.weight_max*cost_next_max + params.redundancy*redundant_next;
 			log::debug!(" new value: {:.5} costs: {}", value_next, costs_next.iter().join("|"));
-			let sol_next = if value_next < value_best || (value_next <= value_best && cost_next_max < cost_max_best) {
+			let tolerance = &params.tolerance;
+			let sol_next = if tolerance.lt(value_next, value_best) || (tolerance.le(value_next, value_best) && tolerance.lt(cost_next_max, cost_max_best)) {
 				log::debug!(" solution accepted");
-				solution = sol_next;
+				std::mem::swap(&mut solution, &mut sol_next);
 				value_best = value_next;
+				converged = iterations + 1;
 				cost_max_best = cost_next_max;
+				redundant_best = redundant_next;
 				if params.clearing == Clearing::All {
-					self.sol_to_alloc(order.iter().cloned(), &solution, &mut alloc, |e| snowy.contains(e));
+					self.sol_to_alloc(order.iter().cloned(), &solution, &mut alloc, |i, e| snowy.contains_key(e) && vehicles[i].clears(e));
 				}
 				&solution
 			} else {
 				&sol_next
 			};
+			costs_last.clone_from(&costs_next);
 			//Try to improve
 			if params.recycle == Recycle::ExpensiveToCheap {
-				let mut sol_improv = sol_next.clone();
-				let mut vycles: Vec<Vec<_>> = sol_next.iter().zip(sps.iter()).map(|(path, n0)| graph::Graph::<SID, N, E>::path_to_nodes(path.iter().map(|e| *e), *n0).into_iter().map(|(v, _)| v).collect()).collect();
+				for ((improv, vycle), (path, v)) in sol_improv.iter_mut().zip(vycles.iter_mut()).zip(sol_next.iter().zip(vehicles.iter())) {
+					improv.clone_from(path);
+					graph::Graph::<SID, N, E>::path_nodes_into(path.iter().copied(), v.sp, vycle);
+				}
 				for i in 0..vs {
 					'nexc: for j in (i+1)..vs {
 						let (i, j) = if costs_next[order[i]] > costs_next[order[j]] { (order[i], order[j]) } else { (order[j], order[i]) };
@@ -182,14 +852,14 @@ where
 							for ju in 0..vycles[j].len() {
 								if vycles[i][iu] == vycles[j][ju] {
 									for iv in (iu+1)..vycles[i].len() {
-										if vycles[i][iv] == vycles[i][iu] {
+										if vycles[i][iv] == vycles[i][iu] && sol_improv[i][iu..iv].iter().all(|e| vehicles[j].clears(e)) {
 											// [i][iu..=iv] <=> [j][ju..=ju]
 											// same as
 											log::trace!("  [{}][{}..{}] => [{}][{}..{}]", i, iu, iv, j, ju, ju);
-											let mine: Vec<_> = sol_improv[i].splice(iu..iv, vec![]).collect();
-											sol_improv[j].splice(ju..ju, mine);
-											let mine: Vec<_> = vycles[i].splice(iu..iv, vec![]).collect();
-											vycles[j].splice(ju..ju, mine);
+											moved.extend(sol_improv[i].drain(iu..iv));
+											sol_improv[j].splice(ju..ju, moved.drain(..));
+											moved_nodes.extend(vycles[i].drain(iu..iv));
+											vycles[j].splice(ju..ju, moved_nodes.drain(..));
 											//don't update costs to avoid swap-backs idk
 											continue 'nexc;
 										}
@@ -200,29 +870,34 @@ where
 					}
 				}
 				//Evaluate improvements
-				let sol_improv = sol_improv;
 				let mut cost_improv_all = n64(0.0);
 				let mut cost_improv_max = n64(0.0);
 				let mut costs_improv = Vec::new();
 				costs_improv.resize(vs, n64(0.0));
 				for i in 0..vs {
-					let cost = cycle_cost_compute!(sol_improv[i], alloc[i]);
-					costs_improv[i] = cost;
+					let clearing = |e: &E| snowy.get(e).copied().filter(|_| alloc[i].contains(e));
+					let penalty = penalties(&sol_improv[i], &vehicles[i], params, clearing);
+					let (cost, adjusted) = path_cost(&sol_improv[i], params, clearing);
+					let (cost, adjusted) = (cost + penalty, adjusted + penalty);
+					costs_improv[i] = adjusted;
 					cost_improv_all = cost_improv_all + cost;
-					if cost > cost_improv_max {
-						cost_improv_max = cost;
+					if adjusted > cost_improv_max {
+						cost_improv_max = adjusted;
 					}
 				}
 				let (cost_improv_all, cost_improv_max, costs_improv) = (cost_improv_all, cost_improv_max, costs_improv);
-				let value_improv = params.weight_total*cost_next_all + params.weight_max*cost_next_max;
+				let redundant_improv = redundant(&sol_improv, snowy, params, |i, e| vehicles[i].clears(e));
+				let value_improv = params.weight_total*cost_improv_all + params.weight_max*cost_improv_max + params.redundancy*redundant_improv;
 				log::debug!(" new value: {:.5} costs: {}", value_improv, costs_improv.iter().join("|"));
 				//if the improved solution is actually better, or with some chance anyway, keep it
-				if value_improv < value_best || (value_improv <= value_best && cost_improv_max < cost_max_best) || (value_improv < value_next && n64(rng.gen_range(0.0..1.0)) < ((value_improv-value_next)/temperature).exp()) {
+				if tolerance.lt(value_improv, value_best) || (tolerance.le(value_improv, value_best) && tolerance.lt(cost_improv_max, cost_max_best)) || (tolerance.lt(value_improv, value_next) && n64(rng.gen_range(0.0..1.0)) < ((value_improv-value_next)/temperature).exp()) {
 					log::debug!(" improvements accepted");
-					solution = sol_improv;
+					std::mem::swap(&mut solution, &mut sol_improv);
 					value_best = value_improv;
+					converged = iterations + 1;
 					cost_max_best = cost_improv_max;
-					self.sol_to_alloc(order.iter().cloned(), &solution, &mut alloc, |e| snowy.contains(e));
+					redundant_best = redundant_improv;
+					self.sol_to_alloc(order.iter().cloned(), &solution, &mut alloc, |i, e| snowy.contains_key(e) && vehicles[i].clears(e));
 				}
 			}
 			//Update the temperature
@@ -232,8 +907,118 @@ where
 				temperature *= params.annealing.cooling_factor;
 				log::debug!(" t={:.2}", temperature);
 			}
+			iterations += 1;
+			iteration(value_best, &solution);
+		}
+		log::debug!("Path cache hit rate {:.1}%", buffers.cache.hit_rate() * 100.0);
+		let (cache_hits, cache_misses) = std::iter::once(&buffers).chain(&thread_buffers).fold((0, 0), |(h, m), b| (h + b.cache.hits, m + b.cache.misses));
+		Ok((solution, RestartSummary { seed, iterations, converged, value: value_best, max: cost_max_best, redundant: redundant_best, cache_hits, cache_misses }))
+	}
+}
+
+impl<N, E, Gen> PlowSolver<N, E, Gen>
+where
+	N: IdentifiableNode<Id = NodeId> + Positioned,
+	E: graph::Edge<SID> + Weighted + Discriminated,
+	Gen: Fn(&N::Id, SID) -> (SID, SID),
+{
+	/// Locates a route end preference on the graph
+	///
+	/// Returns: `None` for a closed tour, or the end region (empty meaning anywhere)
+	fn locate_end(&self, end: &data::RouteEnd) -> Result<Option<HashSet<SID>>, Error> {
+		let g = &self.graph.graph;
+		match end {
+			data::RouteEnd::Start => Ok(None),
+			data::RouteEnd::Anywhere => Ok(Some(HashSet::default())),
+			data::RouteEnd::Location(data::Location::Node(n)) => {
+				let nid = self.graph.id2nid(n).ok_or_else(|| Error::node(n, "end node not in the road graph"))?;
+				if !g.is_orphan(nid) {
+					Ok(Some(std::iter::once(nid).collect()))
+				} else {
+					Err(Error::node(n, "explicitly specified end node is an orphan"))
+				}
+			},
+			data::RouteEnd::Location(data::Location::Coordinates(lon, lat)) => g.nodes().filter(|(n, _)| !g.is_orphan(*n)).min_by_key(|(_, n)| n64(self.metric.distance((*lon, *lat), n.pos()))).map(|(n, _)| Some(std::iter::once(n).collect())).ok_or_else(|| Error::Invalid(format!("failed to locate end ({},{}) to graph", lon, lat))),
+			data::RouteEnd::Location(data::Location::Depot { depot }) => Err(Error::Invalid(format!("Unresolved reference to depot {}", depot))),
+			data::RouteEnd::Region(ring) => {
+				let nodes: HashSet<_> = self.nodes_within(ring).into_iter().filter(|n| !g.is_orphan(*n)).collect();
+				if !nodes.is_empty() {
+					Ok(Some(nodes))
+				} else {
+					Err(Error::Invalid(format!("End region {:?} contains no reachable node", ring)))
+				}
+			},
+		}
+	}
+	/// Warns about vehicles starting at the same node, they'll share the neighbourhood by direction
+	fn check_coincident(&self, ids: &[data::VehicleId], sns: &[SID]) {
+		for (sp, group) in ids.iter().zip(sns.iter()).into_group_map_by(|(_, sp)| **sp) {
+			if group.len() > 1 {
+				log::warn!("Vehicles {} all start at {}, splitting their initial allocation by direction", group.iter().map(|(id, _)| id).join(", "), self.graph.nid2id(sp).unwrap());
+			}
+		}
+	}
+	/// Nodes within a polygon (exterior ring of coordinates)
+	fn nodes_within(&self, ring: &[Coords]) -> HashSet<SID> {
+		use geo::contains::Contains;
+		let region = geo::Polygon::new(ring.to_vec().into(), vec![]);
+		self.graph.graph.nodes().filter(|(_, node)| region.contains(&geo::Point::from(node.pos()))).map(|(n, _)| n).collect()
+	}
+	/// Locates exclusions to the edges they exclude
+	///
+	/// A region excludes all edges touching a node within; a segment excludes all edges between its nodes with the same discriminator.
+	fn locate_exclusions(&self, ex: &[data::Exclusion]) -> Result<HashSet<&E>, Error> {
+		let g = &self.graph.graph;
+		let mut excluded = HashSet::default();
+		for x in ex {
+			match x {
+				data::Exclusion::Region(ring) => {
+					for n in self.nodes_within(ring) {
+						excluded.extend(g.get_edges(n));
+					}
+				},
+				data::Exclusion::Segment { p1, p2, discriminator } => {
+					let n1 = self.graph.id2nid(p1).ok_or_else(|| Error::segment(p1, p2, discriminator, "excluded segment's node not in the road graph"))?;
+					let n2 = self.graph.id2nid(p2).ok_or_else(|| Error::segment(p1, p2, discriminator, "excluded segment's node not in the road graph"))?;
+					let discr = discriminator.as_ref().map(|d| self.graph.id2nid(d).ok_or_else(|| Error::segment(p1, p2, discriminator, "excluded segment's discriminator not in the road graph"))).transpose()?;
+					excluded.extend(g.get_edges_between(n1, n2).into_iter().filter(|e| e.discriminator() == discr));
+				},
+			}
 		}
-		solution
+		Ok(excluded)
+	}
+}
+
+impl<N, E, Gen> PlowSolver<N, E, Gen>
+where
+	N: IdentifiableNode<Id = NodeId> + Positioned,
+	E: graph::Edge<SID> + Weighted + Discriminated + Parallel,
+	Gen: Fn(&N::Id, SID) -> (SID, SID),
+{
+	/// Locates a snow report to the edges it covers, with the depth on each
+	///
+	/// A report addressing a specific parallel edge covers just that one, otherwise it is distributed among the parallels according to `policy`.
+	/// Unknown nodes locate to nothing.
+	fn locate_snow(&self, s: &data::SnowStatusElement, policy: Parallels, filter: impl Fn(&E) -> bool) -> Vec<(&E, Snow)> {
+		let p1 = self.graph.id2nid(&s.p1);
+		let p2 = self.graph.id2nid(&s.p2);
+		let discr = s.discriminator.as_ref().map(|d| self.graph.id2nid(d));
+		let (p1, p2, discr) = match (p1, p2, discr) {
+			(Some(p1), Some(p2), None) => (p1, p2, None),
+			(Some(p1), Some(p2), Some(Some(d))) => (p1, p2, Some(d)),
+			_ => return Vec::new(),
+		};
+		let edges: Vec<&E> = self.graph.graph.get_edges_between(p1, p2).into_iter().filter(|e| e.discriminator() == discr && filter(e)).collect();
+		let parallels: Vec<usize> = edges.iter().map(|e| e.parallel()).sorted().dedup().collect();
+		let n = n64(parallels.len().max(1) as f64);
+		let snow = Snow { depth: s.depth, deviation: s.variance.map_or_else(|| n64(0.0), |v| v.sqrt()) };
+		let (covered, snow) = match (s.parallel_index, policy) {
+			(Some(i), _) => (vec![i], snow),
+			(None, Parallels::First) => (parallels.into_iter().take(1).collect(), snow),
+			(None, Parallels::All) => (parallels, snow),
+			(None, Parallels::Split) => (parallels, Snow { depth: snow.depth / n, deviation: snow.deviation / n }),
+		};
+		edges.into_iter().filter(|e| covered.contains(&e.parallel())).map(|e| (e, snow)).collect()
 	}
 }
 
@@ -241,6 +1026,9 @@ where
 mod common {
 	use super::*;
 
+	const _: () = assert_send_sync::<NodeId>();
+	const _: () = assert_send_sync::<RoadNode>();
+
 	#[derive(Clone, Debug)]
 	pub struct RoadNode {
 		pub id: NodeId,
@@ -272,14 +1060,15 @@ mod common {
 			{
 				let sns: Vec<_> = $locs.iter().try_map_all(|l| match l {
 					data::Location::Node(n) => {
-						let nid = $g.graph.id2nid(n).ok_or_else(|| format!("Couldn't find node {}", n))?;
+						let nid = $g.graph.id2nid(n).ok_or_else(|| Error::node(n, "not in the road graph"))?;
 						if !$g.graph.graph.is_orphan(nid) {
 							Ok(nid)
 						} else {
-							Err(format!("Explicitly specified node {} is an orphan", nid))
+							Err(Error::node(n, "explicitly specified node is an orphan"))
 						}
 					},
-					data::Location::Coordinates(lon, lat) => $g.graph.graph.nodes().filter(|(n, _)| !$g.graph.graph.is_orphan(*n)).min_by_key(|(_, n)| n64((*lon, *lat).distance(&n.pos()))).map(|(n, _)| n).ok_or_else(|| format!("failed to locate ({},{}) to graph", lon, lat))
+					data::Location::Coordinates(lon, lat) => $g.graph.graph.nodes().filter(|(n, _)| !$g.graph.graph.is_orphan(*n)).min_by_key(|(_, n)| n64($g.metric.distance((*lon, *lat), n.pos()))).map(|(n, _)| n).ok_or_else(|| Error::Invalid(format!("failed to locate ({},{}) to graph", lon, lat))),
+					data::Location::Depot { depot } => Err(Error::Invalid(format!("Unresolved reference to depot {}", depot))),
 				})?.collect();
 				log::info!("Located {}", $v);
 				log::debug!("{:?}", sns.iter().cloned().map(|n| $g.graph.nid2id(n).unwrap()).collect::<Vec<_>>());
@@ -300,7 +1089,7 @@ mod common {
 {:?}
 (^nodes in each region^)
 Only the regions with {} will be considered!"#, sccs.iter().map(HashSet::len).collect::<Vec<_>>(), $v);
-					let mut reachable = HashSet::new();
+					let mut reachable = HashSet::default();
 					for scc in sccs {
 						if $sns.iter().any(|s| scc.contains(s)) {
 							reachable.extend(scc);
@@ -325,7 +1114,7 @@ Only the regions with {} will be considered!"#, sccs.iter().map(HashSet::len).co
 {:?}
 (^nodes in each region^)
 Only the regions with {} will be considered!"#, sccs.iter().map(HashSet::len).collect::<Vec<_>>(), $v);
-					let mut reachable = HashSet::new();
+					let mut reachable = HashSet::default();
 					for scc in sccs {
 						if $sns.iter().any(|s| scc.contains(s)) {
 							reachable.extend(scc);
@@ -350,7 +1139,11 @@ pub mod fly {
 		p1: SID,
 		p2: SID,
 		discriminator: Option<SID>,
+		/// one-way corridor
+		directed: bool,
 		length: N64,
+		/// across a sensitive site - not flown for itself, and penalized
+		sensitive: bool,
 	}
 	impl PartialEq<RoadEdge> for RoadEdge {
 		fn eq(&self, other: &Self) -> bool {
@@ -367,6 +1160,11 @@ pub mod fly {
 			self.length
 		}
 	}
+	impl Discriminated for RoadEdge {
+		fn discriminator(&self) -> Option<SID> {
+			self.discriminator
+		}
+	}
 	impl Edge<SID> for RoadEdge {
 		fn p1(&self) -> SID {
 			self.p1
@@ -375,33 +1173,188 @@ pub mod fly {
 			self.p2
 		}
 		fn directed(&self) -> bool {
-			false
+			self.directed
 		}
 	}
 
-	/// Solves the pathing problem for brrr drones
-	pub fn solve(roads: data::RoadGraph, drones: data::Drones, params: &Parameters) -> Result<data::Paths, String> {
-		let mut g: PlowSolver<RoadNode, RoadEdge, _> = plow_solver!();
+	const _: () = assert_send_sync::<PlowSolver<RoadNode, RoadEdge, IdGen>>();
+
+	/// Sites a segment comes within the buffer of, by name (index if unnamed)
+	fn sites_crossed(sites: &[data::SensitiveSite], c1: Coords, c2: Coords) -> Vec<String> {
+		sites.iter().enumerate().filter(|(_, s)| s.crossed_by(c1, c2)).map(|(i, s)| s.name.clone().unwrap_or_else(|| i.to_string())).collect()
+	}
+
+	/// Constructs the solver graph from the road graph - corridors across sensitive sites cost `1 + sensitive` times more
+	fn construct(roads: data::RoadGraph, sites: &[data::SensitiveSite], sensitive: N64) -> Result<PlowSolver<RoadNode, RoadEdge, IdGen>, Error> {
+		roads.check()?;
+		let coords: HashMap<_, _> = roads.nodes.nodes.iter().map(|n| (n.id.clone(), n.coordinates)).collect();
+		let mut g: PlowSolver<RoadNode, RoadEdge, IdGen> = plow_solver!();
 		for n in roads.nodes.nodes {
 			g.graph = g.graph.add_node(n.into());
 		}
 		for e in roads.roads {
+			let crossing = !sites_crossed(sites, coords[&e.p1], coords[&e.p2]).is_empty();
 			g.graph.add_edge(RoadEdge {
 				p1: g.graph.id2nid(&e.p1).unwrap(),
 				p2: g.graph.id2nid(&e.p2).unwrap(),
 				discriminator: e.discriminator.map(|id| g.graph.id2nid(&id).unwrap()),
-				length: e.distance,
+				directed: e.directed,
+				length: if crossing { e.distance * (n64(1.0) + sensitive) } else { e.distance },
+				sensitive: crossing,
 			});
 		}
+		Ok(g)
+	}
+
+	/// Dumps the solver graph, as constructed from the road graph
+	pub fn dump(roads: data::RoadGraph) -> Result<dump::GraphDump, Error> {
+		Ok(construct(roads, &[], n64(0.0))?.graph.dump())
+	}
+
+	/// Legs of a drone's path, by step (see [`Graph::path_to_nodes`]) - the takeoff isn't part of any
+	///
+	/// A corridor is surveyed the first time it's flown, and flying back over home between two corridors surveyed makes a recharge stopover of the stretch.
+	fn legs(steps: &[(SID, Option<&RoadEdge>)]) -> Vec<Option<data::Leg>> {
+		let home = steps[0].0;
+		let mut flown = HashSet::default();
+		let surveys: Vec<_> = steps.iter().enumerate().filter(|(_, (_, e))| e.is_some_and(|e| !e.sensitive && flown.insert(e))).map(|(i, _)| i).collect();
+		let mut legs: Vec<_> = (0..steps.len()).map(|i| match (surveys.first(), surveys.last()) {
+			_ if i == 0 => None,
+			(Some(first), _) if i < *first => Some(data::Leg::Outbound),
+			(Some(_), Some(last)) if i <= *last => Some(data::Leg::Survey),
+			(Some(_), _) => Some(data::Leg::Return),
+			(None, _) => Some(data::Leg::Outbound),
+		}).collect();
+		for w in surveys.windows(2) {
+			if w[1] > w[0] + 1 && (w[0]..w[1]).any(|i| steps[i].0 == home) {
+				legs[w[0] + 1..w[1]].fill(Some(data::Leg::Recharge));
+			}
+		}
+		legs
+	}
+
+	/// Solves the pathing problem for brrr drones
+	///
+	/// Corridors across sensitive sites aren't flown for themselves, only crossed (at a penalty, see [`Parameters::sensitive`]) where there's no way around.
+	pub fn solve(roads: data::RoadGraph, drones: data::Drones, sites: &[data::SensitiveSite], params: &Parameters, budget: &Budget, mut checkpoint: impl FnMut(Duration, data::Paths)) -> Result<(data::Paths, Stats), Error> {
+		let mut g = construct(roads, sites, params.sensitive)?;
+		g.metric = params.metric;
 		let sns = locate!(drones, g, "drones");
-		let locations = sns.iter().map(|id| g.graph.graph.get_node(*id).unwrap().coordinates).collect();
 		fix_sccs!(g, sns, "drones");
+		g.check_coincident(&(0..sns.len()).map(|i| i.to_string()).collect::<Vec<_>>(), &sns);
+		// a drone can only fly a corridor it can get to, and back home from
+		let reach: Vec<_> = sns.iter().map(|sp| (g.graph.graph.reachable::<true>(*sp, false), g.graph.graph.reachable::<true>(*sp, true))).collect();
+		let flies = |(to, from): &(HashSet<SID>, HashSet<SID>), e: &RoadEdge| (to.contains(&e.p1) && from.contains(&e.p2)) || (!e.directed && to.contains(&e.p2) && from.contains(&e.p1));
+		let unreachable: Vec<_> = g.graph.graph.edges().filter(|e| !e.sensitive && !reach.iter().any(|r| flies(r, e))).collect();
+		if !unreachable.is_empty() {
+			return Err(Error::Unreachable { segments: g.segment_ids(unreachable), message: "can't be flown by any drone".to_string() });
+		}
+		let fleet: Vec<_> = sns.iter().zip(reach.iter()).map(|(sp, r)| Vehicle {
+			sp: *sp,
+			loc: g.graph.graph.get_node(*sp).unwrap().coordinates,
+			end: None,
+			excluded: HashSet::default(),
+			incompatible: g.graph.graph.edges().filter(|e| !flies(r, e)).collect(),
+			refill: None,
+		}).collect();
 		log::debug!("Constructed graph with {} nodes, {} segments and {} drones", g.graph.graph.node_count(), g.graph.graph.edge_count(), sns.len());
-		let solution = g.solve::<false>(&sns, &locations, &g.graph.graph.edges().collect(), params);
-		Ok(solution.into_iter().zip(sns.into_iter()).map(|(path, n)| Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(path.into_iter(), n).into_iter().map(|(u, e)| data::PathSegment {
-			node: g.graph.nid2id(u).unwrap().clone(),
-			discriminator: e.and_then(|e| e.discriminator).map(|d| g.graph.nid2id(d).unwrap().clone()),
-		}).collect()).collect())
+		let paths = |solution: &[Vec<&RoadEdge>]| -> data::Paths {
+			solution.iter().zip(sns.iter()).enumerate().map(|(i, (path, n))| {
+				let steps = Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(path.iter().copied(), *n);
+				let legs = legs(&steps);
+				(i.to_string(), steps.into_iter().zip(legs).map(|((u, e), leg)| data::PathSegment {
+					node: g.graph.nid2id(u).unwrap().clone(),
+					discriminator: e.and_then(|e| e.discriminator).map(|d| g.graph.nid2id(d).unwrap().clone()),
+					reversal: false,
+					leg,
+				}).collect())
+			}).collect()
+		};
+		let (solution, stats) = g.solve::<true>(&fleet, &g.graph.graph.edges().filter(|e| !e.sensitive).map(|e| (e, Snow::certain(n64(0.0)))).collect(), params, budget, |t, s| checkpoint(t, paths(s)))?;
+		Ok((paths(&solution), stats))
+	}
+
+	/// Crossings of sensitive sites by the drones' paths
+	pub fn crossings(roads: &data::RoadGraph, sites: &[data::SensitiveSite], paths: &data::Paths) -> Result<IndexMap<data::VehicleId, Vec<data::SiteCrossing>>, Error> {
+		let coords: HashMap<_, _> = roads.nodes.nodes.iter().map(|n| (&n.id, n.coordinates)).collect();
+		let at = |n: &NodeId| coords.get(n).copied().ok_or_else(|| Error::node(n, "used by a path, not in the road graph"));
+		paths.0.iter().map(|(id, path)| {
+			let mut crossings = Vec::new();
+			for (u, v) in path.iter().zip(path.iter().skip(1)) {
+				for site in sites_crossed(sites, at(&u.node)?, at(&v.node)?) {
+					crossings.push(data::SiteCrossing { site, p1: u.node.clone(), p2: v.node.clone(), discriminator: v.discriminator.clone() });
+				}
+			}
+			Ok((id.clone(), crossings))
+		}).collect()
+	}
+
+	/// Weight of the total under [`Objective::Max`] - just enough for it to break ties between plans as busy, the busiest drone only flying a unit more to save the others over a hundred
+	const TIE_BREAK: f64 = 0.01;
+
+	/// What flight paths are planned to minimize
+	#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+	pub enum Objective {
+		/// Total distance flown by all drones
+		Total,
+		/// Distance flown by the busiest drone - the total breaking ties
+		Max,
+	}
+
+	/// Flight paths, as planned by a [`FlightPlanner`]
+	#[derive(Clone, PartialEq, Debug)]
+	pub struct FlightPlan {
+		/// Path of every drone, keyed by its index
+		pub paths: data::Paths,
+		/// Sensitive sites crossed by every drone, where there was no way around
+		pub crossings: IndexMap<data::VehicleId, Vec<data::SiteCrossing>>,
+		pub stats: Stats,
+	}
+
+	/// Plans the flight paths of drones over a road graph, from where they take off to every corridor and back - the library face of `fly`
+	#[derive(Clone, Debug)]
+	pub struct FlightPlanner {
+		params: Parameters,
+		sites: Vec<data::SensitiveSite>,
+		budget: Budget,
+		objective: Option<Objective>,
+	}
+
+	impl FlightPlanner {
+		/// A planner weighing paths as per the parameters, with no sensitive sites and no time budget
+		pub fn new(params: Parameters) -> Self {
+			Self { params, sites: Vec::new(), budget: Budget::default(), objective: None }
+		}
+		/// Sensitive sites to fly around (see [`solve`])
+		pub fn sites(mut self, sites: Vec<data::SensitiveSite>) -> Self {
+			self.sites = sites;
+			self
+		}
+		/// Anytime mode settings
+		pub fn budget(mut self, budget: Budget) -> Self {
+			self.budget = budget;
+			self
+		}
+		/// Minimizes just the one objective - instead of the parameters' `weight_total` and `weight_max` blend
+		pub fn objective(mut self, objective: Objective) -> Self {
+			self.objective = Some(objective);
+			self
+		}
+		/// Parameters planned with, the objective's weights applied
+		pub fn params(&self) -> Parameters {
+			let (weight_total, weight_max) = match self.objective {
+				Some(Objective::Total) => (n64(1.0), n64(0.0)),
+				Some(Objective::Max) => (n64(TIE_BREAK), n64(1.0)),
+				None => (self.params.weight_total, self.params.weight_max),
+			};
+			Parameters { weight_total, weight_max, ..self.params.clone() }
+		}
+		/// Plans the drones' flight paths, reporting the best so far at the budget's checkpoints
+		pub fn plan(&self, roads: data::RoadGraph, drones: data::Drones, checkpoint: impl FnMut(Duration, data::Paths)) -> Result<FlightPlan, Error> {
+			let (paths, stats) = solve(roads.clone(), drones, &self.sites, &self.params(), &self.budget, checkpoint)?;
+			let crossings = crossings(&roads, &self.sites, &paths)?;
+			Ok(FlightPlan { paths, crossings, stats })
+		}
 	}
 }
 
@@ -415,23 +1368,52 @@ pub mod road {
 		p1: SID,
 		p2: SID,
 		discriminator: Option<SID>,
+		parallel: usize,
 		directed: bool,
 		length: N64,
+		distance: N64,
+		surface: Option<data::Surface>,
+		grade: Option<N64>,
+		bridge: bool,
+		shaded: bool,
+		priority: Option<u32>,
 	}
 	impl PartialEq<RoadEdge> for RoadEdge {
 		fn eq(&self, other: &Self) -> bool {
-			self.p1 == other.p1 && self.p2 == other.p2 && self.discriminator == other.discriminator
+			self.p1 == other.p1 && self.p2 == other.p2 && self.discriminator == other.discriminator && self.parallel == other.parallel
 		}
 	}
 	impl std::hash::Hash for RoadEdge {
 		fn hash<H: std::hash::Hasher>(&self, h: &mut H) {
-			(self.p1, self.p2, self.discriminator).hash(h)
+			(self.p1, self.p2, self.discriminator, self.parallel).hash(h)
 		}
 	}
 	impl Weighted for RoadEdge {
 		fn weight(&self) -> N64 {
 			self.length
 		}
+		fn distance(&self) -> N64 {
+			self.distance
+		}
+		fn grade(&self) -> N64 {
+			self.grade.map_or_else(|| n64(0.0), |g| g.abs())
+		}
+		fn exposure(&self) -> (bool, bool) {
+			(self.bridge, self.shaded)
+		}
+		fn priority(&self) -> Option<u32> {
+			self.priority
+		}
+	}
+	impl Discriminated for RoadEdge {
+		fn discriminator(&self) -> Option<SID> {
+			self.discriminator
+		}
+	}
+	impl Parallel for RoadEdge {
+		fn parallel(&self) -> usize {
+			self.parallel
+		}
 	}
 	impl Edge<SID> for RoadEdge {
 		fn p1(&self) -> SID {
@@ -445,45 +1427,749 @@ pub mod road {
 		}
 	}
 
-	/// Solves the snow plowing problem for roads.
-	///
-	/// Except it also converts all the data both ways and does other safety checks.
-	pub fn solve(roads: data::RoadGraph, snow: data::SnowStatuses, snow_d: Option<f64>, vehicles: data::VehiclesConfiguration, params: &Parameters) -> Result<data::Paths, String> {
-		let mut g: PlowSolver<RoadNode, RoadEdge, _> = plow_solver!();
+	const _: () = assert_send_sync::<PlowSolver<RoadNode, RoadEdge, IdGen>>();
+
+	/// Constructs the solver graph from the road graph - with `speeds`, edge lengths are travel times
+	fn construct(roads: data::RoadGraph, speeds: Option<&Speeds>) -> Result<PlowSolver<RoadNode, RoadEdge, IdGen>, Error> {
+		roads.check()?;
+		let mut g: PlowSolver<RoadNode, RoadEdge, IdGen> = plow_solver!();
+		let parallels = roads.parallel_indices();
+		let grades = roads.grades();
 		for n in roads.nodes.nodes {
 			g.graph = g.graph.add_node(n.into());
 		}
-		for e in roads.roads {
+		for ((e, parallel), grade) in roads.roads.into_iter().zip(parallels).zip(grades) {
 			g.graph.add_edge(RoadEdge {
 				p1: g.graph.id2nid(&e.p1).unwrap(),
 				p2: g.graph.id2nid(&e.p2).unwrap(),
-				discriminator: e.discriminator.map(|id| g.graph.id2nid(&id).unwrap()),
+				discriminator: e.discriminator.as_ref().map(|id| g.graph.id2nid(id).unwrap()),
+				parallel,
 				directed: e.directed,
-				length: e.distance,
+				length: speeds.map_or(e.distance, |s| e.distance / s.of(e.class.as_ref())),
+				distance: e.distance,
+				surface: e.surface,
+				grade: grade.map(n64),
+				bridge: e.bridge,
+				shaded: e.shaded,
+				priority: e.priority,
 			});
 		}
-		let sns = locate!(vehicles.sidewalk, g, "vehicles");
-		let locations = sns.iter().map(|id| g.graph.graph.get_node(*id).unwrap().coordinates).collect();
-		fix_sccs!(g, sns, "vehicles", |e| RoadEdge { directed: false, ..e });
-		let snowy: HashSet<_> = if let Some(_snow_d) = snow_d.filter(|d| *d > 0.0) {
-			log::debug!("Default snow level {:.5} - every edge counts!", _snow_d);
-			g.graph.graph.edges().collect()
-		} else {
-			snow.into_iter().filter(|s| s.depth > 0.0).filter_map(|s| {
-				let p1 = g.graph.id2nid(&s.p1)?;
-				let p2 = g.graph.id2nid(&s.p2)?;
-				let discr = s.discriminator.map(|d| g.graph.id2nid(&d).unwrap());
-				g.graph.graph.get_edges_between(p1, p2).into_iter().find(|e| e.discriminator == discr)
-			}).collect()
-		};
-		log::debug!("Constructed graph with {} nodes, {}/{} snowed segments and {} vehicles", g.graph.graph.node_count(), snowy.len(), g.graph.graph.edge_count(), sns.len());
-		let solution = g.solve::<true>(&sns, &locations, &snowy, params);
-		Ok(solution.into_iter().zip(sns.into_iter()).map(|(path, n)| Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(path.into_iter(), n).into_iter().map(|(u, e)| data::PathSegment {
-			node: g.graph.nid2id(u).unwrap().clone(),
-			discriminator: e.and_then(|e| e.discriminator).map(|d| g.graph.nid2id(d).unwrap().clone()),
-		}).collect()).collect())
+		Ok(g)
 	}
-}
+
+	/// Dumps the solver graph, as constructed from the road graph
+	pub fn dump(roads: data::RoadGraph) -> Result<dump::GraphDump, Error> {
+		Ok(construct(roads, None)?.graph.dump())
+	}
+
+	/// Shortest route between 2 locations (coordinates located to the nearest node by the metric) - by distance, or travel time with `speeds` - going one-way segments the wrong way too unless `directed`
+	///
+	/// Returns: the route, and its length (or time)
+	pub fn route(roads: data::RoadGraph, from: &data::Location, to: &data::Location, speeds: Option<&Speeds>, directed: bool, metric: data::Metric) -> Result<(Vec<data::PathSegment>, N64), Error> {
+		let mut g = construct(roads, speeds)?;
+		g.metric = metric;
+		let ends = locate!([from, to], g, "route ends");
+		let weight = |e: &RoadEdge| Some(e.length);
+		let path = if directed {
+			g.graph.graph.pathfind::<_, _, true>(ends[0], ends[1], weight)
+		} else {
+			g.graph.graph.pathfind::<_, _, false>(ends[0], ends[1], weight)
+		}.ok_or_else(|| format!("No route from {} to {}", g.graph.nid2id(ends[0]).unwrap(), g.graph.nid2id(ends[1]).unwrap()))?;
+		let length = path.iter().map(|e| e.length).sum();
+		let route = Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(path.into_iter(), ends[0]).into_iter().map(|(u, e)| data::PathSegment {
+			node: g.graph.nid2id(u).unwrap().clone(),
+			discriminator: e.and_then(|e| e.discriminator).map(|d| g.graph.nid2id(d).unwrap().clone()),
+			reversal: false,
+			leg: None,
+		}).collect();
+		Ok((route, length))
+	}
+
+	/// Snowy edges - all of them with a default snow depth, otherwise as per the snow statuses
+	fn snowy<'a>(g: &'a PlowSolver<RoadNode, RoadEdge, impl Fn(&NodeId, SID) -> (SID, SID)>, snow: &data::SnowStatuses, snow_d: Option<f64>, parallels: Parallels) -> Snowy<'a, RoadEdge> {
+		if let Some(snow_d) = snow_d.filter(|d| *d > 0.0) {
+			log::debug!("Default snow level {:.5} - every edge counts!", snow_d);
+			g.graph.graph.edges().map(|e| (e, Snow::certain(n64(snow_d)))).collect()
+		} else {
+			snow_depths(snow.iter().flat_map(|s| g.locate_snow(s, parallels, |_| true)))
+		}
+	}
+
+	/// Locates a path on the solver graph
+	///
+	/// Of parallel edges, one traversable in the path's direction is preferred.
+	fn path_edges<'a>(g: &'a PlowSolver<RoadNode, RoadEdge, impl Fn(&NodeId, SID) -> (SID, SID)>, path: &[data::PathSegment]) -> Result<Vec<&'a RoadEdge>, Error> {
+		path.iter().zip(path.iter().skip(1)).map(|(u, v)| {
+			let missing = || Error::segment(&u.node, &v.node, &v.discriminator, "not in the road graph");
+			let n1 = g.graph.id2nid(&u.node).ok_or_else(missing)?;
+			let n2 = g.graph.id2nid(&v.node).ok_or_else(missing)?;
+			let discr = v.discriminator.as_ref().map(|d| g.graph.id2nid(d).ok_or_else(missing)).transpose()?;
+			let es: Vec<_> = g.graph.graph.get_edges_between(n1, n2).into_iter().filter(|e| e.discriminator == discr).collect();
+			es.iter().find(|e| e.is_outgoing::<true>(n1)).or_else(|| es.first()).copied().ok_or_else(missing)
+		}).collect()
+	}
+
+	/// Evaluates road paths with the same cost function the solver optimizes (see [`evaluate_paths`])
+	pub fn evaluate(roads: data::RoadGraph, snow: &data::SnowStatuses, snow_d: Option<f64>, paths: &data::Paths, params: &Parameters) -> Result<PathCosts, Error> {
+		let g = construct(roads, params.speeds.as_ref())?;
+		let snowy = snowy(&g, snow, snow_d, params.parallels);
+		let paths: Vec<_> = paths.0.iter().map(|(id, path)| path_edges(&g, path).map_err(|e| Error::vehicle(id, format!("path uses {}", e)))).collect::<Result<_, _>>()?;
+		Ok(evaluate_paths(&paths, &snowy, params))
+	}
+
+	/// A lower bound of the annealing objective - every snowy segment cleared once without any deadheading, the work split evenly among `vehicles`
+	pub fn lower_bound(roads: data::RoadGraph, snow: &data::SnowStatuses, snow_d: Option<f64>, vehicles: usize, params: &Parameters) -> Result<N64, Error> {
+		let g = construct(roads, params.speeds.as_ref())?;
+		let snowy = snowy(&g, snow, snow_d, params.parallels);
+		let costs: Vec<_> = snowy.iter().map(|(e, s)| path_cost(&[*e], params, |_| Some(*s)).0).collect();
+		let total: N64 = costs.iter().copied().sum();
+		let max = costs.into_iter().max().unwrap_or_else(|| n64(0.0)).max(total / n64(vehicles.max(1) as f64));
+		Ok(params.weight_total*total + params.weight_max*max)
+	}
+
+	/// Which vehicle of a plan clears every snowy edge, and the cost of its path up to it - the first to traverse it clearing it
+	fn cleared<'a, 'p>(g: &'a PlowSolver<RoadNode, RoadEdge, IdGen>, snowy: &Snowy<'a, RoadEdge>, paths: &'p data::Paths, params: &Parameters) -> Result<HashMap<&'a RoadEdge, (&'p data::VehicleId, N64)>, Error> {
+		let mut cleared: HashMap<&RoadEdge, (&data::VehicleId, N64)> = HashMap::default();
+		for (id, path) in &paths.0 {
+			let mut cost = n64(0.0);
+			for e in path_edges(g, path).map_err(|e| Error::vehicle(id, format!("path uses {}", e)))? {
+				match snowy.get(e).filter(|_| !cleared.contains_key(e)) {
+					Some(s) => {
+						cost += e.weight() * clearing_factor(e, s.depth, params);
+						cleared.insert(e, (id, cost));
+					}
+					None => cost += e.weight(),
+				}
+			}
+		}
+		Ok(cleared)
+	}
+
+	/// Segments of a plan boosted for de-icing (see [`Parameters::deicing`]) - which vehicle clears each and when, the earliest first
+	pub fn boosted(roads: data::RoadGraph, snow: &data::SnowStatuses, snow_d: Option<f64>, paths: &data::Paths, params: &Parameters) -> Result<Vec<data::BoostedSegment>, Error> {
+		let g = construct(roads, params.speeds.as_ref())?;
+		let snowy = snowy(&g, snow, snow_d, params.parallels);
+		let cleared = cleared(&g, &snowy, paths, params)?;
+		let mut boosted: Vec<_> = snowy.keys().map(|e| (*e, boost(*e, params))).filter(|(_, b)| *b > 0.0).map(|(e, boost)| data::BoostedSegment {
+			p1: g.graph.nid2id(e.p1).unwrap().clone(),
+			p2: g.graph.nid2id(e.p2).unwrap().clone(),
+			discriminator: e.discriminator.map(|d| g.graph.nid2id(d).unwrap().clone()),
+			bridge: e.bridge,
+			shaded: e.shaded,
+			boost,
+			vehicle: cleared.get(e).map(|(id, _)| (*id).clone()),
+			cleared: cleared.get(e).map(|(_, c)| *c),
+		}).collect();
+		boosted.sort_by_key(|b| (b.cleared.is_none(), b.cleared));
+		Ok(boosted)
+	}
+
+	/// Snowy priority segments of a plan (see [`Parameters::deadlines`]) - which vehicle clears each and when, and how late, the earliest first
+	pub fn priorities(roads: data::RoadGraph, snow: &data::SnowStatuses, snow_d: Option<f64>, paths: &data::Paths, params: &Parameters) -> Result<Vec<data::PrioritySegment>, Error> {
+		let g = construct(roads, params.speeds.as_ref())?;
+		let snowy = snowy(&g, snow, snow_d, params.parallels);
+		let cleared = cleared(&g, &snowy, paths, params)?;
+		let deadlines = params.deadlines.as_ref();
+		let mut priorities: Vec<_> = snowy.keys().filter_map(|e| e.priority.map(|p| (*e, p))).map(|(e, priority)| {
+			let deadline = deadlines.and_then(|d| d.levels.get(&priority)).copied();
+			let cleared = cleared.get(e);
+			data::PrioritySegment {
+				p1: g.graph.nid2id(e.p1).unwrap().clone(),
+				p2: g.graph.nid2id(e.p2).unwrap().clone(),
+				discriminator: e.discriminator.map(|d| g.graph.nid2id(d).unwrap().clone()),
+				priority,
+				deadline,
+				vehicle: cleared.map(|(id, _)| (*id).clone()),
+				cleared: cleared.map(|(_, c)| *c),
+				late: deadline.is_some_and(|d| cleared.is_none_or(|(_, c)| *c > d)),
+			}
+		}).collect();
+		priorities.sort_by_key(|p| (p.cleared.is_none(), p.cleared));
+		Ok(priorities)
+	}
+
+	/// Merges the plans of districts sharing boundary segments
+	///
+	/// A snowy segment cleared (as per [`evaluate_paths`]) in more than one plan stays with the vehicle of the cheapest route, the other vehicles being rerouted over what's left to them - from their start to their last node, if that's any cheaper.
+	///
+	/// Returns: the merged plan - vehicle ids clashing between plans prefixed with the plan's index (e.g. `1:truck`) - and the handoffs of the shared segments
+	pub fn merge(roads: data::RoadGraph, snow: &data::SnowStatuses, snow_d: Option<f64>, plans: Vec<data::Paths>, params: &Parameters) -> Result<(data::Paths, Vec<data::Handoff>), Error> {
+		let g = construct(roads, params.speeds.as_ref())?;
+		let snowy = snowy(&g, snow, snow_d, params.parallels);
+		let mut clashes: HashMap<&data::VehicleId, usize> = HashMap::default();
+		for id in plans.iter().flat_map(|p| p.0.keys()) {
+			*clashes.entry(id).or_default() += 1;
+		}
+		// every vehicle of every plan - id, start, path, and the snowy edges it clears
+		type Planned<'a> = (data::VehicleId, Option<SID>, Vec<&'a RoadEdge>, HashSet<&'a RoadEdge>);
+		let mut vehicles: Vec<Planned> = Vec::new();
+		for (i, plan) in plans.iter().enumerate() {
+			let mut dun = HashSet::default();
+			for (id, path) in &plan.0 {
+				let edges = path_edges(&g, path).map_err(|e| Error::vehicle(id, format!("path uses {}", e)))?;
+				let clears = edges.iter().copied().filter(|e| snowy.contains_key(*e) && dun.insert(*e)).collect();
+				let id = if clashes[id] > 1 { format!("{}:{}", i, id) } else { id.clone() };
+				vehicles.push((id, path.first().and_then(|s| g.graph.id2nid(&s.node)), edges, clears));
+			}
+		}
+		let cost = |edges: &[&RoadEdge], clears: &HashSet<&RoadEdge>| path_cost(edges, params, |e| snowy.get(e).copied().filter(|_| clears.contains(e))).0;
+		let costs: Vec<N64> = vehicles.iter().map(|(_, _, edges, clears)| cost(edges, clears)).collect();
+		let mut clearing: IndexMap<&RoadEdge, Vec<usize>> = IndexMap::new();
+		for (v, (_, _, edges, clears)) in vehicles.iter().enumerate() {
+			for e in edges.iter().filter(|e| clears.contains(*e)) {
+				let vs = clearing.entry(*e).or_default();
+				if !vs.contains(&v) {
+					vs.push(v);
+				}
+			}
+		}
+		let mut handoffs = Vec::new();
+		let mut dropped: Vec<HashSet<&RoadEdge>> = vehicles.iter().map(|_| HashSet::default()).collect();
+		for (e, vs) in clearing.into_iter().filter(|(_, vs)| vs.len() > 1) {
+			let to = *vs.iter().min_by_key(|v| costs[**v]).unwrap();
+			for v in vs.iter().filter(|v| **v != to) {
+				dropped[*v].insert(e);
+			}
+			handoffs.push(data::Handoff {
+				p1: g.graph.nid2id(e.p1).unwrap().clone(),
+				p2: g.graph.nid2id(e.p2).unwrap().clone(),
+				discriminator: e.discriminator.map(|d| g.graph.nid2id(d).unwrap().clone()),
+				to: vehicles[to].0.clone(),
+				from: vs.iter().filter(|v| **v != to).map(|v| vehicles[*v].0.clone()).collect(),
+			});
+		}
+		let mut merged = data::Paths::default();
+		for ((id, start, mut edges, mut clears), dropped) in vehicles.into_iter().zip(dropped) {
+			if let (Some(start), false) = (start, dropped.is_empty()) {
+				clears.retain(|e| !dropped.contains(e));
+				let end: HashSet<SID> = Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(edges.iter().copied(), start).last().map(|(u, _)| *u).into_iter().collect();
+				match graph::heuristics::solve_pwrp::<_, _, _, _, _, true>(&g.graph.graph, start, clears.clone(), Some(&end), |e| Some(e.weight())) {
+					Ok(rerouted) if cost(&rerouted, &clears) < cost(&edges, &clears) => {
+						log::debug!("Vehicle {} rerouted without {} shared segments", id, dropped.len());
+						edges = rerouted;
+					}
+					Ok(_) => (),
+					Err(unreachable) => log::warn!("Vehicle {} can't be rerouted, {} segments unreachable", id, unreachable.len()),
+				}
+			}
+			merged.0.insert(id, match start {
+				Some(start) => Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(edges.into_iter(), start).into_iter().map(|(u, e)| data::PathSegment {
+					node: g.graph.nid2id(u).unwrap().clone(),
+					discriminator: e.and_then(|e| e.discriminator).map(|d| g.graph.nid2id(d).unwrap().clone()),
+					reversal: false,
+					leg: None,
+				}).collect(),
+				None => Vec::new(),
+			});
+		}
+		Ok((merged, handoffs))
+	}
+
+	/// Forms the convoys plowing segments in echelon (see [`data::ConvoySegment`])
+	///
+	/// The vehicle clearing (as per [`evaluate_paths`]) a run of consecutive convoy segments leads it; those with the cheapest detours join it from where they'd be by then in their own routes, and go back there once the convoy splits.
+	/// The convoy forms once the last of them gets there, the others waiting for it. Convoys are formed in the order their runs start, the later ones seeing the detours and waits of the earlier ones - which hold up what their members clear afterwards.
+	///
+	/// Returns: the plan with the members' detours, and the convoys
+	pub fn convoys(roads: data::RoadGraph, snow: &data::SnowStatuses, snow_d: Option<f64>, plan: data::Paths, convoys: &data::ConvoySegments, params: &Parameters) -> Result<(data::Paths, Vec<data::Convoy>), Error> {
+		let g = construct(roads, params.speeds.as_ref())?;
+		let snowy = snowy(&g, snow, snow_d, params.parallels);
+		let segment = |p1: &NodeId, p2: &NodeId, discriminator: &Option<NodeId>| -> Option<&RoadEdge> {
+			let p1 = g.graph.id2nid(p1)?;
+			let p2 = g.graph.id2nid(p2)?;
+			let discr = match discriminator {
+				Some(d) => Some(g.graph.id2nid(d)?),
+				None => None,
+			};
+			g.graph.graph.get_edges_between(p1, p2).into_iter().find(|e| e.discriminator == discr)
+		};
+		let required: HashMap<&RoadEdge, usize> = convoys.iter().map(|c| segment(&c.p1, &c.p2, &c.discriminator).map(|e| (e, c.vehicles)).ok_or_else(|| Error::segment(&c.p1, &c.p2, &c.discriminator, "convoy segment not in the road graph"))).collect::<Result<_, _>>()?;
+		let ids: Vec<data::VehicleId> = plan.0.keys().cloned().collect();
+		let starts: Vec<Option<SID>> = plan.0.values().map(|path| path.first().and_then(|s| g.graph.id2nid(&s.node))).collect();
+		let mut paths: Vec<Vec<&RoadEdge>> = plan.0.iter().map(|(id, path)| path_edges(&g, path).map_err(|e| Error::vehicle(id, format!("path uses {}", e)))).collect::<Result<_, _>>()?;
+		let mut clearer: HashMap<&RoadEdge, (usize, usize)> = HashMap::default();
+		for (v, path) in paths.iter().enumerate() {
+			for (i, e) in path.iter().enumerate().filter(|(_, e)| snowy.contains_key(**e)) {
+				clearer.entry(*e).or_insert((v, i));
+			}
+		}
+		// waits for convoys to form, by vehicle - before which edge of its path, and how long
+		let mut waits: Vec<Vec<(usize, N64)>> = paths.iter().map(|_| Vec::new()).collect();
+		// costs of a path up to each of its nodes, clearing what its vehicle clears, and waiting for convoys
+		let times = |v: usize, path: &[&RoadEdge], waits: &[(usize, N64)]| -> Vec<N64> {
+			let mut cost = n64(0.0);
+			std::iter::once(cost).chain(path.iter().enumerate().map(|(i, e)| {
+				cost += waits.iter().filter(|(j, _)| *j == i).map(|(_, w)| *w).sum::<N64>();
+				cost += path_cost(&[*e], params, |e| snowy.get(e).copied().filter(|_| clearer.get(e).map(|(c, _)| *c) == Some(v))).0;
+				cost
+			})).collect()
+		};
+		let nodes = |start: SID, path: &[&RoadEdge]| -> Vec<SID> {
+			Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(path.iter().copied(), start).into_iter().map(|(u, _)| u).collect()
+		};
+		// runs of convoy segments - lead, first and past the last edge index, vehicles required
+		let mut runs: Vec<(usize, usize, usize, usize)> = Vec::new();
+		for (v, path) in paths.iter().enumerate() {
+			for (i, e) in path.iter().enumerate() {
+				match (required.get(e), clearer.get(e) == Some(&(v, i)), runs.last_mut()) {
+					(Some(k), true, Some((lead, _, end, n))) if *lead == v && *end == i => (*end, *n) = (i + 1, (*n).max(*k)),
+					(Some(k), true, _) if *k > 1 => runs.push((v, i, i + 1, *k)),
+					_ => {},
+				}
+			}
+		}
+		let mut order: Vec<_> = runs.iter().map(|(v, i, _, _)| times(*v, &paths[*v], &[])[*i]).enumerate().collect();
+		order.sort_by_key(|(_, t)| *t);
+		let mut formed = Vec::new();
+		for (r, _) in order {
+			let (lead, i, j, k) = runs[r];
+			let lead_nodes = nodes(starts[lead].unwrap(), &paths[lead]);
+			let lead_times = times(lead, &paths[lead], &waits[lead]);
+			let (from, to) = (lead_nodes[i], lead_nodes[j]);
+			let run: Vec<&RoadEdge> = paths[lead][i..j].to_vec();
+			// the cheapest detour of every other vehicle - where it leaves its route, the detour, and when it gets to the run
+			let mut joining: Vec<_> = (0..paths.len()).filter(|w| *w != lead).filter_map(|w| {
+				let ns = nodes(starts[w]?, &paths[w]);
+				let ts = times(w, &paths[w], &waits[w]);
+				let p = (0..ns.len())
+					.filter(|p| !runs.iter().any(|(v, i, j, _)| *v == w && i < p && p < j))
+					.min_by_key(|p| (ts[*p] - lead_times[i]).abs())?;
+				let there = g.graph.graph.pathfind::<_, _, true>(ns[p], from, |e| Some(e.length))?;
+				let back = g.graph.graph.pathfind::<_, _, true>(to, ns[p], |e| Some(e.length))?;
+				let (arrival, joined) = (ts[p] + path_cost(&there, params, |_| None).0, there.len());
+				let detour: Vec<&RoadEdge> = there.into_iter().chain(run.iter().copied()).chain(back).collect();
+				Some((detour.iter().map(|e| e.length).sum::<N64>(), w, p, detour, joined, arrival))
+			}).collect();
+			joining.sort_by_key(|(cost, w, ..)| (*cost, *w));
+			joining.truncate(k - 1);
+			if joining.len() < k - 1 {
+				log::warn!("Convoy of {} led by {} short of {} vehicles", g.graph.nid2id(from).unwrap(), ids[lead], k - 1 - joining.len());
+			}
+			// the convoy forms once the last one gets there
+			let start = joining.iter().map(|(.., arrival)| *arrival).fold(lead_times[i], N64::max);
+			let mut wait = vec![(start - lead_times[i]).raw()];
+			if start > lead_times[i] {
+				waits[lead].push((i, start - lead_times[i]));
+			}
+			let mut members = Vec::new();
+			for (_, w, p, detour, joined, arrival) in joining {
+				for (_, i, j, _) in runs.iter_mut().filter(|(v, i, _, _)| *v == w && *i >= p) {
+					(*i, *j) = (*i + detour.len(), *j + detour.len());
+				}
+				for (i, _) in waits[w].iter_mut().filter(|(i, _)| *i >= p) {
+					*i += detour.len();
+				}
+				if start > arrival {
+					waits[w].push((p + joined, start - arrival));
+				}
+				paths[w].splice(p..p, detour);
+				members.push(ids[w].clone());
+				wait.push((start - arrival).raw());
+			}
+			formed.push(data::Convoy {
+				lead: ids[lead].clone(),
+				members,
+				required: k,
+				nodes: lead_nodes[i..=j].iter().map(|u| g.graph.nid2id(*u).unwrap().clone()).collect(),
+				start: start.raw(),
+				end: (start + lead_times[j] - lead_times[i]).raw(),
+				waits: wait,
+			});
+		}
+		let mut planned = data::Paths::default();
+		for ((id, start), path) in ids.into_iter().zip(starts).zip(paths) {
+			planned.0.insert(id, match start {
+				Some(start) => Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(path.into_iter(), start).into_iter().map(|(u, e)| data::PathSegment {
+					node: g.graph.nid2id(u).unwrap().clone(),
+					discriminator: e.and_then(|e| e.discriminator).map(|d| g.graph.nid2id(d).unwrap().clone()),
+					reversal: false,
+					leg: None,
+				}).collect(),
+				None => Vec::new(),
+			});
+		}
+		Ok((planned, formed))
+	}
+
+	/// Serves a priority override with a targeted replan - of just the vehicle that can clear its segments the soonest
+	///
+	/// That vehicle clears them first thing from its start, then what it cleared before (as per [`evaluate_paths`]) on its way to where it ended; the other routes are left as they are, whether they drive the segments or not.
+	///
+	/// Returns: the plan with the vehicle rerouted, and how the override was served
+	pub fn reprioritize(roads: data::RoadGraph, snow: &data::SnowStatuses, snow_d: Option<f64>, plan: &data::Paths, priority: &data::PriorityOverride, params: &Parameters) -> Result<(data::Paths, data::Reprioritized), Error> {
+		let g = construct(roads, params.speeds.as_ref())?;
+		let snowy = snowy(&g, snow, snow_d, params.parallels);
+		let segment = |p1: &NodeId, p2: &NodeId, discriminator: &Option<NodeId>| -> Option<&RoadEdge> {
+			let p1 = g.graph.id2nid(p1)?;
+			let p2 = g.graph.id2nid(p2)?;
+			let discr = match discriminator {
+				Some(d) => Some(g.graph.id2nid(d)?),
+				None => None,
+			};
+			g.graph.graph.get_edges_between(p1, p2).into_iter().find(|e| e.discriminator == discr)
+		};
+		let urgent: HashSet<&RoadEdge> = priority.segments.iter().map(|s| segment(&s.p1, &s.p2, &s.discriminator).ok_or_else(|| Error::segment(&s.p1, &s.p2, &s.discriminator, "priority segment not in the road graph"))).collect::<Result<_, _>>()?;
+		let cost = |path: &[&RoadEdge], clears: &HashSet<&RoadEdge>| path_cost(path, params, |e| snowy.get(e).copied().filter(|_| clears.contains(e) || urgent.contains(e))).0;
+		let mut dun = HashSet::default();
+		let mut vehicles = Vec::new();
+		for (id, path) in &plan.0 {
+			let edges = path_edges(&g, path).map_err(|e| Error::vehicle(id, format!("path uses {}", e)))?;
+			let clears: HashSet<&RoadEdge> = edges.iter().copied().filter(|e| snowy.contains_key(*e) && dun.insert(*e)).collect();
+			if let Some(start) = path.first().and_then(|s| g.graph.id2nid(&s.node)) {
+				let end = Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(edges.iter().copied(), start).last().map_or(start, |(u, _)| *u);
+				vehicles.push((id, start, end, clears));
+			}
+		}
+		// the soonest any vehicle clears the urgent segments, from its start
+		let (id, start, end, clears, first) = vehicles.into_iter().filter_map(|(id, start, end, clears)| {
+			let mut first = graph::heuristics::solve_pwrp::<_, _, _, _, _, true>(&g.graph.graph, start, urgent.clone(), Some(&HashSet::default()), |e| Some(e.weight())).ok()?;
+			// up to the last of them cleared
+			let mut left = urgent.clone();
+			first.truncate(first.iter().position(|e| left.remove(e) && left.is_empty())? + 1);
+			Some((id, start, end, clears, first))
+		}).min_by_key(|(_, _, _, clears, first)| cost(first, clears)).ok_or_else(|| format!("No vehicle can reach the {} priority segments", urgent.len()))?;
+		let cleared = cost(&first, &clears);
+		let after = Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(first.iter().copied(), start).last().map_or(start, |(u, _)| *u);
+		let rest: HashSet<&RoadEdge> = clears.iter().copied().filter(|e| !urgent.contains(e)).collect();
+		let rest = if rest.is_empty() {
+			g.graph.graph.pathfind::<_, _, true>(after, end, |e| Some(e.length))
+		} else {
+			graph::heuristics::solve_pwrp::<_, _, _, _, _, true>(&g.graph.graph, after, rest, Some(&std::iter::once(end).collect()), |e| Some(e.weight())).ok()
+		}.ok_or_else(|| Error::vehicle(id, "can't get back to its route after the priority segments"))?;
+		let mut revised = plan.clone();
+		revised.0.insert(id.clone(), Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(first.into_iter().chain(rest), start).into_iter().map(|(u, e)| data::PathSegment {
+			node: g.graph.nid2id(u).unwrap().clone(),
+			discriminator: e.and_then(|e| e.discriminator).map(|d| g.graph.nid2id(d).unwrap().clone()),
+			reversal: false,
+			leg: None,
+		}).collect());
+		Ok((revised, data::Reprioritized {
+			vehicle: id.clone(),
+			cleared: cleared.raw(),
+			late: priority.deadline.is_some_and(|d| cleared > d),
+		}))
+	}
+
+	/// Updates a plan for a change of snow statuses, without solving it all over again
+	///
+	/// The vehicles are set up as for [`solve`] - their exclusions, the surfaces and grades they can clear, where their routes end and their depot trips.
+	/// Routes keep the segments they cleared (as per [`evaluate_paths`]) that are still snowy, and the newly snowed segments no route drives go to the route passing the closest whose vehicle can clear them.
+	/// Only the routes given new segments or having cleared ones no longer snowy are re-solved - locally, from their start - a route losing segments keeping its tour if that's still cheaper.
+	///
+	/// Returns: the updated plan, and what was replanned
+	#[allow(clippy::too_many_arguments)]
+	pub fn replan(roads: data::RoadGraph, before: &data::SnowStatuses, after: &data::SnowStatuses, snow_d: Option<f64>, plan: &data::Paths, vehicles: data::VehiclesConfiguration, params: &Parameters) -> Result<(data::Paths, data::Replanned), Error> {
+		let vehicles = vehicles.resolve()?;
+		let ids = data::vehicle_ids(&vehicles.road).map_err(|id| Error::vehicle(&id, "duplicate id"))?;
+		let mut g = construct(roads, params.speeds.as_ref())?;
+		g.metric = params.metric;
+		let sns = locate!(vehicles.road.iter().map(data::Vehicle::location).collect::<Vec<_>>(), g, "vehicles");
+		fix_sccs!(g, sns, "vehicles", |e| RoadEdge { directed: false, ..e });
+		g.check_coincident(&ids, &sns);
+		let refilling = params.consumption > 0.0 && vehicles.road.iter().any(|v| v.capacity().is_some());
+		let stations: Vec<_> = vehicles.depots.iter().filter(|d| refilling && d.refills(None)).collect();
+		if refilling && stations.is_empty() {
+			return Err("Vehicles with a capacity need depots to refill at".into());
+		}
+		let located: Vec<_> = locate!(stations.iter().map(|d| d.location.clone()).collect::<Vec<_>>(), g, "depots").into_iter().zip(stations).collect();
+		let depots = g.graph.graph.cost_matrix::<_, _, true>(located.iter().map(|(d, _)| *d), |e| Some(e.weight()));
+		let fleet = fleet(&g, &vehicles.road, &sns, Some((&depots, &located[..])).filter(|_| refilling))?;
+		let (was, is) = (snowy(&g, before, snow_d, params.parallels), snowy(&g, after, snow_d, params.parallels));
+		let snowed: HashSet<&RoadEdge> = is.keys().copied().filter(|e| !was.contains_key(*e)).collect();
+		let cleared: HashSet<&RoadEdge> = was.keys().copied().filter(|e| !is.contains_key(*e)).collect();
+		let mut dun = HashSet::default();
+		let mut routes = Vec::new();
+		for (id, path) in &plan.0 {
+			let v = ids.iter().position(|i| i == id).map(|i| &fleet[i]).ok_or_else(|| Error::vehicle(id, "not in the vehicles configuration"))?;
+			let edges = path_edges(&g, path).map_err(|e| Error::vehicle(id, format!("path uses {}", e)))?;
+			let clears: HashSet<&RoadEdge> = edges.iter().copied().filter(|e| (was.contains_key(*e) || is.contains_key(*e)) && v.clears(e) && dun.insert(*e)).collect();
+			let nodes: HashSet<SID> = Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(edges.iter().copied(), v.sp).into_iter().map(|(u, _)| u).collect();
+			routes.push((id, v, edges, clears, nodes));
+		}
+		let mut added = vec![HashSet::default(); routes.len()];
+		for e in snowed.iter().copied().filter(|e| !dun.contains(*e)) {
+			let ends = [e.p1, e.p2].iter().copied().collect();
+			let (i, _) = routes.iter().enumerate().filter(|(_, (_, v, ..))| v.clears(e)).filter_map(|(i, (_, v, _, _, nodes))| {
+				let (_, _, path) = g.graph.graph.pathfind_regions::<_, _, true>(nodes, &ends, |e| if v.excluded.contains(e) { None } else { Some(e.length) })?;
+				Some((i, path.iter().map(|e| e.length).sum::<N64>()))
+			}).min_by_key(|(_, d)| *d).ok_or_else(|| Error::Unreachable { segments: g.segment_ids(std::iter::once(e)), message: "newly snowed, can't be reached by any vehicle".to_string() })?;
+			added[i].insert(e);
+		}
+		let mut revised = plan.clone();
+		let mut vehicles = Vec::new();
+		for ((id, v, edges, clears, _), added) in routes.into_iter().zip(added) {
+			if added.is_empty() && !clears.iter().any(|e| cleared.contains(e)) {
+				continue;
+			}
+			let alloc: HashSet<&RoadEdge> = clears.into_iter().filter(|e| is.contains_key(*e)).chain(added.iter().copied()).collect();
+			let weight = |e: &RoadEdge| if v.excluded.contains(e) { None } else { Some(e.weight()) };
+			let path = if alloc.is_empty() {
+				match &v.end {
+					Some(end) if !end.is_empty() => g.graph.graph.pathfind_regions::<_, _, true>(&std::iter::once(v.sp).collect(), end, weight).map(|(.., path)| path),
+					_ => Some(Vec::new()),
+				}
+			} else {
+				graph::heuristics::solve_pwrp::<_, _, _, _, _, true>(&g.graph.graph, v.sp, alloc.clone(), v.end.as_ref(), weight).ok()
+			}.ok_or_else(|| Error::vehicle(id, "can't be replanned from its start"))?;
+			let path = with_refills(&path, v, params, |e| is.get(e).copied().filter(|_| alloc.contains(e))).ok_or_else(|| Error::vehicle(id, "runs out with no depot open to refill at"))?;
+			let cost = |path: &[&RoadEdge]| path_cost(path, params, |e| is.get(e).copied().filter(|_| alloc.contains(e))).0;
+			if added.is_empty() && cost(&edges) <= cost(&path) {
+				continue;
+			}
+			revised.0.insert(id.clone(), Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(path.into_iter(), v.sp).into_iter().map(|(u, e)| data::PathSegment {
+				node: g.graph.nid2id(u).unwrap().clone(),
+				discriminator: e.and_then(|e| e.discriminator).map(|d| g.graph.nid2id(d).unwrap().clone()),
+				reversal: false,
+				leg: None,
+			}).collect());
+			vehicles.push(id.clone());
+		}
+		Ok((revised, data::Replanned { snowed: snowed.len(), cleared: cleared.len(), vehicles }))
+	}
+
+	/// Evaluates the impact of hypothetical road closures on a plan.
+	///
+	/// Only the affected routes are re-solved, locally - every traversal of a closed segment is replaced with the shortest detour around it,
+	/// and of a slowed one (see [`data::Closure::slowdown`]) with the shortest way, through it or around it.
+	///
+	/// Returns: impact on every affected route, and the revised routes of those that could be detoured
+	pub fn what_if(roads: data::RoadGraph, plan: data::Paths, closures: data::Closures) -> Result<(IndexMap<data::VehicleId, data::RouteImpact>, data::Paths), Error> {
+		let mut g = construct(roads, None)?;
+		let segment = |p1: &NodeId, p2: &NodeId, discriminator: &Option<NodeId>| -> Option<&RoadEdge> {
+			let p1 = g.graph.id2nid(p1)?;
+			let p2 = g.graph.id2nid(p2)?;
+			let discr = match discriminator {
+				Some(d) => Some(g.graph.id2nid(d)?),
+				None => None,
+			};
+			g.graph.graph.get_edges_between(p1, p2).into_iter().find(|e| e.discriminator == discr)
+		};
+		let closures: Vec<(RoadEdge, Option<f64>)> = closures.iter().map(|c| match c.slowdown {
+			Some(s) if s <= 0.0 => Err(Error::segment(&c.p1, &c.p2, &c.discriminator, "slowdown must be positive")),
+			_ => segment(&c.p1, &c.p2, &c.discriminator).map(|e| (e.clone(), c.slowdown)).ok_or_else(|| Error::segment(&c.p1, &c.p2, &c.discriminator, "closed segment not in the road graph")),
+		}).collect::<Result<_, _>>()?;
+		let closed: HashSet<RoadEdge> = closures.iter().filter(|(_, s)| s.is_none()).map(|(e, _)| e.clone()).collect();
+		let changed: HashSet<&RoadEdge> = closures.iter().map(|(e, _)| e).collect();
+		let affected: Vec<(data::VehicleId, SID, Vec<RoadEdge>)> = plan.0.into_iter().map(|(id, path)| {
+			let edges = path_edges(&g, &path).map_err(|e| Error::vehicle(&id, format!("path uses {}", e)))?;
+			Ok(edges.iter().any(|e| changed.contains(*e)).then(|| (id, g.graph.id2nid(&path[0].node).unwrap(), edges.into_iter().cloned().collect())))
+		}).filter_map(Result::transpose).collect::<Result<_, Error>>()?;
+		// slowed segments just get longer, in place
+		for (e, slowdown) in &closures {
+			if let Some(s) = slowdown {
+				g.graph.graph.update_edge(e, |e| e.length *= *s);
+			}
+		}
+		let mut impacts = IndexMap::new();
+		let mut revised = data::Paths::default();
+		g.graph.with_disabled(closed.iter().cloned(), |g| {
+			for (id, start, edges) in affected {
+				let mut detoured = Some(Vec::new());
+				let mut u = start;
+				for e in &edges {
+					let v = e.other(u);
+					if changed.contains(e) {
+						let detour = g.graph.pathfind::<_, _, true>(u, v, |e| Some(e.length));
+						if detour.is_none() {
+							log::debug!("Vehicle {} can't detour around {}<->{}", id, g.nid2id(u).unwrap(), g.nid2id(v).unwrap());
+						}
+						detoured = detoured.zip(detour).map(|(mut d, p)| { d.extend(p); d });
+					} else if let Some(d) = detoured.as_mut() {
+						d.push(e);
+					}
+					u = v;
+				}
+				let cost_before = edges.iter().map(|e| e.length).sum::<N64>().raw();
+				let cost_after = detoured.as_ref().map(|d| d.iter().map(|e| e.length).sum::<N64>().raw());
+				if let Some(d) = detoured {
+					revised.0.insert(id.clone(), Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(d.into_iter(), start).into_iter().map(|(u, e)| data::PathSegment {
+						node: g.nid2id(u).unwrap().clone(),
+						discriminator: e.and_then(|e| e.discriminator).map(|d| g.nid2id(d).unwrap().clone()),
+						reversal: false,
+						leg: None,
+					}).collect());
+				}
+				impacts.insert(id, data::RouteImpact {
+					closed: edges.iter().filter(|e| changed.contains(*e)).count(),
+					cost_before,
+					cost_after,
+					detour: cost_after.map(|c| c - cost_before),
+				});
+			}
+		});
+		Ok((impacts, revised))
+	}
+
+	/// Finds the chokepoints of plow routing - the segments on most of the shortest paths between the segments to clear (see [`Graph::edge_betweenness`]).
+	///
+	/// Returns: the `top` segments by betweenness, highest first
+	pub fn critical_segments(roads: data::RoadGraph, snow: &data::SnowStatuses, snow_d: Option<f64>, top: usize) -> Result<Vec<data::CriticalSegment>, Error> {
+		let mut g = construct(roads, None)?;
+		let snowy: HashSet<RoadEdge> = snowy(&g, snow, snow_d, Parallels::All).into_keys().cloned().collect();
+		let among: HashSet<SID> = snowy.iter().flat_map(|e| [e.p1, e.p2]).collect();
+		let pairs = (among.len() * among.len().saturating_sub(1)).max(1) as f64;
+		log::debug!("{} snowy segments, between {} nodes", snowy.len(), among.len());
+		let critical: Vec<(RoadEdge, f64)> = g.graph.graph.edge_betweenness::<_, _, true>(&among, |e| Some(e.length)).into_iter()
+			.filter(|(_, b)| *b > 0.0)
+			.map(|(e, b)| (e.clone(), b / pairs))
+			.sorted_by(|(e1, b1), (e2, b2)| b2.total_cmp(b1).then_with(|| (e1.p1, e1.p2, e1.parallel).cmp(&(e2.p1, e2.p2, e2.parallel))))
+			.take(top)
+			.collect();
+		let id = |g: &PlowSolver<RoadNode, RoadEdge, IdGen>, n: SID| g.graph.nid2id(n).unwrap().clone();
+		Ok(critical.into_iter().map(|(e, betweenness)| {
+			let cut = g.graph.graph.with_disabled(vec![e.clone()], |g| !g.reachable::<true>(e.p1, false).contains(&e.p2));
+			data::CriticalSegment {
+				p1: id(&g, e.p1),
+				p2: id(&g, e.p2),
+				discriminator: e.discriminator.map(|d| id(&g, d)),
+				betweenness,
+				snowy: snowy.contains(&e),
+				cut,
+			}
+		}).collect())
+	}
+
+	/// Inserts reload trips into spreading routes.
+	///
+	/// Every route spreads `rate` per unit of distance on the required segments it's the first (of the plan, in order) to traverse.
+	/// Whenever the load left doesn't cover the next one, the vehicle makes a round trip to the nearest depot to refill to its capacity (unlimited if not given), and carries on where it left off.
+	///
+	/// Returns: the routes with the reload trips, and what each spread and where it reloaded
+	pub fn reload(roads: data::RoadGraph, plan: data::Paths, required: &data::SnowStatuses, rate: f64, capacities: &IndexMap<data::VehicleId, f64>, depots: &[data::Location]) -> Result<(data::Paths, IndexMap<data::VehicleId, data::SpreadReport>), Error> {
+		let g = construct(roads, None)?;
+		let required: HashSet<&RoadEdge> = snowy(&g, required, None, Parallels::All).into_keys().collect();
+		let depots: HashSet<SID> = locate!(depots, g, "depots").into_iter().collect();
+		let trips = g.graph.graph.cost_matrix::<_, _, true>(depots.iter().copied(), |e| Some(e.length));
+		let mut spread: HashSet<&RoadEdge> = HashSet::default();
+		let mut revised = data::Paths::default();
+		let mut reports = IndexMap::new();
+		for (id, path) in plan.0 {
+			let mut report = data::SpreadReport::default();
+			let start = match path.first() {
+				Some(s) => g.graph.id2nid(&s.node).ok_or_else(|| Error::vehicle(&id, format!("path starts at {} not in the road graph", s.node)))?,
+				None => {
+					revised.0.insert(id.clone(), path);
+					reports.insert(id, report);
+					continue;
+				},
+			};
+			let capacity = capacities.get(&id).copied();
+			let mut load = capacity.unwrap_or(f64::INFINITY);
+			let mut route: Vec<&RoadEdge> = Vec::new();
+			let mut u = start;
+			for e in path_edges(&g, &path).map_err(|e| Error::vehicle(&id, format!("path uses {}", e)))? {
+				if required.contains(e) && spread.insert(e) {
+					let need = rate * e.length.raw();
+					if let Some(capacity) = capacity.filter(|_| need > load) {
+						let trip = trips.nearest(u, |d| trips.cost(d, u).is_some())
+							.and_then(|(d, _)| Some((d, trips.path(u, d)?, trips.path(d, u)?)));
+						match trip {
+							Some((d, there, back)) => {
+								route.extend(there);
+								route.extend(back);
+								load = capacity;
+								report.reloads.push(g.graph.nid2id(d).unwrap().clone());
+							},
+							None => log::warn!("Vehicle {} can't reach a depot to reload at {}", id, g.graph.nid2id(u).unwrap()),
+						}
+					}
+					report.spread += need.min(load);
+					report.shortfall += (need - load).max(0.0);
+					load = (load - need).max(0.0);
+				}
+				route.push(e);
+				u = e.other(u);
+			}
+			revised.0.insert(id.clone(), Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(route.into_iter(), start).into_iter().map(|(u, e)| data::PathSegment {
+				node: g.graph.nid2id(u).unwrap().clone(),
+				discriminator: e.and_then(|e| e.discriminator).map(|d| g.graph.nid2id(d).unwrap().clone()),
+				reversal: false,
+				leg: None,
+			}).collect());
+			reports.insert(id, report);
+		}
+		Ok((revised, reports))
+	}
+
+	/// Shortest paths from and to the depots vehicles refill at, and the located depots
+	type Stations<'a> = (&'a CostMatrix<'a, SID, RoadEdge, N64>, &'a [(SID, &'a data::Depot)]);
+
+	/// The vehicles, as seen by the solver, starting at their located nodes - those with a capacity refilling at the located `depots` serving their resource, if any
+	fn fleet<'a>(g: &'a PlowSolver<RoadNode, RoadEdge, IdGen>, vehicles: &[data::Vehicle], sns: &[SID], depots: Option<Stations<'a>>) -> Result<Vec<Vehicle<'a, RoadEdge>>, Error> {
+		Ok(vehicles.iter().enumerate().zip(sns.iter()).try_map_all(|((i, v), sp)| Ok::<_, Error>(Vehicle {
+			sp: *sp,
+			loc: g.graph.graph.get_node(*sp).unwrap().coordinates,
+			end: g.locate_end(&v.end())?,
+			excluded: g.locate_exclusions(v.exclusions())?,
+			incompatible: g.graph.graph.edges().filter(|e| !v.clears(e.surface.as_ref()) || !v.climbs(e.grade.map(|g| g.raw()))).collect(),
+			refill: match v.capacity().zip(depots) {
+				Some((capacity, (depots, located))) => {
+					let stops: Vec<_> = located.iter().copied().filter(|(_, d)| d.refills(v.resource())).collect();
+					if stops.is_empty() {
+						return Err(Error::vehicle(&v.id(i), "no depot to refill at"));
+					}
+					Some(Refill { capacity: n64(capacity), speed: v.speed().map(n64), depots, stops })
+				},
+				None => None,
+			},
+		}))?.collect())
+	}
+
+	/// Features of a road plowing instance (see [`Features`]), as the solver would see it - without solving it
+	pub fn features(roads: data::RoadGraph, snow: &data::SnowStatuses, snow_d: Option<f64>, vehicles: &data::VehiclesConfiguration, params: &Parameters) -> Result<Features, Error> {
+		let vehicles = &vehicles.resolve()?;
+		let ids = data::vehicle_ids(&vehicles.road).map_err(|id| Error::vehicle(&id, "duplicate id"))?;
+		let mut g = construct(roads, params.speeds.as_ref())?;
+		g.metric = params.metric;
+		let sns = locate!(vehicles.road.iter().map(data::Vehicle::location).collect::<Vec<_>>(), g, "vehicles");
+		fix_sccs!(g, sns, "vehicles", |e| RoadEdge { directed: false, ..e });
+		g.check_coincident(&ids, &sns);
+		let fleet = fleet(&g, &vehicles.road, &sns, None)?;
+		let snowy = snowy(&g, snow, snow_d, params.parallels);
+		Ok(g.features(&fleet, &snowy))
+	}
+
+	/// Solves the snow plowing problem for roads.
+	///
+	/// Except it also converts all the data both ways and does other safety checks.
+	/// Disconnected regions with vehicles are solved separately, in parallel.
+	pub fn solve(roads: data::RoadGraph, snow: data::SnowStatuses, snow_d: Option<f64>, vehicles: data::VehiclesConfiguration, params: &Parameters, budget: &Budget, mut checkpoint: impl FnMut(Duration, data::Paths)) -> Result<(data::Paths, Stats), Error> {
+		let vehicles = vehicles.resolve()?;
+		let ids = data::vehicle_ids(&vehicles.road).map_err(|id| Error::vehicle(&id, "duplicate id"))?;
+		let mut g = construct(roads, params.speeds.as_ref())?;
+		g.metric = params.metric;
+		let sns = locate!(vehicles.road.iter().map(data::Vehicle::location).collect::<Vec<_>>(), g, "vehicles");
+		fix_sccs!(g, sns, "vehicles", |e| RoadEdge { directed: false, ..e });
+		g.check_coincident(&ids, &sns);
+		// vehicles with a capacity run out only if clearing uses their load up
+		let refilling = params.consumption > 0.0 && vehicles.road.iter().any(|v| v.capacity().is_some());
+		let stations: Vec<_> = vehicles.depots.iter().filter(|d| refilling && d.refills(None)).collect();
+		if refilling && stations.is_empty() {
+			return Err("Vehicles with a capacity need depots to refill at".into());
+		}
+		let located: Vec<_> = locate!(stations.iter().map(|d| d.location.clone()).collect::<Vec<_>>(), g, "depots").into_iter().zip(stations).collect();
+		let depots = g.graph.graph.cost_matrix::<_, _, true>(located.iter().map(|(d, _)| *d), |e| Some(e.weight()));
+		let fleet = fleet(&g, &vehicles.road, &sns, Some((&depots, &located[..])).filter(|_| refilling))?;
+		let snowy = snowy(&g, &snow, snow_d, params.parallels);
+		log::debug!("Constructed graph with {} nodes, {}/{} snowed segments and {} vehicles", g.graph.graph.node_count(), snowy.len(), g.graph.graph.edge_count(), sns.len());
+		let paths = |solution: &[Vec<&RoadEdge>]| -> Result<data::Paths, Error> {
+			// depot trips go where the load runs out, every snowy segment being cleared by the first path traversing it (see [`evaluate_paths`])
+			let mut dun = HashSet::default();
+			ids.iter().zip(solution.iter().zip(fleet.iter())).map(|(id, (path, v))| {
+				let path = with_refills(path, v, params, |e| snowy.get(e).copied().filter(|_| v.clears(e) && !dun.contains(e))).ok_or_else(|| Error::vehicle(id, "runs out with no depot open to refill at"))?;
+				dun.extend(path.iter().copied().filter(|e| snowy.contains_key(*e) && v.clears(e)));
+				Ok((id.clone(), Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(path.into_iter(), v.sp).into_iter().map(|(u, e)| data::PathSegment {
+					node: g.graph.nid2id(u).unwrap().clone(),
+					discriminator: e.and_then(|e| e.discriminator).map(|d| g.graph.nid2id(d).unwrap().clone()),
+					reversal: false,
+					leg: None,
+				}).collect()))
+			}).collect()
+		};
+		let (solution, stats) = g.solve_regions::<true>(&fleet, &snowy, params, budget, |t, s| if let Ok(paths) = paths(s) { checkpoint(t, paths) })?;
+		Ok((paths(&solution)?, stats))
+	}
+}
 
 /// Specialization for solving sidewalk plowing paths
 pub mod sidewalk {
@@ -520,23 +2206,52 @@ pub mod sidewalk {
 		p1: SID,
 		p2: SID,
 		discriminator: Option<SID>,
+		parallel: usize,
 		side: SidewalkSide,
 		length: N64,
+		surface: Option<data::Surface>,
+		grade: Option<N64>,
+		bridge: bool,
+		shaded: bool,
+		/// penalty for clearing the sidewalk from the opposite side of the street
+		crossing: N64,
 	}
 	impl PartialEq<RoadEdge> for RoadEdge {
 		fn eq(&self, other: &Self) -> bool {
-			self.p1 == other.p1 && self.p2 == other.p2 && self.discriminator == other.discriminator && self.side == other.side
+			self.p1 == other.p1 && self.p2 == other.p2 && self.discriminator == other.discriminator && self.parallel == other.parallel && self.side == other.side
 		}
 	}
 	impl std::hash::Hash for RoadEdge {
 		fn hash<H: std::hash::Hasher>(&self, h: &mut H) {
-			(self.p1, self.p2, self.discriminator, self.side).hash(h)
+			(self.p1, self.p2, self.discriminator, self.parallel, self.side).hash(h)
 		}
 	}
 	impl Weighted for RoadEdge {
 		fn weight(&self) -> N64 {
 			self.length
 		}
+		fn grade(&self) -> N64 {
+			self.grade.map_or_else(|| n64(0.0), |g| g.abs())
+		}
+		fn exposure(&self) -> (bool, bool) {
+			(self.bridge, self.shaded)
+		}
+		fn crossing(&self, forward: bool) -> N64 {
+			match (self.side, forward) {
+				(SidewalkSide::Left, true) | (SidewalkSide::Right, false) => self.crossing,
+				_ => n64(0.0),
+			}
+		}
+	}
+	impl Discriminated for RoadEdge {
+		fn discriminator(&self) -> Option<SID> {
+			self.discriminator
+		}
+	}
+	impl Parallel for RoadEdge {
+		fn parallel(&self) -> usize {
+			self.parallel
+		}
 	}
 	impl Edge<SID> for RoadEdge {
 		fn p1(&self) -> SID {
@@ -550,23 +2265,32 @@ pub mod sidewalk {
 		}
 	}
 
-	/// Solves the snow plowing problem for roads.
-	///
-	/// Except it also converts all the data both ways and does other safety checks.
-	pub fn solve(roads: data::RoadGraph, snow: data::SnowStatuses, snow_d: Option<f64>, vehicles: data::VehiclesConfiguration, params: &Parameters) -> Result<data::SidewalkPaths, String> {
-		let mut g: PlowSolver<RoadNode, RoadEdge, _> = plow_solver!();
+	const _: () = assert_send_sync::<PlowSolver<RoadNode, RoadEdge, IdGen>>();
+
+	/// Constructs the solver graph from the road graph, with the default crossing penalty of sidewalks
+	fn construct(roads: data::RoadGraph, crossing: N64) -> Result<PlowSolver<RoadNode, RoadEdge, IdGen>, Error> {
+		roads.check()?;
+		let mut g: PlowSolver<RoadNode, RoadEdge, IdGen> = plow_solver!();
+		let parallels = roads.parallel_indices();
+		let grades = roads.grades();
 		for n in roads.nodes.nodes {
 			g.graph = g.graph.add_node(n.into());
 		}
-		for e in roads.roads {
+		for ((e, parallel), grade) in roads.roads.into_iter().zip(parallels).zip(grades) {
 			macro_rules! edge {
 				($side:expr) => {
 					RoadEdge {
 						p1: g.graph.id2nid(&e.p1).unwrap(),
 						p2: g.graph.id2nid(&e.p2).unwrap(),
 						discriminator: e.discriminator.as_ref().map(|id| g.graph.id2nid(id).unwrap()),
+						parallel,
 						side: $side,
 						length: e.distance,
+						surface: e.surface.clone(),
+						grade: grade.map(n64),
+						bridge: e.bridge,
+						shaded: e.shaded,
+						crossing: e.crossing.unwrap_or(crossing),
 					}
 				}
 			}
@@ -578,26 +2302,623 @@ pub mod sidewalk {
 				g.graph.add_edge(edge!(SidewalkSide::Right));
 			}
 		}
-		let sns = locate!(vehicles.sidewalk, g, "vehicles");
-		let locations = sns.iter().map(|id| g.graph.graph.get_node(*id).unwrap().coordinates).collect();
+		Ok(g)
+	}
+
+	/// Dumps the solver graph, as constructed from the road graph
+	pub fn dump(roads: data::RoadGraph) -> Result<dump::GraphDump, Error> {
+		Ok(construct(roads, n64(0.0))?.graph.dump())
+	}
+
+	/// Solves the snow plowing problem for roads.
+	///
+	/// Except it also converts all the data both ways and does other safety checks.
+	pub fn solve(roads: data::RoadGraph, snow: data::SnowStatuses, snow_d: Option<f64>, vehicles: data::VehiclesConfiguration, params: &Parameters, budget: &Budget, mut checkpoint: impl FnMut(Duration, data::SidewalkPaths)) -> Result<(data::SidewalkPaths, Stats), Error> {
+		let vehicles = vehicles.resolve()?;
+		let ids = data::vehicle_ids(&vehicles.sidewalk).map_err(|id| Error::vehicle(&id, "duplicate id"))?;
+		let mut g = construct(roads, params.crossing)?;
+		g.metric = params.metric;
+		let sns = locate!(vehicles.sidewalk.iter().map(data::Vehicle::location).collect::<Vec<_>>(), g, "vehicles");
 		fix_sccs!(g, sns, "vehicles", |e| RoadEdge { side: SidewalkSide::Wroom, ..e });
-		let snowy: HashSet<_> = if let Some(_snow_d) = snow_d.filter(|d| *d > 0.0) {
-			log::debug!("Default snow level {:.5} - every sidewalk counts!", _snow_d);
-			g.graph.graph.edges().filter(|e| e.side.is_sidewalk()).collect()
+		g.check_coincident(&ids, &sns);
+		let fleet: Vec<_> = vehicles.sidewalk.iter().zip(sns.iter()).try_map_all(|(v, sp)| Ok::<_, Error>(Vehicle {
+			sp: *sp,
+			loc: g.graph.graph.get_node(*sp).unwrap().coordinates,
+			end: g.locate_end(&v.end())?,
+			excluded: g.locate_exclusions(v.exclusions())?,
+			incompatible: g.graph.graph.edges().filter(|e| !v.clears(e.surface.as_ref()) || !v.climbs(e.grade.map(|g| g.raw()))).collect(),
+			refill: None,
+		}))?.collect();
+		let snowy: Snowy<_> = if let Some(snow_d) = snow_d.filter(|d| *d > 0.0) {
+			log::debug!("Default snow level {:.5} - every sidewalk counts!", snow_d);
+			g.graph.graph.edges().filter(|e| e.side.is_sidewalk()).map(|e| (e, Snow::certain(n64(snow_d)))).collect()
 		} else {
-			snow.into_iter().filter(|s| s.depth > 0.0).filter_map(|s| {
-				let p1 = g.graph.id2nid(&s.p1)?;
-				let p2 = g.graph.id2nid(&s.p2)?;
-				let discr = s.discriminator.map(|d| g.graph.id2nid(&d).unwrap());
-				Some(g.graph.graph.get_edges_between(p1, p2).into_iter().filter(|e| e.discriminator == discr && e.side.is_sidewalk()).collect::<Vec<_>>())
-			}).flatten().collect()
+			snow_depths(snow.iter().flat_map(|s| g.locate_snow(s, params.parallels, |e| e.side.is_sidewalk())))
 		};
 		log::debug!("Constructed graph with {} nodes, {}/{} snowed segments and {} vehicles", g.graph.graph.node_count(), snowy.len(), g.graph.graph.edge_count(), sns.len());
-		let solution = g.solve::<true>(&sns, &locations, &snowy, params);
-		Ok(solution.into_iter().zip(sns.into_iter()).map(|(path, n)| Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(path.into_iter(), n).into_iter().map(|(u, e)| data::SidewalkPathSegment {
-			node: g.graph.nid2id(u).unwrap().clone(),
-			discriminator: e.and_then(|e| e.discriminator).map(|d| g.graph.nid2id(d).unwrap().clone()),
-			side: e.and_then(|e| e.side.into()),
-		}).collect()).collect())
+		let paths = |solution: &[Vec<&RoadEdge>]| -> data::SidewalkPaths {
+			ids.iter().zip(solution.iter().zip(sns.iter())).map(|(id, (path, n))| (id.clone(), Graph::<SID, RoadNode, RoadEdge>::path_to_nodes(path.iter().copied(), *n).into_iter().map(|(u, e)| data::SidewalkPathSegment {
+				node: g.graph.nid2id(u).unwrap().clone(),
+				discriminator: e.and_then(|e| e.discriminator).map(|d| g.graph.nid2id(d).unwrap().clone()),
+				side: e.and_then(|e| e.side.into()),
+			}).collect())).collect()
+		};
+		let (solution, stats) = g.solve::<true>(&fleet, &snowy, params, budget, |t, s| checkpoint(t, paths(s)))?;
+		Ok((paths(&solution), stats))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use data::{*, Vehicle};
+
+	/// `w`×`h` grid of two-way segments 100 long, node `y*w + x` at `(x, y)` thousandths of a degree
+	fn grid(w: u64, h: u64) -> RoadGraph {
+		let mut roads = Vec::new();
+		for y in 0..h {
+			for x in 0..w {
+				let u = y*w + x;
+				if x + 1 < w {
+					roads.push(RoadSegment::new(u, u + 1, 100.0));
+				}
+				if y + 1 < h {
+					roads.push(RoadSegment::new(u, u + w, 100.0));
+				}
+			}
+		}
+		RoadGraph { roads, nodes: RoadGraphNodes { nodes: (0..w*h).map(|i| Node { id: i.into(), coordinates: ((i % w) as f64 * 0.001, (i / w) as f64 * 0.001), elevation: None }).collect() } }
+	}
+
+	fn at(n: u64) -> Vehicle {
+		Vehicle::Location(Location::Node(n.into()))
+	}
+
+	fn step(n: u64) -> PathSegment {
+		PathSegment { node: n.into(), discriminator: None, reversal: false, leg: None }
+	}
+
+	fn params() -> Parameters {
+		let mut params = Preset::Light.parameters();
+		params.annealing.seed = Some(7);
+		params
+	}
+
+	/// Segments a path traverses, by their nodes (lowest first)
+	fn covered(path: &[PathSegment]) -> HashSet<(NodeId, NodeId)> {
+		path.iter().zip(path.iter().skip(1)).map(|(u, v)| if u.node.to_string() < v.node.to_string() { (u.node.clone(), v.node.clone()) } else { (v.node.clone(), u.node.clone()) }).collect()
+	}
+
+	#[test]
+	fn test_road_fleet(){
+		// sidewalk machines elsewhere don't plow the roads
+		let vehicles = VehiclesConfiguration { road: vec![at(0)], sidewalk: vec![at(5), at(5)], depots: vec![] };
+		let (paths, _) = road::solve(grid(3, 2), vec![], Some(1.0), vehicles.clone(), &params(), &Default::default(), |_, _| ()).unwrap();
+		assert_eq!(paths.0.len(), 1);
+		assert_eq!(paths.0[0][0].node, NodeId::from(0));
+		assert_eq!(covered(&paths.0[0]).len(), 7);
+		assert_eq!(road::features(grid(3, 2), &vec![], Some(1.0), &vehicles, &params()).unwrap().vehicles, 1);
+		let sidewalks_only = VehiclesConfiguration { road: vec![], ..vehicles };
+		assert!(road::solve(grid(3, 2), vec![], Some(1.0), sidewalks_only, &params(), &Default::default(), |_, _| ()).is_err());
+	}
+
+	#[test]
+	fn test_plow(){
+		// as the plow command runs it: some segments of a 3×3 grid snowed, two vehicles, a preset's parameters
+		let snow: SnowStatuses = serde_json::from_str(r#"[{"p1": 0, "p2": 1, "depth": 1}, {"p1": 4, "p2": 5, "depth": 2}, {"p1": 8, "p2": 7, "depth": 1}, {"p1": 2, "p2": 5, "depth": 0.5}]"#).unwrap();
+		let vehicles: VehiclesConfiguration = serde_json::from_str(r#"{"road": [0, {"id": "east", "location": 8}], "sidewalk": []}"#).unwrap();
+		let params: Parameters = serde_json::from_value(serde_json::to_value(params()).unwrap()).unwrap();
+		let (paths, _) = road::solve(grid(3, 3), snow.clone(), None, vehicles, &params, &Default::default(), |_, _| ()).unwrap();
+		assert_eq!(paths.0.keys().collect::<Vec<_>>(), vec!["0", "east"]);
+		assert_eq!((paths.0["0"][0].node.clone(), paths.0["east"][0].node.clone()), (NodeId::from(0), NodeId::from(8)));
+		// every snowy segment cleared, reorienting the routes keeping them so
+		let (oriented, _) = orient::orient(&grid(3, 3), &snow, None, &paths, &params).unwrap();
+		for plan in [&paths, &oriented] {
+			let cleared: HashSet<_> = plan.0.values().flat_map(|p| covered(p)).collect();
+			assert!(snow.iter().all(|s| cleared.contains(&(s.p1.clone(), s.p2.clone())) || cleared.contains(&(s.p2.clone(), s.p1.clone()))));
+		}
+		let value = |plan: &Paths| road::evaluate(grid(3, 3), &snow, None, plan, &params).unwrap().value;
+		assert!(value(&oriented) <= value(&paths));
+		assert_eq!(serde_json::from_value::<Paths>(serde_json::to_value(&paths).unwrap()).unwrap(), paths);
+	}
+
+	#[test]
+	fn test_errors(){
+		// errors tell what they're about
+		let vehicles = VehiclesConfiguration { road: vec![at(9)], sidewalk: vec![], depots: vec![] };
+		match road::solve(grid(3, 2), vec![], Some(1.0), vehicles, &params(), &Default::default(), |_, _| ()) {
+			Err(Error::Node { id, .. }) => assert_eq!(id, NodeId::from(9)),
+			r => panic!("expected a node error, got {:?}", r.map(|_| ())),
+		}
+		let paths = Routes(vec![("0".to_string(), vec![step(0), step(4)])].into_iter().collect());
+		match road::evaluate(grid(3, 2), &vec![], Some(1.0), &paths, &params()) {
+			Err(Error::Vehicle { id, .. }) => assert_eq!(id, "0"),
+			r => panic!("expected a vehicle error, got {:?}", r.map(|_| ())),
+		}
+	}
+
+	#[test]
+	fn test_parallelism(){
+		// the thread count doesn't change the plan of a seed, whether the vehicles' paths are solved in parallel or not
+		let vehicles = VehiclesConfiguration { road: vec![at(0), at(11)], sidewalk: vec![], depots: vec![] };
+		for clearing in [Clearing::OnlyAllocated, Clearing::All] {
+			let solve = |parallelism| {
+				let params = Parameters { parallelism: Some(parallelism), clearing, annealing: Annealing { restarts: 2, ..params().annealing }, ..params() };
+				road::solve(grid(4, 3), vec![], Some(1.0), vehicles.clone(), &params, &Default::default(), |_, _| ()).unwrap()
+			};
+			let ((one, one_stats), (four, four_stats)) = (solve(1), solve(4));
+			assert_eq!(one.0, four.0);
+			assert_eq!(one_stats.best, four_stats.best);
+			assert_eq!(one_stats.restarts.iter().map(|s| s.value).collect::<Vec<_>>(), four_stats.restarts.iter().map(|s| s.value).collect::<Vec<_>>());
+		}
+	}
+
+	#[test]
+	fn test_unreachable(){
+		// a pocket 2 - 3 behind the segment 1 - 2 the vehicle at 0 can't drive, closest to it but only reached by the one coming from 4
+		let node = |i: u64, x: f64, y: f64| Node { id: i.into(), coordinates: (x, y), elevation: None };
+		let roads = RoadGraph {
+			roads: vec![RoadSegment::new(0, 1, 100.0), RoadSegment::new(1, 2, 100.0), RoadSegment::new(2, 3, 100.0), RoadSegment::new(3, 4, 5000.0)],
+			nodes: RoadGraphNodes { nodes: vec![node(0, 0.0, 0.0), node(1, 0.001, 0.0), node(2, 0.001, 0.001), node(3, 0.002, 0.001), node(4, 0.05, 0.05)] },
+		};
+		let excluding = |n: u64, (p1, p2): (u64, u64)| Vehicle::Spec(VehicleSpec {
+			id: None, location: Location::Node(n.into()), end: Default::default(), exclude: vec![Exclusion::Segment { p1: p1.into(), p2: p2.into(), discriminator: None }],
+			surfaces: None, breaks: None, max_grade: None, capacity: None, speed: None, resource: None,
+		});
+		let vehicles = VehiclesConfiguration { road: vec![excluding(0, (1, 2)), at(4)], sidewalk: vec![], depots: vec![] };
+		let (paths, _) = road::solve(roads.clone(), vec![], Some(1.0), vehicles, &params(), &Default::default(), |_, _| ()).unwrap();
+		let pocket = (NodeId::from(2), NodeId::from(3));
+		assert!(!covered(&paths.0[0]).contains(&pocket));
+		assert!(covered(&paths.0[1]).contains(&pocket));
+		// without it, the pocket is left to nobody - the other vehicle at 0 can't clear it
+		let vehicles = VehiclesConfiguration { road: vec![excluding(0, (1, 2)), excluding(0, (2, 3))], sidewalk: vec![], depots: vec![] };
+		let roads = RoadGraph { roads: roads.roads[..3].to_vec(), nodes: RoadGraphNodes { nodes: roads.nodes.nodes[..4].to_vec() } };
+		match road::solve(roads, vec![], Some(1.0), vehicles, &params(), &Default::default(), |_, _| ()) {
+			Err(Error::Unreachable { segments, message }) => {
+				assert_eq!(segments, vec![pocket]);
+				assert_eq!(message, "can't be reached by any vehicle");
+			},
+			r => panic!("expected unreachable segments, got {:?}", r.map(|_| ())),
+		}
+	}
+
+	#[test]
+	fn test_convoys(){
+		// 0 - 1 - 2 - 3 - 4 - 5 - 6, 2 - 3 taking two vehicles
+		let line = RoadGraph {
+			roads: (0..6).map(|i| RoadSegment::new(i, i + 1, 100.0)).collect(),
+			nodes: RoadGraphNodes { nodes: (0..7).map(|i| Node { id: i.into(), coordinates: (i as f64 * 0.001, 0.0), elevation: None }).collect() },
+		};
+		let convoys = vec![ConvoySegment { p1: 2.into(), p2: 3.into(), discriminator: None, vehicles: 2 }];
+		let plan = |b: &[u64]| Routes(vec![("a".to_string(), vec![step(0), step(1), step(2), step(3)]), ("b".to_string(), b.iter().map(|n| step(*n)).collect())].into_iter().collect());
+		let nodes = |path: &[PathSegment]| path.iter().map(|s| s.node.clone()).collect::<Vec<_>>();
+		let ids = |ns: &[u64]| ns.iter().map(|n| NodeId::from(*n)).collect::<Vec<_>>();
+		// b, at 3 from the start, waits for a to clear its way there
+		let (paths, formed) = road::convoys(line.clone(), &vec![], Some(1.0), plan(&[3]), &convoys, &params()).unwrap();
+		assert_eq!(nodes(&paths.0["b"]), ids(&[3, 2, 3]));
+		assert_eq!((formed[0].lead.as_str(), &formed[0].members[..], formed[0].start, formed[0].end), ("a", &["b".to_string()][..], 200.0, 300.0));
+		assert_eq!(formed[0].waits, vec![0.0, 100.0]);
+		// a waits for b, which only gets there from 6 after clearing 6 - 5
+		let (paths, formed) = road::convoys(line, &vec![], Some(1.0), plan(&[6, 5]), &convoys, &params()).unwrap();
+		assert_eq!(nodes(&paths.0["b"]), ids(&[6, 5, 4, 3, 2, 3, 4, 5]));
+		assert_eq!((formed[0].start, formed[0].end), (400.0, 500.0));
+		assert_eq!(formed[0].waits, vec![200.0, 0.0]);
+	}
+
+	#[test]
+	fn test_route(){
+		// 0 - 1 - 3 local, 0 - 2 - 3 a longer arterial, a one-way shortcut 3 → 0, and a one-way 4 → 3
+		let node = |i: u64, x: f64, y: f64| Node { id: i.into(), coordinates: (x, y), elevation: None };
+		let road = |p1: u64, p2: u64, distance: f64, class: Option<&str>, directed: bool| RoadSegment { class: class.map(String::from), directed, ..RoadSegment::new(p1, p2, distance) };
+		let roads = RoadGraph {
+			roads: vec![road(0, 1, 100.0, Some("local"), false), road(1, 3, 100.0, Some("local"), false), road(0, 2, 150.0, Some("arterial"), false), road(2, 3, 150.0, Some("arterial"), false), road(3, 0, 150.0, None, true), road(4, 3, 100.0, None, true)],
+			nodes: RoadGraphNodes { nodes: vec![node(0, 0.0, 0.0), node(1, 0.001, 0.0), node(2, 0.0, 0.001), node(3, 0.001, 0.001), node(4, 0.002, 0.001)] },
+		};
+		let speeds: Speeds = serde_json::from_str(r#"{"default": 1, "classes": {"arterial": 10}}"#).unwrap();
+		let route = |from: u64, to: Location, speeds: Option<&Speeds>, directed: bool| road::route(roads.clone(), &Location::Node(from.into()), &to, speeds, directed, Default::default())
+			.map(|(route, length)| (route.into_iter().map(|s| s.node).collect::<Vec<_>>(), length));
+		let ids = |ns: &[u64]| ns.iter().map(|n| NodeId::from(*n)).collect::<Vec<_>>();
+		// the shortest way, by distance or time
+		assert_eq!(route(0, Location::Node(3.into()), None, true).unwrap(), (ids(&[0, 1, 3]), n64(200.0)));
+		assert_eq!(route(0, Location::Node(3.into()), Some(&speeds), true).unwrap(), (ids(&[0, 2, 3]), n64(30.0)));
+		// the wrong way along the shortcut, unless respecting directions - to the node nearest to coordinates
+		assert_eq!(route(0, Location::Coordinates(0.00099, 0.00101), None, false).unwrap(), (ids(&[0, 3]), n64(150.0)));
+		assert_eq!(route(3, Location::Node(0.into()), None, true).unwrap(), (ids(&[3, 0]), n64(150.0)));
+		assert_eq!(route(3, Location::Node(4.into()), None, true).unwrap_err().to_string(), "No route from 3 to 4");
+		assert_eq!(route(3, Location::Node(4.into()), None, false).unwrap(), (ids(&[3, 4]), n64(100.0)));
+	}
+
+	#[test]
+	fn test_replan(){
+		// 0 - 1 - 2 - 3 - 4 - 5, 4 - 5 a long way
+		let line = RoadGraph {
+			roads: (0..5).map(|i| RoadSegment::new(i, i + 1, if i == 4 { 5000.0 } else { 100.0 })).collect(),
+			nodes: RoadGraphNodes { nodes: (0..6).map(|i| Node { id: i.into(), coordinates: (if i == 5 { 0.05 } else { i as f64 * 0.001 }, 0.0), elevation: None }).collect() },
+		};
+		let snow = |segments: &[(u64, u64)]| segments.iter().map(|(p1, p2)| SnowStatusElement { p1: (*p1).into(), p2: (*p2).into(), discriminator: None, parallel_index: None, depth: n64(1.0), variance: None, estimated: false }).collect::<SnowStatuses>();
+		let spec = |n: u64, exclude: Vec<Exclusion>, capacity: Option<f64>| Vehicle::Spec(VehicleSpec {
+			id: None, location: Location::Node(n.into()), end: Default::default(), exclude,
+			surfaces: None, breaks: None, max_grade: None, capacity, speed: None, resource: None,
+		});
+		let plan = |routes: &[&[u64]]| Routes(routes.iter().enumerate().map(|(i, r)| (i.to_string(), r.iter().map(|n| step(*n)).collect())).collect());
+		let nodes = |path: &[PathSegment]| path.iter().map(|s| s.node.clone()).collect::<Vec<_>>();
+		let ids = |ns: &[u64]| ns.iter().map(|n| NodeId::from(*n)).collect::<Vec<_>>();
+		let before = snow(&[(0, 1), (1, 2), (2, 3)]);
+		let vehicles = VehiclesConfiguration { road: vec![spec(0, vec![Exclusion::Segment { p1: 3.into(), p2: 4.into(), discriminator: None }], None), at(5)], sidewalk: vec![], depots: vec![] };
+		let planned = plan(&[&[0, 1, 2, 3, 2, 1, 0], &[5]]);
+		// nothing changing, nothing is replanned
+		let (paths, replanned) = road::replan(line.clone(), &before, &before, None, &planned, vehicles.clone(), &params()).unwrap();
+		assert_eq!(paths, planned);
+		assert_eq!((replanned.snowed, replanned.cleared, replanned.vehicles.len()), (0, 0, 0));
+		// 3 - 4 snowing goes to the vehicle coming from 5, the closest one having it excluded
+		let (paths, replanned) = road::replan(line.clone(), &before, &snow(&[(0, 1), (1, 2), (2, 3), (3, 4)]), None, &planned, vehicles, &params()).unwrap();
+		assert_eq!(replanned.vehicles, vec!["1".to_string()]);
+		assert_eq!(paths.0["0"], planned.0["0"]);
+		assert_eq!(nodes(&paths.0["1"]), ids(&[5, 4, 3, 4, 5]));
+		// a vehicle given more to clear than it can carry goes back to its depot for more
+		let vehicles = VehiclesConfiguration { road: vec![spec(0, vec![], Some(150.0))], sidewalk: vec![], depots: vec![Depot { id: None, location: Location::Node(0.into()), capabilities: None, resources: None, hours: vec![] }] };
+		let params = Parameters { consumption: n64(1.0), ..params() };
+		let (paths, replanned) = road::replan(line, &snow(&[(0, 1)]), &before, None, &plan(&[&[0, 1, 0]]), vehicles, &params).unwrap();
+		assert_eq!((replanned.snowed, replanned.vehicles.len()), (2, 1));
+		// back to 0 before 1 - 2 and 2 - 3, driving the cleared segments again using none up
+		assert_eq!(nodes(&paths.0["0"]), ids(&[0, 1, 0, 1, 2, 1, 0, 1, 2, 3, 2, 1, 0]));
+	}
+
+	#[test]
+	fn test_merge(){
+		// 0 - 1 - 2 - 3 - 4, all snowy, planned by two districts both clearing 0 - 1 - 2 - 3
+		let line = RoadGraph {
+			roads: (0..4).map(|i| RoadSegment::new(i, i + 1, 100.0)).collect(),
+			nodes: RoadGraphNodes { nodes: (0..5).map(|i| Node { id: i.into(), coordinates: (i as f64 * 0.001, 0.0), elevation: None }).collect() },
+		};
+		let plan = |routes: &[(&str, &[u64])]| Routes(routes.iter().map(|(id, r)| (id.to_string(), r.iter().map(|n| step(*n)).collect())).collect());
+		let nodes = |path: &[PathSegment]| path.iter().map(|s| s.node.clone()).collect::<Vec<_>>();
+		let ids = |ns: &[u64]| ns.iter().map(|n| NodeId::from(*n)).collect::<Vec<_>>();
+		let west = plan(&[("t", &[0, 1, 2, 3, 2, 1, 0])]);
+		let east = plan(&[("t", &[4, 3, 2, 1, 0, 1, 2, 3, 4]), ("u", &[4])]);
+		let (merged, handoffs) = road::merge(line, &vec![], Some(1.0), vec![west, east], &params()).unwrap();
+		// clashing ids get their plan's index, the others are kept as they are
+		assert_eq!(merged.0.keys().collect::<Vec<_>>(), vec!["0:t", "1:t", "u"]);
+		// the cheaper western route keeps the shared segments, the eastern one left only 3 - 4
+		assert_eq!(nodes(&merged.0["0:t"]), ids(&[0, 1, 2, 3, 2, 1, 0]));
+		assert_eq!(nodes(&merged.0["1:t"]), ids(&[4, 3, 4]));
+		assert_eq!(nodes(&merged.0["u"]), ids(&[4]));
+		assert_eq!(handoffs.iter().map(|h| {
+			let (p1, p2) = if h.p1 < h.p2 { (&h.p1, &h.p2) } else { (&h.p2, &h.p1) };
+			(p1.clone(), p2.clone(), h.to.as_str(), h.from.clone())
+		}).collect::<Vec<_>>(), (0..3).map(|i: u64| (NodeId::from(i), NodeId::from(i + 1), "0:t", vec!["1:t".to_string()])).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn test_crossings(){
+		// 0 - 1 with a single sidewalk, plowed there and back
+		let line = |sidewalks: (bool, bool), crossing: Option<f64>| RoadGraph {
+			roads: vec![RoadSegment { sidewalks, crossing: crossing.map(n64), ..RoadSegment::new(0, 1, 100.0) }],
+			nodes: RoadGraphNodes { nodes: (0..2).map(|i| Node { id: i.into(), coordinates: (i as f64 * 0.001, 0.0), elevation: None }).collect() },
+		};
+		// the penalty the plan of a vehicle from `n` is charged
+		let penalty = |sidewalks: (bool, bool), crossing: Option<f64>, n: u64, params: &Parameters| {
+			let vehicles = VehiclesConfiguration { road: vec![], sidewalk: vec![at(n)], depots: vec![] };
+			let max = |roads: RoadGraph, params: &Parameters| {
+				let (paths, stats) = sidewalk::solve(roads, vec![], Some(1.0), vehicles.clone(), params, &Default::default(), |_, _| ()).unwrap();
+				// clearing on the way there
+				assert!(paths.0[0][1].side.is_some());
+				stats.restarts[stats.best].max
+			};
+			max(line(sidewalks, crossing), params) - max(line(sidewalks, Some(0.0)), params)
+		};
+		let params = Parameters { crossing: n64(1000.0), ..params() };
+		// clearing the left sidewalk 0 → 1 crosses the street, 1 → 0 doesn't
+		assert_eq!(penalty((true, false), None, 0, &params), n64(1000.0));
+		assert_eq!(penalty((true, false), None, 1, &params), n64(0.0));
+		// and the other way around for the right one
+		assert_eq!(penalty((false, true), None, 0, &params), n64(0.0));
+		assert_eq!(penalty((false, true), None, 1, &params), n64(1000.0));
+		// a segment's own penalty, instead of the default one
+		assert_eq!(penalty((true, false), Some(10.0), 0, &params), n64(10.0));
+		assert_eq!(penalty((true, false), Some(10.0), 0, &Parameters { crossing: n64(0.0), ..params.clone() }), n64(10.0));
+	}
+
+	#[test]
+	fn test_refills(){
+		#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+		struct Segment(SID, SID);
+		impl Weighted for Segment {
+			fn weight(&self) -> N64 {
+				n64(100.0)
+			}
+		}
+		impl graph::Edge<SID> for Segment {
+			fn p1(&self) -> SID {
+				self.0
+			}
+			fn p2(&self) -> SID {
+				self.1
+			}
+			fn directed(&self) -> bool {
+				false
+			}
+		}
+		// 0 - 1 - 2 - 3, cleared there and back from 0 with a load for 150 - depots at 0 and 3
+		let mut g: Graph<SID, (), Segment> = Default::default();
+		for i in 0..4 {
+			g.add_node(i, ());
+		}
+		for i in 0..3 {
+			g.add_edge(Segment(i, i + 1));
+		}
+		let matrix = g.cost_matrix::<_, _, false>(vec![0, 3], |e| Some(e.weight()));
+		let edge = |i: SID| g.get_edges_between(i, i + 1)[0];
+		let path = vec![edge(0), edge(1), edge(2), edge(2), edge(1), edge(0)];
+		let depot = |hours: Vec<(f64, f64)>| Depot { id: None, location: Location::Node(0.into()), capabilities: None, resources: None, hours };
+		// always open at 0, and at 3 opening late or closing early
+		let depots = [(0, depot(vec![])), (3, depot(vec![(1000.0, 2000.0)])), (3, depot(vec![(0.0, 250.0)]))];
+		let vehicle = |stops: &[usize]| super::Vehicle {
+			sp: 0, loc: (0.0, 0.0), end: None, excluded: HashSet::default(), incompatible: HashSet::default(),
+			refill: Some(Refill { capacity: n64(150.0), speed: None, depots: &matrix, stops: stops.iter().map(|i| (depots[*i].0, &depots[*i].1)).collect() }),
+		};
+		let params = Parameters { consumption: n64(1.0), ..params() };
+		let snowy = |_: &Segment| Some(Snow::certain(n64(1.0)));
+		let nodes = |route: Vec<&Segment>| Graph::<SID, (), Segment>::path_to_nodes(route.into_iter(), 0).into_iter().map(|(u, _)| u).collect::<Vec<_>>();
+		// back to the nearest depot before 1 - 2 and 2 - 3, driving back over the cleared segments using none up
+		let v = vehicle(&[0, 1]);
+		assert_eq!(refills(&path, 0, v.refill.as_ref().unwrap(), &params, snowy), (vec![(1, 1, 0, n64(200.0)), (2, 2, 0, n64(400.0))], None));
+		assert_eq!(nodes(with_refills(&path, &v, &params, snowy).unwrap()), vec![0, 1, 0, 1, 2, 1, 0, 1, 2, 3, 2, 1, 0]);
+		// waiting for the far depot to open - by which time it's on the way
+		let v = vehicle(&[1]);
+		assert_eq!(refills(&path, 0, v.refill.as_ref().unwrap(), &params, snowy), (vec![(1, 1, 3, n64(1100.0)), (2, 2, 3, n64(200.0))], None));
+		assert_eq!(nodes(with_refills(&path, &v, &params, snowy).unwrap()), vec![0, 1, 2, 3, 2, 1, 2, 3, 2, 3, 2, 1, 0]);
+		// closed by the time it would get there
+		let v = vehicle(&[2]);
+		assert_eq!(refills(&path, 0, v.refill.as_ref().unwrap(), &params, snowy), (vec![], Some(1)));
+		assert_eq!(with_refills(&path, &v, &params, snowy), None);
+		// running out costs the rest of the path once more
+		assert_eq!(penalties(&path, &v, &params, snowy), n64(500.0));
+	}
+
+	#[test]
+	fn test_depot_hours(){
+		// 0 - 1 - 2 - 3, all snowy, with a depot at 0 open until 300 and a load for 250
+		let line = RoadGraph {
+			roads: (0..3).map(|i| RoadSegment::new(i, i + 1, 100.0)).collect(),
+			nodes: RoadGraphNodes { nodes: (0..4).map(|i| Node { id: i.into(), coordinates: (i as f64 * 0.001, 0.0), elevation: None }).collect() },
+		};
+		let vehicles = |speed: Option<f64>| VehiclesConfiguration {
+			road: vec![Vehicle::Spec(VehicleSpec {
+				id: None, location: Location::Node(0.into()), end: Default::default(), exclude: vec![],
+				surfaces: None, breaks: None, max_grade: None, capacity: Some(250.0), speed, resource: None,
+			})],
+			sidewalk: vec![],
+			depots: vec![Depot { id: None, location: Location::Node(0.into()), capabilities: None, resources: None, hours: vec![(0.0, 300.0)] }],
+		};
+		let params = Parameters { consumption: n64(1.0), ..params() };
+		// at twice the speed, the vehicle is back at 0 by 200, in time to refill
+		let (paths, _) = road::solve(line.clone(), vec![], Some(1.0), vehicles(Some(2.0)), &params, &Default::default(), |_, _| ()).unwrap();
+		assert_eq!(paths.0[0].iter().map(|s| s.node.clone()).collect::<Vec<_>>(), [0, 1, 2, 1, 0, 1, 2, 3, 2, 1, 0].iter().map(|n| NodeId::from(*n)).collect::<Vec<_>>());
+		// at the pace of its path costs, only by 400, after the depot closes
+		match road::solve(line, vec![], Some(1.0), vehicles(None), &params, &Default::default(), |_, _| ()) {
+			Err(Error::Vehicle { id, message }) => {
+				assert_eq!(id, "0");
+				assert_eq!(message, "runs out with no depot open to refill at");
+			},
+			r => panic!("expected a vehicle error, got {:?}", r.map(|_| ())),
+		}
+	}
+
+	#[test]
+	fn test_objectives(){
+		// spokes 0 - 1, 0 - 2 and 0 - 3 off a hub with a drone, another one taking off from 4, across a site from the hub
+		let node = |i: u64, x: f64, y: f64| Node { id: i.into(), coordinates: (x, y), elevation: None };
+		let roads = RoadGraph {
+			roads: (1..5).map(|i| RoadSegment::new(0, i, 100.0)).collect(),
+			nodes: RoadGraphNodes { nodes: vec![node(0, 0.0, 0.0), node(1, 0.001, 0.0), node(2, 0.0, 0.001), node(3, -0.001, 0.0), node(4, 0.0, -0.001)] },
+		};
+		let site = SensitiveSite { name: None, region: vec![(-0.0001, -0.0006), (0.0001, -0.0006), (0.0001, -0.0004), (-0.0001, -0.0004)], buffer: 0.0 };
+		let drones = vec![Location::Node(0.into()), Location::Node(4.into())];
+		// the spokes are all closest to the hub, the other drone only getting one from the restarts' shaken-up allocations
+		let params = Parameters { annealing: Annealing { restarts: 4, ..params().annealing }, ..params() };
+		let plan = |objective| fly::FlightPlanner::new(params.clone()).sites(vec![site.clone()]).objective(objective).plan(roads.clone(), drones.clone(), |_, _| ()).unwrap().paths;
+		// the hub's drone flies it all, crossing over being a detour
+		let total = plan(fly::Objective::Total);
+		assert_eq!(covered(&total.0["0"]).len(), 3);
+		assert_eq!(total.0["1"].len(), 1);
+		// the other one takes a spoke off it, flying as much
+		let max = plan(fly::Objective::Max);
+		assert_eq!(covered(&max.0["0"]).len(), 2);
+		assert_eq!(covered(&max.0["1"]).len(), 2);
+		assert_eq!(max.0["1"].len(), 5);
+	}
+
+	#[test]
+	fn test_legs(){
+		// spokes 0 - 1, 0 - 2 and 0 - 3 off a hub, and 0 - 4 across a site
+		let node = |i: u64, x: f64, y: f64| Node { id: i.into(), coordinates: (x, y), elevation: None };
+		let roads = RoadGraph {
+			roads: (1..5).map(|i| RoadSegment::new(0, i, 100.0)).collect(),
+			nodes: RoadGraphNodes { nodes: vec![node(0, 0.0, 0.0), node(1, 0.001, 0.0), node(2, 0.0, 0.001), node(3, -0.001, 0.0), node(4, 0.0, -0.001)] },
+		};
+		let site = SensitiveSite { name: None, region: vec![(-0.0001, -0.0006), (0.0001, -0.0006), (0.0001, -0.0004), (-0.0001, -0.0004)], buffer: 0.0 };
+		let legs = |n: u64| {
+			let plan = fly::FlightPlanner::new(params()).sites(vec![site.clone()]).plan(roads.clone(), vec![Location::Node(n.into())], |_, _| ()).unwrap();
+			plan.paths.0["0"].iter().map(|s| s.leg).collect::<Vec<_>>()
+		};
+		// taking off from the hub, every spoke flown back over it makes a recharge stopover
+		assert_eq!(legs(0), vec![None, Some(Leg::Survey), Some(Leg::Recharge), Some(Leg::Survey), Some(Leg::Recharge), Some(Leg::Survey), Some(Leg::Return)]);
+		// from across the site, crossing it isn't surveying, and the hub isn't home
+		let mut expected = vec![None, Some(Leg::Outbound)];
+		expected.extend(vec![Some(Leg::Survey); 5]);
+		expected.extend(vec![Some(Leg::Return); 2]);
+		assert_eq!(legs(4), expected);
+	}
+
+	#[test]
+	fn test_regions(){
+		// two squares far apart, 0 - 1 - 3 - 2 and 10 - 11 - 13 - 12, with a vehicle in each
+		let square = |o: u64| vec![(o, o + 1), (o, o + 2), (o + 1, o + 3), (o + 2, o + 3)];
+		let node = |i: u64| Node { id: i.into(), coordinates: ((i / 10) as f64 * 0.01 + (i % 2) as f64 * 0.001, (i % 10 / 2) as f64 * 0.001), elevation: None };
+		let roads = RoadGraph {
+			roads: square(0).into_iter().chain(square(10)).map(|(p1, p2)| RoadSegment::new(p1, p2, 100.0)).collect(),
+			nodes: RoadGraphNodes { nodes: (0..4).chain(10..14).map(node).collect() },
+		};
+		let region = |o: u64| square(o).into_iter().map(|(p1, p2)| (NodeId::from(p1), NodeId::from(p2))).collect::<HashSet<_>>();
+		let vehicles = VehiclesConfiguration { road: vec![at(0), at(10)], sidewalk: vec![], depots: vec![] };
+		let budget = Budget { time: None, checkpoints: vec![Duration::from_secs(60), Duration::from_secs(120)] };
+		let mut checkpoints = Vec::new();
+		let (paths, _) = road::solve(roads, vec![], Some(1.0), vehicles, &params(), &budget, |t, paths| checkpoints.push((t, paths))).unwrap();
+		assert_eq!(covered(&paths.0["0"]), region(0));
+		assert_eq!(covered(&paths.0["1"]), region(10));
+		// every checkpoint has both regions' plans
+		assert_eq!(checkpoints.iter().map(|(t, _)| *t).collect::<Vec<_>>(), budget.checkpoints);
+		for (_, paths) in &checkpoints {
+			assert_eq!(covered(&paths.0["0"]), region(0));
+			assert_eq!(covered(&paths.0["1"]), region(10));
+		}
+	}
+
+	#[test]
+	fn test_evaluate(){
+		// 0 - 1 - 2 all snowy, clearing taking twice as long, 0 - 1 driven by both paths
+		let plan = |paths: &[&[u64]]| Routes(paths.iter().enumerate().map(|(i, p)| (i.to_string(), p.iter().map(|n| step(*n)).collect())).collect());
+		let (a, b): (&[u64], &[u64]) = (&[0, 1, 0], &[0, 1, 2, 1, 0]);
+		let params = |clearing| Parameters { clearing, slowdown: Slowdown::Constant(n64(2.0)), redundancy: n64(1.0), ..params() };
+		let evaluate = |paths: &Paths, clearing| road::evaluate(grid(3, 1), &vec![], Some(1.0), paths, &params(clearing)).unwrap();
+		// the first path clears 0 - 1 (both ways), the second only 1 - 2
+		let costs = evaluate(&plan(&[a, b]), Clearing::All);
+		assert_eq!(costs.costs, vec![400.0, 600.0]);
+		assert_eq!((costs.total, costs.max, costs.value), (n64(1000.0), n64(600.0), n64(1600.0)));
+		// the second path driving 0 - 1 still snowy covers it redundantly
+		let costs = evaluate(&plan(&[a, b]), Clearing::OnlyAllocated);
+		assert_eq!(costs.costs, vec![400.0, 600.0]);
+		assert_eq!(costs.value, 1700.0);
+		// in the other order, the longer path clears it all
+		let costs = evaluate(&plan(&[b, a]), Clearing::All);
+		assert_eq!(costs.costs, vec![800.0, 200.0]);
+	}
+
+	#[test]
+	fn test_parallels(){
+		// two parallel segments 0 - 1, a report 2 deep on them, clearing slowed down by 1 per unit of depth
+		let roads = RoadGraph {
+			roads: vec![RoadSegment::new(0, 1, 100.0), RoadSegment::new(0, 1, 100.0)],
+			nodes: RoadGraphNodes { nodes: (0..2).map(|i| Node { id: i.into(), coordinates: (i as f64 * 0.001, 0.0), elevation: None }).collect() },
+		};
+		let snow = |parallel_index| vec![SnowStatusElement { p1: 0.into(), p2: 1.into(), discriminator: None, parallel_index, depth: n64(2.0), variance: None, estimated: false }];
+		let cost = |parallels, parallel_index| {
+			let params = Parameters { parallels, slowdown: Slowdown::Curve { depth: vec![(n64(0.0), n64(1.0)), (n64(10.0), n64(11.0))], temperature: vec![] }, weight_max: n64(0.0), ..params() };
+			road::lower_bound(roads.clone(), &snow(parallel_index), None, 1, &params).unwrap()
+		};
+		assert_eq!(cost(Parallels::default(), None), cost(Parallels::First, None));
+		assert_eq!(cost(Parallels::First, None), 300.0);
+		assert_eq!(cost(Parallels::All, None), 600.0);
+		assert_eq!(cost(Parallels::Split, None), 400.0);
+		// a report of a specific one only snows it
+		assert_eq!(cost(Parallels::All, Some(1)), 300.0);
+	}
+
+	#[test]
+	fn test_restarts(){
+		// every run is summed up, from the seed on, and the cheapest one's plan is kept
+		let vehicles = VehiclesConfiguration { road: vec![at(0), at(8)], sidewalk: vec![], depots: vec![] };
+		let params = Parameters { annealing: Annealing { restarts: 3, ..params().annealing }, ..params() };
+		let solve = || road::solve(grid(3, 3), vec![], Some(1.0), vehicles.clone(), &params, &Default::default(), |_, _| ()).unwrap();
+		let (paths, stats) = solve();
+		assert_eq!(stats.restarts.iter().map(|s| s.seed).collect::<Vec<_>>(), vec![7, 8, 9]);
+		assert_eq!(stats.restarts[stats.best].value, stats.restarts.iter().map(|s| s.value).min().unwrap());
+		// the same seed makes the same plan
+		let (again, again_stats) = solve();
+		assert_eq!(again.0, paths.0);
+		assert_eq!(again_stats.best, stats.best);
+		assert_eq!(again_stats.restarts.iter().map(|s| (s.value, s.iterations)).collect::<Vec<_>>(), stats.restarts.iter().map(|s| (s.value, s.iterations)).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn test_seeded_runs(){
+		// vehicles at opposite corners, all snowy - the same seed makes the same plan, as experiments count on
+		let vehicles = VehiclesConfiguration { road: vec![at(0), at(15)], sidewalk: vec![], depots: vec![] };
+		let solve = || road::solve(grid(4, 4), vec![], Some(1.0), vehicles.clone(), &params(), &Default::default(), |_, _| ()).unwrap().0;
+		assert_eq!(solve(), solve());
+	}
+
+	#[test]
+	fn test_grades(){
+		// 0 - 1 flat, 1 - 2 climbing 10 over 100
+		let line = RoadGraph {
+			roads: vec![RoadSegment::new(0, 1, 100.0), RoadSegment::new(1, 2, 100.0)],
+			nodes: RoadGraphNodes { nodes: (0..3).map(|i| Node { id: i.into(), coordinates: (i as f64 * 0.001, 0.0), elevation: Some(if i == 2 { 10.0 } else { 0.0 }) }).collect() },
+		};
+		// clearing the climb is slowed down by the multiplier times its grade, driving it isn't
+		let evaluate = |path: &[u64], grade_multiplier| {
+			let paths = Routes(vec![("0".to_string(), path.iter().map(|n| step(*n)).collect())].into_iter().collect());
+			road::evaluate(line.clone(), &vec![], Some(1.0), &paths, &Parameters { grade_multiplier: n64(grade_multiplier), ..params() }).unwrap().total
+		};
+		assert_eq!(evaluate(&[1, 2, 1], 0.0), 200.0);
+		assert_eq!(evaluate(&[1, 2, 1], 5.0), 300.0);
+		assert_eq!(evaluate(&[0, 1, 0], 5.0), 200.0);
+		// a vehicle that can't take the climb leaves it to one that can
+		let climbing = |n: u64, max_grade| Vehicle::Spec(VehicleSpec {
+			id: None, location: Location::Node(n.into()), end: Default::default(), exclude: vec![],
+			surfaces: None, breaks: None, max_grade, capacity: None, speed: None, resource: None,
+		});
+		let vehicles = VehiclesConfiguration { road: vec![climbing(0, Some(0.05)), climbing(2, None)], sidewalk: vec![], depots: vec![] };
+		let (paths, _) = road::solve(line.clone(), vec![], Some(1.0), vehicles, &params(), &Default::default(), |_, _| ()).unwrap();
+		let climb = (NodeId::from(1), NodeId::from(2));
+		assert!(!covered(&paths.0["0"]).contains(&climb));
+		assert!(covered(&paths.0["1"]).contains(&climb));
+		assert!(road::solve(line, vec![], Some(1.0), VehiclesConfiguration { road: vec![climbing(0, Some(0.05))], sidewalk: vec![], depots: vec![] }, &params(), &Default::default(), |_, _| ()).is_err());
+	}
+
+	#[test]
+	fn test_risk_aversion(){
+		// 0 - 1 - 2 driven once, 2 deep give or take 2 and 1.5, clearing slowed down by 1 per unit of depth
+		let snow = vec![(0, 4.0), (1, 2.25)].into_iter().map(|(p1, variance)| SnowStatusElement { p1: p1.into(), p2: (p1 + 1).into(), discriminator: None, parallel_index: None, depth: n64(2.0), variance: Some(n64(variance)), estimated: false }).collect::<SnowStatuses>();
+		let paths = Routes(vec![("0".to_string(), vec![step(0), step(1), step(2)])].into_iter().collect());
+		let costs = |risk_aversion| {
+			let params = Parameters { risk_aversion: n64(risk_aversion), slowdown: Slowdown::Curve { depth: vec![(n64(0.0), n64(1.0)), (n64(10.0), n64(11.0))], temperature: vec![] }, ..params() };
+			road::evaluate(grid(3, 1), &snow, None, &paths, &params).unwrap()
+		};
+		let neutral = costs(0.0);
+		assert_eq!((neutral.costs[0], neutral.adjusted[0]), (n64(600.0), n64(600.0)));
+		// the deviations, 200 and 150 of cost, add up as independent ones - to 250
+		let averse = costs(2.0);
+		assert_eq!((averse.costs[0], averse.adjusted[0]), (n64(600.0), n64(1100.0)));
+		assert_eq!((averse.total, averse.max, averse.value), (n64(600.0), n64(1100.0), n64(1700.0)));
+	}
+
+	#[test]
+	fn test_recycle(){
+		// spokes 0 - 1 .. 0 - 4 off a hub both vehicles start at - the busy one's tour giving away spokes to the empty one's
+		let node = |i: u64, x: f64, y: f64| Node { id: i.into(), coordinates: (x, y), elevation: None };
+		let roads = RoadGraph {
+			roads: (1..5).map(|i| RoadSegment::new(0, i, 100.0)).collect(),
+			nodes: RoadGraphNodes { nodes: vec![node(0, 0.0, 0.0), node(1, 0.001, 0.0), node(2, 0.0, 0.001), node(3, -0.001, 0.0), node(4, 0.0, -0.001)] },
+		};
+		let vehicles = VehiclesConfiguration { road: vec![at(0), at(0)], sidewalk: vec![], depots: vec![] };
+		let params = Parameters { recycle: Recycle::ExpensiveToCheap, annealing: Annealing { main_iterations: 1, ..params().annealing }, ..params() };
+		let (paths, stats) = road::solve(roads.clone(), vec![], Some(1.0), vehicles, &params, &Default::default(), |_, _| ()).unwrap();
+		assert!(paths.0.values().all(|p| !covered(p).is_empty()));
+		// the recycled plan is scored by its own costs, not those of the plan it was recycled from
+		let costs = road::evaluate(roads, &vec![], Some(1.0), &paths, &params).unwrap();
+		assert_eq!((stats.restarts[stats.best].value, stats.restarts[stats.best].max), (costs.value, costs.max));
+	}
+
+	#[test]
+	fn test_redundant(){
+		#[derive(PartialEq, Eq, Hash, Debug)]
+		struct Segment(u32, N64);
+		impl Weighted for Segment {
+			fn weight(&self) -> N64 {
+				self.1
+			}
+		}
+		let (a, b, c) = (Segment(0, n64(100.0)), Segment(1, n64(50.0)), Segment(2, n64(10.0)));
+		let snowy: Snowy<Segment> = [&a, &b, &c].iter().map(|e| (*e, Snow::certain(n64(1.0)))).collect();
+		// b is driven by all three vehicles, the first one twice - counting once
+		let paths = vec![vec![&a, &b, &b], vec![&b, &c], vec![&b]];
+		let redundancy = |clearing, clears: &dyn Fn(usize, &Segment) -> bool| redundant(&paths, &snowy, &Parameters { clearing, ..params() }, clears);
+		assert_eq!(redundancy(Clearing::OnlyAllocated, &|_, _| true), 100.0);
+		// a vehicle unable to clear it doesn't cover it
+		assert_eq!(redundancy(Clearing::OnlyAllocated, &|i, e| i != 2 || e != &b), 50.0);
+		// the first one to drive it clears it for the others
+		assert_eq!(redundancy(Clearing::All, &|_, _| true), 0.0);
 	}
 }
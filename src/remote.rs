@@ -0,0 +1,184 @@
+//! Remote workers
+//!
+//! A matrix of instances, parameter sets and seeds outgrows a machine quickly, and its runs are independent of each other.
+//! Workers - the same binary, serving - take single runs over plain HTTP, and a coordinator hands them out a run at a time per worker.
+//! A worker that can't be reached or drops the connection is retired, and its run goes to the others.
+
+use crate::*;
+use experiment::{Problem, Record};
+use meta::Parameters;
+use plow::Budget;
+
+use std::{borrow::Cow, collections::VecDeque, io::{BufRead, BufReader, Write}, net::{TcpListener, TcpStream}, sync::{atomic::{AtomicUsize, Ordering}, mpsc, Mutex}, time::Duration};
+use serde::*;
+
+/// A single run of a matrix, as sent to a worker
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Job<'a> {
+	pub instance: String,
+	pub parameters: String,
+	pub seed: u64,
+	pub problem: Cow<'a, Problem>,
+	pub params: Cow<'a, Parameters>,
+	/// Wall-clock budget, in seconds
+	#[serde(default)]
+	pub budget: Option<f64>,
+}
+
+impl Job<'_> {
+	/// Runs the job here
+	pub fn run(&self) -> Result<Record, Error> {
+		let budget = Budget { time: self.budget.map(Duration::from_secs_f64), ..Default::default() };
+		let outcome = experiment::run(&self.problem, &self.params, self.seed, &budget)?;
+		Ok(Record { instance: self.instance.clone(), parameters: self.parameters.clone(), seed: self.seed, outcome })
+	}
+}
+
+/// Serves jobs, one at a time, as a worker
+///
+/// `POST`ed jobs are answered with their [`Record`] - `200` - or why they couldn't be run - `422`.
+pub fn serve(listener: TcpListener) {
+	for stream in listener.incoming() {
+		let mut stream = match stream {
+			Ok(s) => s,
+			Err(e) => {
+				log::warn!("Can't accept a connection: {}", e);
+				continue;
+			}
+		};
+		let (status, body) = match message(&mut BufReader::new(&stream)).and_then(|(_, body)| serde_json::from_slice::<Job>(&body).map_err(|e| e.to_string())) {
+			Ok(job) => {
+				log::info!("{} / {} / {}: running", job.instance, job.parameters, job.seed);
+				match job.run() {
+					Ok(record) => ("200 OK", serde_json::to_string(&record).unwrap()),
+					Err(e) => ("422 Unprocessable Entity", e.to_string()),
+				}
+			}
+			Err(e) => ("400 Bad Request", e),
+		};
+		if let Err(e) = write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body) {
+			log::warn!("Can't respond: {}", e);
+		}
+	}
+}
+
+/// Runs the jobs on the workers (`host:port`s), calling `done` with every job's outcome as it comes in
+///
+/// Every worker runs a job at a time, and failing workers are retired - it's only an error when all of them failed with jobs left.
+pub fn distribute<'a>(workers: &[String], jobs: Vec<Job<'a>>, mut done: impl FnMut(&Job<'a>, Result<Record, String>)) -> Result<(), Error> {
+	let pending = AtomicUsize::new(jobs.len());
+	let queue = Mutex::new(jobs.into_iter().collect::<VecDeque<_>>());
+	let (tx, rx) = mpsc::channel();
+	std::thread::scope(|s| {
+		for worker in workers {
+			let (pending, queue, tx) = (&pending, &queue, tx.clone());
+			s.spawn(move || loop {
+				let job = queue.lock().unwrap().pop_front();
+				let job = match job {
+					Some(job) => job,
+					// the jobs in flight may yet come back from failed workers
+					None if pending.load(Ordering::SeqCst) > 0 => {
+						std::thread::sleep(Duration::from_millis(100));
+						continue;
+					}
+					None => break,
+				};
+				match call(worker, &job) {
+					Ok(outcome) => {
+						pending.fetch_sub(1, Ordering::SeqCst);
+						tx.send((job, outcome)).unwrap();
+					}
+					Err(e) => {
+						log::warn!("Worker {} failed, retiring it: {}", worker, e);
+						queue.lock().unwrap().push_back(job);
+						break;
+					}
+				}
+			});
+		}
+		drop(tx);
+		for (job, outcome) in rx {
+			done(&job, outcome);
+		}
+	});
+	match queue.into_inner().unwrap().len() {
+		0 => Ok(()),
+		left => Err(format!("All workers failed, with {} runs left", left).into()),
+	}
+}
+
+/// Runs a job on a worker
+///
+/// Returns: the run's outcome, or an error if the worker failed
+fn call(worker: &str, job: &Job) -> Result<Result<Record, String>, String> {
+	let address = worker.strip_prefix("http://").unwrap_or(worker).trim_end_matches('/');
+	let body = serde_json::to_string(job).map_err(|e| e.to_string())?;
+	let mut stream = TcpStream::connect(address).map_err(|e| format!("Can't connect to {}: {}", address, e))?;
+	write!(stream, "POST /run HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", address, body.len(), body).map_err(|e| e.to_string())?;
+	let (status, body) = message(&mut BufReader::new(stream))?;
+	let body = String::from_utf8_lossy(&body);
+	match status.split_whitespace().nth(1) {
+		Some("200") => serde_json::from_str(&body).map(Ok).map_err(|e| format!("Invalid record: {}", e)),
+		Some("422") => Ok(Err(body.into_owned())),
+		_ => Err(format!("Worker responded {}: {}", status, body)),
+	}
+}
+
+/// Reads an HTTP message
+///
+/// Returns: its start line and body (`Content-Length` long)
+fn message(reader: &mut impl BufRead) -> Result<(String, Vec<u8>), String> {
+	let mut start = String::new();
+	reader.read_line(&mut start).map_err(|e| e.to_string())?;
+	if start.is_empty() {
+		return Err("Connection closed".to_string());
+	}
+	let mut length = 0;
+	loop {
+		let mut line = String::new();
+		reader.read_line(&mut line).map_err(|e| e.to_string())?;
+		let line = line.trim_end();
+		if line.is_empty() {
+			break;
+		}
+		if let Some((name, value)) = line.split_once(':') {
+			if name.eq_ignore_ascii_case("content-length") {
+				length = value.trim().parse().map_err(|_| format!("Invalid Content-Length: {}", value.trim()))?;
+			}
+		}
+	}
+	let mut body = vec![0; length];
+	reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+	Ok((start.trim_end().to_string(), body))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use data::*;
+
+	#[test]
+	fn test_distribute(){
+		// a square of roads, all snowy
+		let id = |i: u64| NodeId::Int(i);
+		let road = |p1, p2| RoadSegment::new(id(p1), id(p2), 100.0);
+		let roads = RoadGraph {
+			roads: vec![road(0, 1), road(1, 2), road(2, 3), road(3, 0)],
+			nodes: RoadGraphNodes { nodes: (0..4).map(|i| Node { id: id(i), coordinates: ((i % 2) as f64 * 0.001, (i / 2) as f64 * 0.001), elevation: None }).collect() },
+		};
+		let problem = Problem { roads, snow: vec![], snow_d: Some(1.0), vehicles: VehiclesConfiguration { road: vec![Vehicle::Location(Location::Node(id(0)))], sidewalk: vec![], depots: vec![] } };
+		let params = meta::Preset::Light.parameters();
+		let jobs: Vec<_> = (1..=3).map(|seed| Job { instance: "square".into(), parameters: "light".into(), seed, problem: Cow::Borrowed(&problem), params: Cow::Borrowed(&params), budget: None }).collect();
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let worker = listener.local_addr().unwrap().to_string();
+		std::thread::spawn(move || serve(listener));
+		// nothing listens there anymore
+		let dead = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+		let mut records = Vec::new();
+		distribute(&[dead.clone(), worker], jobs.clone(), |_, outcome| records.push(outcome.unwrap())).unwrap();
+		records.sort_by_key(|r| r.seed);
+		assert_eq!(records.iter().map(|r| r.seed).collect::<Vec<_>>(), vec![1, 2, 3]);
+		assert_eq!(records[0].outcome.value, jobs[0].run().unwrap().outcome.value);
+		assert!(distribute(&[dead], jobs, |_, _| ()).unwrap_err().to_string().contains("3 runs left"));
+	}
+}
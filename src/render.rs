@@ -0,0 +1,139 @@
+//! Plan maps, for quick visual QA without GIS
+//!
+//! Draws the road graph, the snowy segments and the vehicle routes into an SVG, with a scale bar and a legend.
+//! With the `png` feature, the SVG rasterizes into a PNG (the legend's text with the system fonts).
+
+use crate::*;
+use data::*;
+use timeline::{PALETTE, xml_escape};
+
+use std::{collections::{HashMap, HashSet}, fmt::Write};
+
+const WIDTH: f64 = 1000.0;
+const MARGIN: f64 = 20.0;
+const LEGEND: f64 = 20.0;
+const ROADS: &str = "#c7c7c7";
+const SNOW: &str = "#9ecae1";
+
+/// Equirectangular projection of the road graph's bounding box into the drawing
+struct Projection {
+	min: (f64, f64),
+	scale: (f64, f64),
+	height: f64,
+}
+
+impl Projection {
+	fn fit(nodes: &[Node]) -> Self {
+		let (mut min, mut max) = ((f64::INFINITY, f64::INFINITY), (f64::NEG_INFINITY, f64::NEG_INFINITY));
+		for n in nodes {
+			min = (min.0.min(n.coordinates.0), min.1.min(n.coordinates.1));
+			max = (max.0.max(n.coordinates.0), max.1.max(n.coordinates.1));
+		}
+		if nodes.is_empty() {
+			min = (0.0, 0.0);
+			max = (0.0, 0.0);
+		}
+		let aspect = ((min.1 + max.1) / 2.0).to_radians().cos();
+		let span = ((max.0 - min.0) * aspect).max(max.1 - min.1).max(1e-9);
+		let s = (WIDTH - 2.0 * MARGIN) / span;
+		Self {
+			min,
+			scale: (s * aspect, s),
+			height: (max.1 - min.1) * s + 2.0 * MARGIN,
+		}
+	}
+	fn xy(&self, c: (f64, f64)) -> (f64, f64) {
+		(MARGIN + (c.0 - self.min.0) * self.scale.0, self.height - MARGIN - (c.1 - self.min.1) * self.scale.1)
+	}
+	/// Meters per drawing unit
+	fn resolution(&self) -> f64 {
+		validate::geodesic(self.min, (self.min.0 + 1.0 / self.scale.0, self.min.1))
+	}
+}
+
+/// A round length (1, 2 or 5 times a power of 10) of about `target` meters
+fn round_length(target: f64) -> f64 {
+	let p = 10f64.powf(target.log10().floor());
+	[5.0, 2.0, 1.0].iter().map(|m| m * p).find(|l| *l <= target).unwrap_or(p)
+}
+
+/// Renders the road graph (grey), the snowy segments (blue) and the routes (coloured, per vehicle) as an SVG map
+pub fn to_svg(roads: &RoadGraph, snow: Option<&SnowStatuses>, paths: &Paths) -> String {
+	let proj = Projection::fit(&roads.nodes.nodes);
+	let coords: HashMap<_, _> = roads.nodes.nodes.iter().map(|n| (&n.id, proj.xy(n.coordinates))).collect();
+	let key = |p1: &NodeId, p2: &NodeId| if p1 <= p2 { (p1.clone(), p2.clone()) } else { (p2.clone(), p1.clone()) };
+	let snowy: HashSet<_> = snow.into_iter().flatten().filter(|s| s.depth > 0.0).map(|s| key(&s.p1, &s.p2)).collect();
+	let legend = 1 + paths.0.len() + if snow.is_some() { 1 } else { 0 };
+	let height = proj.height + LEGEND * (legend + 1) as f64;
+	let mut svg = String::new();
+	writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{:.0}" font-family="sans-serif" font-size="12">"#, WIDTH, height).unwrap();
+	writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+	for e in &roads.roads {
+		if let (Some(a), Some(b)) = (coords.get(&e.p1), coords.get(&e.p2)) {
+			let (stroke, width) = if snowy.contains(&key(&e.p1, &e.p2)) { (SNOW, 5.0) } else { (ROADS, 2.0) };
+			writeln!(svg, r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="{}" stroke-width="{}" stroke-linecap="round"/>"#, a.0, a.1, b.0, b.1, stroke, width).unwrap();
+		}
+	}
+	for (i, path) in paths.0.values().enumerate() {
+		let points: Vec<_> = path.iter().filter_map(|s| coords.get(&s.node)).map(|(x, y)| format!("{:.2},{:.2}", x, y)).collect();
+		writeln!(svg, r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2" stroke-opacity="0.8"/>"#, points.join(" "), PALETTE[i % PALETTE.len()]).unwrap();
+	}
+	let resolution = proj.resolution();
+	let bar = round_length(WIDTH / 5.0 * resolution);
+	let y = proj.height;
+	writeln!(svg, r#"<line x1="{}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="black" stroke-width="3"/>"#, WIDTH - MARGIN - bar / resolution, y, WIDTH - MARGIN, y).unwrap();
+	let label = if bar >= 1000.0 { format!("{} km", bar / 1000.0) } else { format!("{} m", bar) };
+	writeln!(svg, r#"<text x="{}" y="{:.2}" text-anchor="end">{}</text>"#, WIDTH - MARGIN, y + LEGEND * 0.75, label).unwrap();
+	let entries = std::iter::once(("roads".to_string(), ROADS))
+		.chain(snow.map(|_| ("snow".to_string(), SNOW)))
+		.chain(paths.0.keys().enumerate().map(|(i, id)| (format!("vehicle {}", id), PALETTE[i % PALETTE.len()])));
+	for (i, (name, colour)) in entries.enumerate() {
+		let y = proj.height + LEGEND * (i as f64 + 1.0);
+		writeln!(svg, r#"<rect x="{}" y="{:.2}" width="20" height="4" fill="{}"/>"#, MARGIN, y - 4.0, colour).unwrap();
+		writeln!(svg, r#"<text x="{}" y="{:.2}">{}</text>"#, MARGIN + 28.0, y, xml_escape(&name)).unwrap();
+	}
+	svg.push_str("</svg>\n");
+	svg
+}
+
+/// Rasterize a [`to_svg`] drawing into a PNG
+#[cfg(feature = "png")]
+pub fn to_png(svg: &str) -> Result<Vec<u8>, Error> {
+	use resvg::{tiny_skia, usvg::{self, TreeParsing, TreePostProc}};
+	let mut tree = usvg::Tree::from_str(svg, &usvg::Options::default()).map_err(|e| format!("Plan map: {}", e))?;
+	let mut fonts = usvg::fontdb::Database::new();
+	fonts.load_system_fonts();
+	tree.postprocess(usvg::PostProcessingSteps { convert_text_into_paths: true }, &fonts);
+	let size = tree.size.to_int_size();
+	let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or("Plan map: empty drawing")?;
+	pixmap.fill(tiny_skia::Color::WHITE);
+	resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+	pixmap.encode_png().map_err(|e| format!("Plan map: {}", e).into())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_render(){
+		assert_eq!(round_length(740.0), 500.0);
+		assert_eq!(round_length(2100.0), 2000.0);
+		let node = |id: &'static str, lon: f64, lat: f64| Node { id: id.into(), coordinates: (lon, lat), elevation: None };
+		let road = |p1: &'static str, p2: &'static str| RoadSegment::new(p1, p2, 10.0);
+		let roads = RoadGraph {
+			roads: vec![road("a", "b"), road("b", "c")],
+			nodes: RoadGraphNodes { nodes: vec![node("a", 0.0, 0.0), node("b", 0.01, 0.0), node("c", 0.01, 0.01)] },
+		};
+		let snow = vec![SnowStatusElement { p1: "c".into(), p2: "b".into(), discriminator: None, parallel_index: None, depth: n64(1.0), variance: None, estimated: false }];
+		let seg = |n: &'static str| PathSegment { node: n.into(), discriminator: None, reversal: false, leg: None };
+		let plan = vec![("t<1>".to_string(), vec![seg("a"), seg("b"), seg("c")])].into_iter().collect();
+		let svg = to_svg(&roads, Some(&snow), &plan);
+		assert_eq!(svg.matches(SNOW).count(), 2);
+		assert!(svg.contains(r#"<polyline points="20.00,980.00 980.00,980.00 980.00,20.00""#));
+		assert!(svg.contains("vehicle t&lt;1&gt;"));
+		assert!(svg.contains(">200 m</text>"));
+		#[cfg(feature = "png")]
+		assert!(to_png(&svg).unwrap().starts_with(b"\x89PNG"));
+	}
+}
@@ -0,0 +1,185 @@
+//! Interactive tuning sessions
+//!
+//! Calibrating meta parameters means solving the same instance over and over. A session loads the instance once,
+//! and takes commands to tweak the parameters and re-solve, reporting how the objective moved since the previous solve.
+
+use crate::*;
+use experiment::Problem;
+use meta::Parameters;
+use plow::Budget;
+
+use std::{path::PathBuf, time::Instant};
+use serde_json::Value;
+
+/// Session command help
+pub const HELP: &str = "\
+set <key> <value>  set a meta parameter, e.g. `set annealing.main_iterations 50` (the value is JSON, or else a string)
+show [key]         show the meta parameters, or one of them
+solve              solve with the current parameters, comparing the objective with the previous solve
+save <file>        write the last plan to a JSON file
+reset              restore the parameters loaded
+help               this help
+quit               end the session";
+
+/// A session command
+#[derive(Clone, PartialEq, Debug)]
+pub enum Command {
+	Set(String, Value),
+	Show(Option<String>),
+	Solve,
+	Save(PathBuf),
+	Reset,
+	Help,
+	Quit,
+}
+
+impl Command {
+	/// Parses a command line, `None` for a blank one
+	pub fn parse(line: &str) -> Result<Option<Self>, Error> {
+		let mut words = line.split_whitespace();
+		let command = match words.next() {
+			Some(c) => c,
+			None => return Ok(None),
+		};
+		let rest: Vec<_> = words.collect();
+		let cmd = match (command, &rest[..]) {
+			("set", [key, value @ ..]) if !value.is_empty() => {
+				let value = value.join(" ");
+				Self::Set(key.to_string(), serde_json::from_str(&value).unwrap_or(Value::String(value)))
+			},
+			("show", []) => Self::Show(None),
+			("show", [key]) => Self::Show(Some(key.to_string())),
+			("solve", []) => Self::Solve,
+			("save", [file]) => Self::Save(file.into()),
+			("reset", []) => Self::Reset,
+			("help", []) => Self::Help,
+			("quit" | "exit", []) => Self::Quit,
+			_ => return Err(format!("Invalid command `{}` (try `help`)", line.trim()).into()),
+		};
+		Ok(Some(cmd))
+	}
+}
+
+/// A meta parameter, by its dotted key (e.g. `annealing.cooling_factor`)
+pub fn get(params: &Parameters, key: &str) -> Result<Value, Error> {
+	let params = serde_json::to_value(params).map_err(|e| e.to_string())?;
+	key.split('.').try_fold(&params, |v, k| v.get(k)).cloned().ok_or_else(|| format!("Unknown parameter {}", key).into())
+}
+
+/// Meta parameters with one of them, by its dotted key, set
+///
+/// Keys under a parameter not set (e.g. `speeds.default` without `speeds`) start it out empty.
+pub fn set(params: &Parameters, key: &str, value: Value) -> Result<Parameters, Error> {
+	let mut params = serde_json::to_value(params).map_err(|e| e.to_string())?;
+	let mut v = &mut params;
+	let mut unset = false;
+	for k in key.split('.') {
+		if v.is_null() {
+			*v = Value::Object(Default::default());
+			unset = true;
+		}
+		let o = v.as_object_mut().ok_or_else(|| format!("Parameter {} isn't a structure", key))?;
+		if !unset && !o.contains_key(k) {
+			return Err(format!("Unknown parameter {}", key).into());
+		}
+		v = o.entry(k).or_insert(Value::Null);
+	}
+	*v = value;
+	serde_json::from_value(params).map_err(|e| format!("Invalid {}: {}", key, e).into())
+}
+
+/// How a solve did
+#[derive(Clone, PartialEq, Debug)]
+pub struct Solved {
+	/// Annealing objective of the plan (see [`plow::PathCosts::value`])
+	pub value: N64,
+	pub total: N64,
+	pub max: N64,
+	/// Solving time, in seconds
+	pub runtime: f64,
+}
+
+/// An interactive session on a road plowing problem
+pub struct Session {
+	problem: Problem,
+	budget: Budget,
+	loaded: Parameters,
+	pub params: Parameters,
+	/// The last solve, and its plan
+	pub last: Option<(Solved, data::Paths)>,
+}
+
+impl Session {
+	pub fn new(problem: Problem, params: Parameters, budget: Budget) -> Self {
+		Self { problem, budget, loaded: params.clone(), params, last: None }
+	}
+	/// Solves with the current parameters, from scratch - so solves with different parameters compare on an equal footing
+	pub fn solve(&mut self) -> Result<&Solved, Error> {
+		let p = &self.problem;
+		let start = Instant::now();
+		let (paths, _) = plow::road::solve(p.roads.clone(), p.snow.clone(), p.snow_d, p.vehicles.clone(), &self.params, &self.budget, |_, _| ())?;
+		let runtime = start.elapsed().as_secs_f64();
+		let costs = plow::road::evaluate(p.roads.clone(), &p.snow, p.snow_d, &paths, &self.params)?;
+		let solved = Solved { value: costs.value, total: costs.total, max: costs.max, runtime };
+		Ok(&self.last.insert((solved, paths)).0)
+	}
+	/// Runs a command, returning what to print
+	pub fn run(&mut self, cmd: Command) -> Result<String, Error> {
+		match cmd {
+			Command::Set(key, value) => {
+				let before = get(&self.params, &key)?;
+				self.params = set(&self.params, &key, value)?;
+				Ok(format!("{}: {} -> {}", key, before, get(&self.params, &key)?))
+			},
+			Command::Show(None) => serde_json::to_string_pretty(&self.params).map_err(|e| e.to_string().into()),
+			Command::Show(Some(key)) => get(&self.params, &key).map(|v| v.to_string()),
+			Command::Solve => {
+				let previous = self.last.as_ref().map(|(s, _)| s.clone());
+				let s = self.solve()?;
+				let delta = |now: N64, then: Option<N64>| match then {
+					Some(then) => format!("{:.1} ({:+.1})", now, now - then),
+					None => format!("{:.1}", now),
+				};
+				Ok(format!("value {}, total {}, max {} in {:.2}s",
+					delta(s.value, previous.as_ref().map(|p| p.value)),
+					delta(s.total, previous.as_ref().map(|p| p.total)),
+					delta(s.max, previous.as_ref().map(|p| p.max)),
+					s.runtime))
+			},
+			Command::Save(file) => {
+				let (_, paths) = self.last.as_ref().ok_or("Nothing solved yet")?;
+				let f = std::fs::File::create(&file).map_err(|e| format!("Can't write {}: {}", file.display(), e))?;
+				serde_json::to_writer(f, paths).map_err(|e| e.to_string())?;
+				Ok(format!("Saved to {}", file.display()))
+			},
+			Command::Reset => {
+				self.params = self.loaded.clone();
+				Ok("Parameters restored".to_string())
+			},
+			Command::Help => Ok(HELP.to_string()),
+			Command::Quit => Ok(String::new()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_commands(){
+		assert_eq!(Command::parse("  ").unwrap(), None);
+		assert_eq!(Command::parse("set annealing.main_iterations 50").unwrap(), Some(Command::Set("annealing.main_iterations".into(), 50.into())));
+		assert_eq!(Command::parse("set clearing All").unwrap(), Some(Command::Set("clearing".into(), "All".into())));
+		assert!(Command::parse("solve now").is_err());
+		let params: Parameters = serde_json::from_str(r#"{"recycle": "No", "clearing": "All", "reorder": "No", "realloc": "No",
+			"annealing": {"main_iterations": 8, "ft_iterations": 2, "starting_temperature": 1000, "cooling_factor": 0.3},
+			"slowdown": 2, "weight_total": 1, "weight_max": 10}"#).unwrap();
+		let tuned = set(&params, "annealing.cooling_factor", 0.5.into()).unwrap();
+		assert_eq!(get(&tuned, "annealing.cooling_factor").unwrap(), 0.5);
+		assert_eq!(tuned.annealing.main_iterations, 8);
+		assert!(set(&params, "annealing.colling_factor", 0.5.into()).is_err());
+		assert!(set(&params, "clearing", "Some".into()).is_err());
+		assert_eq!(set(&params, "temperature", (-5).into()).unwrap().temperature, Some(n64(-5.0)));
+	}
+}
@@ -0,0 +1,57 @@
+//! JSON Schemas of the input/output types
+//!
+//! Partner systems can validate their payloads against these before calling us.
+//! The schemas are derived from the types (`JsonSchema`, under this feature) and describe their spec'd field names - the aliases they also deserialize from (e.g. `from`/`to`) are left out.
+
+use crate::*;
+use data::*;
+use meta::*;
+
+use indexmap::IndexMap;
+use schemars::{JsonSchema, gen::SchemaSettings};
+use serde_json::Value;
+
+/// A standalone schema document of a type, subschemas inlined
+pub fn document<T: JsonSchema>(title: &str) -> Value {
+	let mut schema = SchemaSettings::draft07().with(|s| s.inline_subschemas = true).into_generator().into_root_schema_for::<T>();
+	schema.schema.metadata().title = Some(title.to_string());
+	serde_json::to_value(schema).unwrap()
+}
+
+/// Schema documents of all the input/output types, by type name
+pub fn schemas() -> IndexMap<&'static str, Value> {
+	vec![
+		("RoadGraph", document::<RoadGraph>("RoadGraph")),
+		("SnowStatuses", document::<SnowStatuses>("SnowStatuses")),
+		("VehiclesConfiguration", document::<VehiclesConfiguration>("VehiclesConfiguration")),
+		("Parameters", document::<Parameters>("Parameters")),
+		("Paths", document::<Paths>("Paths")),
+		("Schedules", document::<Schedules>("Schedules")),
+	].into_iter().collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_schemas(){
+		let schemas = schemas();
+		assert_eq!(schemas.keys().cloned().collect::<Vec<_>>(), vec!["RoadGraph", "SnowStatuses", "VehiclesConfiguration", "Parameters", "Paths", "Schedules"]);
+		let road = &schemas["RoadGraph"]["properties"]["roads"]["items"];
+		assert_eq!(road["properties"]["distance"]["type"], "number");
+		assert!(road["required"].as_array().unwrap().contains(&"p1".into()));
+		// defaulted fields aren't
+		assert!(!road["required"].as_array().unwrap().contains(&"directed".into()));
+		// flattened in
+		assert!(schemas["RoadGraph"]["properties"]["nodes"].is_object());
+		assert_eq!(schemas["SnowStatuses"]["type"], "array");
+		assert_eq!(schemas["SnowStatuses"]["title"], "SnowStatuses");
+		// a depot or a vehicle is a spec, or a bare location
+		assert_eq!(schemas["VehiclesConfiguration"]["properties"]["depots"]["items"]["anyOf"].as_array().unwrap().len(), 2);
+		assert_eq!(schemas["VehiclesConfiguration"]["properties"]["road"]["items"]["anyOf"].as_array().unwrap().len(), 2);
+		// keyed by vehicle id, or positional
+		assert_eq!(schemas["Paths"]["anyOf"].as_array().unwrap().len(), 2);
+		assert!(serde_json::to_string(&schemas["Parameters"]["properties"]["parallels"]).unwrap().contains("\"All\""));
+	}
+}
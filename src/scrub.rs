@@ -0,0 +1,131 @@
+//! Anonymized instances
+//!
+//! Municipal data can't be attached to a public bug report as is. A scrubbed instance has the same topology, lengths, surfaces and snow - what the
+//! solvers see - but opaque node ids, coordinates jittered by up to a tolerance, and none of the properties carried over from the source data.
+
+use crate::*;
+use data::*;
+
+use std::collections::HashMap;
+use rand::{Rng, SeedableRng, prelude::SliceRandom, rngs::StdRng};
+
+/// Scrubs a road graph, and the snow statuses and vehicles configurations referencing its nodes, consistently
+pub struct Scrubber {
+	/// Token of every node id
+	ids: HashMap<NodeId, NodeId>,
+	/// Tolerance, in meters
+	jitter: f64,
+	rng: StdRng,
+}
+
+impl Scrubber {
+	/// Tokens for the nodes of a road graph, numbered in random order - coordinates to be jittered by up to `jitter` meters
+	pub fn new(roads: &RoadGraph, jitter: f64, seed: Option<u64>) -> Self {
+		let mut rng = StdRng::seed_from_u64(seed.unwrap_or_else(rand::random));
+		let mut tokens: Vec<u64> = (0..roads.nodes.nodes.len() as u64).collect();
+		tokens.shuffle(&mut rng);
+		let ids = roads.nodes.nodes.iter().zip(tokens).map(|(n, t)| (n.id.clone(), NodeId::Int(t))).collect();
+		Self { ids, jitter, rng }
+	}
+	/// Token of a node id
+	pub fn id(&self, id: &NodeId) -> Result<NodeId, Error> {
+		self.ids.get(id).cloned().ok_or_else(|| Error::node(id, "not in the road graph"))
+	}
+	fn ids(&self, p1: &NodeId, p2: &NodeId, discriminator: &Option<NodeId>) -> Result<(NodeId, NodeId, Option<NodeId>), String> {
+		Ok((self.id(p1)?, self.id(p2)?, discriminator.as_ref().map(|d| self.id(d)).transpose()?))
+	}
+	/// `(lon, lat)` moved a random distance up to the tolerance, in a random direction
+	pub fn coordinates(&mut self, (lon, lat): (f64, f64)) -> (f64, f64) {
+		if self.jitter <= 0.0 {
+			return (lon, lat);
+		}
+		// uniform over the disc
+		let r = self.jitter * self.rng.gen::<f64>().sqrt();
+		let a = self.rng.gen_range(0.0..std::f64::consts::TAU);
+		(lon + r * a.cos() / (111_320.0 * lat.to_radians().cos()).max(1.0), lat + r * a.sin() / 110_574.0)
+	}
+	fn location(&mut self, l: &Location) -> Result<Location, String> {
+		Ok(match l {
+			Location::Node(id) => Location::Node(self.id(id)?),
+			Location::Coordinates(lon, lat) => {
+				let (lon, lat) = self.coordinates((*lon, *lat));
+				Location::Coordinates(lon, lat)
+			},
+			Location::Depot { depot } => return Err(format!("Unresolved reference to depot {}", depot)),
+		})
+	}
+	fn region(&mut self, region: &[(f64, f64)]) -> Vec<(f64, f64)> {
+		region.iter().map(|c| self.coordinates(*c)).collect()
+	}
+	/// The road graph, its segments keeping their lengths and everything solving depends on
+	pub fn roads(&mut self, roads: &RoadGraph) -> Result<RoadGraph, Error> {
+		let segments = roads.roads.iter().map(|e| {
+			let (p1, p2, discriminator) = self.ids(&e.p1, &e.p2, &e.discriminator)?;
+			Ok(RoadSegment { p1, p2, discriminator, properties: Default::default(), ..e.clone() })
+		}).collect::<Result<_, String>>()?;
+		let nodes = roads.nodes.nodes.iter().map(|n| Ok(Node { id: self.id(&n.id)?, coordinates: self.coordinates(n.coordinates), elevation: n.elevation })).collect::<Result<_, String>>()?;
+		Ok(RoadGraph { roads: segments, nodes: RoadGraphNodes { nodes } })
+	}
+	/// Snow statuses of the road graph
+	pub fn snow(&self, snow: &SnowStatuses) -> Result<SnowStatuses, Error> {
+		snow.iter().map(|s| {
+			let (p1, p2, discriminator) = self.ids(&s.p1, &s.p2, &s.discriminator)?;
+			Ok(SnowStatusElement { p1, p2, discriminator, ..s.clone() })
+		}).collect()
+	}
+	/// Vehicles configuration over the road graph - vehicles and depots identified by their index, references to depots resolved
+	pub fn vehicles(&mut self, vehicles: &VehiclesConfiguration) -> Result<VehiclesConfiguration, Error> {
+		let vehicles = &vehicles.resolve()?;
+		let mut scrub = |vs: &[Vehicle]| vs.iter().map(|v| Ok(match v {
+			Vehicle::Location(l) => Vehicle::Location(self.location(l)?),
+			Vehicle::Spec(spec) => Vehicle::Spec(VehicleSpec {
+				id: None,
+				location: self.location(&spec.location)?,
+				end: match &spec.end {
+					RouteEnd::Location(l) => RouteEnd::Location(self.location(l)?),
+					RouteEnd::Region(r) => RouteEnd::Region(self.region(r)),
+					end => end.clone(),
+				},
+				exclude: spec.exclude.iter().map(|x| Ok(match x {
+					Exclusion::Region(r) => Exclusion::Region(self.region(r)),
+					Exclusion::Segment { p1, p2, discriminator } => {
+						let (p1, p2, discriminator) = self.ids(p1, p2, discriminator)?;
+						Exclusion::Segment { p1, p2, discriminator }
+					},
+				})).collect::<Result<_, String>>()?,
+				..spec.clone()
+			}),
+		})).collect::<Result<Vec<_>, String>>();
+		let (road, sidewalk) = (scrub(&vehicles.road)?, scrub(&vehicles.sidewalk)?);
+		Ok(VehiclesConfiguration { road, sidewalk, depots: vehicles.depots.iter().map(|d| Ok(Depot { id: None, location: self.location(&d.location)?, ..d.clone() })).collect::<Result<_, String>>()? })
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_scrub(){
+		let node = |id: &'static str, lon: f64| Node { id: id.into(), coordinates: (lon, 45.0), elevation: None };
+		let road = |p1: &'static str, p2: &'static str| RoadSegment { surface: Some("gravel".into()), properties: serde_json::from_value(serde_json::json!({"name": "Main St"})).unwrap(), ..RoadSegment::new(p1, p2, 10.0) };
+		let g = RoadGraph {
+			roads: vec![road("a", "b"), road("b", "c")],
+			nodes: RoadGraphNodes { nodes: vec![node("a", 0.0), node("b", 0.01), node("c", 0.02)] },
+		};
+		let mut scrubber = Scrubber::new(&g, 10.0, Some(7));
+		let scrubbed = scrubber.roads(&g).unwrap();
+		assert!(scrubbed.roads.iter().all(|e| e.properties.is_empty() && e.distance == 10.0 && e.surface.as_deref() == Some("gravel")));
+		assert_eq!(scrubbed.roads[0].p2, scrubbed.roads[1].p1);
+		assert!(scrubbed.nodes.nodes.iter().all(|n| n.id.name().is_none()));
+		for (n, s) in g.nodes.nodes.iter().zip(&scrubbed.nodes.nodes) {
+			let moved = validate::geodesic(n.coordinates, s.coordinates);
+			assert!(moved > 0.0 && moved <= 10.0 + 1e-6, "moved {}", moved);
+		}
+		let vehicles = VehiclesConfiguration { road: vec![Vehicle::Spec(VehicleSpec { id: Some("truck-7".into()), location: Location::Node("c".into()), end: Default::default(), exclude: vec![], surfaces: None, breaks: None, max_grade: None, capacity: None, speed: None, resource: None })], sidewalk: vec![], depots: vec![] };
+		let vehicles = scrubber.vehicles(&vehicles).unwrap();
+		assert_eq!(vehicles.road[0].id(0), "0");
+		assert_eq!(vehicles.road[0].location(), &Location::Node(scrubbed.roads[1].p2.clone()));
+		assert!(scrubber.snow(&vec![SnowStatusElement { p1: "x".into(), p2: "a".into(), discriminator: None, parallel_index: None, depth: n64(1.0), variance: None, estimated: false }]).is_err());
+	}
+}
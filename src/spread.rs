@@ -0,0 +1,115 @@
+//! Salt/abrasive spreading
+//!
+//! Spreading isn't plowing everything snowy - only the segments matching coverage rules (hills, bridges, intersections) are to be spread on.
+//! They are routed with the road plowing machinery, then the routes get reload trips to the depots as the spreaders run out.
+
+use crate::*;
+use data::*;
+use meta::Parameters;
+use plow::{Budget, Stats};
+
+use std::{cmp::Reverse, collections::{BinaryHeap, HashMap}, time::Duration};
+use indexmap::IndexMap;
+use serde::*;
+
+/// Which road segments are to be spread on - any matching one of the rules
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct Rules {
+	/// Segments at least this steep, either way (see [`RoadGraph::grades`])
+	#[serde(default)]
+	pub min_grade: Option<f64>,
+	/// Segments of these classes (e.g. `bridge`)
+	#[serde(default)]
+	pub classes: Vec<StreetClass>,
+	/// Segments within this distance of an intersection (a node of 3 segments or more)
+	#[serde(default)]
+	pub intersections: Option<f64>,
+}
+
+/// Spreading configuration
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Spreading {
+	pub rules: Rules,
+	/// Amount spread per unit of distance
+	pub rate: f64,
+	/// Where spreaders reload - the vehicles configuration's depots by default
+	#[serde(default)]
+	pub depots: Vec<Depot>,
+}
+
+/// Distance of every node to the nearest intersection, up to `max`
+fn intersection_distances(roads: &RoadGraph, max: f64) -> HashMap<&NodeId, f64> {
+	let mut adjacent: HashMap<&NodeId, Vec<(&NodeId, f64)>> = HashMap::new();
+	for e in &roads.roads {
+		adjacent.entry(&e.p1).or_default().push((&e.p2, e.distance.raw()));
+		adjacent.entry(&e.p2).or_default().push((&e.p1, e.distance.raw()));
+	}
+	let mut distances = HashMap::new();
+	let mut q: BinaryHeap<_> = adjacent.iter().filter(|(_, es)| es.len() >= 3).map(|(n, _)| (Reverse(n64(0.0)), *n)).collect();
+	while let Some((Reverse(d), u)) = q.pop() {
+		if distances.contains_key(u) {
+			continue;
+		}
+		distances.insert(u, d.raw());
+		for (v, l) in &adjacent[u] {
+			let d = d + l;
+			if d <= max && !distances.contains_key(v) {
+				q.push((Reverse(d), v));
+			}
+		}
+	}
+	distances
+}
+
+/// Segments to be spread on, as snow statuses (of unit depth) addressing each
+pub fn required(roads: &RoadGraph, rules: &Rules) -> SnowStatuses {
+	let near = rules.intersections.map(|max| intersection_distances(roads, max));
+	roads.roads.iter().zip(roads.parallel_indices()).zip(roads.grades()).filter(|((e, _), grade)| {
+		rules.min_grade.zip(*grade).is_some_and(|(min, g)| g.abs() >= min)
+			|| e.class.as_ref().is_some_and(|c| rules.classes.contains(c))
+			|| near.as_ref().is_some_and(|near| near.contains_key(&e.p1) || near.contains_key(&e.p2))
+	}).map(|((e, parallel), _)| SnowStatusElement {
+		p1: e.p1.clone(),
+		p2: e.p2.clone(),
+		discriminator: e.discriminator.clone(),
+		parallel_index: Some(parallel),
+		depth: n64(1.0),
+		variance: None,
+		estimated: false,
+	}).collect()
+}
+
+/// Plans spreading routes for road vehicles - routed as if plowing the required segments, with reload trips inserted (see [`plow::road::reload`])
+///
+/// Returns: the routes, what each spreads and where it reloads, and the solver statistics
+pub fn plan(roads: RoadGraph, vehicles: VehiclesConfiguration, spreading: &Spreading, params: &Parameters, budget: &Budget, checkpoint: impl FnMut(Duration, Paths)) -> Result<(Paths, IndexMap<VehicleId, SpreadReport>, Stats), Error> {
+	let required = required(&roads, &spreading.rules);
+	log::info!("{} segments to spread on", required.len());
+	let ids = vehicle_ids(&vehicles.road).map_err(|id| format!("Duplicate vehicle id {}", id))?;
+	let capacities = ids.into_iter().zip(vehicles.road.iter().map(Vehicle::capacity)).filter_map(|(id, c)| c.map(|c| (id, c))).collect();
+	let depots = if spreading.depots.is_empty() { &vehicles.depots } else { &spreading.depots };
+	let depots: Vec<_> = depots.iter().filter(|d| d.refills(None)).map(|d| d.location.clone()).collect();
+	let (paths, stats) = plow::road::solve(roads.clone(), required.clone(), None, vehicles, params, budget, checkpoint)?;
+	let (paths, reports) = plow::road::reload(roads, paths, &required, spreading.rate, &capacities, &depots)?;
+	Ok((paths, reports, stats))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_required(){
+		let road = |p1: &'static str, p2: &'static str, class: Option<&'static str>| RoadSegment { class: class.map(Into::into), ..RoadSegment::new(p1, p2, 100.0) };
+		let node = |id: &'static str, elevation: f64| Node { id: id.into(), coordinates: (0.0, 0.0), elevation: Some(elevation) };
+		let roads = RoadGraph {
+			roads: vec![road("a", "b", None), road("a", "c", None), road("a", "d", None), road("d", "e", Some("bridge")), road("e", "f", None), road("f", "g", None)],
+			nodes: RoadGraphNodes { nodes: vec![node("f", 0.0), node("g", 10.0)] },
+		};
+		let spread = |rules: Rules| required(&roads, &rules).into_iter().map(|s| format!("{}{}", s.p1, s.p2)).collect::<Vec<_>>();
+		assert_eq!(spread(Rules { intersections: Some(0.0), ..Default::default() }), vec!["ab", "ac", "ad"]);
+		assert_eq!(spread(Rules { intersections: Some(150.0), ..Default::default() }), vec!["ab", "ac", "ad", "de"]);
+		assert_eq!(spread(Rules { min_grade: Some(0.05), classes: vec!["bridge".into()], intersections: None }), vec!["de", "fg"]);
+		assert!(spread(Rules::default()).is_empty());
+	}
+}
@@ -0,0 +1,139 @@
+//! Multi-day storm planning
+//!
+//! Chains road plowing rounds over a forecast horizon: the snow falling before each round adds up with whatever was left uncleared,
+//! segments traversed by the round's plan are cleared, and the vehicles resume from where they ended.
+
+use crate::*;
+use data::*;
+use plow::Budget;
+
+use std::{collections::HashSet, time::Duration};
+use indexmap::IndexMap;
+
+/// A single plowing round of a storm plan
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+pub struct Round {
+	/// Snow depth the round starts with
+	pub snow: SnowStatuses,
+	pub paths: Paths,
+	/// Snow left uncleared after the round
+	pub carryover: SnowStatuses,
+}
+
+type SnowKey = (NodeId, NodeId, Option<NodeId>, Option<usize>);
+
+/// Accumulated snow depth, per snow status location
+#[derive(Default)]
+struct Accumulation(IndexMap<SnowKey, N64>);
+
+impl Accumulation {
+	fn key(s: &SnowStatusElement) -> SnowKey {
+		let (p1, p2) = if s.p1 <= s.p2 { (&s.p1, &s.p2) } else { (&s.p2, &s.p1) };
+		(p1.clone(), p2.clone(), s.discriminator.clone(), s.parallel_index)
+	}
+	fn fall(&mut self, snow: SnowStatuses) {
+		for s in snow {
+			*self.0.entry(Self::key(&s)).or_insert(n64(0.0)) += s.depth;
+		}
+	}
+	/// Resets all locations traversed by the paths
+	fn clear(&mut self, paths: &Paths) {
+		let traversed: HashSet<_> = paths.0.values().flat_map(|path| path.iter().zip(path.iter().skip(1)).map(|(u, v)| {
+			let (p1, p2) = if u.node <= v.node { (&u.node, &v.node) } else { (&v.node, &u.node) };
+			(p1.clone(), p2.clone(), v.discriminator.clone())
+		})).collect();
+		self.0.retain(|(p1, p2, d, _), _| !traversed.contains(&(p1.clone(), p2.clone(), d.clone())));
+	}
+	fn statuses(&self) -> SnowStatuses {
+		self.0.iter().filter(|(_, d)| **d > 0.0).map(|((p1, p2, discriminator, parallel_index), depth)| SnowStatusElement {
+			p1: p1.clone(),
+			p2: p2.clone(),
+			discriminator: discriminator.clone(),
+			parallel_index: *parallel_index,
+			depth: *depth,
+			variance: None,
+			estimated: false,
+		}).collect()
+	}
+}
+
+/// Plans road plowing rounds, one per forecast snowfall
+///
+/// Every round is solved with the round's share of the budget (the checkpoints are not used).
+pub fn plan(roads: &RoadGraph, forecast: Vec<SnowStatuses>, vehicles: VehiclesConfiguration, params: &meta::Parameters, budget: &Budget) -> Result<Vec<Round>, Error> {
+	let mut acc = Accumulation::default();
+	let mut vehicles = vehicles;
+	let budget = Budget {
+		time: budget.time.map(|t| t / forecast.len().max(1) as u32),
+		checkpoints: Vec::new(),
+	};
+	let mut rounds = Vec::new();
+	for (r, fall) in forecast.into_iter().enumerate() {
+		acc.fall(fall);
+		let snow = acc.statuses();
+		let paths = if snow.is_empty() {
+			log::info!("Round {}: no snow", r);
+			Paths::default()
+		} else {
+			log::info!("Round {}: {} snowy segments", r, snow.len());
+			plow::road::solve(roads.clone(), snow.clone(), None, vehicles.clone(), params, &budget, |_: Duration, _| {}).map_err(|e| format!("Round {}: {}", r, e))?.0
+		};
+		acc.clear(&paths);
+		// the sidewalk vehicles weren't out, whatever their ids
+		resume(&mut vehicles.road, &paths);
+		rounds.push(Round { snow, paths, carryover: acc.statuses() });
+	}
+	Ok(rounds)
+}
+
+/// Moves the vehicles to where their paths ended
+fn resume(vehicles: &mut [Vehicle], paths: &Paths) {
+	for (i, v) in vehicles.iter_mut().enumerate() {
+		if let Some(end) = paths.0.get(&v.id(i)).and_then(|p| p.last()) {
+			let location = Location::Node(end.node.clone());
+			match v {
+				Vehicle::Spec(spec) => spec.location = location,
+				Vehicle::Location(l) => *l = location,
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn snow(p1: &'static str, p2: &'static str, depth: f64) -> SnowStatusElement {
+		SnowStatusElement { p1: p1.into(), p2: p2.into(), discriminator: None, parallel_index: None, depth: n64(depth), variance: None, estimated: false }
+	}
+
+	#[test]
+	fn test_carryover() {
+		let mut acc = Accumulation::default();
+		acc.fall(vec![snow("a", "b", 1.0), snow("b", "c", 2.0)]);
+		acc.fall(vec![snow("b", "a", 0.5)]);
+		let paths: Paths = vec![("0".to_string(), vec![PathSegment { node: "c".into(), discriminator: None, reversal: false, leg: None }, PathSegment { node: "b".into(), discriminator: None, reversal: false, leg: None }])].into_iter().collect();
+		acc.clear(&paths);
+		assert_eq!(acc.statuses(), vec![snow("a", "b", 1.5)]);
+	}
+
+	#[test]
+	fn test_plan() {
+		let roads = RoadGraph {
+			roads: vec![RoadSegment::new("a", "b", 10.0), RoadSegment::new("b", "c", 10.0), RoadSegment::new("c", "d", 10.0)],
+			nodes: RoadGraphNodes { nodes: ["a", "b", "c", "d"].iter().enumerate().map(|(i, id)| Node { id: (*id).into(), coordinates: (i as f64 * 0.001, 0.0), elevation: None }).collect() },
+		};
+		let plow = VehicleSpec { id: None, location: Location::Node("a".into()), end: RouteEnd::Anywhere, exclude: vec![], surfaces: None, breaks: None, max_grade: None, capacity: None, speed: None, resource: None };
+		let vehicles = VehiclesConfiguration { road: vec![Vehicle::Spec(plow)], sidewalk: vec![Vehicle::Location(Location::Node("d".into()))], depots: vec![] };
+		let mut params = meta::Preset::Light.parameters();
+		params.annealing.seed = Some(1);
+		let rounds = plan(&roads, vec![vec![snow("a", "b", 1.0)], vec![snow("c", "d", 1.0)], vec![]], vehicles, &params, &Default::default()).unwrap();
+		let nodes = |r: usize| rounds[r].paths.0.get("0").map(|p| p.iter().map(|s| s.node.to_string()).collect::<Vec<_>>());
+		assert_eq!(nodes(0), Some(vec!["a".to_string(), "b".to_string()]));
+		// a-b was cleared, and the plow goes on from b
+		assert_eq!(rounds[1].snow, vec![snow("c", "d", 1.0)]);
+		assert_eq!(nodes(1), Some(vec!["b".to_string(), "c".to_string(), "d".to_string()]));
+		assert!(rounds[1].carryover.is_empty());
+		assert_eq!((rounds[2].snow.len(), nodes(2)), (0, None));
+	}
+}
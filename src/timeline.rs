@@ -0,0 +1,589 @@
+//! Route timelines, Gantt style
+//!
+//! Routes are laid out in time at a constant pace (slowed down while clearing),
+//! to see when street classes get done and where the deadheading piles up.
+
+use crate::*;
+use data::*;
+
+use std::{collections::{HashMap, HashSet}, fmt::Write};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use serde::*;
+
+/// What a vehicle is doing on a segment
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Activity {
+	/// clearing snow
+	#[serde(rename="working")]
+	Working,
+	/// just driving through
+	#[serde(rename="deadhead")]
+	Deadhead,
+	/// taking a mandatory break
+	#[serde(rename="break")]
+	Break,
+}
+
+/// A stretch of a route spent doing the same thing on the same street class
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Bar {
+	pub start: f64,
+	pub end: f64,
+	pub activity: Activity,
+	pub class: Option<StreetClass>,
+	/// Number of segments traversed
+	pub segments: usize,
+	/// Wall-clock start & end, with a [`Clock`]
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub at: Option<(String, String)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct VehicleTimeline {
+	pub bars: Vec<Bar>,
+	/// Start and end of traversing each segment of the path, in order
+	pub legs: Vec<(f64, f64)>,
+	/// Total time spent working
+	pub working: f64,
+	/// Total time spent deadheading
+	pub deadhead: f64,
+	pub breaks: Vec<BreakEvent>,
+	/// When the vehicle is done working each street class
+	pub completion: IndexMap<StreetClass, f64>,
+	/// Wall-clock time the route is done at, with a [`Clock`]
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub eta: Option<String>,
+}
+
+/// A mandatory break taken
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct BreakEvent {
+	pub start: f64,
+	pub end: f64,
+	pub node: NodeId,
+	/// Whether taken at a rest spot (or the start), rather than wherever the vehicle ran out of time
+	pub rest_spot: bool,
+	/// Wall-clock start & end, with a [`Clock`]
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub at: Option<(String, String)>,
+}
+
+/// Fleet-wide vehicle-time spent within a time bucket
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct HistogramBin {
+	pub start: f64,
+	pub working: f64,
+	pub deadhead: f64,
+	/// on a mandatory break
+	pub resting: f64,
+	/// with the route already done
+	pub idle: f64,
+	/// Wall-clock start, with a [`Clock`]
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub at: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Timeline {
+	pub vehicles: IndexMap<VehicleId, VehicleTimeline>,
+	/// When the last route ends
+	pub horizon: f64,
+	/// When the fleet is done working each street class
+	pub completion: IndexMap<StreetClass, f64>,
+	pub histogram: Vec<HistogramBin>,
+	/// Wall-clock start of the plan, with a [`Clock`]
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub start: Option<String>,
+	/// Wall-clock time the fleet is done working each street class, with a [`Clock`]
+	#[serde(default, skip_serializing_if="IndexMap::is_empty")]
+	pub completion_at: IndexMap<StreetClass, String>,
+	#[serde(skip)]
+	pub clock: Option<Clock>,
+}
+
+/// Wall-clock time the plan starts at, for absolute timestamps - timeline times being seconds since
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Clock {
+	/// UNIX time of the start
+	pub start: f64,
+	/// UTC offset timestamps are written with, in minutes
+	pub offset: i32,
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+	let y = if m <= 2 { y - 1 } else { y };
+	let era = if y >= 0 { y } else { y - 399 } / 400;
+	let yoe = y - era * 400;
+	let doy = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+	era * 146097 + yoe * 365 + yoe / 4 - yoe / 100 + doy - 719468
+}
+
+/// Proleptic Gregorian date of days since 1970-01-01
+fn civil_from_days(z: i64) -> (i64, i64, i64) {
+	let z = z + 719468;
+	let era = if z >= 0 { z } else { z - 146096 } / 146097;
+	let doe = z - era * 146097;
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let d = doy - (153 * mp + 2) / 5 + 1;
+	let m = if mp < 10 { mp + 3 } else { mp - 9 };
+	(yoe + era * 400 + if m <= 2 { 1 } else { 0 }, m, d)
+}
+
+impl Clock {
+	/// Parses an RFC3339 timestamp (e.g. `2024-01-15T02:00:00-05:00`), timestamps keeping its offset
+	pub fn parse(s: &str) -> Result<Self, Error> {
+		let bad = || format!("Invalid RFC3339 timestamp {}", s);
+		let num = |s: &str| s.parse::<i64>().map_err(|_| bad());
+		let (date, time) = s.split_at(s.find(['T', 't', ' ']).ok_or_else(bad)?);
+		let time = &time[1..];
+		let (time, offset) = match time.strip_suffix(['Z', 'z']) {
+			Some(time) => (time, 0),
+			None => {
+				let (time, zone) = time.split_at(time.rfind(['+', '-']).ok_or_else(bad)?);
+				let (h, m) = zone[1..].split_once(':').ok_or_else(bad)?;
+				(time, if zone.starts_with('-') { -1 } else { 1 } * (num(h)? * 60 + num(m)?))
+			},
+		};
+		let date: Vec<_> = date.split('-').collect();
+		let time: Vec<_> = time.split(':').collect();
+		if date.len() != 3 || time.len() != 3 {
+			return Err(bad().into());
+		}
+		let secs: f64 = time[2].parse().map_err(|_| bad())?;
+		let days = days_from_civil(num(date[0])?, num(date[1])?, num(date[2])?);
+		Ok(Self { start: (days * 86400 + num(time[0])? * 3600 + num(time[1])? * 60 - offset * 60) as f64 + secs, offset: offset as i32 })
+	}
+	/// RFC3339 timestamp of `t` seconds after the start, to the second
+	pub fn at(&self, t: f64) -> String {
+		let local = (self.start + t).round() as i64 + self.offset as i64 * 60;
+		let (y, m, d) = civil_from_days(local.div_euclid(86400));
+		let secs = local.rem_euclid(86400);
+		let zone = match self.offset {
+			0 => "Z".to_string(),
+			o => format!("{}{:02}:{:02}", if o < 0 { '-' } else { '+' }, o.abs() / 60, o.abs() % 60),
+		};
+		format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}", y, m, d, secs / 3600, secs / 60 % 60, secs % 60, zone)
+	}
+}
+
+impl Timeline {
+	/// Aligns the timeline to a wall-clock start, adding absolute timestamps throughout
+	pub fn stamp(&mut self, clock: Clock) {
+		let span = |s: f64, e: f64| Some((clock.at(s), clock.at(e)));
+		for v in self.vehicles.values_mut() {
+			for b in &mut v.bars {
+				b.at = span(b.start, b.end);
+			}
+			for b in &mut v.breaks {
+				b.at = span(b.start, b.end);
+			}
+			v.eta = Some(clock.at(v.bars.last().map_or(0.0, |b| b.end)));
+		}
+		for b in &mut self.histogram {
+			b.at = Some(clock.at(b.start));
+		}
+		self.start = Some(clock.at(0.0));
+		self.completion_at = self.completion.iter().map(|(c, t)| (c.clone(), clock.at(*t))).collect();
+		self.clock = Some(clock);
+	}
+	/// Wall-clock start & end of a span, if aligned to a [`Clock`]
+	pub fn at(&self, (start, end): (f64, f64)) -> Option<(String, String)> {
+		self.clock.map(|c| (c.at(start), c.at(end)))
+	}
+}
+
+/// Timing assumptions
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Pace {
+	/// Driving speed, in meters per time unit
+	pub speed: f64,
+	/// Clearing slowdown factor
+	pub slowdown: f64,
+	/// Histogram bucket width, in time units
+	pub bucket: f64,
+}
+
+/// Mandatory breaks
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Breaks {
+	/// Break rules, of vehicles that have one
+	pub rules: HashMap<VehicleId, BreakRule>,
+	/// Designated rest spots - vehicles can always rest at their start too
+	pub rest_spots: HashSet<NodeId>,
+}
+
+/// Lays out planned paths in time
+///
+/// A vehicle works a segment the first time it traverses it, if it is snowy (any segment is, without snow statuses), otherwise it's deadheading.
+/// Vehicles with a break rule take their break at the last rest spot (or their start) they pass before running out of time, or wherever they are if there is none.
+pub fn timeline(roads: &RoadGraph, plan: &Paths, snow: Option<&SnowStatuses>, pace: Pace, breaks: &Breaks) -> Timeline {
+	let view = view::SolutionView::new(roads, plan, snow).pace(pace.speed, pace.slowdown);
+	let vehicles: IndexMap<_, _> = plan.0.iter().map(|(id, path)| {
+		let steps: Vec<_> = view.vehicle(id).map(|t| {
+			let activity = if t.clearing { Activity::Working } else { Activity::Deadhead };
+			(activity, t.road.and_then(|e| e.class.clone()), t.end - t.start)
+		}).collect();
+		let restful = |n: &NodeId| n == &path[0].node || breaks.rest_spots.contains(n);
+		// route time from each node to the next rest spot, or the end
+		let mut to_rest = vec![0.0; path.len()];
+		for i in (0..steps.len()).rev() {
+			to_rest[i] = steps[i].2 + if restful(&path[i+1].node) { 0.0 } else { to_rest[i+1] };
+		}
+		let rule = breaks.rules.get(id);
+		let mut bars: Vec<Bar> = Vec::new();
+		let mut legs = Vec::new();
+		let mut rests = Vec::new();
+		let mut t = 0.0;
+		let mut since = 0.0;
+		for (i, (activity, class, d)) in steps.into_iter().enumerate() {
+			if let Some(rule) = rule.filter(|_| since > 0.0) {
+				let node = &path[i].node;
+				let rest_spot = restful(node);
+				if since + if rest_spot { to_rest[i] } else { d } > rule.every {
+					if !rest_spot {
+						log::warn!("Vehicle {} can't make it to a rest spot in time, taking a break at {}", id, node);
+					}
+					bars.push(Bar { start: t, end: t + rule.duration, activity: Activity::Break, class: None, segments: 0, at: None });
+					rests.push(BreakEvent { start: t, end: t + rule.duration, node: node.clone(), rest_spot, at: None });
+					t += rule.duration;
+					since = 0.0;
+				}
+			}
+			match bars.last_mut() {
+				Some(b) if b.activity == activity && b.class == class => {
+					b.end += d;
+					b.segments += 1;
+				},
+				_ => bars.push(Bar { start: t, end: t + d, activity, class, segments: 1, at: None }),
+			}
+			legs.push((t, t + d));
+			t += d;
+			since += d;
+		}
+		let time = |a: Activity| bars.iter().filter(|b| b.activity == a).map(|b| b.end - b.start).fold(0.0, |s, d| s + d);
+		let mut completion = IndexMap::new();
+		for b in bars.iter().filter(|b| b.activity == Activity::Working) {
+			if let Some(c) = &b.class {
+				completion.insert(c.clone(), b.end);
+			}
+		}
+		(id.clone(), VehicleTimeline { working: time(Activity::Working), deadhead: time(Activity::Deadhead), bars, legs, breaks: rests, completion, eta: None })
+	}).collect();
+	let mut completion: IndexMap<StreetClass, f64> = IndexMap::new();
+	for (c, t) in vehicles.values().flat_map(|v| v.completion.iter()) {
+		let ct = completion.entry(c.clone()).or_insert(*t);
+		*ct = ct.max(*t);
+	}
+	let horizon = vehicles.values().filter_map(|v| v.bars.last()).map(|b| b.end).fold(0.0, f64::max);
+	let overlap = |s: f64, e: f64, bs: f64| (e.min(bs + pace.bucket) - s.max(bs)).max(0.0);
+	let histogram = (0..(horizon / pace.bucket).ceil() as usize).map(|i| {
+		let bs = i as f64 * pace.bucket;
+		let time = |a: Activity| vehicles.values().flat_map(|v| v.bars.iter()).filter(|b| b.activity == a).map(|b| overlap(b.start, b.end, bs)).fold(0.0, |s, d| s + d);
+		HistogramBin {
+			start: bs,
+			working: time(Activity::Working),
+			deadhead: time(Activity::Deadhead),
+			resting: time(Activity::Break),
+			idle: vehicles.values().map(|v| overlap(v.bars.last().map_or(0.0, |b| b.end), horizon, bs)).fold(0.0, |s, d| s + d),
+			at: None,
+		}
+	}).collect();
+	Timeline { vehicles, horizon, completion, histogram, start: None, completion_at: IndexMap::new(), clock: None }
+}
+
+/// Estimates every vehicle's schedule - how far it has driven, and when it gets to every node of its path
+///
+/// Vehicles drive at their own speed (`speeds`, distance per time unit), or else at the `speeds` meta parameter of the street class - without either, times are distances.
+/// The first vehicle (in plan order) to traverse a snowy segment clears it, slowed down as per the meta parameters' slowdown at its depth - every segment being `snow_d` deep, if given.
+/// Breaks are not counted (see [`timeline`]).
+pub fn schedule(roads: &RoadGraph, plan: &Paths, snow: &SnowStatuses, snow_d: Option<f64>, params: &meta::Parameters, speeds: &HashMap<VehicleId, f64>) -> Schedules {
+	let mut segments = HashMap::new();
+	for e in &roads.roads {
+		segments.entry(view::segment_key(&e.p1, &e.p2, &e.discriminator)).or_insert(e);
+	}
+	let mut depths: HashMap<view::SegmentKey, N64> = HashMap::new();
+	for s in snow.iter().filter(|s| s.depth > 0.0) {
+		let d = depths.entry(view::segment_key(&s.p1, &s.p2, &s.discriminator)).or_insert(s.depth);
+		*d = (*d).max(s.depth);
+	}
+	let depth = |k: &view::SegmentKey| snow_d.filter(|d| *d > 0.0).map(n64).or_else(|| depths.get(k).copied());
+	let mut cleared = HashSet::new();
+	plan.0.iter().map(|(id, path)| {
+		let (mut distance, mut eta) = (0.0, 0.0);
+		let schedule = path.iter().enumerate().map(|(i, s)| {
+			let k = i.checked_sub(1).map(|j| view::segment_key(&path[j].node, &s.node, &s.discriminator));
+			if let Some((k, e)) = k.and_then(|k| segments.get(&k).map(|e| (k, *e))) {
+				let speed = speeds.get(id).copied().or_else(|| params.speeds.as_ref().map(|sp| sp.of(e.class.as_ref()).raw())).unwrap_or(1.0);
+				let slowdown = match depth(&k) {
+					Some(d) if cleared.insert(k) => params.slowdown.factor(d, params.temperature).raw(),
+					_ => 1.0,
+				};
+				distance += e.distance.raw();
+				eta += e.distance.raw() / speed * slowdown;
+			}
+			ScheduledSegment { segment: s.clone(), distance, eta }
+		}).collect();
+		(id.clone(), schedule)
+	}).collect()
+}
+
+/// A piece of a route, to be driven within a time interval
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Chunk {
+	pub vehicle: VehicleId,
+	/// Index of the time interval
+	pub interval: usize,
+	/// When the first segment is entered
+	pub start: f64,
+	/// When the last segment is left
+	pub end: f64,
+	/// Wall-clock start & end, if the timeline is aligned to a [`Clock`]
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub at: Option<(String, String)>,
+	/// The piece of the path, from the node the first segment is entered at
+	pub path: Vec<PathSegment>,
+	/// Recommended cast side on every segment of the piece
+	pub cast: Vec<Option<view::CastSide>>,
+}
+
+/// Cuts every path into chunks by time intervals, each segment going with the interval it is entered in
+pub fn chunks(view: &view::SolutionView, tl: &Timeline, interval: f64) -> Vec<Chunk> {
+	let mut chunks = Vec::new();
+	for (id, path) in &view.plan().0 {
+		let cast: Vec<_> = view.vehicle(id).map(|t| t.cast).collect();
+		let legs = tl.vehicles.get(id).map_or(&[][..], |v| &v.legs[..]);
+		for (i, group) in &legs.iter().enumerate().group_by(|(_, (start, _))| (start / interval).floor() as usize) {
+			let group: Vec<_> = group.map(|(j, _)| j).collect();
+			let (first, last) = (group[0], group[group.len()-1]);
+			chunks.push(Chunk {
+				vehicle: id.clone(),
+				interval: i,
+				start: legs[first].0,
+				end: legs[last].1,
+				at: tl.at((legs[first].0, legs[last].1)),
+				path: path[first..=last+1].to_vec(),
+				cast: cast[first..=last].to_vec(),
+			});
+		}
+	}
+	chunks
+}
+
+/// A segment driven against its one way, within one of its direction windows
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct WindowViolation {
+	pub vehicle: VehicleId,
+	/// Index of the traversal along the path
+	pub index: usize,
+	/// Node the segment is entered at
+	pub from: NodeId,
+	pub to: PathSegment,
+	/// When the segment is entered
+	pub start: f64,
+	/// When the segment is left
+	pub end: f64,
+	/// Wall-clock start & end, if the timeline is aligned to a [`Clock`]
+	#[serde(default, skip_serializing_if="Option::is_none")]
+	pub at: Option<(String, String)>,
+	pub window: DirectionWindow,
+}
+
+/// Checks the timed paths against the direction windows of the segments they traverse
+///
+/// A traversal violates a window if any part of it is within the window, driving against the one way.
+pub fn check_windows(view: &view::SolutionView, tl: &Timeline) -> Vec<WindowViolation> {
+	let mut violations = Vec::new();
+	for id in view.plan().0.keys() {
+		let legs = tl.vehicles.get(id).map_or(&[][..], |v| &v.legs[..]);
+		for (t, (start, end)) in view.vehicle(id).zip(legs.iter().copied()) {
+			let road = match t.road {
+				Some(road) => road,
+				None => continue,
+			};
+			let forward = road.p1 == *t.from;
+			for w in road.windows.iter().filter(|w| w.reverse == forward && start < w.end && w.start < end) {
+				violations.push(WindowViolation { vehicle: id.clone(), index: t.index, from: t.from.clone(), to: t.to.clone(), start, end, at: tl.at((start, end)), window: w.clone() });
+			}
+		}
+	}
+	violations
+}
+
+pub(crate) fn xml_escape(s: &str) -> String {
+	s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+pub(crate) const PALETTE: [&str; 8] = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#17becf"];
+const UNCLASSED: &str = "#bcbd22";
+const DEADHEAD: &str = "#c7c7c7";
+const BREAK: &str = "#333333";
+
+/// Renders a timeline as an SVG Gantt chart
+///
+/// A row per vehicle, working bars coloured by street class, deadheading in grey and breaks in black, with the fleet-wide class completion times marked.
+pub fn to_svg(t: &Timeline) -> String {
+	const LABEL: f64 = 120.0;
+	const WIDTH: f64 = 1000.0;
+	const ROW: f64 = 20.0;
+	let horizon = if t.horizon > 0.0 { t.horizon } else { 1.0 };
+	let x = |time: f64| LABEL + time / horizon * WIDTH;
+	let colour = |c: &Option<StreetClass>| c.as_ref().and_then(|c| t.completion.get_index_of(c)).map_or(UNCLASSED, |i| PALETTE[i % PALETTE.len()]);
+	let height = ROW * (t.vehicles.len() + 2) as f64;
+	let mut svg = String::new();
+	writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="12">"#, LABEL + WIDTH + 10.0, height + ROW * t.completion.len() as f64).unwrap();
+	for (r, (id, v)) in t.vehicles.iter().enumerate() {
+		let y = ROW * r as f64;
+		writeln!(svg, r#"<text x="4" y="{}">{}</text>"#, y + ROW * 0.75, xml_escape(id)).unwrap();
+		for b in &v.bars {
+			let fill = match b.activity {
+				Activity::Working => colour(&b.class),
+				Activity::Deadhead => DEADHEAD,
+				Activity::Break => BREAK,
+			};
+			writeln!(svg, r#"<rect x="{:.2}" y="{}" width="{:.2}" height="{}" fill="{}"><title>{:?} {} ({} segments) {:.1}-{:.1}</title></rect>"#, x(b.start), y + 2.0, x(b.end) - x(b.start), ROW - 4.0, fill, b.activity, xml_escape(b.class.as_deref().unwrap_or("-")), b.segments, b.start, b.end).unwrap();
+		}
+	}
+	let axis = ROW * t.vehicles.len() as f64;
+	writeln!(svg, r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="black"/>"#, LABEL, axis, LABEL + WIDTH, axis).unwrap();
+	let ticks = t.histogram.len().div_ceil(20).max(1);
+	for b in t.histogram.iter().step_by(ticks) {
+		let label = b.at.as_ref().map_or_else(|| b.start.to_string(), |at| at[11..16].to_string());
+		writeln!(svg, r#"<text x="{:.2}" y="{}" text-anchor="middle">{}</text>"#, x(b.start), axis + ROW * 0.75, label).unwrap();
+	}
+	for (i, (c, ct)) in t.completion.iter().enumerate() {
+		let fill = PALETTE[i % PALETTE.len()];
+		writeln!(svg, r#"<line x1="{:.2}" y1="0" x2="{:.2}" y2="{}" stroke="{}" stroke-dasharray="4 2"/>"#, x(*ct), x(*ct), axis, fill).unwrap();
+		let at = t.completion_at.get(c).map_or_else(String::new, |at| format!(" ({})", at));
+		writeln!(svg, r#"<text x="4" y="{}" fill="{}">{} done at {:.1}{}</text>"#, axis + ROW * (i as f64 + 1.75), fill, xml_escape(c), ct, at).unwrap();
+	}
+	svg.push_str("</svg>\n");
+	svg
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_timeline(){
+		let road = |p1: &'static str, p2: &'static str, class: &'static str| RoadSegment { class: Some(class.into()), ..RoadSegment::new(p1, p2, 10.0) };
+		let roads = RoadGraph {
+			roads: vec![road("a", "b", "arterial"), road("b", "c", "arterial"), road("c", "d", "local")],
+			nodes: RoadGraphNodes { nodes: vec![] },
+		};
+		let seg = |n: &'static str| PathSegment { node: n.into(), discriminator: None, reversal: false, leg: None };
+		let plan = vec![("t".to_string(), vec![seg("a"), seg("b"), seg("c"), seg("d"), seg("c")]), ("u".to_string(), vec![seg("a")])].into_iter().collect();
+		let pace = Pace { speed: 1.0, slowdown: 2.0, bucket: 25.0 };
+		let tl = timeline(&roads, &plan, None, pace, &Breaks::default());
+		let t = &tl.vehicles["t"];
+		assert_eq!(t.bars.iter().map(|b| (b.activity, b.segments)).collect::<Vec<_>>(), vec![(Activity::Working, 2), (Activity::Working, 1), (Activity::Deadhead, 1)]);
+		assert_eq!((t.working, t.deadhead), (60.0, 10.0));
+		assert_eq!(tl.completion["arterial"], 40.0);
+		assert_eq!(tl.completion["local"], 60.0);
+		assert_eq!(tl.histogram.len(), 3);
+		assert_eq!((tl.histogram[2].working, tl.histogram[2].deadhead, tl.histogram[2].idle), (10.0, 10.0, 20.0));
+		assert!(to_svg(&tl).contains("local done at 60.0"));
+		let breaks = Breaks { rules: vec![("t".to_string(), BreakRule { every: 30.0, duration: 5.0 })].into_iter().collect(), rest_spots: vec!["b".into()].into_iter().collect() };
+		let tl = timeline(&roads, &plan, None, pace, &breaks);
+		let t = &tl.vehicles["t"];
+		assert_eq!(t.breaks.iter().map(|b| (b.start, b.node.name().unwrap(), b.rest_spot)).collect::<Vec<_>>(), vec![(20.0, "b", true), (45.0, "c", false)]);
+		assert_eq!(tl.horizon, 80.0);
+		assert_eq!(t.legs, vec![(0.0, 20.0), (25.0, 45.0), (50.0, 70.0), (70.0, 80.0)]);
+		let clock = Clock::parse("2024-02-29T23:59:30-05:00").unwrap();
+		assert_eq!((clock.at(0.0), clock.at(45.0)), ("2024-02-29T23:59:30-05:00".to_string(), "2024-03-01T00:00:15-05:00".to_string()));
+		assert_eq!(Clock::parse("2024-03-01T04:59:30Z").unwrap().start, clock.start);
+		assert!(Clock::parse("2024-02-29 23:59").is_err());
+		let mut tl = tl;
+		tl.stamp(clock);
+		assert_eq!(tl.vehicles["t"].eta.as_deref(), Some("2024-03-01T00:00:50-05:00"));
+		let chunks = chunks(&view::SolutionView::new(&roads, &plan, None), &tl, 30.0);
+		assert_eq!(chunks.iter().map(|c| (c.vehicle.as_str(), c.interval, c.start, c.end, c.path.len(), c.cast.len())).collect::<Vec<_>>(), vec![("t", 0, 0.0, 45.0, 3, 2), ("t", 1, 50.0, 70.0, 2, 1), ("t", 2, 70.0, 80.0, 2, 1)]);
+		let mut roads = roads;
+		roads.roads[1].windows = vec![DirectionWindow { start: 30.0, end: 60.0, reverse: true }];
+		roads.roads[2].windows = vec![DirectionWindow { start: 60.0, end: 90.0, reverse: false }];
+		let violations = check_windows(&view::SolutionView::new(&roads, &plan, None), &tl);
+		assert_eq!(violations.iter().map(|v| (v.index, v.start, v.window.start)).collect::<Vec<_>>(), vec![(1, 25.0, 30.0), (3, 70.0, 60.0)]);
+	}
+
+	#[test]
+	fn test_check_windows(){
+		// a → b one-way from 10 to 20, c → b one-way throughout
+		let roads = RoadGraph {
+			roads: vec![
+				RoadSegment { windows: vec![DirectionWindow { start: 10.0, end: 20.0, reverse: false }], ..RoadSegment::new("a", "b", 10.0) },
+				RoadSegment { windows: vec![DirectionWindow { start: 0.0, end: 100.0, reverse: true }], ..RoadSegment::new("b", "c", 10.0) },
+			],
+			nodes: RoadGraphNodes { nodes: vec![] },
+		};
+		let seg = |n: &'static str| PathSegment { node: n.into(), discriminator: None, reversal: false, leg: None };
+		let plan: Paths = vec![
+			("t".to_string(), vec![seg("a"), seg("b"), seg("a"), seg("b"), seg("a")]),
+			("u".to_string(), vec![seg("b"), seg("a")]),
+			("v".to_string(), vec![seg("c"), seg("b"), seg("c")]),
+		].into_iter().collect();
+		let mut tl = timeline(&roads, &plan, None, Pace { speed: 1.0, slowdown: 1.0, bucket: 10.0 }, &Breaks::default());
+		tl.stamp(Clock::parse("2024-01-15T07:00:00-05:00").unwrap());
+		let violations = check_windows(&view::SolutionView::new(&roads, &plan, None), &tl);
+		// only driving against the one way within the window - u is off a - b just as it starts
+		assert_eq!(violations.iter().map(|v| (v.vehicle.as_str(), v.index, v.from.name().unwrap(), v.to.node.name().unwrap(), v.start, v.end)).collect::<Vec<_>>(), vec![("t", 1, "b", "a", 10.0, 20.0), ("v", 1, "b", "c", 10.0, 20.0)]);
+		assert_eq!(violations[0].at, Some(("2024-01-15T07:00:10-05:00".to_string(), "2024-01-15T07:00:20-05:00".to_string())));
+		assert!(violations[1].window.reverse);
+	}
+
+	#[test]
+	fn test_clock(){
+		let at = |s: &str, t: f64| Clock::parse(s).unwrap().at(t);
+		assert_eq!(Clock::parse("1970-01-01T00:00:00Z").unwrap(), Clock { start: 0.0, offset: 0 });
+		assert_eq!(Clock::parse("1969-12-31t23:59:59.5z").unwrap().start, -0.5);
+		assert_eq!(Clock::parse("2000-01-01 05:30:00+05:30").unwrap(), Clock { start: 946684800.0, offset: 330 });
+		// timestamps keep the offset they're given, across days, months and years
+		assert_eq!(at("2023-12-31T23:00:00+05:30", 3600.0), "2024-01-01T00:00:00+05:30");
+		assert_eq!(at("2023-12-31T23:00:00Z", 86400.0 * 60.0), "2024-02-29T23:00:00Z");
+		assert_eq!(at("1969-12-31T23:59:59-00:30", 1.4), "1970-01-01T00:00:00-00:30");
+		for bad in ["2024-01-15", "2024-01-15T02:00", "2024-01-15T02:00:00+0500", "2024-01T02:00:00Z", "2024-01-15Tab:00:00Z"].iter() {
+			assert!(Clock::parse(bad).is_err(), "{}", bad);
+		}
+		// a - b - c, with a break due at b
+		let roads = RoadGraph { roads: vec![RoadSegment::new("a", "b", 10.0), RoadSegment::new("b", "c", 10.0)], nodes: RoadGraphNodes { nodes: vec![] } };
+		let seg = |n: &'static str| PathSegment { node: n.into(), discriminator: None, reversal: false, leg: None };
+		let plan: Paths = vec![("t".to_string(), vec![seg("a"), seg("b"), seg("c")])].into_iter().collect();
+		let breaks = Breaks { rules: vec![("t".to_string(), BreakRule { every: 15.0, duration: 5.0 })].into_iter().collect(), rest_spots: vec!["b".into()].into_iter().collect() };
+		let mut tl = timeline(&roads, &plan, None, Pace { speed: 1.0, slowdown: 1.0, bucket: 20.0 }, &breaks);
+		// unaligned timelines have no timestamps at all
+		assert!(!serde_json::to_string(&tl).unwrap().contains("at"));
+		tl.stamp(Clock::parse("2024-01-15T06:59:50-05:00").unwrap());
+		let t = &tl.vehicles["t"];
+		assert_eq!(t.bars.iter().map(|b| b.at.clone().unwrap().0).collect::<Vec<_>>(), vec!["2024-01-15T06:59:50-05:00", "2024-01-15T07:00:00-05:00", "2024-01-15T07:00:05-05:00"]);
+		assert_eq!(t.breaks[0].at, Some(("2024-01-15T07:00:00-05:00".to_string(), "2024-01-15T07:00:05-05:00".to_string())));
+		assert_eq!(t.eta.as_deref(), Some("2024-01-15T07:00:15-05:00"));
+		assert_eq!(tl.histogram.iter().map(|b| b.at.clone().unwrap()).collect::<Vec<_>>(), vec!["2024-01-15T06:59:50-05:00", "2024-01-15T07:00:10-05:00"]);
+		assert_eq!((tl.start.as_deref(), tl.at((20.0, 25.0)).unwrap().1), (Some("2024-01-15T06:59:50-05:00"), "2024-01-15T07:00:15-05:00".to_string()));
+		let chunks = chunks(&view::SolutionView::new(&roads, &plan, None), &tl, 100.0);
+		assert_eq!(chunks[0].at, Some(("2024-01-15T06:59:50-05:00".to_string(), "2024-01-15T07:00:15-05:00".to_string())));
+	}
+
+	#[test]
+	fn test_schedule(){
+		let road = |p1: &'static str, p2: &'static str, class: &'static str| RoadSegment { class: Some(class.into()), ..RoadSegment::new(p1, p2, 10.0) };
+		let roads = RoadGraph {
+			roads: vec![road("a", "b", "arterial"), road("b", "c", "arterial"), road("c", "d", "local")],
+			nodes: RoadGraphNodes { nodes: vec![] },
+		};
+		let snow = serde_json::from_str(r#"[{"p1": "a", "p2": "b", "discriminator": null, "depth": 1}, {"p1": "d", "p2": "c", "discriminator": null, "depth": 1}]"#).unwrap();
+		let seg = |n: &'static str| PathSegment { node: n.into(), discriminator: None, reversal: false, leg: None };
+		let plan = vec![("t".to_string(), vec![seg("a"), seg("b"), seg("c"), seg("d"), seg("c")]), ("u".to_string(), vec![seg("d"), seg("c"), seg("b")])].into_iter().collect();
+		let mut params = meta::Preset::Light.parameters();
+		params.slowdown = serde_json::from_str("2").unwrap();
+		params.speeds = serde_json::from_str(r#"{"default": 1, "classes": {"local": 5}}"#).unwrap();
+		// t drives at its own speed, clearing both snowy segments - u then only drives through
+		let schedules = schedule(&roads, &plan, &snow, None, &params, &vec![("t".to_string(), 2.0)].into_iter().collect());
+		let timing = |id: &str| schedules.0[id].iter().map(|s| (s.distance, s.eta)).collect::<Vec<_>>();
+		assert_eq!(timing("t"), vec![(0.0, 0.0), (10.0, 10.0), (20.0, 15.0), (30.0, 25.0), (40.0, 30.0)]);
+		assert_eq!(timing("u"), vec![(0.0, 0.0), (10.0, 2.0), (20.0, 12.0)]);
+		assert_eq!(schedules.paths(), plan);
+	}
+}
@@ -0,0 +1,116 @@
+//! Road graph validation
+//!
+//! Input data is only as good as whoever made it.
+
+use crate::*;
+use data::*;
+
+use std::collections::HashMap;
+use geo::algorithm::haversine_distance::HaversineDistance;
+use serde::*;
+
+/// A road segment whose length doesn't match its geometry
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LengthIssue {
+	pub p1: NodeId,
+	pub p2: NodeId,
+	pub discriminator: Option<NodeId>,
+	/// Specified length
+	pub distance: N64,
+	/// Geodesic length between the end nodes
+	pub computed: f64,
+}
+
+impl LengthIssue {
+	/// Relative deviation of the specified length from the computed one
+	pub fn deviation(&self) -> f64 {
+		(self.distance.raw() - self.computed).abs() / self.computed
+	}
+}
+
+/// Geodesic (haversine) distance, in meters, between 2 `(lon, lat)` coordinates
+pub fn geodesic(c1: (f64, f64), c2: (f64, f64)) -> f64 {
+	geo::Point::from(c1).haversine_distance(&geo::Point::from(c2))
+}
+
+/// Computes geodesic lengths of all road segments, in order (`None` for segments with unknown nodes)
+pub fn segment_lengths(g: &RoadGraph) -> Vec<Option<f64>> {
+	let coords: HashMap<_, _> = g.nodes.nodes.iter().map(|n| (&n.id, n.coordinates)).collect();
+	g.roads.iter().map(|e| Some(geodesic(*coords.get(&e.p1)?, *coords.get(&e.p2)?))).collect()
+}
+
+/// Finds all road segments whose length deviates from the geodesic one by more than `tolerance` (relative)
+///
+/// Segments with coincident nodes are not checked, having no geometry to speak of.
+pub fn check_lengths(g: &RoadGraph, tolerance: f64) -> Vec<LengthIssue> {
+	g.roads.iter().zip(segment_lengths(g)).filter_map(|(e, computed)| computed.filter(|c| *c > 0.0).map(|computed| LengthIssue {
+		p1: e.p1.clone(),
+		p2: e.p2.clone(),
+		discriminator: e.discriminator.clone(),
+		distance: e.distance,
+		computed,
+	})).filter(|i| i.deviation() > tolerance).collect()
+}
+
+/// Replaces lengths of all road segments deviating more than `tolerance` with the geodesic ones
+///
+/// Returns: the replaced issues
+pub fn fix_lengths(g: &mut RoadGraph, tolerance: f64) -> Vec<LengthIssue> {
+	let issues = check_lengths(g, tolerance);
+	let fixes: HashMap<_, _> = issues.iter().map(|i| ((&i.p1, &i.p2, &i.discriminator), i.computed)).collect();
+	for e in &mut g.roads {
+		if let Some(c) = fixes.get(&(&e.p1, &e.p2, &e.discriminator)) {
+			e.distance = n64(*c);
+		}
+	}
+	issues
+}
+
+/// Finds all snowy road segments no vehicle can clear, due to their surface or grade
+///
+/// Arguments:
+/// - `snow`: snow statuses, `None` meaning every segment is snowy
+pub fn check_surfaces<'a>(g: &'a RoadGraph, snow: Option<&SnowStatuses>, vehicles: &[Vehicle]) -> Vec<&'a RoadSegment> {
+	let snowy = |e: &RoadSegment, parallel: usize| snow.is_none_or(|snow| snow.iter().any(|s| s.depth > 0.0 && s.discriminator == e.discriminator && s.parallel_index.is_none_or(|i| i == parallel) && ((s.p1 == e.p1 && s.p2 == e.p2) || (s.p1 == e.p2 && s.p2 == e.p1))));
+	g.roads.iter().zip(g.parallel_indices()).zip(g.grades()).filter(|((e, parallel), grade)| snowy(e, *parallel) && !vehicles.iter().any(|v| v.clears(e.surface.as_ref()) && v.climbs(*grade))).map(|((e, _), _)| e).collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_lengths(){
+		let node = |id: &'static str, lat: f64| Node { id: id.into(), coordinates: (-73.5, lat), elevation: None };
+		let road = |p2: &'static str, distance: f64| RoadSegment::new("a", p2, distance);
+		let mut g = RoadGraph {
+			roads: vec![road("b", 111.2), road("c", 0.0), road("a", 5.0)],
+			nodes: RoadGraphNodes { nodes: vec![node("a", 45.0), node("b", 45.001), node("c", 45.002)] },
+		};
+		let issues = check_lengths(&g, 0.05);
+		assert_eq!(issues.len(), 1);
+		assert_eq!(issues[0].p2, NodeId::from("c"));
+		assert!((issues[0].computed - 222.4).abs() < 1.0);
+		fix_lengths(&mut g, 0.05);
+		assert!(check_lengths(&g, 0.05).is_empty());
+		assert_eq!(g.roads[2].distance, n64(5.0));
+	}
+
+	#[test]
+	fn test_surfaces(){
+		let road = |p2: &'static str, surface: Option<&'static str>| RoadSegment { surface: surface.map(Into::into), ..RoadSegment::new("a", p2, 1.0) };
+		let node = |id: &'static str, elevation: f64| Node { id: id.into(), coordinates: (0.0, 0.0), elevation: Some(elevation) };
+		let g = RoadGraph {
+			roads: vec![road("b", Some("gravel")), road("c", Some("asphalt")), road("d", None)],
+			nodes: RoadGraphNodes { nodes: vec![node("a", 0.0), node("d", -0.2)] },
+		};
+		let vehicle = |surfaces: Vec<&'static str>| Vehicle::Spec(VehicleSpec { id: None, location: Location::Node("a".into()), end: RouteEnd::Start, exclude: vec![], surfaces: Some(surfaces.into_iter().map(Into::into).collect()), breaks: None, max_grade: None, capacity: None, speed: None, resource: None });
+		assert_eq!(check_surfaces(&g, None, &[vehicle(vec!["asphalt"])]), vec![&g.roads[0]]);
+		let snow = vec![SnowStatusElement { p1: "c".into(), p2: "a".into(), discriminator: None, parallel_index: None, depth: n64(1.0), variance: None, estimated: false }];
+		assert!(check_surfaces(&g, Some(&snow), &[vehicle(vec!["asphalt"])]).is_empty());
+		assert!(check_surfaces(&g, None, &[vehicle(vec!["asphalt"]), Vehicle::Location(Location::Node("a".into()))]).is_empty());
+		assert_eq!(g.grades(), vec![None, None, Some(-0.2)]);
+		let steep = Vehicle::Spec(VehicleSpec { id: None, location: Location::Node("a".into()), end: RouteEnd::Start, exclude: vec![], surfaces: None, breaks: None, max_grade: Some(0.1), capacity: None, speed: None, resource: None });
+		assert_eq!(check_surfaces(&g, None, &[steep]), vec![&g.roads[2]]);
+	}
+}
@@ -0,0 +1,228 @@
+//! Solutions in context
+//!
+//! Planned paths are bare node sequences; the view walks them with the road segments traversed,
+//! the snow on them and the distance and time driven so far, so that every consumer sees the same thing.
+
+use crate::*;
+use data::*;
+
+use std::collections::{HashMap, HashSet};
+use serde::*;
+
+/// Undirected road segment key
+pub(crate) type SegmentKey = (NodeId, NodeId, Option<NodeId>);
+
+pub(crate) fn segment_key(p1: &NodeId, p2: &NodeId, discriminator: &Option<NodeId>) -> SegmentKey {
+	if p1 <= p2 {
+		(p1.clone(), p2.clone(), discriminator.clone())
+	} else {
+		(p2.clone(), p1.clone(), discriminator.clone())
+	}
+}
+
+/// Side of the road, in the direction of travel
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CastSide {
+	#[serde(rename="left")]
+	Left,
+	#[default]
+	#[serde(rename="right")]
+	Right,
+}
+
+impl CastSide {
+	pub fn other(self) -> Self {
+		match self {
+			Self::Left => Self::Right,
+			Self::Right => Self::Left,
+		}
+	}
+}
+
+/// Which side plow blades cast snow to
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct CastPolicy {
+	/// The side cast to, unless it is to be avoided and the other one isn't
+	pub side: CastSide,
+	/// Avoid casting onto sidewalks
+	pub avoid_sidewalks: bool,
+	/// Avoid casting onto parking lanes
+	pub avoid_parking: bool,
+}
+
+impl Default for CastPolicy {
+	fn default() -> Self {
+		Self { side: CastSide::Right, avoid_sidewalks: true, avoid_parking: true }
+	}
+}
+
+impl CastPolicy {
+	/// Recommended cast side when traversing a road segment, forward (`p1`→`p2`) or not
+	pub fn cast(&self, road: &RoadSegment, forward: bool) -> CastSide {
+		let avoid = |(left, right): (bool, bool)| if forward { (left, right) } else { (right, left) };
+		let (sidewalks, parking) = (avoid(road.sidewalks), avoid(road.parking));
+		let avoided = |side: CastSide| {
+			let pick = |(left, right): (bool, bool)| if side == CastSide::Left { left } else { right };
+			(self.avoid_sidewalks && pick(sidewalks)) || (self.avoid_parking && pick(parking))
+		};
+		if avoided(self.side) && !avoided(self.side.other()) {
+			self.side.other()
+		} else {
+			self.side
+		}
+	}
+}
+
+/// A single segment traversal of a path
+#[derive(Clone, PartialEq, Debug)]
+pub struct Traversal<'a> {
+	pub vehicle: &'a VehicleId,
+	/// Index of the traversal along the path
+	pub index: usize,
+	pub from: &'a NodeId,
+	pub to: &'a PathSegment,
+	/// The road segment traversed, if in the road graph
+	pub road: Option<&'a RoadSegment>,
+	/// Whether the segment is snowy (any segment is, without snow statuses)
+	pub snowy: bool,
+	/// Whether the vehicle is clearing the segment - it's snowy and traversed for the first time
+	pub clearing: bool,
+	/// Recommended side to cast snow to, if the segment is in the road graph
+	pub cast: Option<CastSide>,
+	/// Distance driven by the end of the traversal
+	pub distance: f64,
+	/// When the traversal starts, not counting any breaks
+	pub start: f64,
+	/// When the traversal ends, not counting any breaks
+	pub end: f64,
+}
+
+/// Planned paths, in the context of the road graph and snow
+pub struct SolutionView<'a> {
+	plan: &'a Paths,
+	segments: HashMap<SegmentKey, &'a RoadSegment>,
+	snowy: Option<HashSet<SegmentKey>>,
+	speed: f64,
+	slowdown: f64,
+	cast: CastPolicy,
+}
+
+impl<'a> SolutionView<'a> {
+	/// A view driving at unit speed, without slowing down while clearing, casting as per the default policy
+	pub fn new(roads: &'a RoadGraph, plan: &'a Paths, snow: Option<&SnowStatuses>) -> Self {
+		let mut segments = HashMap::new();
+		for e in &roads.roads {
+			segments.entry(segment_key(&e.p1, &e.p2, &e.discriminator)).or_insert(e);
+		}
+		Self {
+			plan,
+			segments,
+			snowy: snow.map(|snow| snow.iter().filter(|s| s.depth > 0.0).map(|s| segment_key(&s.p1, &s.p2, &s.discriminator)).collect()),
+			speed: 1.0,
+			slowdown: 1.0,
+			cast: CastPolicy::default(),
+		}
+	}
+	/// Sets the driving speed (in meters per time unit) and the clearing slowdown factor
+	pub fn pace(self, speed: f64, slowdown: f64) -> Self {
+		Self { speed, slowdown, ..self }
+	}
+	/// Sets the snow casting policy
+	pub fn cast(self, cast: CastPolicy) -> Self {
+		Self { cast, ..self }
+	}
+	/// The planned paths
+	pub fn plan(&self) -> &'a Paths {
+		self.plan
+	}
+	/// Traversals of a vehicle's path, in order
+	pub fn vehicle<'s>(&'s self, id: &'s VehicleId) -> Traversals<'s> {
+		let (vehicle, path) = self.plan.0.get_key_value(id).map_or((id, &[][..]), |(id, path)| (id, &path[..]));
+		self.walk(vehicle, path)
+	}
+	/// Traversals of all paths, vehicle by vehicle
+	pub fn traversals(&self) -> impl Iterator<Item = Traversal<'_>> {
+		self.plan.0.iter().flat_map(move |(id, path)| self.walk(id, path))
+	}
+	fn walk<'s>(&'s self, vehicle: &'s VehicleId, path: &'s [PathSegment]) -> Traversals<'s> {
+		Traversals { view: self, vehicle, path, index: 0, cleared: HashSet::new(), distance: 0.0, time: 0.0 }
+	}
+}
+
+/// Iterator over the traversals of a path
+pub struct Traversals<'a> {
+	view: &'a SolutionView<'a>,
+	vehicle: &'a VehicleId,
+	path: &'a [PathSegment],
+	index: usize,
+	cleared: HashSet<SegmentKey>,
+	distance: f64,
+	time: f64,
+}
+
+impl<'a> Iterator for Traversals<'a> {
+	type Item = Traversal<'a>;
+	fn next(&mut self) -> Option<Self::Item> {
+		let (u, v) = (self.path.get(self.index)?, self.path.get(self.index + 1)?);
+		let k = segment_key(&u.node, &v.node, &v.discriminator);
+		let road = self.view.segments.get(&k).copied();
+		if road.is_none() {
+			log::warn!("Path of vehicle {} uses segment {}<->{} not in the road graph", self.vehicle, u.node, v.node);
+		}
+		let snowy = road.is_some() && self.view.snowy.as_ref().is_none_or(|s| s.contains(&k));
+		let clearing = snowy && self.cleared.insert(k);
+		let d = road.map_or(0.0, |e| e.distance.raw());
+		let start = self.time;
+		self.distance += d;
+		self.time += d / self.view.speed * if clearing { self.view.slowdown } else { 1.0 };
+		self.index += 1;
+		Some(Traversal {
+			vehicle: self.vehicle,
+			index: self.index - 1,
+			from: &u.node,
+			to: v,
+			road,
+			snowy,
+			clearing,
+			cast: road.map(|e| self.view.cast.cast(e, e.p1 == u.node)),
+			distance: self.distance,
+			start,
+			end: self.time,
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_view(){
+		let road = |p1: &'static str, p2: &'static str, distance: f64| RoadSegment::new(p1, p2, distance);
+		let roads = RoadGraph {
+			roads: vec![road("a", "b", 10.0), road("b", "c", 20.0)],
+			nodes: RoadGraphNodes { nodes: vec![] },
+		};
+		let snow = vec![SnowStatusElement { p1: "c".into(), p2: "b".into(), discriminator: None, parallel_index: None, depth: n64(1.0), variance: None, estimated: false }];
+		let seg = |n: &'static str| PathSegment { node: n.into(), discriminator: None, reversal: false, leg: None };
+		let plan = vec![("t".to_string(), vec![seg("a"), seg("b"), seg("c"), seg("b"), seg("x")])].into_iter().collect();
+		let view = SolutionView::new(&roads, &plan, Some(&snow)).pace(2.0, 3.0);
+		let (t, u) = ("t".to_string(), "u".to_string());
+		let ts: Vec<_> = view.vehicle(&t).map(|t| (t.from.name().unwrap(), t.to.node.name().unwrap(), t.snowy, t.clearing, t.distance, t.start, t.end)).collect();
+		assert_eq!(ts, vec![
+			("a", "b", false, false, 10.0, 0.0, 5.0),
+			("b", "c", true, true, 30.0, 5.0, 35.0),
+			("c", "b", true, false, 50.0, 35.0, 45.0),
+			("b", "x", false, false, 50.0, 45.0, 45.0),
+		]);
+		assert_eq!(view.traversals().count(), 4);
+		let mut avenue = road("a", "b", 10.0);
+		avenue.sidewalks = (false, true);
+		let policy = CastPolicy::default();
+		assert_eq!((policy.cast(&avenue, true), policy.cast(&avenue, false)), (CastSide::Left, CastSide::Right));
+		avenue.parking = (true, false);
+		assert_eq!(policy.cast(&avenue, true), CastSide::Right);
+		assert_eq!(CastPolicy { avoid_parking: false, ..policy }.cast(&avenue, true), CastSide::Left);
+		assert_eq!(view.vehicle(&u).count(), 0);
+	}
+}
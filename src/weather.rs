@@ -0,0 +1,95 @@
+//! Simulated snowfall
+//!
+//! Rehearsing a storm shouldn't have to wait for one: a simple storm model - snowing hardest at its center, less and less farther away -
+//! generates plausible snow statuses over a road graph.
+
+use crate::*;
+use data::*;
+use validate::geodesic;
+
+use std::collections::{HashMap, HashSet};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+/// A storm, as seen from the ground
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+pub struct StormModel {
+	/// `(lon, lat)` of the storm center, the centroid of the road graph if not given
+	#[serde(default)]
+	pub center: Option<(f64, f64)>,
+	/// Snowfall rate at the center, in depth per hour
+	pub intensity: f64,
+	/// Fraction of the center snowfall rate lost per kilometer away from it
+	pub gradient: f64,
+	/// How long it snows, in hours
+	pub duration: f64,
+	/// Relative amplitude of the random variation of every segment's depth
+	#[serde(default)]
+	pub noise: f64,
+	/// Random seed, for a reproducible storm
+	#[serde(default)]
+	pub seed: Option<u64>,
+}
+
+impl StormModel {
+	/// Snow depth at a location, before random variation
+	pub fn depth(&self, center: (f64, f64), at: (f64, f64)) -> f64 {
+		let km = geodesic(center, at) / 1000.0;
+		self.intensity * self.duration * (1.0 - self.gradient * km).max(0.0)
+	}
+	/// Snow statuses of all road segments the storm covers, at their midpoints (parallel segments get the same snow)
+	pub fn simulate(&self, roads: &RoadGraph) -> Result<SnowStatuses, Error> {
+		let coords: HashMap<_, _> = roads.nodes.nodes.iter().map(|n| (&n.id, n.coordinates)).collect();
+		let center = match self.center {
+			Some(c) => c,
+			None if coords.is_empty() => return Err("Road graph has no nodes to center the storm on".into()),
+			None => {
+				let n = roads.nodes.nodes.len() as f64;
+				let (lon, lat) = roads.nodes.nodes.iter().fold((0.0, 0.0), |(lon, lat), node| (lon + node.coordinates.0, lat + node.coordinates.1));
+				(lon / n, lat / n)
+			},
+		};
+		let mut rng = StdRng::seed_from_u64(self.seed.unwrap_or_else(rand::random));
+		let mut seen = HashSet::new();
+		let mut snow = Vec::new();
+		for e in &roads.roads {
+			let (p1, p2) = if e.p1 <= e.p2 { (&e.p1, &e.p2) } else { (&e.p2, &e.p1) };
+			if !seen.insert((p1, p2, &e.discriminator)) {
+				continue;
+			}
+			let (c1, c2) = match (coords.get(&e.p1), coords.get(&e.p2)) {
+				(Some(c1), Some(c2)) => (c1, c2),
+				_ => return Err(Error::segment(&e.p1, &e.p2, &e.discriminator, "references a node not in the road graph")),
+			};
+			let depth = self.depth(center, ((c1.0 + c2.0) / 2.0, (c1.1 + c2.1) / 2.0));
+			let depth = if self.noise > 0.0 { depth * (1.0 + rng.gen_range(-self.noise..=self.noise)) } else { depth };
+			if depth > 0.0 {
+				snow.push(SnowStatusElement { p1: e.p1.clone(), p2: e.p2.clone(), discriminator: e.discriminator.clone(), parallel_index: None, depth: n64(depth), variance: None, estimated: false });
+			}
+		}
+		Ok(snow)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_simulate(){
+		let node = |id: &'static str, lon: f64| Node { id: id.into(), coordinates: (lon, 0.0), elevation: None };
+		let road = |p1: &'static str, p2: &'static str| RoadSegment::new(p1, p2, 10.0);
+		let g = RoadGraph {
+			roads: vec![road("a", "b"), road("b", "a"), road("b", "c"), road("c", "d")],
+			nodes: RoadGraphNodes { nodes: vec![node("a", 0.0), node("b", 0.01), node("c", 0.02), node("d", 0.2)] },
+		};
+		let mut storm = StormModel { center: Some((0.0, 0.0)), intensity: 2.0, gradient: 0.1, duration: 5.0, noise: 0.0, seed: Some(7) };
+		let snow = storm.simulate(&g).unwrap();
+		let depths: Vec<_> = snow.iter().map(|s| (s.p1.name().unwrap(), s.depth.raw())).collect();
+		assert_eq!(depths.len(), 2, "parallel segments get the snow once, the far one none: {:?}", depths);
+		assert!(depths[0].1 > depths[1].1 && depths[0].1 < 10.0);
+		storm.noise = 0.5;
+		let noisy = storm.simulate(&g).unwrap();
+		assert_eq!(noisy, storm.simulate(&g).unwrap());
+		assert_ne!(noisy, snow);
+	}
+}